@@ -0,0 +1,27 @@
+-- Full-text search over the names and free text users type in.
+-- The 'simple' configuration doesn't stem, so it works the same for every language our users write in.
+
+ALTER TABLE clients ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+	setweight(to_tsvector('simple', name), 'A') ||
+	setweight(to_tsvector('simple', coalesce(company_name, '')), 'A') ||
+	setweight(to_tsvector('simple', coalesce(first_name, '') || ' ' || coalesce(last_name, '')), 'B')
+) STORED;
+
+ALTER TABLE projects ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+	setweight(to_tsvector('simple', name), 'A') ||
+	setweight(to_tsvector('simple', coalesce(description, '')), 'B')
+) STORED;
+
+ALTER TABLE jobs ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+	setweight(to_tsvector('simple', name), 'A') ||
+	setweight(to_tsvector('simple', coalesce(description, '')), 'B')
+) STORED;
+
+ALTER TABLE time_entries ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+	to_tsvector('simple', coalesce(description, ''))
+) STORED;
+
+CREATE INDEX clients_search_idx ON clients USING GIN (search_vector);
+CREATE INDEX projects_search_idx ON projects USING GIN (search_vector);
+CREATE INDEX jobs_search_idx ON jobs USING GIN (search_vector);
+CREATE INDEX time_entries_search_idx ON time_entries USING GIN (search_vector);
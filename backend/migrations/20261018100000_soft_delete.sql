@@ -0,0 +1,19 @@
+-- Soft delete: rows with deleted_at set are hidden from every query and permanently
+-- removed by the trash purge once the retention period has passed.
+-- Children of a deleted row (a project's jobs, a job's time entries, ...) are hidden through
+-- their parent and come back with it on restore.
+
+ALTER TABLE users ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE clients ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE projects ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE jobs ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE time_entries ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE milestones ADD COLUMN deleted_at TIMESTAMPTZ;
+
+-- The trash listing and the purge only ever look at deleted rows.
+CREATE INDEX users_deleted_at_idx ON users (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX clients_deleted_at_idx ON clients (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX projects_deleted_at_idx ON projects (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX jobs_deleted_at_idx ON jobs (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX time_entries_deleted_at_idx ON time_entries (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX milestones_deleted_at_idx ON milestones (deleted_at) WHERE deleted_at IS NOT NULL;
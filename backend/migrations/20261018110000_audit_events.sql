@@ -0,0 +1,33 @@
+-- Who changed what and when. Rows are written in the same transaction as the change they describe
+-- and can never be altered afterwards. There are no foreign keys, so history outlives purged data.
+
+CREATE TABLE audit_events (
+	id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+	occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	-- NULL for changes made by the system itself, such as the trash purge.
+	actor_id UUID,
+	entity_type TEXT NOT NULL,
+	entity_id UUID NOT NULL,
+	action TEXT NOT NULL,
+	-- {"field": {"before": ..., "after": ...}} for every field the change touched.
+	changes JSONB NOT NULL DEFAULT '{}',
+	request_id TEXT,
+	ip INET
+);
+
+CREATE INDEX audit_events_entity_idx ON audit_events (entity_type, entity_id, occurred_at DESC);
+CREATE INDEX audit_events_actor_idx ON audit_events (actor_id, occurred_at DESC);
+CREATE INDEX audit_events_occurred_at_idx ON audit_events (occurred_at DESC);
+
+CREATE FUNCTION audit_events_append_only() RETURNS trigger AS $$
+BEGIN
+	RAISE EXCEPTION 'audit_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_events_no_update BEFORE UPDATE OR DELETE ON audit_events
+	FOR EACH ROW EXECUTE FUNCTION audit_events_append_only();
+CREATE TRIGGER audit_events_no_truncate BEFORE TRUNCATE ON audit_events
+	FOR EACH STATEMENT EXECUTE FUNCTION audit_events_append_only();
+
+ALTER TABLE users ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,30 @@
+-- Optimistic concurrency: every update of a row bumps its version, which is sent as the
+-- `ETag` and must come back in `If-Match` before the row may be changed or deleted.
+-- A trigger keeps the counter honest for every write path, including the trash.
+
+CREATE FUNCTION bump_version() RETURNS trigger AS $$
+BEGIN
+	NEW.version := OLD.version + 1;
+	RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE users ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE clients ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE projects ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE jobs ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE time_entries ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE milestones ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+
+CREATE TRIGGER users_bump_version BEFORE UPDATE ON users
+	FOR EACH ROW EXECUTE FUNCTION bump_version();
+CREATE TRIGGER clients_bump_version BEFORE UPDATE ON clients
+	FOR EACH ROW EXECUTE FUNCTION bump_version();
+CREATE TRIGGER projects_bump_version BEFORE UPDATE ON projects
+	FOR EACH ROW EXECUTE FUNCTION bump_version();
+CREATE TRIGGER jobs_bump_version BEFORE UPDATE ON jobs
+	FOR EACH ROW EXECUTE FUNCTION bump_version();
+CREATE TRIGGER time_entries_bump_version BEFORE UPDATE ON time_entries
+	FOR EACH ROW EXECUTE FUNCTION bump_version();
+CREATE TRIGGER milestones_bump_version BEFORE UPDATE ON milestones
+	FOR EACH ROW EXECUTE FUNCTION bump_version();
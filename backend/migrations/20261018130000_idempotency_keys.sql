@@ -0,0 +1,19 @@
+-- Responses to POST and PATCH requests sent with an `Idempotency-Key`, replayed when a client retries.
+-- A row without a status belongs to a request that is still being handled.
+
+CREATE TABLE idempotency_keys (
+	id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+	-- NULL for anonymous requests such as signup, which share one key space.
+	user_id UUID REFERENCES users (id) ON DELETE CASCADE,
+	key TEXT NOT NULL,
+	-- SHA-256 over method, path and body; the same key with a different request is a client bug.
+	fingerprint TEXT NOT NULL,
+	status SMALLINT,
+	headers JSONB NOT NULL DEFAULT '{}',
+	body BYTEA,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	expires_at TIMESTAMPTZ NOT NULL,
+	UNIQUE NULLS NOT DISTINCT (user_id, key)
+);
+
+CREATE INDEX idempotency_keys_expires_at_idx ON idempotency_keys (expires_at);
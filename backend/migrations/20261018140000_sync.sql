@@ -0,0 +1,33 @@
+-- Delta sync for offline clients: every write stamps the row with the id of the writing transaction.
+-- A sync cursor is the oldest transaction still running when it was issued, so rows stamped at or
+-- after it are sent (again) on the next sync and nothing committed late is ever skipped.
+
+CREATE FUNCTION stamp_sync_xid() RETURNS trigger AS $$
+BEGIN
+	NEW.sync_xid := pg_current_xact_id();
+	RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE clients ADD COLUMN sync_xid xid8 NOT NULL DEFAULT pg_current_xact_id();
+ALTER TABLE projects ADD COLUMN sync_xid xid8 NOT NULL DEFAULT pg_current_xact_id();
+ALTER TABLE jobs ADD COLUMN sync_xid xid8 NOT NULL DEFAULT pg_current_xact_id();
+ALTER TABLE time_entries ADD COLUMN sync_xid xid8 NOT NULL DEFAULT pg_current_xact_id();
+ALTER TABLE milestones ADD COLUMN sync_xid xid8 NOT NULL DEFAULT pg_current_xact_id();
+
+CREATE TRIGGER clients_stamp_sync_xid BEFORE UPDATE ON clients
+	FOR EACH ROW EXECUTE FUNCTION stamp_sync_xid();
+CREATE TRIGGER projects_stamp_sync_xid BEFORE UPDATE ON projects
+	FOR EACH ROW EXECUTE FUNCTION stamp_sync_xid();
+CREATE TRIGGER jobs_stamp_sync_xid BEFORE UPDATE ON jobs
+	FOR EACH ROW EXECUTE FUNCTION stamp_sync_xid();
+CREATE TRIGGER time_entries_stamp_sync_xid BEFORE UPDATE ON time_entries
+	FOR EACH ROW EXECUTE FUNCTION stamp_sync_xid();
+CREATE TRIGGER milestones_stamp_sync_xid BEFORE UPDATE ON milestones
+	FOR EACH ROW EXECUTE FUNCTION stamp_sync_xid();
+
+CREATE INDEX clients_sync_xid_idx ON clients (sync_xid);
+CREATE INDEX projects_sync_xid_idx ON projects (sync_xid);
+CREATE INDEX jobs_sync_xid_idx ON jobs (sync_xid);
+CREATE INDEX time_entries_sync_xid_idx ON time_entries (sync_xid);
+CREATE INDEX milestones_sync_xid_idx ON milestones (sync_xid);
@@ -0,0 +1,52 @@
+-- Real-time updates: every committed change to project data is announced on the `domain_events`
+-- channel, so each backend replica can pass it on to the `/events` streams it serves.
+-- Payloads only identify the change (NOTIFY caps them at 8000 bytes); subscribers fetch what they need.
+
+CREATE FUNCTION notify_change() RETURNS trigger AS $$
+DECLARE
+	data JSONB;
+	action TEXT;
+	project_id UUID;
+BEGIN
+	IF TG_OP = 'DELETE' THEN
+		data := to_jsonb(OLD);
+		action := 'purged';
+	ELSE
+		data := to_jsonb(NEW);
+		action := CASE
+			WHEN TG_OP = 'INSERT' THEN 'created'
+			WHEN OLD.deleted_at IS NULL AND NEW.deleted_at IS NOT NULL THEN 'deleted'
+			WHEN OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL THEN 'restored'
+			ELSE 'updated'
+		END;
+	END IF;
+
+	project_id := CASE TG_TABLE_NAME
+		WHEN 'projects' THEN (data->>'id')::uuid
+		WHEN 'time_entries' THEN (SELECT j.project_id FROM jobs j WHERE j.id = (data->>'job_id')::uuid)
+		ELSE (data->>'project_id')::uuid
+	END;
+
+	-- Gone together with its project, so nobody is left to tell.
+	IF project_id IS NOT NULL THEN
+		PERFORM pg_notify('domain_events', jsonb_build_object(
+			'entity_type', TG_ARGV[0],
+			'entity_id', data->'id',
+			'action', action,
+			'project_id', project_id,
+			'version', data->'version'
+		)::text);
+	END IF;
+
+	RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER projects_notify_change AFTER INSERT OR UPDATE OR DELETE ON projects
+	FOR EACH ROW EXECUTE FUNCTION notify_change('project');
+CREATE TRIGGER jobs_notify_change AFTER INSERT OR UPDATE OR DELETE ON jobs
+	FOR EACH ROW EXECUTE FUNCTION notify_change('job');
+CREATE TRIGGER time_entries_notify_change AFTER INSERT OR UPDATE OR DELETE ON time_entries
+	FOR EACH ROW EXECUTE FUNCTION notify_change('time_entry');
+CREATE TRIGGER milestones_notify_change AFTER INSERT OR UPDATE OR DELETE ON milestones
+	FOR EACH ROW EXECUTE FUNCTION notify_change('milestone');
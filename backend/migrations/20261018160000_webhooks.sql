@@ -0,0 +1,90 @@
+-- Outgoing webhooks. Triggers queue one delivery per subscribed webhook in the same transaction as the
+-- change, so no event is lost between the write and the dispatcher, whichever code path made the change.
+
+CREATE TABLE webhooks (
+	id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+	user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	url TEXT NOT NULL,
+	-- Shared with the receiver to verify signatures, so it can't be hashed.
+	secret TEXT NOT NULL,
+	events TEXT[] NOT NULL,
+	description TEXT,
+	-- Failed attempts since the last successful one; too many disable the webhook.
+	consecutive_failures INT NOT NULL DEFAULT 0,
+	disabled_at TIMESTAMPTZ,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX webhooks_user_id_idx ON webhooks (user_id);
+
+CREATE TABLE webhook_deliveries (
+	id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+	webhook_id UUID NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+	event_type TEXT NOT NULL,
+	data JSONB NOT NULL,
+	-- pending until delivered (succeeded) or out of attempts (failed).
+	status TEXT NOT NULL DEFAULT 'pending',
+	attempts INT NOT NULL DEFAULT 0,
+	next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	response_status SMALLINT,
+	error TEXT,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	delivered_at TIMESTAMPTZ
+);
+
+CREATE INDEX webhook_deliveries_webhook_id_idx ON webhook_deliveries (webhook_id, created_at DESC);
+CREATE INDEX webhook_deliveries_due_idx ON webhook_deliveries (next_attempt_at) WHERE status = 'pending';
+
+-- Queues `event` for the live webhooks subscribed to it. Project events go to the project's owner
+-- and members; events without a project go to administrators.
+CREATE FUNCTION queue_webhook_deliveries(event TEXT, project UUID, payload JSONB) RETURNS void AS $$
+	INSERT INTO webhook_deliveries (webhook_id, event_type, data)
+	SELECT w.id, event, payload
+	FROM webhooks w
+	JOIN users u ON u.id = w.user_id AND u.deleted_at IS NULL
+	WHERE w.disabled_at IS NULL
+		AND event = ANY (w.events)
+		AND CASE
+			WHEN project IS NULL THEN u.is_admin
+			ELSE EXISTS (SELECT 1 FROM projects p WHERE p.id = project AND p.created_by = u.id)
+				OR EXISTS (SELECT 1 FROM project_members m WHERE m.project_id = project AND m.user_id = u.id)
+		END
+$$ LANGUAGE sql;
+
+CREATE FUNCTION queue_user_signed_up() RETURNS trigger AS $$
+BEGIN
+	PERFORM queue_webhook_deliveries('user.signed_up', NULL,
+		jsonb_build_object('id', NEW.id, 'email', NEW.email, 'created_at', NEW.created_at));
+	RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION queue_time_entry_created() RETURNS trigger AS $$
+BEGIN
+	PERFORM queue_webhook_deliveries(
+		'time_entry.created',
+		(SELECT j.project_id FROM jobs j WHERE j.id = NEW.job_id),
+		(to_jsonb(NEW) - ARRAY['search_vector', 'sync_xid', 'deleted_at', 'time_spent'])
+			|| jsonb_build_object('time_spent_seconds', EXTRACT(EPOCH FROM NEW.time_spent)::BIGINT)
+	);
+	RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION queue_milestone_completed() RETURNS trigger AS $$
+BEGIN
+	PERFORM queue_webhook_deliveries('milestone.completed', NEW.project_id,
+		to_jsonb(NEW) - ARRAY['search_vector', 'sync_xid', 'deleted_at']);
+	RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER users_queue_webhooks AFTER INSERT ON users
+	FOR EACH ROW EXECUTE FUNCTION queue_user_signed_up();
+CREATE TRIGGER time_entries_queue_webhooks AFTER INSERT ON time_entries
+	FOR EACH ROW EXECUTE FUNCTION queue_time_entry_created();
+CREATE TRIGGER milestones_queue_webhooks AFTER UPDATE ON milestones
+	FOR EACH ROW
+	WHEN ((OLD.completed_at IS NULL AND NEW.completed_at IS NOT NULL)
+		OR (OLD.status <> 'COMPLETED' AND NEW.status = 'COMPLETED'))
+	EXECUTE FUNCTION queue_milestone_completed();
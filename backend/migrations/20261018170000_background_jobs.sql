@@ -0,0 +1,22 @@
+-- Durable background work. Workers claim queued jobs with `FOR UPDATE SKIP LOCKED`, so any number of
+-- them, in any number of processes, can poll the same table without running a job twice.
+-- A running job whose `locked_until` has passed belonged to a worker that died and is claimed again.
+
+CREATE TABLE background_jobs (
+	id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+	kind TEXT NOT NULL,
+	payload JSONB NOT NULL,
+	-- queued, running, succeeded, or dead once out of attempts.
+	status TEXT NOT NULL DEFAULT 'queued',
+	attempts INT NOT NULL DEFAULT 0,
+	max_attempts INT NOT NULL,
+	run_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	locked_until TIMESTAMPTZ,
+	last_error TEXT,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	finished_at TIMESTAMPTZ
+);
+
+CREATE INDEX background_jobs_queued_idx ON background_jobs (run_at) WHERE status = 'queued';
+CREATE INDEX background_jobs_running_idx ON background_jobs (locked_until) WHERE status = 'running';
+CREATE INDEX background_jobs_created_at_idx ON background_jobs (created_at);
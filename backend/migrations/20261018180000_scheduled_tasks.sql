@@ -0,0 +1,34 @@
+-- Recurring work. The replica holding the scheduler's advisory lock queues a background job for each task
+-- whose `next_run_at` has passed and moves `next_run_at` on according to `schedule`, a cron expression in UTC.
+
+CREATE TABLE scheduled_tasks (
+	id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+	name TEXT NOT NULL UNIQUE,
+	description TEXT,
+	-- `sec min hour day-of-month month day-of-week [year]`; five-field expressions get `0` seconds.
+	schedule TEXT NOT NULL,
+	-- `Job::KIND` of the background job queued on every run, with this payload.
+	job_kind TEXT NOT NULL,
+	payload JSONB NOT NULL DEFAULT '{}',
+	enabled BOOLEAN NOT NULL DEFAULT TRUE,
+	-- Computed by the scheduler; a new task fires on the first tick.
+	next_run_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	last_run_at TIMESTAMPTZ,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE scheduled_task_runs (
+	id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+	task_id UUID NOT NULL REFERENCES scheduled_tasks (id) ON DELETE CASCADE,
+	job_id UUID REFERENCES background_jobs (id) ON DELETE SET NULL,
+	-- schedule, or manual for "run now".
+	trigger TEXT NOT NULL,
+	triggered_by UUID REFERENCES users (id) ON DELETE SET NULL,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX scheduled_task_runs_task_idx ON scheduled_task_runs (task_id, created_at);
+
+INSERT INTO scheduled_tasks (name, description, schedule, job_kind) VALUES
+	('purge-trash', 'Permanently delete rows past the trash retention', '0 0 * * * *', 'trash.purge'),
+	('cleanup-idempotency-keys', 'Delete expired Idempotency-Key responses', '0 30 * * * *', 'idempotency_keys.cleanup');
@@ -0,0 +1,136 @@
+-- Workspaces own clients and projects (and through them jobs, milestones and time entries), so several
+-- freelancers can share them. Every user gets a personal workspace with the account; more are created
+-- and joined by invitation. Requests act in one active workspace at a time.
+
+CREATE TYPE workspace_role AS ENUM ('OWNER', 'ADMIN', 'MEMBER');
+
+CREATE TABLE workspaces (
+	id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+	name TEXT NOT NULL,
+	-- Created with the account and used when a request names no workspace. Its creator can't leave it.
+	personal BOOLEAN NOT NULL DEFAULT false,
+	-- Workspaces outlive the account that created them; only the memberships go.
+	created_by UUID REFERENCES users (id) ON DELETE SET NULL,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX workspaces_personal_idx ON workspaces (created_by) WHERE personal;
+
+CREATE TABLE workspace_members (
+	workspace_id UUID NOT NULL REFERENCES workspaces (id) ON DELETE CASCADE,
+	user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+	role workspace_role NOT NULL DEFAULT 'MEMBER',
+	joined_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	PRIMARY KEY (workspace_id, user_id)
+);
+
+CREATE INDEX workspace_members_user_idx ON workspace_members (user_id);
+
+CREATE TABLE workspace_invitations (
+	id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+	workspace_id UUID NOT NULL REFERENCES workspaces (id) ON DELETE CASCADE,
+	-- Only the account with this address can accept.
+	email TEXT NOT NULL,
+	role workspace_role NOT NULL DEFAULT 'MEMBER',
+	-- SHA-256 of the token handed to the invitee; the token itself is never stored.
+	token_hash TEXT NOT NULL UNIQUE,
+	invited_by UUID REFERENCES users (id) ON DELETE SET NULL,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	expires_at TIMESTAMPTZ NOT NULL,
+	accepted_at TIMESTAMPTZ
+);
+
+CREATE INDEX workspace_invitations_workspace_idx ON workspace_invitations (workspace_id, created_at DESC);
+
+-- Existing accounts get their personal workspace, and their projects move into it.
+INSERT INTO workspaces (name, personal, created_by, created_at)
+SELECT 'Personal', true, u.id, COALESCE(u.created_at AT TIME ZONE 'UTC', NOW()) FROM users u;
+
+INSERT INTO workspace_members (workspace_id, user_id, role, joined_at)
+SELECT w.id, w.created_by, 'OWNER', w.created_at FROM workspaces w;
+
+ALTER TABLE projects ADD COLUMN workspace_id UUID REFERENCES workspaces (id);
+UPDATE projects p SET workspace_id = w.id FROM workspaces w WHERE w.personal AND w.created_by = p.created_by;
+ALTER TABLE projects ALTER COLUMN workspace_id SET NOT NULL;
+
+-- Clients had no owner of their own. Each joins the workspace of its oldest project, and a client billed
+-- from several workspaces is copied into the others. Clients nobody billed stay unassigned until a project
+-- refers to them.
+ALTER TABLE clients ADD COLUMN workspace_id UUID REFERENCES workspaces (id);
+UPDATE clients c SET workspace_id = oldest.workspace_id
+FROM (
+	SELECT DISTINCT ON (client_id) client_id, workspace_id FROM projects ORDER BY client_id, created_at, id
+) oldest
+WHERE oldest.client_id = c.id;
+
+CREATE TEMPORARY TABLE client_copies ON COMMIT DROP AS
+SELECT DISTINCT p.client_id, p.workspace_id, gen_random_uuid() AS copy_id
+FROM projects p JOIN clients c ON c.id = p.client_id
+WHERE p.workspace_id <> c.workspace_id;
+
+INSERT INTO clients (id, workspace_id, name, first_name, last_name, phone, company_name, address_line1,
+	address_line2, city, postal_code, country, created_at, deleted_at)
+SELECT cc.copy_id, cc.workspace_id, c.name, c.first_name, c.last_name, c.phone, c.company_name, c.address_line1,
+	c.address_line2, c.city, c.postal_code, c.country, c.created_at, c.deleted_at
+FROM client_copies cc JOIN clients c ON c.id = cc.client_id;
+
+UPDATE projects p SET client_id = cc.copy_id
+FROM client_copies cc WHERE p.client_id = cc.client_id AND p.workspace_id = cc.workspace_id;
+
+-- A project can only be billed to a client of its own workspace.
+ALTER TABLE clients ADD CONSTRAINT clients_id_workspace_key UNIQUE (id, workspace_id);
+ALTER TABLE projects ADD CONSTRAINT projects_client_workspace_fkey
+	FOREIGN KEY (client_id, workspace_id) REFERENCES clients (id, workspace_id);
+
+CREATE INDEX clients_workspace_idx ON clients (workspace_id);
+CREATE INDEX projects_workspace_idx ON projects (workspace_id);
+
+-- Project members keep seeing the projects they were added to by joining the owner's workspace.
+INSERT INTO workspace_members (workspace_id, user_id, role, joined_at)
+SELECT p.workspace_id, m.user_id, 'MEMBER', MIN(m.joined_at)
+FROM project_members m JOIN projects p ON p.id = m.project_id
+GROUP BY p.workspace_id, m.user_id
+ON CONFLICT DO NOTHING;
+
+-- Every write path gets the same defaults: new accounts their personal workspace, projects that name
+-- no workspace their creator's personal one, and an unassigned client the workspace of its first project.
+CREATE FUNCTION create_personal_workspace() RETURNS trigger AS $$
+DECLARE
+	workspace UUID;
+BEGIN
+	INSERT INTO workspaces (name, personal, created_by) VALUES ('Personal', true, NEW.id) RETURNING id INTO workspace;
+	INSERT INTO workspace_members (workspace_id, user_id, role) VALUES (workspace, NEW.id, 'OWNER');
+	RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION assign_project_workspace() RETURNS trigger AS $$
+BEGIN
+	IF NEW.workspace_id IS NULL THEN
+		NEW.workspace_id := (SELECT id FROM workspaces WHERE personal AND created_by = NEW.created_by);
+	END IF;
+	UPDATE clients SET workspace_id = NEW.workspace_id WHERE id = NEW.client_id AND workspace_id IS NULL;
+	RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER users_create_personal_workspace AFTER INSERT ON users
+	FOR EACH ROW EXECUTE FUNCTION create_personal_workspace();
+CREATE TRIGGER projects_assign_workspace BEFORE INSERT ON projects
+	FOR EACH ROW EXECUTE FUNCTION assign_project_workspace();
+
+-- Project events now reach the members of the project's workspace.
+CREATE OR REPLACE FUNCTION queue_webhook_deliveries(event TEXT, project UUID, payload JSONB) RETURNS void AS $$
+	INSERT INTO webhook_deliveries (webhook_id, event_type, data)
+	SELECT w.id, event, payload
+	FROM webhooks w
+	JOIN users u ON u.id = w.user_id AND u.deleted_at IS NULL
+	WHERE w.disabled_at IS NULL
+		AND event = ANY (w.events)
+		AND CASE
+			WHEN project IS NULL THEN u.is_admin
+			ELSE EXISTS (SELECT 1 FROM projects p
+				JOIN workspace_members m ON m.workspace_id = p.workspace_id
+				WHERE p.id = project AND m.user_id = u.id)
+		END
+$$ LANGUAGE sql;
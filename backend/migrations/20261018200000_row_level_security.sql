@@ -0,0 +1,53 @@
+-- Row-level security as a second line of defense behind the handlers' own filters. Request transactions
+-- name the acting user and workspace in `app.current_user_id` and `app.current_workspace_id`
+-- (see `util::tenant`), and tenant-owned rows outside that workspace are then invisible and unwritable,
+-- even to a query that forgets its `WHERE`.
+--
+-- Without a current user, e.g. in migrations, background jobs, the trash purge or the change listener,
+-- every row is reachable as before. Superusers and roles with BYPASSRLS skip the policies altogether,
+-- so the API has to connect as an ordinary role for them to apply.
+
+-- NULL unless set in the current transaction. SET LOCAL leaves an empty string behind, hence NULLIF.
+CREATE FUNCTION app_current_user_id() RETURNS UUID AS $$
+	SELECT NULLIF(current_setting('app.current_user_id', true), '')::uuid
+$$ LANGUAGE sql STABLE;
+
+CREATE FUNCTION app_current_workspace_id() RETURNS UUID AS $$
+	SELECT NULLIF(current_setting('app.current_workspace_id', true), '')::uuid
+$$ LANGUAGE sql STABLE;
+
+ALTER TABLE clients ENABLE ROW LEVEL SECURITY;
+ALTER TABLE projects ENABLE ROW LEVEL SECURITY;
+ALTER TABLE project_members ENABLE ROW LEVEL SECURITY;
+ALTER TABLE jobs ENABLE ROW LEVEL SECURITY;
+ALTER TABLE milestones ENABLE ROW LEVEL SECURITY;
+ALTER TABLE time_entries ENABLE ROW LEVEL SECURITY;
+
+-- The API usually connects as the owner of these tables, who would otherwise be exempt.
+ALTER TABLE clients FORCE ROW LEVEL SECURITY;
+ALTER TABLE projects FORCE ROW LEVEL SECURITY;
+ALTER TABLE project_members FORCE ROW LEVEL SECURITY;
+ALTER TABLE jobs FORCE ROW LEVEL SECURITY;
+ALTER TABLE milestones FORCE ROW LEVEL SECURITY;
+ALTER TABLE time_entries FORCE ROW LEVEL SECURITY;
+
+-- Without a WITH CHECK clause the USING clause also applies to new and updated rows, so nothing
+-- can be written into, or moved to, another workspace either. Subqueries on other tenant tables
+-- are filtered by those tables' own policies.
+CREATE POLICY clients_tenant ON clients
+	USING (app_current_user_id() IS NULL OR workspace_id = app_current_workspace_id());
+
+CREATE POLICY projects_tenant ON projects
+	USING (app_current_user_id() IS NULL OR workspace_id = app_current_workspace_id());
+
+CREATE POLICY project_members_tenant ON project_members
+	USING (app_current_user_id() IS NULL OR project_id IN (SELECT id FROM projects));
+
+CREATE POLICY jobs_tenant ON jobs
+	USING (app_current_user_id() IS NULL OR project_id IN (SELECT id FROM projects));
+
+CREATE POLICY milestones_tenant ON milestones
+	USING (app_current_user_id() IS NULL OR project_id IN (SELECT id FROM projects));
+
+CREATE POLICY time_entries_tenant ON time_entries
+	USING (app_current_user_id() IS NULL OR job_id IN (SELECT id FROM jobs));
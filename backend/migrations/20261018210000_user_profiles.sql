@@ -0,0 +1,22 @@
+-- Profile and business details, printed on invoices and used to format what the user sees.
+-- Validation lives in `user_service`; the columns only keep what is structural.
+ALTER TABLE users
+	ADD COLUMN display_name TEXT,
+	ADD COLUMN avatar_url TEXT,
+	-- BCP 47 language tag
+	ADD COLUMN locale TEXT NOT NULL DEFAULT 'en',
+	-- IANA time zone name
+	ADD COLUMN timezone TEXT NOT NULL DEFAULT 'UTC',
+	-- ISO 4217 currency code
+	ADD COLUMN currency TEXT NOT NULL DEFAULT 'EUR',
+	ADD COLUMN date_format TEXT NOT NULL DEFAULT 'year_month_day',
+	ADD COLUMN number_format TEXT NOT NULL DEFAULT 'comma_period',
+	ADD COLUMN org_number TEXT,
+	ADD COLUMN vat_number TEXT,
+	ADD COLUMN bank_account TEXT,
+	ADD COLUMN address_line1 TEXT,
+	ADD COLUMN address_line2 TEXT,
+	ADD COLUMN postal_code TEXT,
+	ADD COLUMN city TEXT,
+	-- ISO 3166-1 alpha-2 country code
+	ADD COLUMN country TEXT;
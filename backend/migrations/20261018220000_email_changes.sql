@@ -0,0 +1,18 @@
+-- Requested email changes. The address only switches once the link sent to the new one is followed,
+-- and the old one gets a link to undo the change, pending or not, for a while.
+CREATE TABLE email_changes (
+	id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+	user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+	old_email TEXT NOT NULL,
+	new_email TEXT NOT NULL,
+	-- SHA-256 of the tokens in the two links; the tokens themselves are never stored.
+	confirm_token_hash TEXT NOT NULL UNIQUE,
+	undo_token_hash TEXT NOT NULL UNIQUE,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	-- Until when the new address can be confirmed.
+	expires_at TIMESTAMPTZ NOT NULL,
+	confirmed_at TIMESTAMPTZ,
+	undone_at TIMESTAMPTZ
+);
+
+CREATE INDEX email_changes_user_idx ON email_changes (user_id, created_at DESC);
@@ -0,0 +1,34 @@
+-- Email addresses are about to be compared case-insensitively. Accounts whose addresses only differ
+-- in case can't all keep them, and which one should is not for a migration to decide, so they are
+-- listed here for an operator to merge or rename. The next migration waits until this table is empty.
+CREATE TABLE email_collisions (
+	-- The address in lowercase.
+	email TEXT PRIMARY KEY,
+	-- Oldest account first.
+	user_ids UUID[] NOT NULL,
+	emails TEXT[] NOT NULL,
+	detected_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO email_collisions (email, user_ids, emails)
+SELECT lower(email), array_agg(id ORDER BY created_at, id), array_agg(email ORDER BY created_at, id)
+FROM users
+GROUP BY lower(email)
+HAVING COUNT(*) > 1;
+
+-- New addresses are stored with a lowercase domain; bring the others in line. IDNA conversion of
+-- non-ASCII domains is left to the application, which applies it whenever an address is entered.
+UPDATE users SET email = substring(email FROM '^(.*)@') || '@' || lower(substring(email FROM '@([^@]*)$'))
+WHERE email LIKE '%@%'
+	AND email <> substring(email FROM '^(.*)@') || '@' || lower(substring(email FROM '@([^@]*)$'))
+	AND lower(email) NOT IN (SELECT email FROM email_collisions);
+
+DO $$
+DECLARE
+	collision RECORD;
+BEGIN
+	FOR collision IN SELECT * FROM email_collisions LOOP
+		RAISE WARNING 'Accounts % share the address % when case is ignored: %',
+			collision.user_ids, collision.email, collision.emails;
+	END LOOP;
+END $$;
@@ -0,0 +1,11 @@
+-- One account per address regardless of case. Lookups compare `lower(email)`, which this index serves.
+DO $$
+BEGIN
+	IF EXISTS (SELECT 1 FROM email_collisions) THEN
+		RAISE EXCEPTION 'Some accounts share an email address when case is ignored'
+			USING HINT = 'Merge or rename the accounts listed in email_collisions, delete their rows, and run the migrations again';
+	END IF;
+END $$;
+
+CREATE UNIQUE INDEX users_email_lower_key ON users (lower(email));
+ALTER TABLE users DROP CONSTRAINT users_email_key;
@@ -0,0 +1,8 @@
+-- Payloads with secrets, like the links in an email, are hidden from the job API and deleted once the job succeeds.
+
+ALTER TABLE background_jobs
+	ADD COLUMN sensitive BOOLEAN NOT NULL DEFAULT FALSE,
+	ALTER COLUMN payload DROP NOT NULL;
+
+UPDATE background_jobs SET sensitive = TRUE WHERE kind = 'mail.send';
+UPDATE background_jobs SET payload = NULL WHERE sensitive AND status = 'succeeded';
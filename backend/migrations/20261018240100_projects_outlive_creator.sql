@@ -0,0 +1,2 @@
+-- Projects in shared workspaces belong to the workspace and stay when the account that created them is purged.
+ALTER TABLE projects ALTER COLUMN created_by DROP NOT NULL;
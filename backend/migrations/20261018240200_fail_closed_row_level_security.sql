@@ -0,0 +1,42 @@
+-- Row-level security now fails closed: a transaction that names no workspace sees no tenant rows at all,
+-- instead of all of them. Work that isn't done for one user, such as the trash purge or the admin views,
+-- has to say so by setting `app.bypass_rls` (see `util::tenant::bypass`).
+--
+-- The setting guards against queries that forget their filter, not against SQL injection: anyone who
+-- can run arbitrary statements can set it too.
+
+-- False unless set in the current transaction. SET LOCAL leaves an empty string behind, hence NULLIF.
+CREATE FUNCTION app_bypass_rls() RETURNS BOOLEAN AS $$
+	SELECT COALESCE(NULLIF(current_setting('app.bypass_rls', true), '')::boolean, false)
+$$ LANGUAGE sql STABLE;
+
+-- Without a current workspace the comparison is NULL, and so is every subquery on the tables above.
+ALTER POLICY clients_tenant ON clients
+	USING (app_bypass_rls() OR workspace_id = app_current_workspace_id());
+
+ALTER POLICY projects_tenant ON projects
+	USING (app_bypass_rls() OR workspace_id = app_current_workspace_id());
+
+ALTER POLICY project_members_tenant ON project_members
+	USING (app_bypass_rls() OR project_id IN (SELECT id FROM projects));
+
+ALTER POLICY jobs_tenant ON jobs
+	USING (app_bypass_rls() OR project_id IN (SELECT id FROM projects));
+
+ALTER POLICY milestones_tenant ON milestones
+	USING (app_bypass_rls() OR project_id IN (SELECT id FROM projects));
+
+ALTER POLICY time_entries_tenant ON time_entries
+	USING (app_bypass_rls() OR job_id IN (SELECT id FROM jobs));
+
+-- The audit log holds before and after values of tenant rows, and outlives them, so there is no row
+-- left to decide its visibility by. Any transaction may append to it, but it can only be read with
+-- the bypass, by the audit routes once they have checked access themselves.
+ALTER TABLE audit_events ENABLE ROW LEVEL SECURITY;
+ALTER TABLE audit_events FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY audit_events_append ON audit_events FOR INSERT
+	WITH CHECK (true);
+
+CREATE POLICY audit_events_read ON audit_events FOR SELECT
+	USING (app_bypass_rls());
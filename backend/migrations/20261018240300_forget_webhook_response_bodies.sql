@@ -0,0 +1,4 @@
+-- Failed deliveries no longer keep what the receiver answered, only its status. Drop the bodies already
+-- stored after "Receiver answered <status>: ".
+UPDATE webhook_deliveries SET error = regexp_replace(error, '^(Receiver answered [^:]*): .*$', '\1', 's')
+WHERE error LIKE 'Receiver answered %';
@@ -0,0 +1,49 @@
+-- Change notifications name the workspace of the project, so streams can filter them without asking the
+-- database, and purged projects, which can no longer be looked up, still reach their workspace.
+
+CREATE OR REPLACE FUNCTION notify_change() RETURNS trigger AS $$
+DECLARE
+	data JSONB;
+	action TEXT;
+	project_id UUID;
+	workspace_id UUID;
+BEGIN
+	IF TG_OP = 'DELETE' THEN
+		data := to_jsonb(OLD);
+		action := 'purged';
+	ELSE
+		data := to_jsonb(NEW);
+		action := CASE
+			WHEN TG_OP = 'INSERT' THEN 'created'
+			WHEN OLD.deleted_at IS NULL AND NEW.deleted_at IS NOT NULL THEN 'deleted'
+			WHEN OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL THEN 'restored'
+			ELSE 'updated'
+		END;
+	END IF;
+
+	IF TG_TABLE_NAME = 'projects' THEN
+		project_id := (data->>'id')::uuid;
+		workspace_id := (data->>'workspace_id')::uuid;
+	ELSE
+		project_id := CASE TG_TABLE_NAME
+			WHEN 'time_entries' THEN (SELECT j.project_id FROM jobs j WHERE j.id = (data->>'job_id')::uuid)
+			ELSE (data->>'project_id')::uuid
+		END;
+		workspace_id := (SELECT p.workspace_id FROM projects p WHERE p.id = project_id);
+	END IF;
+
+	-- Gone together with its project, so nobody is left to tell.
+	IF workspace_id IS NOT NULL THEN
+		PERFORM pg_notify('domain_events', jsonb_build_object(
+			'entity_type', TG_ARGV[0],
+			'entity_id', data->'id',
+			'action', action,
+			'project_id', project_id,
+			'workspace_id', workspace_id,
+			'version', data->'version'
+		)::text);
+	END IF;
+
+	RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
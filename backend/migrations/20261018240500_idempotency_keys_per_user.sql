@@ -0,0 +1,6 @@
+-- Idempotency keys are only honored on authenticated requests, and responses carrying credentials are no
+-- longer stored. Stored responses can't be told apart, so all of them go, including the anonymous ones
+-- with their login tokens; clients retrying in the next moments simply run their request again.
+DELETE FROM idempotency_keys;
+
+ALTER TABLE idempotency_keys ALTER COLUMN user_id SET NOT NULL;
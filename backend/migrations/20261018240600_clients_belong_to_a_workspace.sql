@@ -0,0 +1,31 @@
+-- Clients that no project referred to were left without a workspace when workspaces came in. Row-level
+-- security hides them from every tenant, so nothing could use or adopt them any more. Each now goes to
+-- the personal workspace of whoever created it, as the audit log records; the rest have nobody to
+-- belong to and are removed, which the audit log records too.
+
+-- Migrations may run as an ordinary role, which row-level security would show no clients at all.
+SELECT set_config('app.bypass_rls', 'on', true);
+
+UPDATE clients c SET workspace_id = w.id
+FROM (
+	SELECT DISTINCT ON (entity_id) entity_id, actor_id FROM audit_events
+	WHERE entity_type = 'client' AND action = 'created' AND actor_id IS NOT NULL
+	ORDER BY entity_id, occurred_at
+) creator
+JOIN workspaces w ON w.personal AND w.created_by = creator.actor_id
+WHERE creator.entity_id = c.id AND c.workspace_id IS NULL;
+
+WITH gone AS (DELETE FROM clients WHERE workspace_id IS NULL RETURNING id)
+INSERT INTO audit_events (entity_type, entity_id, action) SELECT 'client', id, 'purged' FROM gone;
+
+ALTER TABLE clients ALTER COLUMN workspace_id SET NOT NULL;
+
+-- There are no unassigned clients left for a project to adopt.
+CREATE OR REPLACE FUNCTION assign_project_workspace() RETURNS trigger AS $$
+BEGIN
+	IF NEW.workspace_id IS NULL THEN
+		NEW.workspace_id := (SELECT id FROM workspaces WHERE personal AND created_by = NEW.created_by);
+	END IF;
+	RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
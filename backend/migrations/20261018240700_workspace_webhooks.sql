@@ -0,0 +1,32 @@
+-- Webhooks can belong to a workspace as well as to a user. A workspace's owners and admins subscribe it
+-- to the events of its projects, whoever caused them; a webhook without a workspace stays the user's
+-- own and follows them into every workspace they are a member of, as before.
+ALTER TABLE webhooks ADD COLUMN workspace_id UUID REFERENCES workspaces(id) ON DELETE CASCADE;
+
+CREATE INDEX webhooks_workspace_id_idx ON webhooks (workspace_id) WHERE workspace_id IS NOT NULL;
+
+ALTER TABLE webhooks ENABLE ROW LEVEL SECURITY;
+ALTER TABLE webhooks FORCE ROW LEVEL SECURITY;
+
+-- Like the other tenant tables, except that a user's own webhooks belong to no workspace and are
+-- reachable from any transaction; the queries filter those by user, as they did before.
+CREATE POLICY webhooks_tenant ON webhooks
+	USING (app_bypass_rls() OR workspace_id IS NULL OR workspace_id = app_current_workspace_id());
+
+-- A workspace's webhooks only hear of its own projects, and only while whoever set them up is still
+-- a member. Events without a project remain for administrators' own webhooks.
+CREATE OR REPLACE FUNCTION queue_webhook_deliveries(event TEXT, project UUID, payload JSONB) RETURNS void AS $$
+	INSERT INTO webhook_deliveries (webhook_id, event_type, data)
+	SELECT w.id, event, payload
+	FROM webhooks w
+	JOIN users u ON u.id = w.user_id AND u.deleted_at IS NULL
+	WHERE w.disabled_at IS NULL
+		AND event = ANY (w.events)
+		AND CASE
+			WHEN project IS NULL THEN w.workspace_id IS NULL AND u.is_admin
+			ELSE EXISTS (SELECT 1 FROM projects p
+				JOIN workspace_members m ON m.workspace_id = p.workspace_id
+				WHERE p.id = project AND m.user_id = u.id
+					AND (w.workspace_id IS NULL OR w.workspace_id = p.workspace_id))
+		END
+$$ LANGUAGE sql;
@@ -0,0 +1,41 @@
+use axum::{
+	extract::{FromRef, FromRequestParts},
+	http::request::Parts,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	auth::jwt::AuthUser,
+	util::error::{AppError, AppResult, ErrorCode},
+};
+
+/// # AdminUser
+/// Like `AuthUser`, but also rejects users without `users.is_admin` with 403.
+/// The flag is read on every request, so revoking it takes effect before the token expires.
+pub struct AdminUser(pub Uuid);
+
+impl<S> FromRequestParts<S> for AdminUser
+where
+	PgPool: FromRef<S>,
+	S: Send + Sync,
+{
+	type Rejection = AppError;
+
+	async fn from_request_parts(parts: &mut Parts, state: &S) -> AppResult<Self> {
+		let AuthUser(user_id) = AuthUser::from_request_parts(parts, state).await?;
+		let pool = PgPool::from_ref(state);
+		let is_admin = sqlx::query_scalar::<_, bool>(
+			"SELECT is_admin FROM users WHERE id = $1 AND deleted_at IS NULL"
+		)
+			.bind(user_id)
+			.fetch_optional(&pool)
+			.await
+			.map_err(|err| AppError::database("Failed to check admin flag", err))?
+			.unwrap_or(false);
+
+		match is_admin {
+			true => Ok(AdminUser(user_id)),
+			false => Err(AppError::Forbidden(ErrorCode::Forbidden, "Administrator access required".into())),
+		}
+	}
+}
@@ -5,18 +5,26 @@ use argon2::{
 	},
 	Argon2
 };
+use std::time::Instant;
+use crate::util::metrics::record_password_hash;
 
 pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
 	let salt = SaltString::generate(&mut OsRng);
 	let argon2 = Argon2::default();
+	let started = Instant::now();
 
 	let password_hash = argon2.hash_password(password.as_bytes(), &salt)?;
+	record_password_hash("hash", started.elapsed());
 	Ok(password_hash.to_string())
 }
 
 pub fn verify_password(password: &str, hash: &str) -> Result<bool, Error> {
 	let parsed_hash = PasswordHash::new(hash)?;
-	Ok(Argon2::default().verify_password(password.as_bytes(), &parsed_hash).is_ok())
+	let started = Instant::now();
+	let is_valid = Argon2::default().verify_password(password.as_bytes(), &parsed_hash).is_ok();
+
+	record_password_hash("verify", started.elapsed());
+	Ok(is_valid)
 }
 
 #[cfg(test)]
@@ -10,7 +10,7 @@ use serde::{Serialize, Deserialize};
 use uuid::Uuid;
 use crate::{
 	models::user::{User, PublicUser},
-	util::error::{AppError, AppResult}
+	util::error::{AppError, AppResult, ErrorCode, InternalError}
 };
 
 const JWT_EXPIRATION_HOURS: i64 = 24;
@@ -19,6 +19,9 @@ const JWT_EXPIRATION_HOURS: i64 = 24;
 pub struct Claims {
 	pub sub: Uuid,
 	pub exp: usize,
+	/// Active workspace of tokens issued by `POST /workspaces/{id}/switch`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub workspace: Option<Uuid>,
 }
 pub struct AuthUser(pub Uuid);
 
@@ -44,49 +47,59 @@ where
 		parts: &mut Parts,
 		_state: &S,
 	) -> AppResult<Self> {
-		let auth_result = parts.headers.get("authorization")
-			.and_then(|h| h.to_str().ok())
-			.ok_or(AppError::Auth("Missing authorization header".into()))
-			.and_then(|header| {
-				header.strip_prefix("Bearer ")
-					.ok_or(AppError::Auth("Invalid authorization header".into()))
-			});
-		let token = match auth_result {
-			Ok(t) => t,
-			Err(e) => return Err(e),
-		};
-		let secret = std::env::var("JWT_SECRET")
-			.map_err(|_| AppError::Internal("JWT secret not configured".into()))?;
-		let token_data = decode::<Claims>(
-			token,
-			&DecodingKey::from_secret(secret.as_bytes()),
-			&Validation::new(Algorithm::HS256),
-		).map_err(|_| AppError::Auth("Invalid or expired token".into()))?;
-
-		Ok(AuthUser(token_data.claims.sub))
+		bearer_claims(parts).map(|claims| AuthUser(claims.sub))
 	}
 }
 
+/// Claims of the `Authorization: Bearer` token of a request.
+pub fn bearer_claims(parts: &Parts) -> AppResult<Claims> {
+	let token = parts.headers.get("authorization")
+		.and_then(|h| h.to_str().ok())
+		.ok_or(AppError::Auth(ErrorCode::MissingToken, "Missing authorization header".into()))
+		.and_then(|header| {
+			header.strip_prefix("Bearer ")
+				.ok_or(AppError::Auth(ErrorCode::InvalidToken, "Invalid authorization header".into()))
+		})?;
+
+	validate_jwt(token)
+}
+
 pub fn generate_jwt_token(user: &User) -> AppResult<String> {
+	encode_claims(user.id, None)
+}
+
+/// A token for `user_id` that carries `workspace_id` as the active workspace.
+pub fn generate_workspace_token(user_id: Uuid, workspace_id: Uuid) -> AppResult<String> {
+	encode_claims(user_id, Some(workspace_id))
+}
+
+fn encode_claims(user_id: Uuid, workspace: Option<Uuid>) -> AppResult<String> {
 	let secret = std::env::var("JWT_SECRET")
-		.map_err(|_| AppError::Internal("JWT secret not set".into()))?;
+		.map_err(|err| AppError::Internal(
+			InternalError::new(ErrorCode::Misconfigured, "JWT secret not set").with_source(err)
+		))?;
 	let exp = (chrono::Utc::now() + chrono::Duration::hours(JWT_EXPIRATION_HOURS))
 		.timestamp() as usize;
 	let claims = Claims {
-		sub: user.id,
+		sub: user_id,
 		exp,
+		workspace,
 	};
 
 	encode(
 		&Header::default(),
 		&claims,
 		&EncodingKey::from_secret(secret.as_bytes()),
-	).map_err(|_| AppError::Internal("Failed to generate token".into()))
+	).map_err(|err| AppError::Internal(
+		InternalError::new(ErrorCode::TokenGenerationFailed, "Failed to generate token").with_source(err)
+	))
 }
 
 pub fn validate_jwt(token: &str) -> AppResult<Claims> {
 	let secret = std::env::var("JWT_SECRET")
-		.map_err(|_| AppError::Internal("JWT secret not configured".into()))?;
+		.map_err(|err| AppError::Internal(
+				InternalError::new(ErrorCode::Misconfigured, "JWT secret not configured").with_source(err)
+			))?;
 
 	decode::<Claims>(
 		token,
@@ -94,7 +107,7 @@ pub fn validate_jwt(token: &str) -> AppResult<Claims> {
 		&Validation::new(Algorithm::HS256),
 	)
 	.map(|token_data| token_data.claims)
-	.map_err(|_| AppError::Auth("Invalid or expired token".into()))
+	.map_err(|_| AppError::Auth(ErrorCode::InvalidToken, "Invalid or expired token".into()))
 }
 
 #[cfg(test)]
@@ -109,10 +122,22 @@ mod tests {
 			email: "<Email>".into(),
 			password_hash: "<PasswordHash>".into(),
 			created_at: chrono::Utc::now().naive_utc(),
+			version: 1,
+			profile: Default::default(),
 		};
 		let token = generate_jwt_token(&user).unwrap();
 		let claims = validate_jwt(&token).unwrap();
 
 		assert_eq!(claims.sub, user.id);
 	}
+
+	#[test]
+	fn test_workspace_token_carries_workspace() {
+		let user_id = Uuid::new_v4();
+		let workspace_id = Uuid::new_v4();
+		let token = generate_workspace_token(user_id, workspace_id).unwrap();
+		let claims = validate_jwt(&token).unwrap();
+
+		assert_eq!((claims.sub, claims.workspace), (user_id, Some(workspace_id)));
+	}
 }
\ No newline at end of file
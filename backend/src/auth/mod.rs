@@ -1,2 +1,4 @@
+pub mod admin;
 pub mod hash;
-pub mod jwt;
\ No newline at end of file
+pub mod jwt;
+pub mod workspace;
\ No newline at end of file
@@ -0,0 +1,48 @@
+use axum::{
+	extract::{FromRef, FromRequestParts},
+	http::request::Parts,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	auth::jwt::bearer_claims,
+	util::{
+		error::{AppError, AppResult, ErrorCode},
+		workspace_service,
+	},
+};
+
+/// Names the active workspace of a single request, ahead of the one in the token.
+pub static WORKSPACE_HEADER: &str = "X-Workspace-Id";
+
+/// # WorkspaceUser
+/// Like `AuthUser`, plus the workspace the request acts in. Role checks happen in the queries of each action.
+/// The workspace comes from `X-Workspace-Id`, else from the token, else it is the user's personal one.
+/// Membership is read on every request, so removing a member takes effect before their token expires.
+pub struct WorkspaceUser {
+	pub user_id: Uuid,
+	pub workspace_id: Uuid,
+}
+
+impl<S> FromRequestParts<S> for WorkspaceUser
+where
+	PgPool: FromRef<S>,
+	S: Send + Sync,
+{
+	type Rejection = AppError;
+
+	async fn from_request_parts(parts: &mut Parts, state: &S) -> AppResult<Self> {
+		let claims = bearer_claims(parts)?;
+		let requested = match parts.headers.get(WORKSPACE_HEADER) {
+			Some(value) => Some(value.to_str().ok()
+				.and_then(|value| value.trim().parse::<Uuid>().ok())
+				.ok_or(AppError::BadRequest(ErrorCode::BadRequest, format!("{} must be a UUID", WORKSPACE_HEADER)))?),
+			None => claims.workspace,
+		};
+		let pool = PgPool::from_ref(state);
+		let workspace_id = workspace_service::active_membership(&pool, &claims.sub, requested).await?
+			.ok_or(AppError::Forbidden(ErrorCode::NotWorkspaceMember, "Not a member of this workspace".into()))?;
+
+		Ok(WorkspaceUser { user_id: claims.sub, workspace_id })
+	}
+}
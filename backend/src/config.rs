@@ -0,0 +1,218 @@
+use std::{env, path::PathBuf, time::Duration};
+
+/// # Config
+/// Runtime settings read from the environment on startup.
+/// Optional subsystems (storage, mailer) are `None` when their variables are not set.
+#[derive(Clone, Debug)]
+pub struct Config {
+	pub bind_addr: String,
+	/// When set, `/metrics` is served on this address only instead of the public listener.
+	pub metrics_bind_addr: Option<String>,
+	pub health_timeout: Duration,
+	pub log_format: LogFormat,
+	/// `EnvFilter` directive, e.g. `info` or `backend=debug,tower_http=info`.
+	pub log_level: String,
+	pub storage: Option<StorageConfig>,
+	pub mailer: Option<MailerConfig>,
+	/// How long soft-deleted rows stay restorable before the purge removes them.
+	pub trash_retention: Duration,
+	/// Take the client IP from `X-Forwarded-For` instead of the socket. Only safe behind a proxy that sets it.
+	pub trust_proxy_headers: bool,
+	/// How long the response to a request with an `Idempotency-Key` is kept for replay.
+	pub idempotency_ttl: Duration,
+	/// How long a webhook receiver gets to answer a delivery.
+	pub webhook_timeout: Duration,
+	/// How often each replica looks for webhook deliveries that are due.
+	pub webhook_poll_interval: Duration,
+	/// Let webhooks reach loopback, private and link-local addresses. For development only: it lets any
+	/// user make the server send requests into its own network.
+	pub webhook_allow_private_targets: bool,
+	/// Background job workers in this process; 0 leaves the queue to `backend worker` processes.
+	pub job_concurrency: usize,
+	/// How long an idle worker waits before looking for due jobs again.
+	pub job_poll_interval: Duration,
+	/// Attempts running longer fail, and their job becomes claimable by another worker.
+	pub job_timeout: Duration,
+	/// How often replicas try to take the scheduler lock, and the leader looks for due scheduled tasks.
+	pub scheduler_interval: Duration,
+	/// Where the web app is served; links in emails point here.
+	pub app_url: String,
+	pub password_policy: PasswordPolicy,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+	Text,
+	Json,
+}
+
+impl std::str::FromStr for LogFormat {
+	type Err = String;
+
+	fn from_str(value: &str) -> Result<Self, Self::Err> {
+		match value.to_ascii_lowercase().as_str() {
+			"text" | "pretty" => Ok(LogFormat::Text),
+			"json" => Ok(LogFormat::Json),
+			other => Err(format!("Unknown log format: {}", other)),
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct StorageConfig {
+	pub root: PathBuf,
+}
+
+#[derive(Clone, Debug)]
+pub struct MailerConfig {
+	pub smtp_host: String,
+	pub smtp_port: Option<u16>,
+	pub from_address: Option<String>,
+	/// Both must be set to log in to the server.
+	pub username: Option<String>,
+	pub password: Option<String>,
+}
+
+/// What new passwords must satisfy. Lengths count characters, not bytes.
+#[derive(Clone, Debug)]
+pub struct PasswordPolicy {
+	pub min_length: usize,
+	pub max_length: usize,
+	pub require_uppercase: bool,
+	pub require_lowercase: bool,
+	pub require_digit: bool,
+	pub require_symbol: bool,
+	/// The most identical characters allowed in a row; `None` allows any number.
+	pub max_repeats: Option<usize>,
+	/// Lowest accepted zxcvbn score, from 0 (accept anything) to 4.
+	pub min_strength: u8,
+	/// Directory of breached password hashes in the k-anonymity range format: one `{PREFIX}.txt` per
+	/// first five hex digits of the SHA-1, with `SUFFIX:COUNT` lines. No check when `None`.
+	pub breached_dir: Option<PathBuf>,
+}
+
+impl Default for PasswordPolicy {
+	fn default() -> Self {
+		Self {
+			min_length: 8,
+			max_length: 128,
+			require_uppercase: true,
+			require_lowercase: true,
+			require_digit: true,
+			require_symbol: false,
+			max_repeats: Some(3),
+			min_strength: 2,
+			breached_dir: None,
+		}
+	}
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			bind_addr: "0.0.0.0:5000".into(),
+			metrics_bind_addr: None,
+			health_timeout: Duration::from_secs(2),
+			log_format: LogFormat::Text,
+			log_level: "info".into(),
+			storage: None,
+			mailer: None,
+			trash_retention: Duration::from_secs(30 * 24 * 60 * 60),
+			trust_proxy_headers: false,
+			idempotency_ttl: Duration::from_secs(24 * 60 * 60),
+			webhook_timeout: Duration::from_secs(10),
+			webhook_poll_interval: Duration::from_secs(5),
+			webhook_allow_private_targets: false,
+			job_concurrency: 4,
+			job_poll_interval: Duration::from_secs(1),
+			job_timeout: Duration::from_secs(5 * 60),
+			scheduler_interval: Duration::from_secs(15),
+			app_url: "http://localhost:5173".into(),
+			password_policy: PasswordPolicy::default(),
+		}
+	}
+}
+
+impl Config {
+	pub fn from_env() -> Self {
+		let defaults = Self::default();
+
+		Self {
+			bind_addr: env::var("BIND_ADDR").unwrap_or(defaults.bind_addr),
+			metrics_bind_addr: env::var("METRICS_BIND_ADDR").ok(),
+			health_timeout: env_parse::<u64>("HEALTH_TIMEOUT_MS")
+				.map(Duration::from_millis)
+				.unwrap_or(defaults.health_timeout),
+			log_format: env_parse("LOG_FORMAT").unwrap_or(defaults.log_format),
+			log_level: env::var("LOG_LEVEL").unwrap_or(defaults.log_level),
+			storage: env::var("STORAGE_DIR").ok()
+				.map(|root| StorageConfig { root: root.into() }),
+			mailer: env::var("SMTP_HOST").ok()
+				.map(|smtp_host| MailerConfig {
+					smtp_host,
+					smtp_port: env_parse("SMTP_PORT"),
+					from_address: env::var("MAIL_FROM").ok(),
+					username: env::var("SMTP_USERNAME").ok(),
+					password: env::var("SMTP_PASSWORD").ok(),
+				}),
+			trash_retention: env_parse::<u64>("TRASH_RETENTION_DAYS")
+				.map(|days| Duration::from_secs(days * 24 * 60 * 60))
+				.unwrap_or(defaults.trash_retention),
+			trust_proxy_headers: env_parse("TRUST_PROXY_HEADERS").unwrap_or(defaults.trust_proxy_headers),
+			idempotency_ttl: env_parse::<u64>("IDEMPOTENCY_KEY_TTL_HOURS")
+				.map(|hours| Duration::from_secs(hours * 60 * 60))
+				.unwrap_or(defaults.idempotency_ttl),
+			webhook_timeout: env_parse::<u64>("WEBHOOK_TIMEOUT_SECS")
+				.map(Duration::from_secs)
+				.unwrap_or(defaults.webhook_timeout),
+			webhook_poll_interval: env_parse::<u64>("WEBHOOK_POLL_INTERVAL_SECS")
+				.map(Duration::from_secs)
+				.unwrap_or(defaults.webhook_poll_interval),
+			webhook_allow_private_targets: env_parse("WEBHOOK_ALLOW_PRIVATE_TARGETS")
+				.unwrap_or(defaults.webhook_allow_private_targets),
+			job_concurrency: env_parse("JOB_CONCURRENCY").unwrap_or(defaults.job_concurrency),
+			job_poll_interval: env_parse::<u64>("JOB_POLL_INTERVAL_MS")
+				.map(Duration::from_millis)
+				.unwrap_or(defaults.job_poll_interval),
+			job_timeout: env_parse::<u64>("JOB_TIMEOUT_SECS")
+				.map(Duration::from_secs)
+				.unwrap_or(defaults.job_timeout),
+			scheduler_interval: env_parse::<u64>("SCHEDULER_INTERVAL_SECS")
+				.map(Duration::from_secs)
+				.unwrap_or(defaults.scheduler_interval),
+			app_url: env::var("APP_URL")
+				.map(|url| url.trim_end_matches('/').to_string())
+				.unwrap_or(defaults.app_url),
+			password_policy: PasswordPolicy::from_env(defaults.password_policy),
+		}
+	}
+}
+
+impl PasswordPolicy {
+	fn from_env(defaults: Self) -> Self {
+		Self {
+			min_length: env_parse("PASSWORD_MIN_LENGTH").unwrap_or(defaults.min_length),
+			max_length: env_parse("PASSWORD_MAX_LENGTH").unwrap_or(defaults.max_length),
+			require_uppercase: env_parse("PASSWORD_REQUIRE_UPPERCASE").unwrap_or(defaults.require_uppercase),
+			require_lowercase: env_parse("PASSWORD_REQUIRE_LOWERCASE").unwrap_or(defaults.require_lowercase),
+			require_digit: env_parse("PASSWORD_REQUIRE_DIGIT").unwrap_or(defaults.require_digit),
+			require_symbol: env_parse("PASSWORD_REQUIRE_SYMBOL").unwrap_or(defaults.require_symbol),
+			// 0 turns the limit off.
+			max_repeats: match env_parse::<usize>("PASSWORD_MAX_REPEATS") {
+				Some(0) => None,
+				Some(max) => Some(max),
+				None => defaults.max_repeats,
+			},
+			min_strength: env_parse::<u8>("PASSWORD_MIN_STRENGTH")
+				.map(|strength| strength.min(4))
+				.unwrap_or(defaults.min_strength),
+			breached_dir: env::var("PASSWORD_BREACHED_DIR").ok()
+				.map(PathBuf::from)
+				.or(defaults.breached_dir),
+		}
+	}
+}
+
+fn env_parse<T: std::str::FromStr>(key: &str) -> Option<T> {
+	env::var(key).ok().and_then(|value| value.parse().ok())
+}
@@ -1,40 +1,93 @@
 mod util;
+#[cfg(test)]
 mod tests;
 mod models;
 mod routes;
 mod auth;
+mod config;
+mod state;
+mod openapi;
 
-use std::env;
+use std::{env, net::SocketAddr};
 use tracing::{info, Level};
-use tracing_subscriber;
-use axum::{Router, routing::post, routing::get, routing::put, routing::delete};
+use axum::{Router, middleware, routing::get};
 use sqlx::postgres::PgPoolOptions;
-use tower_http::cors::CorsLayer;
+use tower_http::{
+	cors::CorsLayer,
+	trace::{DefaultOnResponse, TraceLayer},
+	LatencyUnit,
+};
 use dotenvy::dotenv;
+use crate::{config::Config, state::AppState, util::{events, idempotency, job_queue, logging, metrics, problem, request_id, scheduler, webhook_service}};
 
 #[tokio::main]
 async fn main() -> Result<(), sqlx::Error> {
-	tracing_subscriber::fmt().with_max_level(Level::INFO).init();
 	dotenv().ok();
 
+	let config = Config::from_env();
+	logging::init(&config);
+	metrics::spawn_upkeep();
+
 	let db_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
 	let pool = PgPoolOptions::new()
 		//.max_connections(5)
 		.connect(&db_url)
 		.await?;
-	let app = Router::new()
+
+	// `backend worker` only runs background jobs, so they can be scaled apart from the API.
+	if env::args().nth(1).as_deref() == Some("worker") {
+		let config = std::sync::Arc::new(Config { job_concurrency: config.job_concurrency.max(1), ..config });
+		job_queue::spawn_workers(pool, config, job_queue::registry());
+		tokio::signal::ctrl_c().await?;
+		return Ok(());
+	}
+
+	let bind_addr = config.bind_addr.clone();
+	let metrics_bind_addr = config.metrics_bind_addr.clone();
+	let state = AppState::new(pool, config);
+	webhook_service::spawn_dispatcher(state.pool.clone(), state.config.clone())
+		.expect("webhook client can be built");
+	events::spawn_listener(state.pool.clone(), state.events.clone()).await?;
+	job_queue::spawn_workers(state.pool.clone(), state.config.clone(), job_queue::registry());
+	scheduler::spawn_scheduler(state.pool.clone(), state.config.clone(), job_queue::registry());
+	let mut app = Router::new()
 		.without_v07_checks()
-		.route("/auth/signup", post(routes::auth::signup))
-		.route("/health", get(routes::auth::health_check))
+		.merge(routes::router());
+
+	match metrics_bind_addr {
+		Some(addr) => {
+			let metrics_app = Router::new()
+				.route("/metrics", get(routes::metrics::render))
+				.with_state(state.clone());
+			let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+
+			info!("Metrics are served on http://{}/metrics", addr);
+			tokio::spawn(async move {
+				axum::serve(listener, metrics_app).await.unwrap();
+			});
+		}
+		None => app = app.route("/metrics", get(routes::metrics::render)),
+	}
+
+	let app = app
+		.layer(middleware::from_fn_with_state(state.clone(), idempotency::handle))
+		.layer(middleware::from_fn(metrics::track_requests))
+		.layer(
+			TraceLayer::new_for_http()
+				.make_span_with(request_id::make_span)
+				.on_response(DefaultOnResponse::new().level(Level::INFO).latency_unit(LatencyUnit::Millis))
+		)
+		.layer(middleware::from_fn(problem::negotiate))
+		.layer(middleware::from_fn(request_id::propagate))
 		.layer(CorsLayer::permissive())
-		.with_state(pool);
-	let listener = tokio::net::TcpListener::bind("0.0.0.0:5000").await.unwrap();
+		.with_state(state);
+	let listener = tokio::net::TcpListener::bind(&bind_addr).await.unwrap();
 
-	info!("Server is running on http://0.0.0.0:5000");
+	info!("Server is running on http://{}", bind_addr);
 
-	axum::serve(listener, app)
+	axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
 		.await
 		.unwrap();
 
 	Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,169 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use crate::{
+	models::trash::TrashKind,
+	util::{
+		error::AppResult,
+		pagination::{validate_date_range, Keyset, ListFilter, SortField, SortType},
+	},
+};
+
+#[derive(Serialize, Deserialize, sqlx::Type, ToSchema, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum EntityType {
+	User,
+	Client,
+	Project,
+	Job,
+	TimeEntry,
+	Milestone,
+	/// Membership changes are recorded as updates of the workspace, under `members.<user id>`.
+	Workspace,
+}
+
+impl EntityType {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			EntityType::User => "user",
+			EntityType::Client => "client",
+			EntityType::Project => "project",
+			EntityType::Job => "job",
+			EntityType::TimeEntry => "time_entry",
+			EntityType::Milestone => "milestone",
+			EntityType::Workspace => "workspace",
+		}
+	}
+}
+
+impl From<TrashKind> for EntityType {
+	fn from(kind: TrashKind) -> Self {
+		match kind {
+			TrashKind::Client => EntityType::Client,
+			TrashKind::Project => EntityType::Project,
+			TrashKind::Job => EntityType::Job,
+			TrashKind::TimeEntry => EntityType::TimeEntry,
+			TrashKind::Milestone => EntityType::Milestone,
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, ToSchema, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum AuditAction {
+	Created,
+	Updated,
+	Deleted,
+	Restored,
+	/// Removed for good by the trash purge.
+	Purged,
+}
+
+/// # AuditEvent
+/// One change to one entity. `actor_id` is empty for changes made by the system.
+/// `changes` maps each touched field to its `before` and `after` value; secrets are redacted.
+#[derive(Serialize, Deserialize, FromRow, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct AuditEvent {
+	pub id: Uuid,
+	pub occurred_at: DateTime<Utc>,
+	pub actor_id: Option<Uuid>,
+	pub entity_type: EntityType,
+	pub entity_id: Uuid,
+	pub action: AuditAction,
+	#[schema(value_type = Object)]
+	#[ts(type = "Record<string, { before: unknown, after: unknown }>")]
+	pub changes: serde_json::Value,
+	pub request_id: Option<String>,
+	pub ip: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, TS, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum AuditSort {
+	#[default]
+	OccurredAt,
+}
+
+impl SortField for AuditSort {
+	fn column(&self) -> &'static str {
+		"a.occurred_at"
+	}
+
+	fn sql_type(&self) -> SortType {
+		SortType::Timestamp
+	}
+}
+
+impl Keyset for AuditEvent {
+	type Sort = AuditSort;
+
+	fn keyset_id(&self) -> Uuid {
+		self.id
+	}
+
+	fn keyset_value(&self, _sort: AuditSort) -> String {
+		self.occurred_at.to_rfc3339()
+	}
+}
+
+/// Query parameters of an entity's history.
+#[derive(Deserialize, IntoParams, TS, Default)]
+#[into_params(parameter_in = Query)]
+#[ts(export)]
+pub struct HistoryFilter {
+	#[ts(optional)]
+	pub sort: Option<AuditSort>,
+}
+
+impl ListFilter for HistoryFilter {
+	type Sort = AuditSort;
+
+	fn sort(&self) -> AuditSort {
+		self.sort.unwrap_or_default()
+	}
+}
+
+/// # AuditFilter
+/// Query parameters of the admin audit log. `from` and `to` are inclusive UTC dates.
+#[derive(Deserialize, IntoParams, TS, Default)]
+#[into_params(parameter_in = Query)]
+#[ts(export)]
+pub struct AuditFilter {
+	#[ts(optional)]
+	pub sort: Option<AuditSort>,
+	#[ts(optional)]
+	pub actor_id: Option<Uuid>,
+	#[ts(optional)]
+	pub entity_type: Option<EntityType>,
+	#[ts(optional)]
+	pub entity_id: Option<Uuid>,
+	#[ts(optional)]
+	pub action: Option<AuditAction>,
+	#[ts(optional)]
+	pub request_id: Option<String>,
+	#[ts(optional)]
+	pub from: Option<NaiveDate>,
+	#[ts(optional)]
+	pub to: Option<NaiveDate>,
+}
+
+impl ListFilter for AuditFilter {
+	type Sort = AuditSort;
+
+	fn sort(&self) -> AuditSort {
+		self.sort.unwrap_or_default()
+	}
+
+	fn validate(&self) -> AppResult<()> {
+		validate_date_range(self.from, self.to)
+	}
+}
@@ -0,0 +1,99 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use crate::util::pagination::{Keyset, ListFilter, SortField, SortType};
+
+#[derive(Serialize, Deserialize, sqlx::Type, ToSchema, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum JobStatus {
+	/// Waiting for `run_at`, either new or to be retried.
+	Queued,
+	Running,
+	Succeeded,
+	/// Failed on its last attempt, or no handler knows its kind. Only an administrator retries it.
+	Dead,
+}
+
+/// # BackgroundJob
+/// One unit of work in the queue. `last_error` is the failure of the most recent attempt.
+#[derive(Serialize, Deserialize, FromRow, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct BackgroundJob {
+	pub id: Uuid,
+	pub kind: String,
+	/// `null` for jobs whose payload holds secrets, like emails.
+	#[schema(value_type = Option<Object>)]
+	#[ts(type = "Record<string, unknown> | null")]
+	pub payload: Option<Value>,
+	pub status: JobStatus,
+	pub attempts: i32,
+	pub max_attempts: i32,
+	pub run_at: DateTime<Utc>,
+	pub last_error: Option<String>,
+	pub created_at: DateTime<Utc>,
+	pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, TS, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum JobSort {
+	#[default]
+	CreatedAt,
+	RunAt,
+}
+
+impl SortField for JobSort {
+	fn column(&self) -> &'static str {
+		match self {
+			JobSort::CreatedAt => "x.created_at",
+			JobSort::RunAt => "x.run_at",
+		}
+	}
+
+	fn sql_type(&self) -> SortType {
+		SortType::Timestamp
+	}
+}
+
+impl Keyset for BackgroundJob {
+	type Sort = JobSort;
+
+	fn keyset_id(&self) -> Uuid {
+		self.id
+	}
+
+	fn keyset_value(&self, sort: JobSort) -> String {
+		match sort {
+			JobSort::CreatedAt => self.created_at.to_rfc3339(),
+			JobSort::RunAt => self.run_at.to_rfc3339(),
+		}
+	}
+}
+
+/// Query parameters of the admin job list.
+#[derive(Deserialize, IntoParams, TS, Default)]
+#[into_params(parameter_in = Query)]
+#[ts(export)]
+pub struct JobFilter {
+	#[ts(optional)]
+	pub sort: Option<JobSort>,
+	#[ts(optional)]
+	pub status: Option<JobStatus>,
+	#[ts(optional)]
+	pub kind: Option<String>,
+}
+
+impl ListFilter for JobFilter {
+	type Sort = JobSort;
+
+	fn sort(&self) -> JobSort {
+		self.sort.unwrap_or_default()
+	}
+}
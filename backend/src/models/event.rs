@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use crate::models::audit::{AuditAction, EntityType};
+
+/// # DomainEvent
+/// A committed change to project data, as pushed to `/events` subscribers.
+/// Only identifies the change; fetch the entity for its current state.
+#[derive(Serialize, Deserialize, ToSchema, TS, Clone, Debug, PartialEq)]
+#[ts(export)]
+pub struct DomainEvent {
+	pub entity_type: EntityType,
+	pub entity_id: Uuid,
+	pub action: AuditAction,
+	/// The project the entity belongs to.
+	pub project_id: Uuid,
+	/// The workspace of the project; subscribers only hear about their active workspace.
+	pub workspace_id: Uuid,
+	/// Version of the row after the change; the last version for purged rows.
+	#[ts(type = "number")]
+	pub version: i64,
+}
@@ -0,0 +1,76 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, ToSchema, TS)]
+#[ts(export)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+	Ok,
+	/// The component is not configured on this instance and is not required.
+	Disabled,
+	Error,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema, TS)]
+#[ts(export)]
+pub struct ComponentHealth {
+	pub status: HealthStatus,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[ts(optional, type = "number")]
+	pub latency_ms: Option<u64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[ts(optional)]
+	pub detail: Option<String>,
+}
+
+impl ComponentHealth {
+	pub fn ok() -> Self {
+		Self { status: HealthStatus::Ok, latency_ms: None, detail: None }
+	}
+
+	pub fn disabled(detail: impl Into<String>) -> Self {
+		Self { status: HealthStatus::Disabled, latency_ms: None, detail: Some(detail.into()) }
+	}
+
+	pub fn error(detail: impl Into<String>) -> Self {
+		Self { status: HealthStatus::Error, latency_ms: None, detail: Some(detail.into()) }
+	}
+
+	pub fn with_latency(mut self, latency_ms: u64) -> Self {
+		self.latency_ms = Some(latency_ms);
+		self
+	}
+}
+
+/// # ReadinessReport
+/// Per-component result of the readiness probe.
+/// The overall `status` is `error` as soon as one component reports `error`.
+#[derive(Serialize, Deserialize, Debug, ToSchema, TS)]
+#[ts(export)]
+pub struct ReadinessReport {
+	pub status: HealthStatus,
+	pub components: BTreeMap<String, ComponentHealth>,
+}
+
+impl ReadinessReport {
+	pub fn from_components(components: BTreeMap<String, ComponentHealth>) -> Self {
+		let status = match components.values().any(|c| c.status == HealthStatus::Error) {
+			true => HealthStatus::Error,
+			false => HealthStatus::Ok,
+		};
+
+		Self { status, components }
+	}
+
+	pub fn is_ready(&self) -> bool {
+		self.status != HealthStatus::Error
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema, TS)]
+#[ts(export)]
+pub struct LivenessReport {
+	pub status: HealthStatus,
+}
@@ -1,2 +1,14 @@
 pub mod user;
-pub mod response;
\ No newline at end of file
+pub mod response;
+pub mod health;
+pub mod project;
+pub mod time_entry;
+pub mod search;
+pub mod trash;
+pub mod audit;
+pub mod sync;
+pub mod event;
+pub mod webhook;
+pub mod background_job;
+pub mod schedule;
+pub mod workspace;
\ No newline at end of file
@@ -0,0 +1,120 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use crate::util::{
+	error::AppResult,
+	pagination::{validate_date_range, Keyset, ListFilter, SortField, SortType},
+};
+
+/// Mirrors the `project_status` Postgres enum shared by projects, jobs and milestones.
+#[derive(Serialize, Deserialize, sqlx::Type, ToSchema, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "project_status", rename_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[ts(export)]
+pub enum ProjectStatus {
+	Active,
+	Completed,
+	OnHold,
+	Archived,
+}
+
+#[derive(Serialize, Deserialize, FromRow, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct Project {
+	pub id: Uuid,
+	pub name: String,
+	pub description: Option<String>,
+	pub client_id: Uuid,
+	#[schema(value_type = Option<String>)]
+	#[ts(type = "string | null")]
+	pub total_budget: Option<Decimal>,
+	#[schema(value_type = Option<String>)]
+	#[ts(type = "string | null")]
+	pub default_hourly_rate: Option<Decimal>,
+	pub is_fixed_price: bool,
+	pub start_date: Option<NaiveDate>,
+	pub end_date: Option<NaiveDate>,
+	pub status: ProjectStatus,
+	pub created_at: DateTime<Utc>,
+	/// `null` once the account that created the project is gone.
+	pub created_by: Option<Uuid>,
+	pub workspace_id: Uuid,
+	/// Also sent as the `ETag` of `GET /projects/{id}`; changes must send it back in `If-Match`.
+	#[ts(type = "number")]
+	pub version: i64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, TS, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum ProjectSort {
+	#[default]
+	CreatedAt,
+	Name,
+}
+
+impl SortField for ProjectSort {
+	fn column(&self) -> &'static str {
+		match self {
+			ProjectSort::CreatedAt => "p.created_at",
+			ProjectSort::Name => "p.name",
+		}
+	}
+
+	fn sql_type(&self) -> SortType {
+		match self {
+			ProjectSort::CreatedAt => SortType::Timestamp,
+			ProjectSort::Name => SortType::Text,
+		}
+	}
+}
+
+/// # ProjectFilter
+/// Query parameters of `GET /projects`.
+/// `from` and `to` bound the start date and are inclusive; projects without one are left out when either is set.
+#[derive(Deserialize, IntoParams, TS, Default)]
+#[into_params(parameter_in = Query)]
+#[ts(export)]
+pub struct ProjectFilter {
+	#[ts(optional)]
+	pub sort: Option<ProjectSort>,
+	#[ts(optional)]
+	pub status: Option<ProjectStatus>,
+	#[ts(optional)]
+	pub client_id: Option<Uuid>,
+	#[ts(optional)]
+	pub from: Option<NaiveDate>,
+	#[ts(optional)]
+	pub to: Option<NaiveDate>,
+}
+
+impl ListFilter for ProjectFilter {
+	type Sort = ProjectSort;
+
+	fn sort(&self) -> ProjectSort {
+		self.sort.unwrap_or_default()
+	}
+
+	fn validate(&self) -> AppResult<()> {
+		validate_date_range(self.from, self.to)
+	}
+}
+
+impl Keyset for Project {
+	type Sort = ProjectSort;
+
+	fn keyset_id(&self) -> Uuid {
+		self.id
+	}
+
+	fn keyset_value(&self, sort: ProjectSort) -> String {
+		match sort {
+			ProjectSort::CreatedAt => self.created_at.to_rfc3339(),
+			ProjectSort::Name => self.name.clone(),
+		}
+	}
+}
@@ -1,18 +1,52 @@
-use serde::{Serialize, Deserialize, Deserializer, de::DeserializeOwned};
+use serde::{Serialize, Deserialize};
 use axum::{response::IntoResponse, Json};
-use axum::http::StatusCode;
-use crate::util::error::{AppError, AppResult};
-
-#[derive(Serialize, Deserialize)]
-pub struct ApiResponse<T> 
-where 
-	T: Serialize
-{
+use axum::http::{header, HeaderValue, StatusCode};
+use ts_rs::TS;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use crate::util::{
+	error::{AppError, AppResult, ErrorCode, FieldError},
+	metrics,
+	pagination::Page,
+	problem::{self, ProblemDetails, PROBLEM_JSON},
+	request_id
+};
+
+/// # ApiResponse
+/// Envelope around every JSON response.
+/// `data` is set on success; `error`, `code` and `details` are set on failure.
+/// List endpoints set `next_cursor` while more pages follow.
+#[derive(Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct ApiResponse<T> {
 	pub status: u16,
 	pub data: Option<T>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[ts(optional)]
+	pub next_cursor: Option<String>,
 	pub error: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[ts(optional)]
+	pub code: Option<ErrorCode>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	#[ts(as = "Option<_>", optional)]
+	pub details: Vec<FieldError>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[ts(optional)]
+	pub error_id: Option<Uuid>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[ts(optional)]
+	pub request_id: Option<String>,
 }
 
+/// Stand-in for `data` in the API docs on responses that never carry any, such as errors.
+/// Serializes to `null`, the same as the `()` handlers actually return.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct NoData;
+
+/// Envelope of a failed request, or of a success without a body.
+pub type EmptyResponse = ApiResponse<NoData>;
+
 impl<T> ApiResponse<T> 
 where 
 	T: Serialize
@@ -25,27 +59,30 @@ where
 		Self {
 			status: status.as_u16(),
 			data: Some(data),
+			next_cursor: None,
 			error: None,
+			code: None,
+			details: Vec::new(),
+			error_id: None,
+			request_id: None,
 		}
 	}
 
 	pub fn error(error: &AppError) -> Self {
-		let status = match error {
-			AppError::Auth(_) => StatusCode::UNAUTHORIZED,
-			AppError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
-			AppError::Validation(_) => StatusCode::BAD_REQUEST,
-			AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
-			AppError::NotFound(_) => StatusCode::NOT_FOUND,
-			AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
-			AppError::Forbidden(_) => StatusCode::FORBIDDEN,
-		};
+		let status = error.status_code();
 
 		error.log();
+		metrics::record_app_error(error.kind(), status.as_u16());
 
 		Self {
 			status: status.as_u16(),
 			data: None,
-			error: Some(error.to_string()),
+			next_cursor: None,
+			error: Some(error.public_message()),
+			code: Some(error.code()),
+			details: error.details().to_vec(),
+			error_id: error.error_id(),
+			request_id: request_id::current(),
 		}
 	}
 
@@ -56,14 +93,55 @@ where
 		}
 	}
 
-	pub fn is_success(&self) -> bool {
-		StatusCode::from_u16(self.status).unwrap().is_success()
+	/// Rebuilds the `AppError` this response was created from.
+	#[cfg(test)]
+	pub fn get_error(&self) -> Option<AppError> {
+		let error = self.error.as_ref()?;
+		let status = StatusCode::from_u16(self.status)
+			.unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+		let code = self.code.unwrap_or(ErrorCode::Internal);
+		let rebuilt = AppError::from_parts(status, code, String::new(), self.details.clone(), None);
+		// `error` holds `public_message`, which prefixes the message per variant.
+		let message = match rebuilt.error_id() {
+			Some(_) => rebuilt.message(),
+			None => {
+				let prefix = rebuilt.to_string();
+				error.strip_prefix(prefix.as_str()).unwrap_or(error).to_string()
+			}
+		};
+
+		Some(AppError::from_parts(status, code, message, self.details.clone(), self.error_id))
 	}
 
-	pub fn get_error(&self) -> Option<AppError> {
-		match &self.error {
-			Some(err) => Some(AppError::Internal(err.clone())),
-			None => None,
+	fn into_problem(self) -> ProblemDetails {
+		let status = StatusCode::from_u16(self.status)
+			.unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+		let code = self.code.unwrap_or(ErrorCode::Internal);
+
+		ProblemDetails {
+			problem_type: ProblemDetails::type_uri(code),
+			title: status.canonical_reason().unwrap_or("Error").into(),
+			status: status.as_u16(),
+			detail: self.error.unwrap_or_default(),
+			code,
+			errors: self.details,
+			error_id: self.error_id,
+			request_id: self.request_id,
+		}
+	}
+}
+
+impl<T> ApiResponse<Vec<T>>
+where
+	T: Serialize
+{
+	pub fn from_page(result: AppResult<Page<T>>) -> Self {
+		match result {
+			Ok(page) => Self {
+				next_cursor: page.next_cursor,
+				..Self::success(page.items)
+			},
+			Err(err) => Self::error(&err),
 		}
 	}
 }
@@ -76,6 +154,13 @@ where
 		let status = StatusCode::from_u16(self.status)
 			.unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
 
+		if self.error.is_some() && problem::preferred() {
+			let mut response = (status, Json(self.into_problem())).into_response();
+			response.headers_mut()
+				.insert(header::CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+			return response;
+		}
+
 		(status, Json(self)).into_response()
 	}
 }
@@ -106,19 +191,49 @@ mod tests {
 
 	#[test]
 	fn test_api_response_error() {
-		let resp: ApiResponse<serde_json::Value> = 
-			ApiResponse::error(&AppError::Internal("An error occurred".to_string()));
+		let error = AppError::internal("An error occurred");
+		let resp: ApiResponse<serde_json::Value> = ApiResponse::error(&error);
 		assert_eq!(resp.status, 500);
 		assert!(resp.data.is_none());
-		assert_eq!(resp.error, Some("Internal error: An error occurred".to_string()));
+		assert_eq!(resp.error, Some(format!("Internal server error (error id: {})", error.error_id().unwrap())));
+		assert_eq!(resp.error_id, error.error_id());
 	}
 
 	#[test]
 	fn test_api_response_not_found() {
 		let resp: ApiResponse<serde_json::Value> = 
-			ApiResponse::error(&AppError::NotFound("User not found".to_string()));
+			ApiResponse::error(&AppError::NotFound(ErrorCode::UserNotFound, "User not found".to_string()));
 		assert_eq!(resp.status, 404);
 		assert!(resp.data.is_none());
 		assert_eq!(resp.error, Some("Not found: User not found".to_string()));
 	}
-}
\ No newline at end of file
+
+	#[test]
+	fn test_api_response_error_code() {
+		let resp: ApiResponse<serde_json::Value> = 
+			ApiResponse::error(&AppError::Auth(ErrorCode::InvalidCredentials, "Invalid credentials".to_string()));
+		let json = serde_json::to_value(&resp).unwrap();
+
+		assert_eq!(json["code"], "auth.invalid_credentials");
+		assert!(json.get("details").is_none());
+	}
+
+	#[test]
+	fn test_api_response_get_error() {
+		let resp: ApiResponse<serde_json::Value> = 
+			ApiResponse::error(&AppError::NotFound(ErrorCode::UserNotFound, "User not found".to_string()));
+		let error = resp.get_error().unwrap();
+
+		assert!(matches!(
+			error,
+			AppError::NotFound(ErrorCode::UserNotFound, ref msg) if msg == "User not found"
+		));
+
+		let validation = AppError::Validation(vec![
+			FieldError::new("password", ErrorCode::PasswordTooShort, "Too short"),
+		]);
+		let resp: ApiResponse<serde_json::Value> = ApiResponse::error(&validation);
+
+		assert!(matches!(resp.get_error(), Some(AppError::Validation(fields)) if fields == validation.details()));
+	}
+}
@@ -0,0 +1,148 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use crate::{
+	models::background_job::JobStatus,
+	util::pagination::{Keyset, ListFilter, SortField, SortType},
+};
+
+/// # ScheduledTask
+/// Recurring work: a background job of `job_kind` is queued with `payload` whenever `schedule` fires.
+#[derive(Serialize, Deserialize, FromRow, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct ScheduledTask {
+	pub id: Uuid,
+	pub name: String,
+	pub description: Option<String>,
+	/// Cron expression in UTC, with or without a leading seconds field.
+	pub schedule: String,
+	pub job_kind: String,
+	#[schema(value_type = Object)]
+	#[ts(type = "Record<string, unknown>")]
+	pub payload: Value,
+	pub enabled: bool,
+	pub next_run_at: DateTime<Utc>,
+	pub last_run_at: Option<DateTime<Utc>>,
+	pub created_at: DateTime<Utc>,
+}
+
+/// # UpdateScheduledTaskPayload
+/// Fields left out stay as they are. A new schedule takes effect from now on.
+#[derive(Serialize, Deserialize, ToSchema, TS, Debug, Default)]
+#[ts(export)]
+pub struct UpdateScheduledTaskPayload {
+	#[serde(default)]
+	#[ts(optional)]
+	pub schedule: Option<String>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub enabled: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, ToSchema, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum RunTrigger {
+	Schedule,
+	/// An administrator asked to run it now.
+	Manual,
+}
+
+/// # ScheduledTaskRun
+/// One time a task fired. The outcome is that of the queued job, as long as the job is kept.
+#[derive(Serialize, Deserialize, FromRow, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct ScheduledTaskRun {
+	pub id: Uuid,
+	pub task_id: Uuid,
+	pub job_id: Option<Uuid>,
+	pub trigger: RunTrigger,
+	pub triggered_by: Option<Uuid>,
+	pub created_at: DateTime<Utc>,
+	pub job_status: Option<JobStatus>,
+	pub job_error: Option<String>,
+	pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, TS, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum ScheduleSort {
+	#[default]
+	CreatedAt,
+}
+
+impl SortField for ScheduleSort {
+	fn column(&self) -> &'static str {
+		"x.created_at"
+	}
+
+	fn sql_type(&self) -> SortType {
+		SortType::Timestamp
+	}
+}
+
+impl Keyset for ScheduledTask {
+	type Sort = ScheduleSort;
+
+	fn keyset_id(&self) -> Uuid {
+		self.id
+	}
+
+	fn keyset_value(&self, _sort: ScheduleSort) -> String {
+		self.created_at.to_rfc3339()
+	}
+}
+
+impl Keyset for ScheduledTaskRun {
+	type Sort = ScheduleSort;
+
+	fn keyset_id(&self) -> Uuid {
+		self.id
+	}
+
+	fn keyset_value(&self, _sort: ScheduleSort) -> String {
+		self.created_at.to_rfc3339()
+	}
+}
+
+/// Query parameters of the scheduled task list.
+#[derive(Deserialize, IntoParams, TS, Default)]
+#[into_params(parameter_in = Query)]
+#[ts(export)]
+pub struct ScheduleFilter {
+	#[ts(optional)]
+	pub sort: Option<ScheduleSort>,
+}
+
+impl ListFilter for ScheduleFilter {
+	type Sort = ScheduleSort;
+
+	fn sort(&self) -> ScheduleSort {
+		self.sort.unwrap_or_default()
+	}
+}
+
+/// Query parameters of a task's run history.
+#[derive(Deserialize, IntoParams, TS, Default)]
+#[into_params(parameter_in = Query)]
+#[ts(export)]
+pub struct RunFilter {
+	#[ts(optional)]
+	pub trigger: Option<RunTrigger>,
+	#[ts(optional)]
+	pub sort: Option<ScheduleSort>,
+}
+
+impl ListFilter for RunFilter {
+	type Sort = ScheduleSort;
+
+	fn sort(&self) -> ScheduleSort {
+		self.sort.unwrap_or_default()
+	}
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, sqlx::Type, ToSchema, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum SearchKind {
+	Client,
+	Project,
+	Job,
+	TimeEntry,
+}
+
+/// # SearchResult
+/// One match, ranked against all other kinds.
+/// `highlight` is HTML-escaped text with the matched words wrapped in `<mark>` tags.
+/// `project_id` points at the enclosing project for jobs and time entries.
+#[derive(Serialize, Deserialize, FromRow, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct SearchResult {
+	pub kind: SearchKind,
+	pub id: Uuid,
+	pub project_id: Option<Uuid>,
+	pub title: String,
+	pub highlight: String,
+	pub rank: f32,
+}
+
+/// # SearchParams
+/// `q` uses web search syntax: `"quoted phrases"`, `or` and `-excluded` words.
+#[derive(Deserialize, IntoParams, TS)]
+#[into_params(parameter_in = Query)]
+#[ts(export)]
+pub struct SearchParams {
+	#[serde(default)]
+	pub q: String,
+	/// Only return results of this kind.
+	#[serde(rename = "type")]
+	#[ts(optional)]
+	pub kind: Option<SearchKind>,
+	/// 1 to 50. Defaults to 20.
+	#[ts(optional)]
+	pub limit: Option<u32>,
+}
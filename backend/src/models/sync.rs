@@ -0,0 +1,176 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use crate::models::audit::EntityType;
+
+/// The tables an offline client keeps a copy of.
+#[derive(Serialize, Deserialize, sqlx::Type, ToSchema, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum SyncKind {
+	Client,
+	Project,
+	Job,
+	TimeEntry,
+	Milestone,
+}
+
+impl SyncKind {
+	/// Parents before children, the order in which a fresh client can insert them.
+	pub const ALL: [SyncKind; 5] = [
+		SyncKind::Client,
+		SyncKind::Project,
+		SyncKind::Job,
+		SyncKind::TimeEntry,
+		SyncKind::Milestone,
+	];
+
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			SyncKind::Client => "client",
+			SyncKind::Project => "project",
+			SyncKind::Job => "job",
+			SyncKind::TimeEntry => "time_entry",
+			SyncKind::Milestone => "milestone",
+		}
+	}
+}
+
+impl From<SyncKind> for EntityType {
+	fn from(kind: SyncKind) -> Self {
+		match kind {
+			SyncKind::Client => EntityType::Client,
+			SyncKind::Project => EntityType::Project,
+			SyncKind::Job => EntityType::Job,
+			SyncKind::TimeEntry => EntityType::TimeEntry,
+			SyncKind::Milestone => EntityType::Milestone,
+		}
+	}
+}
+
+/// Query parameters of `GET /sync`.
+#[derive(Deserialize, IntoParams, TS, Default)]
+#[into_params(parameter_in = Query)]
+#[ts(export)]
+pub struct SyncParams {
+	/// `cursor` of the previous sync. Leave out for a full copy.
+	#[ts(optional)]
+	pub since: Option<String>,
+}
+
+/// # SyncRecord
+/// A row as the server has it. `data` holds the same fields as the REST representation of the kind;
+/// `deleted_at` is set for rows in the trash.
+#[derive(Serialize, Deserialize, FromRow, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct SyncRecord {
+	pub kind: SyncKind,
+	pub id: Uuid,
+	#[ts(type = "number")]
+	pub version: i64,
+	pub deleted_at: Option<DateTime<Utc>>,
+	#[schema(value_type = Object)]
+	#[ts(type = "Record<string, unknown>")]
+	pub data: Value,
+}
+
+/// # SyncChanges
+/// Everything that changed since the cursor. `created` and `updated` are both upserts for the client:
+/// a row counts as created while it is still at its first version. Rows may show up again on the next sync.
+/// With `reset` set the client must drop its copy first, because this is a full copy rather than a delta.
+#[derive(Serialize, Deserialize, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct SyncChanges {
+	pub created: Vec<SyncRecord>,
+	pub updated: Vec<SyncRecord>,
+	pub deleted: Vec<SyncRecord>,
+	/// Send back as `since` on the next sync.
+	pub cursor: String,
+	pub reset: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum SyncOperation {
+	/// Create the row under the client's id, or change the fields given in `data`.
+	Upsert,
+	/// Move the row to the trash.
+	Delete,
+}
+
+/// # SyncChange
+/// One change made offline. `base_version` is the version the change was made against and is required
+/// for rows that exist on the server; new rows leave it out.
+#[derive(Serialize, Deserialize, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct SyncChange {
+	pub kind: SyncKind,
+	pub id: Uuid,
+	pub op: SyncOperation,
+	#[serde(default)]
+	#[ts(type = "number", optional)]
+	pub base_version: Option<i64>,
+	#[serde(default)]
+	#[schema(value_type = Object)]
+	#[ts(type = "Record<string, unknown>")]
+	pub data: Value,
+}
+
+/// # SyncPush
+/// Body of `POST /sync`. Changes are applied in order, each on its own.
+#[derive(Serialize, Deserialize, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct SyncPush {
+	pub changes: Vec<SyncChange>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum SyncOutcome {
+	Applied,
+	/// The row changed on the server since `base_version`. The server's copy wins and comes back in `server`.
+	Conflict,
+	/// Not allowed or invalid; `error` says why.
+	Rejected,
+}
+
+/// # SyncResult
+/// What became of one `SyncChange`.
+#[derive(Serialize, Deserialize, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct SyncResult {
+	pub kind: SyncKind,
+	pub id: Uuid,
+	pub outcome: SyncOutcome,
+	/// Version of the row after the change.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[ts(type = "number", optional)]
+	pub version: Option<i64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[ts(optional)]
+	pub server: Option<SyncRecord>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[ts(optional)]
+	pub error: Option<String>,
+}
+
+impl SyncResult {
+	pub fn applied(change: &SyncChange, version: Option<i64>) -> Self {
+		Self { kind: change.kind, id: change.id, outcome: SyncOutcome::Applied, version, server: None, error: None }
+	}
+
+	pub fn conflict(change: &SyncChange, server: SyncRecord) -> Self {
+		Self { kind: change.kind, id: change.id, outcome: SyncOutcome::Conflict, version: None, server: Some(server), error: None }
+	}
+
+	pub fn rejected(change: &SyncChange, error: impl Into<String>) -> Self {
+		Self { kind: change.kind, id: change.id, outcome: SyncOutcome::Rejected, version: None, server: None, error: Some(error.into()) }
+	}
+}
@@ -0,0 +1,100 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use crate::util::{
+	error::AppResult,
+	pagination::{validate_date_range, Keyset, ListFilter, SortField, SortType},
+};
+
+/// # TimeEntry
+/// `time_spent` is stored as an `INTERVAL` and exposed in whole seconds.
+#[derive(Serialize, Deserialize, FromRow, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct TimeEntry {
+	pub id: Uuid,
+	pub job_id: Uuid,
+	pub user_id: Uuid,
+	#[ts(type = "number")]
+	pub time_spent_seconds: i64,
+	pub description: Option<String>,
+	pub entry_date: NaiveDate,
+	pub created_at: DateTime<Utc>,
+	/// Also sent as the `ETag` of `GET /time-entries/{id}`; changes must send it back in `If-Match`.
+	#[ts(type = "number")]
+	pub version: i64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, TS, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum TimeEntrySort {
+	#[default]
+	EntryDate,
+	CreatedAt,
+}
+
+impl SortField for TimeEntrySort {
+	fn column(&self) -> &'static str {
+		match self {
+			TimeEntrySort::EntryDate => "t.entry_date",
+			TimeEntrySort::CreatedAt => "t.created_at",
+		}
+	}
+
+	fn sql_type(&self) -> SortType {
+		match self {
+			TimeEntrySort::EntryDate => SortType::Date,
+			TimeEntrySort::CreatedAt => SortType::Timestamp,
+		}
+	}
+}
+
+/// # TimeEntryFilter
+/// Query parameters of `GET /time-entries`. `from` and `to` bound the entry date and are inclusive.
+#[derive(Deserialize, IntoParams, TS, Default)]
+#[into_params(parameter_in = Query)]
+#[ts(export)]
+pub struct TimeEntryFilter {
+	#[ts(optional)]
+	pub sort: Option<TimeEntrySort>,
+	#[ts(optional)]
+	pub from: Option<NaiveDate>,
+	#[ts(optional)]
+	pub to: Option<NaiveDate>,
+	#[ts(optional)]
+	pub job_id: Option<Uuid>,
+	#[ts(optional)]
+	pub project_id: Option<Uuid>,
+	#[ts(optional)]
+	pub client_id: Option<Uuid>,
+}
+
+impl ListFilter for TimeEntryFilter {
+	type Sort = TimeEntrySort;
+
+	fn sort(&self) -> TimeEntrySort {
+		self.sort.unwrap_or_default()
+	}
+
+	fn validate(&self) -> AppResult<()> {
+		validate_date_range(self.from, self.to)
+	}
+}
+
+impl Keyset for TimeEntry {
+	type Sort = TimeEntrySort;
+
+	fn keyset_id(&self) -> Uuid {
+		self.id
+	}
+
+	fn keyset_value(&self, sort: TimeEntrySort) -> String {
+		match sort {
+			TimeEntrySort::EntryDate => self.entry_date.to_string(),
+			TimeEntrySort::CreatedAt => self.created_at.to_rfc3339(),
+		}
+	}
+}
@@ -0,0 +1,101 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use crate::util::pagination::{Keyset, ListFilter, SortField, SortType};
+
+#[derive(Serialize, Deserialize, sqlx::Type, ToSchema, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum TrashKind {
+	Client,
+	Project,
+	Job,
+	TimeEntry,
+	Milestone,
+}
+
+impl TrashKind {
+	pub const ALL: [TrashKind; 5] = [
+		TrashKind::Client,
+		TrashKind::Project,
+		TrashKind::Job,
+		TrashKind::TimeEntry,
+		TrashKind::Milestone,
+	];
+
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			TrashKind::Client => "client",
+			TrashKind::Project => "project",
+			TrashKind::Job => "job",
+			TrashKind::TimeEntry => "time_entry",
+			TrashKind::Milestone => "milestone",
+		}
+	}
+}
+
+/// # TrashItem
+/// A soft-deleted row the user may restore until `purge_at`.
+#[derive(Serialize, Deserialize, FromRow, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct TrashItem {
+	pub kind: TrashKind,
+	pub id: Uuid,
+	pub title: String,
+	pub deleted_at: DateTime<Utc>,
+	pub purge_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, TS, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum TrashSort {
+	#[default]
+	DeletedAt,
+}
+
+impl SortField for TrashSort {
+	fn column(&self) -> &'static str {
+		"trash.deleted_at"
+	}
+
+	fn sql_type(&self) -> SortType {
+		SortType::Timestamp
+	}
+}
+
+#[derive(Deserialize, IntoParams, TS, Default)]
+#[into_params(parameter_in = Query)]
+#[ts(export)]
+pub struct TrashFilter {
+	#[ts(optional)]
+	pub sort: Option<TrashSort>,
+	/// Only list deleted rows of this kind.
+	#[serde(rename = "type")]
+	#[ts(optional)]
+	pub kind: Option<TrashKind>,
+}
+
+impl ListFilter for TrashFilter {
+	type Sort = TrashSort;
+
+	fn sort(&self) -> TrashSort {
+		self.sort.unwrap_or_default()
+	}
+}
+
+impl Keyset for TrashItem {
+	type Sort = TrashSort;
+
+	fn keyset_id(&self) -> Uuid {
+		self.id
+	}
+
+	fn keyset_value(&self, _sort: TrashSort) -> String {
+		self.deleted_at.to_rfc3339()
+	}
+}
@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use ts_rs::TS;
+use utoipa::ToSchema;
 
 #[derive(Serialize, Deserialize, FromRow)]
 pub struct User {
@@ -9,16 +11,104 @@ pub struct User {
 	pub email: String,
 	pub password_hash: String,
 	pub created_at: NaiveDateTime,
+	pub version: i64,
+	#[sqlx(flatten)]
+	pub profile: UserProfile,
+}
+
+/// How dates are shown, e.g. on invoices.
+#[derive(Serialize, Deserialize, sqlx::Type, ToSchema, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum DateFormat {
+	/// 2026-10-18
+	YearMonthDay,
+	/// 18.10.2026
+	DayMonthYear,
+	/// 18/10/2026
+	DayMonthYearSlash,
+	/// 10/18/2026
+	MonthDayYear,
+}
+
+/// How amounts are shown: the digit group separator, then the decimal separator.
+#[derive(Serialize, Deserialize, sqlx::Type, ToSchema, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum NumberFormat {
+	/// 1,234.56
+	CommaPeriod,
+	/// 1.234,56
+	PeriodComma,
+	/// 1 234,56
+	SpaceComma,
+}
+
+/// # UserProfile
+/// Preferences that shape what the user sees, and the business details printed on their invoices.
+#[derive(Serialize, Deserialize, FromRow, ToSchema, TS, Clone, Debug, PartialEq)]
+#[ts(export)]
+pub struct UserProfile {
+	pub display_name: Option<String>,
+	pub avatar_url: Option<String>,
+	/// BCP 47 language tag, e.g. `nb-NO`.
+	pub locale: String,
+	/// IANA time zone, e.g. `Europe/Oslo`.
+	pub timezone: String,
+	/// ISO 4217 code used for new amounts.
+	pub currency: String,
+	pub date_format: DateFormat,
+	pub number_format: NumberFormat,
+	pub org_number: Option<String>,
+	pub vat_number: Option<String>,
+	/// An IBAN, or a domestic account number.
+	pub bank_account: Option<String>,
+	pub address_line1: Option<String>,
+	pub address_line2: Option<String>,
+	pub postal_code: Option<String>,
+	pub city: Option<String>,
+	/// ISO 3166-1 alpha-2 code, e.g. `NO`.
+	pub country: Option<String>,
+}
+
+/// The column defaults of a new account.
+impl Default for UserProfile {
+	fn default() -> Self {
+		UserProfile {
+			display_name: None,
+			avatar_url: None,
+			locale: "en".into(),
+			timezone: "UTC".into(),
+			currency: "EUR".into(),
+			date_format: DateFormat::YearMonthDay,
+			number_format: NumberFormat::CommaPeriod,
+			org_number: None,
+			vat_number: None,
+			bank_account: None,
+			address_line1: None,
+			address_line2: None,
+			postal_code: None,
+			city: None,
+			country: None,
+		}
+	}
 }
 
 /// # PublicUser
 /// A public representation of a user, excluding sensitive information like password hash.
 /// This is used for responses that do not require sensitive data.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
 pub struct PublicUser {
 	pub id: Uuid,
 	pub email: String,
 	pub created_at: NaiveDateTime,
+	/// Also sent as the `ETag`; changes to the user must send it back in `If-Match`.
+	#[ts(type = "number")]
+	pub version: i64,
+	pub profile: UserProfile,
 }
 
 impl From<&User> for PublicUser {
@@ -27,36 +117,110 @@ impl From<&User> for PublicUser {
 			id: user.id,
 			email: user.email.clone(),
 			created_at: user.created_at,
+			version: user.version,
+			profile: user.profile.clone(),
 		}
 	}
 }
 
-impl Into<PublicUser> for User {
-	fn into(self) -> PublicUser {
-		PublicUser::from(&self)
+impl From<User> for PublicUser {
+	fn from(user: User) -> Self {
+		PublicUser::from(&user)
 	}
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema, TS)]
+#[ts(export)]
 pub struct RegisterPayload {
 	pub email: String,
 	pub password: String,
 }
 
 /// # UpdateUserPayload
-/// Contains fields that the database will try to update.
-/// Fields that are `None` will not be updated.
-/// the `email` field is optional, but if provided, it must be unique.
-/// The `password` field is optional, but if provided, adhere to the password policy.
-/// If the `password` is provided, it will not be hashed here, but should be hashed in the handler before saving to the database.
-#[derive(Deserialize)]
+/// Profile fields to change with `PATCH /me`; fields left out stay as they are.
+/// Text is trimmed, and an empty string clears a field that may be empty.
+/// The email address and password have their own endpoints, which ask for the current password.
+#[derive(Serialize, Deserialize, ToSchema, TS, Debug, Default)]
+#[ts(export)]
 pub struct UpdateUserPayload {
-	pub email: Option<String>,
-	pub password: Option<String>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub display_name: Option<String>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub avatar_url: Option<String>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub locale: Option<String>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub timezone: Option<String>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub currency: Option<String>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub date_format: Option<DateFormat>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub number_format: Option<NumberFormat>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub org_number: Option<String>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub vat_number: Option<String>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub bank_account: Option<String>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub address_line1: Option<String>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub address_line2: Option<String>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub postal_code: Option<String>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub city: Option<String>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub country: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema, TS)]
+#[ts(export)]
 pub struct ChangePasswordPayload {
 	pub old_password: String,
 	pub new_password: String,
-}
\ No newline at end of file
+}
+
+/// # ChangeEmailPayload
+/// Asks to move the account to `new_email`. The current password guards against a hijacked session.
+#[derive(Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct ChangeEmailPayload {
+	pub new_email: String,
+	pub password: String,
+}
+
+/// # EmailChange
+/// A requested email change, waiting for the link sent to `new_email` to be followed before `expires_at`.
+#[derive(Serialize, Deserialize, FromRow, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct EmailChange {
+	pub id: Uuid,
+	pub new_email: String,
+	pub created_at: DateTime<Utc>,
+	pub expires_at: DateTime<Utc>,
+}
+
+/// # EmailChangeTokenPayload
+/// The token from the confirmation link sent to the new address, or the undo link sent to the old one.
+#[derive(Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct EmailChangeTokenPayload {
+	pub token: String,
+}
@@ -0,0 +1,211 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use crate::util::pagination::{Keyset, ListFilter, SortField, SortType};
+
+/// Events a webhook can subscribe to. Project events reach the webhooks of the project's workspace and
+/// of its members, `user.signed_up` reaches administrators' own.
+#[derive(Serialize, Deserialize, sqlx::Type, ToSchema, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "text")]
+#[ts(export)]
+pub enum WebhookEventType {
+	#[serde(rename = "time_entry.created")]
+	#[sqlx(rename = "time_entry.created")]
+	TimeEntryCreated,
+	#[serde(rename = "milestone.completed")]
+	#[sqlx(rename = "milestone.completed")]
+	MilestoneCompleted,
+	#[serde(rename = "user.signed_up")]
+	#[sqlx(rename = "user.signed_up")]
+	UserSignedUp,
+	/// Sent by the test endpoint only; can't be subscribed to.
+	#[serde(rename = "ping")]
+	#[sqlx(rename = "ping")]
+	Ping,
+}
+
+/// Whom a new webhook belongs to.
+#[derive(Serialize, Deserialize, ToSchema, TS, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum WebhookScope {
+	/// The user, who then hears of the projects of every workspace they are a member of.
+	#[default]
+	User,
+	/// The active workspace, which then hears of its own projects only. Needs the owner or admin role there.
+	Workspace,
+}
+
+/// # Webhook
+/// A subscription of the user's, or of a workspace they manage. `disabled_at` is set after too many failed
+/// deliveries in a row; re-enabling it resumes the deliveries that were still pending.
+#[derive(Serialize, Deserialize, FromRow, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct Webhook {
+	pub id: Uuid,
+	/// The workspace the webhook belongs to; none for the user's own.
+	pub workspace_id: Option<Uuid>,
+	pub url: String,
+	pub events: Vec<WebhookEventType>,
+	pub description: Option<String>,
+	pub consecutive_failures: i32,
+	pub disabled_at: Option<DateTime<Utc>>,
+	pub created_at: DateTime<Utc>,
+}
+
+/// # CreatedWebhook
+/// The new webhook with the secret its deliveries are signed with. The secret is only ever shown here.
+#[derive(Serialize, Deserialize, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct CreatedWebhook {
+	#[serde(flatten)]
+	pub webhook: Webhook,
+	pub secret: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct CreateWebhookPayload {
+	/// `http` or `https` URL the events are posted to. It must lead to a public address.
+	pub url: String,
+	pub events: Vec<WebhookEventType>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub description: Option<String>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub scope: Option<WebhookScope>,
+}
+
+/// # UpdateWebhookPayload
+/// Fields left out stay as they are. `enabled: true` also resets the failure count.
+#[derive(Serialize, Deserialize, ToSchema, TS, Debug, Default)]
+#[ts(export)]
+pub struct UpdateWebhookPayload {
+	#[serde(default)]
+	#[ts(optional)]
+	pub url: Option<String>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub events: Option<Vec<WebhookEventType>>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub description: Option<String>,
+	#[serde(default)]
+	#[ts(optional)]
+	pub enabled: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, ToSchema, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum DeliveryStatus {
+	/// Not delivered yet; retried at `next_attempt_at`.
+	Pending,
+	Succeeded,
+	/// Gave up after the last attempt.
+	Failed,
+}
+
+/// # WebhookDelivery
+/// One event sent, or still to be sent, to one webhook. `response_status` and `error` describe the last attempt.
+#[derive(Serialize, Deserialize, FromRow, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct WebhookDelivery {
+	pub id: Uuid,
+	pub webhook_id: Uuid,
+	pub event_type: WebhookEventType,
+	#[schema(value_type = Object)]
+	#[ts(type = "Record<string, unknown>")]
+	pub data: Value,
+	pub status: DeliveryStatus,
+	pub attempts: i32,
+	pub next_attempt_at: DateTime<Utc>,
+	pub response_status: Option<i16>,
+	pub error: Option<String>,
+	pub created_at: DateTime<Utc>,
+	pub delivered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, TS, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum WebhookSort {
+	#[default]
+	CreatedAt,
+}
+
+impl SortField for WebhookSort {
+	fn column(&self) -> &'static str {
+		"x.created_at"
+	}
+
+	fn sql_type(&self) -> SortType {
+		SortType::Timestamp
+	}
+}
+
+impl Keyset for Webhook {
+	type Sort = WebhookSort;
+
+	fn keyset_id(&self) -> Uuid {
+		self.id
+	}
+
+	fn keyset_value(&self, _sort: WebhookSort) -> String {
+		self.created_at.to_rfc3339()
+	}
+}
+
+impl Keyset for WebhookDelivery {
+	type Sort = WebhookSort;
+
+	fn keyset_id(&self) -> Uuid {
+		self.id
+	}
+
+	fn keyset_value(&self, _sort: WebhookSort) -> String {
+		self.created_at.to_rfc3339()
+	}
+}
+
+/// Query parameters of the webhook list.
+#[derive(Deserialize, IntoParams, TS, Default)]
+#[into_params(parameter_in = Query)]
+#[ts(export)]
+pub struct WebhookFilter {
+	#[ts(optional)]
+	pub sort: Option<WebhookSort>,
+}
+
+impl ListFilter for WebhookFilter {
+	type Sort = WebhookSort;
+
+	fn sort(&self) -> WebhookSort {
+		self.sort.unwrap_or_default()
+	}
+}
+
+/// Query parameters of a webhook's delivery log.
+#[derive(Deserialize, IntoParams, TS, Default)]
+#[into_params(parameter_in = Query)]
+#[ts(export)]
+pub struct DeliveryFilter {
+	#[ts(optional)]
+	pub status: Option<DeliveryStatus>,
+	#[ts(optional)]
+	pub sort: Option<WebhookSort>,
+}
+
+impl ListFilter for DeliveryFilter {
+	type Sort = WebhookSort;
+
+	fn sort(&self) -> WebhookSort {
+		self.sort.unwrap_or_default()
+	}
+}
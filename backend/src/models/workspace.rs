@@ -0,0 +1,94 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use ts_rs::TS;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Mirrors the `workspace_role` Postgres enum. Variants are ordered by what they may do,
+/// so `role >= WorkspaceRole::Admin` reads as "at least an admin".
+#[derive(Serialize, Deserialize, sqlx::Type, ToSchema, TS, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[sqlx(type_name = "workspace_role", rename_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[ts(export)]
+pub enum WorkspaceRole {
+	/// Works on projects, manages the ones they created, and logs time.
+	Member,
+	/// Also manages every client and project, the members and the invitations.
+	Admin,
+	/// Also grants and revokes ownership. Every workspace keeps at least one.
+	Owner,
+}
+
+/// # Workspace
+/// A workspace the user belongs to, with their role in it.
+#[derive(Serialize, Deserialize, FromRow, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct Workspace {
+	pub id: Uuid,
+	pub name: String,
+	/// Created with the account and active when a request names no other workspace.
+	pub personal: bool,
+	pub role: WorkspaceRole,
+	pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct CreateWorkspacePayload {
+	pub name: String,
+}
+
+/// # WorkspaceToken
+/// A token that makes `workspace` the active workspace of the requests it authorizes.
+#[derive(Serialize, Deserialize, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct WorkspaceToken {
+	pub token: String,
+	pub workspace: Workspace,
+}
+
+#[derive(Serialize, Deserialize, FromRow, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct WorkspaceMember {
+	pub user_id: Uuid,
+	pub email: String,
+	pub role: WorkspaceRole,
+	pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct UpdateMemberPayload {
+	pub role: WorkspaceRole,
+}
+
+/// # Invitation
+/// An open invitation to join a workspace, for the account with `email`.
+#[derive(Serialize, Deserialize, FromRow, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct Invitation {
+	pub id: Uuid,
+	pub workspace_id: Uuid,
+	pub email: String,
+	pub role: WorkspaceRole,
+	pub invited_by: Option<Uuid>,
+	pub created_at: DateTime<Utc>,
+	pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct CreateInvitationPayload {
+	pub email: String,
+	/// Defaults to `MEMBER`. Only owners may invite owners.
+	#[serde(default)]
+	#[ts(optional)]
+	pub role: Option<WorkspaceRole>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, TS, Debug)]
+#[ts(export)]
+pub struct AcceptInvitationPayload {
+	pub token: String,
+}
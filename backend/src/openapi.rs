@@ -0,0 +1,118 @@
+use axum::{Json, response::IntoResponse};
+use utoipa::{
+	openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+	Modify, OpenApi,
+};
+use crate::routes;
+
+/// # ApiDoc
+/// OpenAPI document generated from the handler annotations and model types.
+/// A copy is committed as `openapi.json`; `tests::openapi` fails when the two drift.
+#[derive(OpenApi)]
+#[openapi(
+	info(
+		title = "Kvitter API",
+		description = "Time, project and finance tracking for freelancers.\n\n\
+			Authenticated POST and PATCH requests may send an `Idempotency-Key` header. Retries with the same key \
+			get the stored response back with `Idempotent-Replayed: true`; the same key with a different request \
+			is a 409. Responses with `Cache-Control: no-store` carry credentials and are never stored, so their \
+			retries run again.\n\n\
+			Clients, projects and what belongs to them live in workspaces. Requests act in the workspace named by \
+			the `X-Workspace-Id` header, else the one of the token (see `POST /workspaces/{id}/switch`), \
+			else the user's personal workspace. Naming a workspace the user doesn't belong to is a 403.",
+	),
+	servers((url = "/api/v1")),
+	paths(
+		routes::auth::signup,
+		routes::auth::login,
+		routes::user::get_me,
+		routes::user::update_me,
+		routes::user::delete_me,
+		routes::user::change_password,
+		routes::user::request_email_change,
+		routes::user::confirm_email_change,
+		routes::user::undo_email_change,
+		routes::workspace::get_workspaces,
+		routes::workspace::create_workspace,
+		routes::workspace::switch_workspace,
+		routes::workspace::get_members,
+		routes::workspace::update_member,
+		routes::workspace::remove_member,
+		routes::workspace::get_invitations,
+		routes::workspace::create_invitation,
+		routes::workspace::revoke_invitation,
+		routes::workspace::accept_invitation,
+		routes::project::get_projects,
+		routes::project::get_project,
+		routes::project::delete_project,
+		routes::time_entry::get_time_entries,
+		routes::time_entry::get_time_entry,
+		routes::time_entry::delete_time_entry,
+		routes::search::search_all,
+		routes::sync::get_sync,
+		routes::sync::post_sync,
+		routes::trash::get_trash,
+		routes::trash::restore_item,
+		routes::audit::get_entity_history,
+		routes::audit::get_audit_events,
+		routes::job::get_jobs,
+		routes::job::retry_job,
+		routes::schedule::get_schedules,
+		routes::schedule::update_schedule,
+		routes::schedule::run_schedule,
+		routes::schedule::get_schedule_runs,
+		routes::events::stream_events,
+		routes::webhook::get_webhooks,
+		routes::webhook::create_webhook,
+		routes::webhook::update_webhook,
+		routes::webhook::delete_webhook,
+		routes::webhook::get_deliveries,
+		routes::webhook::test_webhook,
+		routes::health::live,
+		routes::health::ready,
+	),
+	components(schemas(
+		crate::models::response::NoData,
+		crate::util::error::ErrorCode,
+		crate::util::problem::ProblemDetails,
+	)),
+	modifiers(&BearerAuth),
+	tags(
+		(name = "auth", description = "Signup and login"),
+		(name = "user", description = "The authenticated user"),
+		(name = "workspaces", description = "Shared workspaces, their members and invitations"),
+		(name = "projects", description = "Projects of the active workspace"),
+		(name = "time entries", description = "Time logged against jobs"),
+		(name = "search", description = "Full-text search across the user's data"),
+		(name = "sync", description = "Delta sync for offline clients"),
+		(name = "trash", description = "Deleted data that can still be restored"),
+		(name = "audit", description = "Who changed what and when"),
+		(name = "events", description = "Live changes to the projects of the active workspace"),
+		(name = "webhooks", description = "Signed HTTP callbacks for events, with retries and a delivery log"),
+		(name = "admin", description = "Administrator-only endpoints"),
+		(name = "health", description = "Probes for load balancers and orchestrators"),
+	)
+)]
+pub struct ApiDoc;
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+	fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+		let components = openapi.components.get_or_insert_with(Default::default);
+
+		components.add_security_scheme(
+			"bearer",
+			SecurityScheme::Http(
+				HttpBuilder::new()
+					.scheme(HttpAuthScheme::Bearer)
+					.bearer_format("JWT")
+					.build()
+			),
+		);
+	}
+}
+
+pub async fn openapi_json() -> impl IntoResponse {
+	Json(ApiDoc::openapi())
+}
@@ -0,0 +1,67 @@
+use axum::{
+	extract::{Path, State},
+	response::IntoResponse,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	auth::{admin::AdminUser, workspace::WorkspaceUser},
+	models::{
+		audit::{AuditEvent, AuditFilter, EntityType, HistoryFilter},
+		response::{ApiResponse, EmptyResponse},
+	},
+	util::{
+		audit_service::{list_audit_events, list_entity_history},
+		pagination::{ListQuery, PageParams},
+	},
+};
+
+#[utoipa::path(
+	get,
+	path = "/audit/{entity_type}/{entity_id}",
+	tag = "audit",
+	security(("bearer" = [])),
+	params(
+		("entity_type" = EntityType, Path),
+		("entity_id" = Uuid, Path),
+		PageParams,
+		HistoryFilter,
+	),
+	responses(
+		(status = 200, description = "Changes to the entity, most recent first", body = ApiResponse<Vec<AuditEvent>>),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace", body = EmptyResponse),
+		(status = 404, description = "The entity doesn't exist or isn't visible to the user", body = EmptyResponse),
+	)
+)]
+pub async fn get_entity_history(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	Path((entity_type, entity_id)): Path<(EntityType, Uuid)>,
+	query: ListQuery<HistoryFilter>,
+) -> impl IntoResponse {
+	let result = list_entity_history(&pool, &user_id, &workspace_id, entity_type, &entity_id, &query).await;
+	ApiResponse::from_page(result).into_response()
+}
+
+#[utoipa::path(
+	get,
+	path = "/admin/audit-events",
+	tag = "admin",
+	security(("bearer" = [])),
+	params(PageParams, AuditFilter),
+	responses(
+		(status = 200, description = "Matching audit events, most recent first", body = ApiResponse<Vec<AuditEvent>>),
+		(status = 400, description = "Invalid filter, limit or cursor", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "The user is not an administrator", body = EmptyResponse),
+	)
+)]
+pub async fn get_audit_events(
+	_admin: AdminUser,
+	State(pool): State<PgPool>,
+	query: ListQuery<AuditFilter>,
+) -> impl IntoResponse {
+	let result = list_audit_events(&pool, &query).await;
+	ApiResponse::from_page(result).into_response()
+}
@@ -1,86 +1,136 @@
+use std::sync::Arc;
 use axum::{
 	extract::State, Json, 
 	response::IntoResponse, http::StatusCode
 };
+use serde_json::{json, Value};
 use sqlx::PgPool;
 use crate::{
+	config::Config,
 	models::{
+		audit::{AuditAction, EntityType},
 		user::{RegisterPayload, User, PublicUser},
-		response::ApiResponse
+		response::{ApiResponse, EmptyResponse}
 	},
 	auth::{
 		hash::{hash_password, verify_password},
 		jwt::generate_jwt_token
 	},
 	util::{
-		validation::validate_password,
-		error::{AppError, AppResult},
-		user_service::{is_email_unique, fetch_user_by_email}
+		audit::{self, AuditContext},
+		validation::{normalize_email, validate_password},
+		error::{AppError, AppResult, ErrorCode},
+		idempotency::NO_STORE,
+		user_service::{is_email_unique, fetch_user_by_email},
+		metrics
 	}
 };
 use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::ToSchema;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
 pub struct AuthResponse {
 	pub token: String,
 	pub user: PublicUser,
 }
 
+#[utoipa::path(
+	post,
+	path = "/auth/signup",
+	tag = "auth",
+	request_body = RegisterPayload,
+	responses(
+		(status = 201, description = "Account created", body = EmptyResponse),
+		(status = 400, description = "Invalid email address, or the password does not meet the policy", body = EmptyResponse),
+		(status = 401, description = "Email is already taken", body = EmptyResponse),
+	)
+)]
 pub async fn signup(
 	State(pool): State<PgPool>,
+	State(config): State<Arc<Config>>,
+	context: AuditContext,
 	Json(payload): Json<RegisterPayload>,
 ) -> impl IntoResponse {
 	let result: AppResult<()> = async {
-		is_email_unique(&pool, &payload.email).await?;
-		validate_password(&payload.password)?;
+		let email = normalize_email("email", &payload.email)?;
+		is_email_unique(&pool, &email).await?;
+		validate_password(&config.password_policy, &payload.password, &[&email]).await?;
 
 		let password_hash = hash_password(&payload.password)
-			.map_err(|err| AppError::Internal(err.to_string()))?;
+			.map_err(|err| AppError::internal_from("Failed to hash password", err))?;
 
-		sqlx::query_as::<_, User>("INSERT INTO users (email, password_hash) VALUES ($1, $2) RETURNING *")
-			.bind(&payload.email)
+		let mut tx = pool.begin().await
+			.map_err(|err| AppError::database("Failed to start transaction", err))?;
+		let user = sqlx::query_as::<_, User>("INSERT INTO users (email, password_hash) VALUES ($1, $2) RETURNING *")
+			.bind(&email)
 			.bind(&password_hash)
-			.fetch_one(&pool)
+			.fetch_one(&mut *tx)
 			.await
-			.map_err(|_| AppError::Internal("Failed to create user".into()))?;
+			.map_err(|err| AppError::database("Failed to create user", err))?;
 
+		audit::record(
+			&mut tx,
+			&context.with_actor(user.id),
+			EntityType::User,
+			user.id,
+			AuditAction::Created,
+			audit::diff(&Value::Null, &json!(PublicUser::from(&user))),
+		).await?;
+		tx.commit().await
+			.map_err(|err| AppError::database("Failed to create user", err))?;
+
+		metrics::record_signup();
 		Ok(())
 	}.await;
 
 	ApiResponse::from_result(result, StatusCode::CREATED).into_response()
 }
 
+#[utoipa::path(
+	post,
+	path = "/auth/login",
+	tag = "auth",
+	request_body = RegisterPayload,
+	responses(
+		(status = 200, description = "Logged in", body = ApiResponse<AuthResponse>),
+		(status = 401, description = "Invalid credentials", body = EmptyResponse),
+	)
+)]
 pub async fn login(
 	State(pool): State<PgPool>,
 	Json(payload): Json<RegisterPayload>,
 ) -> impl IntoResponse {
 	let result: AppResult<AuthResponse> = async {
-		let user = fetch_user_by_email(&pool, &payload.email).await
+		let invalid_credentials = || AppError::Auth(ErrorCode::InvalidCredentials, "Invalid credentials".into());
+		// No syntax check: accounts may predate the current rules. Normalizing still helps where it works,
+		// since stored domains are in their ASCII form.
+		let email = normalize_email("email", &payload.email)
+			.unwrap_or_else(|_| payload.email.trim().to_owned());
+		let user = fetch_user_by_email(&pool, &email).await
 			.map_err(|err| match err {
-				AppError::NotFound(_) => AppError::Auth("Invalid credentials".into()),
+				AppError::NotFound(..) => invalid_credentials(),
 				_ => err,
 			})?;
 		let is_valid = verify_password(&payload.password, &user.password_hash)
-			.map_err(|err| AppError::Internal(err.to_string()))?;
+			.map_err(|err| AppError::internal_from("Stored password hash is invalid", err))?;
 
 		match is_valid {
 			true => {
 				let token = generate_jwt_token(&user)
-					.map_err(|_| AppError::Auth("Failed to generate token".into()))?;
+					.map_err(|_| AppError::Auth(ErrorCode::TokenGenerationFailed, "Failed to generate token".into()))?;
 
 				Ok(AuthResponse {
 					token,
 					user: user.into(),
 				})
 			},
-			false => Err(AppError::Auth("Invalid credentials".into())),
+			false => Err(invalid_credentials()),
 		}
 
 	}.await;
 
-	ApiResponse::from_result(result, StatusCode::OK).into_response()
-}
-
-pub async fn health_check() -> impl IntoResponse {
-	(StatusCode::OK, Json("Service is up and running"))
+	metrics::record_login(result.is_ok());
+	(NO_STORE, ApiResponse::from_result(result, StatusCode::OK)).into_response()
 }
\ No newline at end of file
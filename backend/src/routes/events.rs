@@ -0,0 +1,51 @@
+use std::convert::Infallible;
+use axum::{
+	extract::State,
+	response::{sse::{Event, KeepAlive, Sse}, IntoResponse},
+};
+use futures_util::stream;
+use sqlx::PgPool;
+use crate::{
+	auth::workspace::WorkspaceUser,
+	models::{event::DomainEvent, response::EmptyResponse},
+	util::events::{EventHub, Signal},
+};
+
+#[utoipa::path(
+	get,
+	path = "/events",
+	tag = "events",
+	security(("bearer" = [])),
+	responses(
+		(
+			status = 200,
+			description = "Server-Sent Events: a `change` event per change to the projects of the active workspace, \
+				and `resync` when events may have been missed and data should be refetched. \
+				The stream ends once the user is no longer a member of the workspace",
+			content_type = "text/event-stream",
+			body = DomainEvent,
+		),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace", body = EmptyResponse),
+	)
+)]
+pub async fn stream_events(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	State(hub): State<EventHub>,
+) -> impl IntoResponse {
+	let subscription = hub.subscribe(user_id, workspace_id);
+	let events = stream::unfold(subscription, move |mut subscription| {
+		let pool = pool.clone();
+		async move {
+			let event = match subscription.next(&pool).await? {
+				Signal::Change(change) => Event::default().event("change").json_data(&change)
+					.expect("event serializes"),
+				Signal::Resync => Event::default().event("resync").data("{}"),
+			};
+			Some((Ok::<_, Infallible>(event), subscription))
+		}
+	});
+
+	Sse::new(events).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+use axum::{
+	extract::State,
+	response::IntoResponse,
+	http::StatusCode,
+};
+use sqlx::PgPool;
+use crate::{
+	config::Config,
+	models::{
+		health::{HealthStatus, LivenessReport, ReadinessReport},
+		response::ApiResponse
+	},
+	util::health_service::check_all,
+};
+
+#[utoipa::path(
+	get,
+	path = "/health/live",
+	tag = "health",
+	responses((status = 200, description = "Process is responsive", body = ApiResponse<LivenessReport>))
+)]
+/// Liveness only tells the orchestrator that the process is responsive.
+/// It deliberately does not touch the database so a database outage does not restart every replica.
+pub async fn live() -> impl IntoResponse {
+	ApiResponse::success(LivenessReport { status: HealthStatus::Ok }).into_response()
+}
+
+#[utoipa::path(
+	get,
+	path = "/health/ready",
+	tag = "health",
+	responses(
+		(status = 200, description = "All required components are healthy", body = ApiResponse<ReadinessReport>),
+		(status = 503, description = "At least one component failed", body = ApiResponse<ReadinessReport>),
+	)
+)]
+pub async fn ready(
+	State(pool): State<PgPool>,
+	State(config): State<Arc<Config>>,
+) -> impl IntoResponse {
+	let components = check_all(&pool, &config).await
+		.into_iter()
+		.map(|(name, health)| (name.to_string(), health))
+		.collect();
+	let report = ReadinessReport::from_components(components);
+	let status = match report.is_ready() {
+		true => StatusCode::OK,
+		false => StatusCode::SERVICE_UNAVAILABLE,
+	};
+
+	ApiResponse::with_status(report, status).into_response()
+}
@@ -0,0 +1,62 @@
+use axum::{
+	extract::{Path, State},
+	http::StatusCode,
+	response::IntoResponse,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	auth::admin::AdminUser,
+	models::{
+		background_job::{BackgroundJob, JobFilter},
+		response::{ApiResponse, EmptyResponse},
+	},
+	util::{
+		job_queue,
+		pagination::{ListQuery, PageParams},
+	},
+};
+
+#[utoipa::path(
+	get,
+	path = "/admin/jobs",
+	tag = "admin",
+	security(("bearer" = [])),
+	params(PageParams, JobFilter),
+	responses(
+		(status = 200, description = "Background jobs, newest first unless sorted by `run_at`", body = ApiResponse<Vec<BackgroundJob>>),
+		(status = 400, description = "Invalid filter, limit or cursor", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "The user is not an administrator", body = EmptyResponse),
+	)
+)]
+pub async fn get_jobs(
+	_admin: AdminUser,
+	State(pool): State<PgPool>,
+	query: ListQuery<JobFilter>,
+) -> impl IntoResponse {
+	let result = job_queue::list_jobs(&pool, &query).await;
+	ApiResponse::from_page(result).into_response()
+}
+
+#[utoipa::path(
+	post,
+	path = "/admin/jobs/{id}/retry",
+	tag = "admin",
+	security(("bearer" = [])),
+	params(("id" = Uuid, Path, description = "Id of the dead job")),
+	responses(
+		(status = 200, description = "Queued again with a fresh set of attempts", body = ApiResponse<BackgroundJob>),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "The user is not an administrator", body = EmptyResponse),
+		(status = 404, description = "No dead job with this id", body = EmptyResponse),
+	)
+)]
+pub async fn retry_job(
+	_admin: AdminUser,
+	State(pool): State<PgPool>,
+	Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+	let result = job_queue::retry_job(&pool, &id).await;
+	ApiResponse::from_result(result, StatusCode::OK).into_response()
+}
@@ -0,0 +1,18 @@
+use axum::{
+	extract::State,
+	http::header,
+	response::IntoResponse,
+};
+use sqlx::PgPool;
+use crate::util::metrics::{handle, record_pool};
+
+/// Prometheus scrape endpoint.
+/// Pool gauges are sampled here rather than on every checkout.
+pub async fn render(State(pool): State<PgPool>) -> impl IntoResponse {
+	record_pool(&pool);
+
+	(
+		[(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+		handle().render(),
+	)
+}
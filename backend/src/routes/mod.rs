@@ -1,2 +1,60 @@
+pub mod audit;
 pub mod auth;
-pub mod user;
\ No newline at end of file
+pub mod events;
+pub mod health;
+pub mod job;
+pub mod metrics;
+pub mod project;
+pub mod schedule;
+pub mod search;
+pub mod sync;
+pub mod time_entry;
+pub mod trash;
+pub mod user;
+pub mod v1;
+pub mod webhook;
+pub mod workspace;
+
+use axum::{Router, middleware, routing::{get, post}};
+use chrono::{TimeZone, Utc};
+use utoipa::OpenApi;
+use utoipa_scalar::{Scalar, Servable};
+use crate::{
+	openapi::{self, ApiDoc},
+	state::AppState,
+	util::{deprecation::{self, Deprecation}, etag},
+};
+
+/// Versions are mounted side by side so clients can move over one at a time.
+/// A new version gets its own module with a `router()` and a line here;
+/// once it ships, the previous version's nest is wrapped in `deprecated` with a sunset date.
+pub fn router() -> Router<AppState> {
+	Router::new()
+		.nest("/api/v1", v1::router())
+		.merge(legacy())
+		.layer(middleware::from_fn(etag::conditional))
+}
+
+/// Wraps all routes of `router` in `Deprecation`/`Sunset` headers.
+pub fn deprecated(router: Router<AppState>, deprecation: Deprecation) -> Router<AppState> {
+	router.layer(middleware::from_fn_with_state(deprecation, deprecation::mark))
+}
+
+/// The unversioned root routes served before `/api/v1` existed, kept for installed PWAs until the sunset.
+/// Frozen to that list: routes added since are only served under a version.
+fn legacy() -> Router<AppState> {
+	let deprecation = Deprecation {
+		deprecated_at: Utc.with_ymd_and_hms(2026, 10, 18, 0, 0, 0).unwrap(),
+		sunset: Some(Utc.with_ymd_and_hms(2027, 4, 18, 0, 0, 0).unwrap()),
+		successor_prefix: Some("/api/v1"),
+	};
+	let routes = Router::new()
+		.route("/auth/signup", post(auth::signup))
+		.route("/health", get(health::live))
+		.route("/health/live", get(health::live))
+		.route("/health/ready", get(health::ready))
+		.route("/openapi.json", get(openapi::openapi_json))
+		.merge(Scalar::with_url("/docs", ApiDoc::openapi()));
+
+	deprecated(routes, deprecation)
+}
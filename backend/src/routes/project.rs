@@ -0,0 +1,102 @@
+use axum::{
+	extract::{Path, State},
+	http::StatusCode,
+	response::IntoResponse,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	auth::workspace::WorkspaceUser,
+	models::{
+		project::{Project, ProjectFilter},
+		response::{ApiResponse, EmptyResponse},
+		trash::TrashKind,
+	},
+	util::{
+		audit::AuditContext,
+		etag::{self, IfMatch},
+		pagination::{ListQuery, PageParams},
+		project_service::{fetch_project, list_projects},
+		trash_service::soft_delete,
+	},
+};
+
+#[utoipa::path(
+	get,
+	path = "/projects",
+	tag = "projects",
+	security(("bearer" = [])),
+	params(PageParams, ProjectFilter),
+	responses(
+		(status = 200, description = "Projects of the active workspace", body = ApiResponse<Vec<Project>>),
+		(status = 400, description = "Invalid filter, limit or cursor", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace", body = EmptyResponse),
+	)
+)]
+pub async fn get_projects(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	query: ListQuery<ProjectFilter>,
+) -> impl IntoResponse {
+	let result = list_projects(&pool, &user_id, &workspace_id, &query).await;
+	ApiResponse::from_page(result).into_response()
+}
+
+#[utoipa::path(
+	get,
+	path = "/projects/{id}",
+	tag = "projects",
+	security(("bearer" = [])),
+	params(
+		("id" = Uuid, Path),
+		("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy"),
+	),
+	responses(
+		(status = 200, description = "A project of the active workspace", body = ApiResponse<Project>,
+			headers(("ETag" = String, description = "Version of the project"))),
+		(status = 304, description = "The cached copy is still current"),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace", body = EmptyResponse),
+		(status = 404, description = "No such project visible to the user", body = EmptyResponse),
+	)
+)]
+pub async fn get_project(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+	let result = fetch_project(&pool, &user_id, &workspace_id, &id).await
+		.map(|project| (project.version, project));
+	etag::versioned(result, StatusCode::OK)
+}
+
+/// Only the project creator and workspace admins may delete it. Jobs, milestones and time entries are hidden with it.
+#[utoipa::path(
+	delete,
+	path = "/projects/{id}",
+	tag = "projects",
+	security(("bearer" = [])),
+	params(
+		("id" = Uuid, Path),
+		("If-Match" = String, Header, description = "ETag of the project, or `*`"),
+	),
+	responses(
+		(status = 204, description = "Moved to the trash"),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace", body = EmptyResponse),
+		(status = 404, description = "No such project the user may delete", body = EmptyResponse),
+		(status = 412, description = "The project changed since the given ETag", body = EmptyResponse),
+		(status = 428, description = "If-Match is missing", body = EmptyResponse),
+	)
+)]
+pub async fn delete_project(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	Path(id): Path<Uuid>,
+	if_match: IfMatch,
+	context: AuditContext,
+) -> impl IntoResponse {
+	let result = soft_delete(&pool, TrashKind::Project, &id, &user_id, &workspace_id, &if_match, &context).await;
+	ApiResponse::from_result(result, StatusCode::NO_CONTENT).into_response()
+}
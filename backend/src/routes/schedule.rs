@@ -0,0 +1,114 @@
+use axum::{
+	extract::{Path, State},
+	http::StatusCode,
+	response::IntoResponse,
+	Json,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	auth::admin::AdminUser,
+	models::{
+		response::{ApiResponse, EmptyResponse},
+		schedule::{RunFilter, ScheduleFilter, ScheduledTask, ScheduledTaskRun, UpdateScheduledTaskPayload},
+	},
+	util::{
+		job_queue,
+		pagination::{ListQuery, PageParams},
+		scheduler,
+	},
+};
+
+#[utoipa::path(
+	get,
+	path = "/admin/schedules",
+	tag = "admin",
+	security(("bearer" = [])),
+	params(PageParams, ScheduleFilter),
+	responses(
+		(status = 200, description = "Scheduled tasks with their next run", body = ApiResponse<Vec<ScheduledTask>>),
+		(status = 400, description = "Invalid limit or cursor", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "The user is not an administrator", body = EmptyResponse),
+	)
+)]
+pub async fn get_schedules(
+	_admin: AdminUser,
+	State(pool): State<PgPool>,
+	query: ListQuery<ScheduleFilter>,
+) -> impl IntoResponse {
+	let result = scheduler::list_tasks(&pool, &query).await;
+	ApiResponse::from_page(result).into_response()
+}
+
+#[utoipa::path(
+	patch,
+	path = "/admin/schedules/{id}",
+	tag = "admin",
+	security(("bearer" = [])),
+	params(("id" = Uuid, Path, description = "Id of the scheduled task")),
+	request_body = UpdateScheduledTaskPayload,
+	responses(
+		(status = 200, description = "Updated", body = ApiResponse<ScheduledTask>),
+		(status = 400, description = "Invalid cron expression", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "The user is not an administrator", body = EmptyResponse),
+		(status = 404, description = "No such scheduled task", body = EmptyResponse),
+	)
+)]
+pub async fn update_schedule(
+	_admin: AdminUser,
+	State(pool): State<PgPool>,
+	Path(id): Path<Uuid>,
+	Json(payload): Json<UpdateScheduledTaskPayload>,
+) -> impl IntoResponse {
+	let result = scheduler::update_task(&pool, &id, &payload).await;
+	ApiResponse::from_result(result, StatusCode::OK).into_response()
+}
+
+#[utoipa::path(
+	post,
+	path = "/admin/schedules/{id}/run",
+	tag = "admin",
+	security(("bearer" = [])),
+	params(("id" = Uuid, Path, description = "Id of the scheduled task")),
+	responses(
+		(status = 202, description = "The task's job was queued; its schedule is unchanged", body = ApiResponse<ScheduledTaskRun>),
+		(status = 400, description = "No handler for the task's job kind", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "The user is not an administrator", body = EmptyResponse),
+		(status = 404, description = "No such scheduled task", body = EmptyResponse),
+	)
+)]
+pub async fn run_schedule(
+	AdminUser(admin_id): AdminUser,
+	State(pool): State<PgPool>,
+	Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+	let result = scheduler::run_now(&pool, &job_queue::registry(), &id, &admin_id).await;
+	ApiResponse::from_result(result, StatusCode::ACCEPTED).into_response()
+}
+
+#[utoipa::path(
+	get,
+	path = "/admin/schedules/{id}/runs",
+	tag = "admin",
+	security(("bearer" = [])),
+	params(("id" = Uuid, Path, description = "Id of the scheduled task"), PageParams, RunFilter),
+	responses(
+		(status = 200, description = "Runs of the task with the state of their jobs, newest first", body = ApiResponse<Vec<ScheduledTaskRun>>),
+		(status = 400, description = "Invalid filter, limit or cursor", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "The user is not an administrator", body = EmptyResponse),
+		(status = 404, description = "No such scheduled task", body = EmptyResponse),
+	)
+)]
+pub async fn get_schedule_runs(
+	_admin: AdminUser,
+	State(pool): State<PgPool>,
+	Path(id): Path<Uuid>,
+	query: ListQuery<RunFilter>,
+) -> impl IntoResponse {
+	let result = scheduler::list_runs(&pool, &id, &query).await;
+	ApiResponse::from_page(result).into_response()
+}
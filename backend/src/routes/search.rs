@@ -0,0 +1,75 @@
+use axum::{extract::State, http::{StatusCode, Uri}, response::IntoResponse};
+use sqlx::PgPool;
+use crate::{
+	auth::workspace::WorkspaceUser,
+	models::{
+		response::{ApiResponse, EmptyResponse},
+		search::{SearchParams, SearchResult},
+	},
+	util::{
+		error::{AppError, AppResult, ErrorCode, FieldError},
+		pagination::parse_query,
+		search_service::search,
+	},
+};
+
+const DEFAULT_LIMIT: u32 = 20;
+const MAX_LIMIT: u32 = 50;
+const MAX_QUERY_CHARS: usize = 200;
+
+fn validate(params: &SearchParams) -> AppResult<()> {
+	let mut errors = Vec::new();
+	let query = params.q.trim();
+
+	if query.is_empty() {
+		errors.push(FieldError::new("q", ErrorCode::InvalidQuery, "Search query must not be empty"));
+	}
+	if query.chars().count() > MAX_QUERY_CHARS {
+		errors.push(FieldError::new(
+			"q",
+			ErrorCode::InvalidQuery,
+			format!("Search query must be at most {} characters", MAX_QUERY_CHARS),
+		));
+	}
+	if let Some(limit) = params.limit.filter(|limit| !(1..=MAX_LIMIT).contains(limit)) {
+		errors.push(FieldError::new(
+			"limit",
+			ErrorCode::InvalidQuery,
+			format!("Limit must be between 1 and {}, got {}", MAX_LIMIT, limit),
+		));
+	}
+
+	match errors.is_empty() {
+		true => Ok(()),
+		false => Err(AppError::Validation(errors)),
+	}
+}
+
+#[utoipa::path(
+	get,
+	path = "/search",
+	tag = "search",
+	security(("bearer" = [])),
+	params(SearchParams),
+	responses(
+		(status = 200, description = "Matches ranked by relevance", body = ApiResponse<Vec<SearchResult>>),
+		(status = 400, description = "Empty or invalid query", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace", body = EmptyResponse),
+	)
+)]
+pub async fn search_all(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	uri: Uri,
+) -> impl IntoResponse {
+	let result: AppResult<Vec<SearchResult>> = async {
+		let params: SearchParams = parse_query(&uri)?;
+		validate(&params)?;
+
+		let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+		search(&pool, &user_id, &workspace_id, params.q.trim(), params.kind, limit).await
+	}.await;
+
+	ApiResponse::from_result(result, StatusCode::OK).into_response()
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+use axum::{extract::State, http::{StatusCode, Uri}, response::IntoResponse, Json};
+use sqlx::PgPool;
+use crate::{
+	auth::workspace::WorkspaceUser,
+	config::Config,
+	models::{
+		response::{ApiResponse, EmptyResponse},
+		sync::{SyncChanges, SyncParams, SyncPush, SyncResult},
+	},
+	util::{
+		audit::AuditContext,
+		error::{AppError, AppResult, ErrorCode, FieldError},
+		pagination::parse_query,
+		sync_service::{pull_changes, push_changes},
+	},
+};
+
+const MAX_CHANGES: usize = 500;
+
+#[utoipa::path(
+	get,
+	path = "/sync",
+	tag = "sync",
+	security(("bearer" = [])),
+	params(SyncParams),
+	responses(
+		(status = 200, description = "Rows created, updated and deleted since the cursor", body = ApiResponse<SyncChanges>),
+		(status = 400, description = "Malformed cursor", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace", body = EmptyResponse),
+	)
+)]
+pub async fn get_sync(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	State(config): State<Arc<Config>>,
+	uri: Uri,
+) -> impl IntoResponse {
+	let result: AppResult<SyncChanges> = async {
+		let params: SyncParams = parse_query(&uri)?;
+		pull_changes(&pool, &user_id, &workspace_id, params.since.as_deref(), config.trash_retention).await
+	}.await;
+
+	ApiResponse::from_result(result, StatusCode::OK).into_response()
+}
+
+#[utoipa::path(
+	post,
+	path = "/sync",
+	tag = "sync",
+	security(("bearer" = [])),
+	request_body = SyncPush,
+	responses(
+		(status = 200, description = "One result per change, in order", body = ApiResponse<Vec<SyncResult>>),
+		(status = 400, description = "Too many changes in one batch", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace", body = EmptyResponse),
+	)
+)]
+pub async fn post_sync(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	context: AuditContext,
+	Json(payload): Json<SyncPush>,
+) -> impl IntoResponse {
+	let result: AppResult<Vec<SyncResult>> = async {
+		if payload.changes.len() > MAX_CHANGES {
+			return Err(AppError::Validation(vec![FieldError::new(
+				"changes",
+				ErrorCode::ValidationFailed,
+				format!("At most {} changes per batch, got {}", MAX_CHANGES, payload.changes.len()),
+			)]));
+		}
+
+		push_changes(&pool, &user_id, &workspace_id, &payload.changes, &context).await
+	}.await;
+
+	ApiResponse::from_result(result, StatusCode::OK).into_response()
+}
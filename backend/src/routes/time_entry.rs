@@ -0,0 +1,101 @@
+use axum::{
+	extract::{Path, State},
+	http::StatusCode,
+	response::IntoResponse,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	auth::workspace::WorkspaceUser,
+	models::{
+		response::{ApiResponse, EmptyResponse},
+		time_entry::{TimeEntry, TimeEntryFilter},
+		trash::TrashKind,
+	},
+	util::{
+		audit::AuditContext,
+		etag::{self, IfMatch},
+		pagination::{ListQuery, PageParams},
+		time_entry_service::{fetch_time_entry, list_time_entries},
+		trash_service::soft_delete,
+	},
+};
+
+#[utoipa::path(
+	get,
+	path = "/time-entries",
+	tag = "time entries",
+	security(("bearer" = [])),
+	params(PageParams, TimeEntryFilter),
+	responses(
+		(status = 200, description = "The user's time entries", body = ApiResponse<Vec<TimeEntry>>),
+		(status = 400, description = "Invalid filter, limit or cursor", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace", body = EmptyResponse),
+	)
+)]
+pub async fn get_time_entries(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	query: ListQuery<TimeEntryFilter>,
+) -> impl IntoResponse {
+	let result = list_time_entries(&pool, &user_id, &workspace_id, &query).await;
+	ApiResponse::from_page(result).into_response()
+}
+
+#[utoipa::path(
+	get,
+	path = "/time-entries/{id}",
+	tag = "time entries",
+	security(("bearer" = [])),
+	params(
+		("id" = Uuid, Path),
+		("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy"),
+	),
+	responses(
+		(status = 200, description = "One of the user's time entries", body = ApiResponse<TimeEntry>,
+			headers(("ETag" = String, description = "Version of the time entry"))),
+		(status = 304, description = "The cached copy is still current"),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace", body = EmptyResponse),
+		(status = 404, description = "No such time entry visible to the user", body = EmptyResponse),
+	)
+)]
+pub async fn get_time_entry(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+	let result = fetch_time_entry(&pool, &user_id, &workspace_id, &id).await
+		.map(|time_entry| (time_entry.version, time_entry));
+	etag::versioned(result, StatusCode::OK)
+}
+
+#[utoipa::path(
+	delete,
+	path = "/time-entries/{id}",
+	tag = "time entries",
+	security(("bearer" = [])),
+	params(
+		("id" = Uuid, Path),
+		("If-Match" = String, Header, description = "ETag of the time entry, or `*`"),
+	),
+	responses(
+		(status = 204, description = "Moved to the trash"),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace", body = EmptyResponse),
+		(status = 404, description = "No such time entry owned by the user", body = EmptyResponse),
+		(status = 412, description = "The time entry changed since the given ETag", body = EmptyResponse),
+		(status = 428, description = "If-Match is missing", body = EmptyResponse),
+	)
+)]
+pub async fn delete_time_entry(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	Path(id): Path<Uuid>,
+	if_match: IfMatch,
+	context: AuditContext,
+) -> impl IntoResponse {
+	let result = soft_delete(&pool, TrashKind::TimeEntry, &id, &user_id, &workspace_id, &if_match, &context).await;
+	ApiResponse::from_result(result, StatusCode::NO_CONTENT).into_response()
+}
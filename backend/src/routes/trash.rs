@@ -0,0 +1,70 @@
+use std::sync::Arc;
+use axum::{
+	extract::{Path, State},
+	http::StatusCode,
+	response::IntoResponse,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	auth::workspace::WorkspaceUser,
+	config::Config,
+	models::{
+		response::{ApiResponse, EmptyResponse},
+		trash::{TrashFilter, TrashItem, TrashKind},
+	},
+	util::{
+		audit::AuditContext,
+		pagination::{ListQuery, PageParams},
+		trash_service::{list_trash, restore},
+	},
+};
+
+#[utoipa::path(
+	get,
+	path = "/trash",
+	tag = "trash",
+	security(("bearer" = [])),
+	params(PageParams, TrashFilter),
+	responses(
+		(status = 200, description = "Deleted rows of the active workspace the user may restore, most recent first", body = ApiResponse<Vec<TrashItem>>),
+		(status = 400, description = "Invalid filter, limit or cursor", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace", body = EmptyResponse),
+	)
+)]
+pub async fn get_trash(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	State(config): State<Arc<Config>>,
+	query: ListQuery<TrashFilter>,
+) -> impl IntoResponse {
+	let result = list_trash(&pool, &user_id, &workspace_id, config.trash_retention, &query).await;
+	ApiResponse::from_page(result).into_response()
+}
+
+#[utoipa::path(
+	post,
+	path = "/trash/{kind}/{id}/restore",
+	tag = "trash",
+	security(("bearer" = [])),
+	params(
+		("kind" = TrashKind, Path, description = "Kind of the deleted row"),
+		("id" = Uuid, Path, description = "Id of the deleted row"),
+	),
+	responses(
+		(status = 204, description = "Restored"),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace", body = EmptyResponse),
+		(status = 404, description = "Not in the user's trash", body = EmptyResponse),
+	)
+)]
+pub async fn restore_item(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	Path((kind, id)): Path<(TrashKind, Uuid)>,
+	context: AuditContext,
+) -> impl IntoResponse {
+	let result = restore(&pool, kind, &id, &user_id, &workspace_id, &context).await;
+	ApiResponse::from_result(result, StatusCode::NO_CONTENT).into_response()
+}
@@ -1,88 +1,253 @@
+use std::sync::Arc;
 use axum::{
-	extract::{Path, State},
+	extract::State,
 	Json,
 	response::IntoResponse,
 	http::StatusCode,
 };
+use serde_json::json;
 use sqlx::PgPool;
-use uuid::Uuid;
 use crate::{
+	config::Config,
 	auth::{
 		hash::{verify_password, hash_password}, 
 		jwt::AuthUser
 	},
 	models::{
-		response::ApiResponse, 
-		user::ChangePasswordPayload
+		audit::{AuditAction, EntityType},
+		response::{ApiResponse, EmptyResponse}, 
+		user::{
+			ChangeEmailPayload, ChangePasswordPayload, EmailChange, EmailChangeTokenPayload, PublicUser,
+			UpdateUserPayload,
+		}
 	},
 	util::{
+		audit::{self, AuditContext},
+		email_change_service,
+		etag::{self, IfMatch},
 		validation::validate_password,
-		error::{AppError, AppResult},
+		error::{AppError, AppResult, ErrorCode},
 		user_service::{
 			fetch_user_by_uuid, 
-			delete_user_by_uuid, 
+			delete_user_by_uuid,
 			update_user_password, 
-			fetch_and_map_by_uuid, 
-			fetch_and_map_by_email
+			update_user_profile,
+			validate_profile,
+			fetch_and_map_by_uuid,
 		}
 	},
 };
 
-pub async fn get_user_by_uuid(
-	Path(user_id): Path<Uuid>,
+#[utoipa::path(
+	get,
+	path = "/me",
+	tag = "user",
+	security(("bearer" = [])),
+	params(("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy")),
+	responses(
+		(status = 200, description = "The authenticated user", body = ApiResponse<PublicUser>,
+			headers(("ETag" = String, description = "Version of the user"))),
+		(status = 304, description = "The cached copy is still current"),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 404, description = "User no longer exists", body = EmptyResponse),
+	)
+)]
+pub async fn get_me(
+	AuthUser(user_id): AuthUser,
 	State(pool): State<PgPool>,
 ) -> impl IntoResponse {
-	let result = fetch_and_map_by_uuid(&pool, &user_id).await;
-	ApiResponse::from_result(result, StatusCode::OK).into_response()
+	let result = fetch_and_map_by_uuid(&pool, &user_id).await
+		.map(|user| (user.version, user));
+	etag::versioned(result, StatusCode::OK)
 }
 
-pub async fn get_user_by_email(
-	Path(email): Path<String>,
+#[utoipa::path(
+	patch,
+	path = "/me",
+	tag = "user",
+	security(("bearer" = [])),
+	params(("If-Match" = String, Header, description = "ETag of the user from `GET /me`, or `*`")),
+	request_body = UpdateUserPayload,
+	responses(
+		(status = 200, description = "The updated user", body = ApiResponse<PublicUser>,
+			headers(("ETag" = String, description = "New version of the user"))),
+		(status = 400, description = "Invalid profile fields", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 404, description = "User no longer exists", body = EmptyResponse),
+		(status = 412, description = "The user changed since the given ETag", body = EmptyResponse),
+		(status = 428, description = "If-Match is missing", body = EmptyResponse),
+	)
+)]
+pub async fn update_me(
+	AuthUser(user_id): AuthUser,
 	State(pool): State<PgPool>,
+	if_match: IfMatch,
+	context: AuditContext,
+	Json(payload): Json<UpdateUserPayload>,
 ) -> impl IntoResponse {
-	let result = fetch_and_map_by_email(&pool, &email).await;
-	ApiResponse::from_result(result, StatusCode::OK).into_response()
-}
+	let result: AppResult<(i64, PublicUser)> = async {
+		let payload = validate_profile(&payload)?;
+		let user = fetch_user_by_uuid(&pool, &user_id).await?;
+		if_match.check(user.version)?;
 
-pub async fn delete_user(
-	Path(user_id): Path<Uuid>,
-	State(pool): State<PgPool>,
-) -> impl IntoResponse {
-	let result = delete_user_by_uuid(&pool, &user_id).await;
-	ApiResponse::from_result(result, StatusCode::NO_CONTENT).into_response()
+		let mut tx = pool.begin().await
+			.map_err(|err| AppError::database("Failed to start transaction", err))?;
+		let updated = update_user_profile(&mut tx, &user_id, &payload, &if_match).await?;
+		audit::record(
+			&mut tx,
+			&context,
+			EntityType::User,
+			user_id,
+			AuditAction::Updated,
+			audit::diff(&json!(user.profile), &json!(updated.profile)),
+		).await?;
+		tx.commit().await
+			.map_err(|err| AppError::database("Failed to update profile", err))?;
+		Ok((updated.version, updated.into()))
+	}.await;
+
+	etag::versioned(result, StatusCode::OK)
 }
 
-pub async fn get_me(
+#[utoipa::path(
+	delete,
+	path = "/me",
+	tag = "user",
+	security(("bearer" = [])),
+	responses(
+		(status = 204, description = "Account deleted; it is purged with everything it owns after the trash retention"),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+	)
+)]
+pub async fn delete_me(
 	AuthUser(user_id): AuthUser,
 	State(pool): State<PgPool>,
+	context: AuditContext,
 ) -> impl IntoResponse {
-	let result = fetch_and_map_by_uuid(&pool, &user_id).await;
-	ApiResponse::from_result(result, StatusCode::OK).into_response()
+	let result = delete_user_by_uuid(&pool, &user_id, &context).await;
+	ApiResponse::from_result(result, StatusCode::NO_CONTENT).into_response()
 }
 
+#[utoipa::path(
+	put,
+	path = "/me/password",
+	tag = "user",
+	security(("bearer" = [])),
+	params(("If-Match" = String, Header, description = "ETag of the user from `GET /me`, or `*`")),
+	request_body = ChangePasswordPayload,
+	responses(
+		(status = 204, description = "Password changed",
+			headers(("ETag" = String, description = "New version of the user"))),
+		(status = 400, description = "New password does not meet the policy", body = EmptyResponse),
+		(status = 401, description = "Missing token or wrong current password", body = EmptyResponse),
+		(status = 412, description = "The user changed since the given ETag", body = EmptyResponse),
+		(status = 428, description = "If-Match is missing", body = EmptyResponse),
+	)
+)]
 pub async fn change_password(
 	AuthUser(user_id): AuthUser,
 	State(pool): State<PgPool>,
+	State(config): State<Arc<Config>>,
+	if_match: IfMatch,
+	context: AuditContext,
 	Json(payload): Json<ChangePasswordPayload>,
 ) -> impl IntoResponse {
-	let result: AppResult<()> = async {
-		validate_password(&payload.new_password)?;
-
+	let result: AppResult<(i64, ())> = async {
 		let user = fetch_user_by_uuid(&pool, &user_id).await?;
+		let user_inputs = [Some(user.email.as_str()), user.profile.display_name.as_deref()]
+			.into_iter()
+			.flatten()
+			.collect::<Vec<_>>();
+		validate_password(&config.password_policy, &payload.new_password, &user_inputs).await?;
+		if_match.check(user.version)?;
 		let is_valid = verify_password(&payload.old_password, &user.password_hash)
-			.map_err(|err| AppError::Auth(err.to_string()))?;
+			.map_err(|err| AppError::internal_from("Stored password hash is invalid", err))?;
 
 		match is_valid {
 			true => {
 				let hashed = hash_password(&payload.new_password)
-					.map_err(|_| AppError::Internal("Failed to hash new password".into()))?;
+					.map_err(|err| AppError::internal_from("Failed to hash new password", err))?;
 
-				update_user_password(&pool, &user_id, &hashed).await?;
-				Ok(())
+				let mut tx = pool.begin().await
+					.map_err(|err| AppError::database("Failed to start transaction", err))?;
+				let version = update_user_password(&mut tx, &user_id, &hashed, &if_match).await?;
+				audit::record(
+					&mut tx,
+					&context,
+					EntityType::User,
+					user_id,
+					AuditAction::Updated,
+					audit::diff(&json!({ "password_hash": user.password_hash }), &json!({ "password_hash": hashed })),
+				).await?;
+				tx.commit().await
+					.map_err(|err| AppError::database("Failed to update password", err))?;
+				Ok((version, ()))
 			}
-			false => Err(AppError::Auth("Current password is incorrect".into())),
+			false => Err(AppError::Auth(ErrorCode::IncorrectPassword, "Current password is incorrect".into())),
 		}
 	}.await;
 
+	etag::versioned(result, StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+	post,
+	path = "/me/email",
+	tag = "user",
+	security(("bearer" = [])),
+	request_body = ChangeEmailPayload,
+	responses(
+		(status = 202, description = "Confirmation sent to the new address, notice to the old one", body = ApiResponse<EmailChange>),
+		(status = 400, description = "Not an email address, or the current one", body = EmptyResponse),
+		(status = 401, description = "Missing token, wrong password, or the address is taken", body = EmptyResponse),
+	)
+)]
+pub async fn request_email_change(
+	AuthUser(user_id): AuthUser,
+	State(pool): State<PgPool>,
+	State(config): State<Arc<Config>>,
+	Json(payload): Json<ChangeEmailPayload>,
+) -> impl IntoResponse {
+	let result = email_change_service::request_email_change(&pool, &config, &user_id, &payload).await;
+	ApiResponse::from_result(result, StatusCode::ACCEPTED).into_response()
+}
+
+#[utoipa::path(
+	post,
+	path = "/email-changes/confirm",
+	tag = "user",
+	request_body = EmailChangeTokenPayload,
+	responses(
+		(status = 204, description = "The account uses the new address"),
+		(status = 401, description = "The address was taken in the meantime", body = EmptyResponse),
+		(status = 404, description = "The link is invalid, expired, used or undone", body = EmptyResponse),
+	)
+)]
+pub async fn confirm_email_change(
+	State(pool): State<PgPool>,
+	context: AuditContext,
+	Json(payload): Json<EmailChangeTokenPayload>,
+) -> impl IntoResponse {
+	let result = email_change_service::confirm_email_change(&pool, &payload.token, &context).await;
 	ApiResponse::from_result(result, StatusCode::NO_CONTENT).into_response()
-}
\ No newline at end of file
+}
+
+#[utoipa::path(
+	post,
+	path = "/email-changes/undo",
+	tag = "user",
+	request_body = EmailChangeTokenPayload,
+	responses(
+		(status = 204, description = "The change is cancelled, or the old address restored"),
+		(status = 401, description = "The old address was taken in the meantime", body = EmptyResponse),
+		(status = 404, description = "The link is invalid, expired or used", body = EmptyResponse),
+	)
+)]
+pub async fn undo_email_change(
+	State(pool): State<PgPool>,
+	context: AuditContext,
+	Json(payload): Json<EmailChangeTokenPayload>,
+) -> impl IntoResponse {
+	let result = email_change_service::undo_email_change(&pool, &payload.token, &context).await;
+	ApiResponse::from_result(result, StatusCode::NO_CONTENT).into_response()
+}
@@ -0,0 +1,52 @@
+use axum::{Router, routing::{delete, get, patch, post, put}};
+use utoipa::OpenApi;
+use utoipa_scalar::{Scalar, Servable};
+use crate::{
+	openapi::{self, ApiDoc},
+	routes::{audit, auth, events, health, job, project, schedule, search, sync, time_entry, trash, user, webhook, workspace},
+	state::AppState,
+};
+
+/// Every route of API version 1, relative to its `/api/v1` mount point.
+pub fn router() -> Router<AppState> {
+	Router::new()
+		.route("/auth/signup", post(auth::signup))
+		.route("/auth/login", post(auth::login))
+		.route("/me", get(user::get_me).patch(user::update_me).delete(user::delete_me))
+		.route("/me/password", put(user::change_password))
+		.route("/me/email", post(user::request_email_change))
+		.route("/email-changes/confirm", post(user::confirm_email_change))
+		.route("/email-changes/undo", post(user::undo_email_change))
+		.route("/workspaces", get(workspace::get_workspaces).post(workspace::create_workspace))
+		.route("/workspaces/{id}/switch", post(workspace::switch_workspace))
+		.route("/workspaces/{id}/members", get(workspace::get_members))
+		.route("/workspaces/{id}/members/{user_id}", patch(workspace::update_member).delete(workspace::remove_member))
+		.route("/workspaces/{id}/invitations", get(workspace::get_invitations).post(workspace::create_invitation))
+		.route("/workspaces/{id}/invitations/{invitation_id}", delete(workspace::revoke_invitation))
+		.route("/invitations/accept", post(workspace::accept_invitation))
+		.route("/projects", get(project::get_projects))
+		.route("/projects/{id}", get(project::get_project).delete(project::delete_project))
+		.route("/time-entries", get(time_entry::get_time_entries))
+		.route("/time-entries/{id}", get(time_entry::get_time_entry).delete(time_entry::delete_time_entry))
+		.route("/search", get(search::search_all))
+		.route("/sync", get(sync::get_sync).post(sync::post_sync))
+		.route("/trash", get(trash::get_trash))
+		.route("/trash/{kind}/{id}/restore", post(trash::restore_item))
+		.route("/events", get(events::stream_events))
+		.route("/webhooks", get(webhook::get_webhooks).post(webhook::create_webhook))
+		.route("/webhooks/{id}", patch(webhook::update_webhook).delete(webhook::delete_webhook))
+		.route("/webhooks/{id}/deliveries", get(webhook::get_deliveries))
+		.route("/webhooks/{id}/test", post(webhook::test_webhook))
+		.route("/audit/{entity_type}/{entity_id}", get(audit::get_entity_history))
+		.route("/admin/audit-events", get(audit::get_audit_events))
+		.route("/admin/jobs", get(job::get_jobs))
+		.route("/admin/jobs/{id}/retry", post(job::retry_job))
+		.route("/admin/schedules", get(schedule::get_schedules))
+		.route("/admin/schedules/{id}", patch(schedule::update_schedule))
+		.route("/admin/schedules/{id}/run", post(schedule::run_schedule))
+		.route("/admin/schedules/{id}/runs", get(schedule::get_schedule_runs))
+		.route("/health/live", get(health::live))
+		.route("/health/ready", get(health::ready))
+		.route("/openapi.json", get(openapi::openapi_json))
+		.merge(Scalar::with_url("/docs", ApiDoc::openapi()))
+}
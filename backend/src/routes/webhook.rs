@@ -0,0 +1,170 @@
+use std::sync::Arc;
+use axum::{
+	extract::{Path, State},
+	http::StatusCode,
+	response::IntoResponse,
+	Json,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	auth::workspace::WorkspaceUser,
+	config::Config,
+	models::{
+		response::{ApiResponse, EmptyResponse},
+		webhook::{
+			CreateWebhookPayload, CreatedWebhook, DeliveryFilter, UpdateWebhookPayload, Webhook, WebhookDelivery,
+			WebhookFilter,
+		},
+	},
+	util::{
+		error::AppResult,
+		idempotency::NO_STORE,
+		pagination::{ListQuery, PageParams},
+		webhook_service,
+	},
+};
+
+#[utoipa::path(
+	get,
+	path = "/webhooks",
+	tag = "webhooks",
+	security(("bearer" = [])),
+	params(PageParams, WebhookFilter),
+	responses(
+		(status = 200, description = "The user's webhooks and, to its owners and admins, the active workspace's, newest first", body = ApiResponse<Vec<Webhook>>),
+		(status = 400, description = "Invalid limit or cursor", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace", body = EmptyResponse),
+	)
+)]
+pub async fn get_webhooks(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	query: ListQuery<WebhookFilter>,
+) -> impl IntoResponse {
+	let result = webhook_service::list_webhooks(&pool, &user_id, &workspace_id, &query).await;
+	ApiResponse::from_page(result).into_response()
+}
+
+#[utoipa::path(
+	post,
+	path = "/webhooks",
+	tag = "webhooks",
+	security(("bearer" = [])),
+	request_body = CreateWebhookPayload,
+	responses(
+		(status = 201, description = "Subscribed; the response holds the signing secret", body = ApiResponse<CreatedWebhook>),
+		(status = 400, description = "Invalid or non-public URL, or invalid events for the scope", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace, or not an owner or admin of it for a workspace's webhook", body = EmptyResponse),
+	)
+)]
+pub async fn create_webhook(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	State(config): State<Arc<Config>>,
+	Json(payload): Json<CreateWebhookPayload>,
+) -> impl IntoResponse {
+	let result = webhook_service::create_webhook(&pool, &config, &user_id, &workspace_id, &payload).await;
+	(NO_STORE, ApiResponse::from_result(result, StatusCode::CREATED)).into_response()
+}
+
+#[utoipa::path(
+	patch,
+	path = "/webhooks/{id}",
+	tag = "webhooks",
+	security(("bearer" = [])),
+	params(("id" = Uuid, Path, description = "Id of the webhook")),
+	request_body = UpdateWebhookPayload,
+	responses(
+		(status = 200, description = "Updated", body = ApiResponse<Webhook>),
+		(status = 400, description = "Invalid or non-public URL, or invalid events", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace", body = EmptyResponse),
+		(status = 404, description = "No such webhook the user manages", body = EmptyResponse),
+	)
+)]
+pub async fn update_webhook(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	State(config): State<Arc<Config>>,
+	Path(id): Path<Uuid>,
+	Json(payload): Json<UpdateWebhookPayload>,
+) -> impl IntoResponse {
+	let result = webhook_service::update_webhook(&pool, &config, &user_id, &workspace_id, &id, &payload).await;
+	ApiResponse::from_result(result, StatusCode::OK).into_response()
+}
+
+#[utoipa::path(
+	delete,
+	path = "/webhooks/{id}",
+	tag = "webhooks",
+	security(("bearer" = [])),
+	params(("id" = Uuid, Path, description = "Id of the webhook")),
+	responses(
+		(status = 204, description = "Deleted together with its delivery log"),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace", body = EmptyResponse),
+		(status = 404, description = "No such webhook the user manages", body = EmptyResponse),
+	)
+)]
+pub async fn delete_webhook(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+	let result = webhook_service::delete_webhook(&pool, &user_id, &workspace_id, &id).await;
+	ApiResponse::from_result(result, StatusCode::NO_CONTENT).into_response()
+}
+
+#[utoipa::path(
+	get,
+	path = "/webhooks/{id}/deliveries",
+	tag = "webhooks",
+	security(("bearer" = [])),
+	params(("id" = Uuid, Path, description = "Id of the webhook"), PageParams, DeliveryFilter),
+	responses(
+		(status = 200, description = "Deliveries to the webhook, newest first", body = ApiResponse<Vec<WebhookDelivery>>),
+		(status = 400, description = "Invalid filter, limit or cursor", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace", body = EmptyResponse),
+		(status = 404, description = "No such webhook the user manages", body = EmptyResponse),
+	)
+)]
+pub async fn get_deliveries(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	Path(id): Path<Uuid>,
+	query: ListQuery<DeliveryFilter>,
+) -> impl IntoResponse {
+	let result = webhook_service::list_deliveries(&pool, &user_id, &workspace_id, &id, &query).await;
+	ApiResponse::from_page(result).into_response()
+}
+
+#[utoipa::path(
+	post,
+	path = "/webhooks/{id}/test",
+	tag = "webhooks",
+	security(("bearer" = [])),
+	params(("id" = Uuid, Path, description = "Id of the webhook")),
+	responses(
+		(status = 200, description = "A `ping` was sent; the delivery says how the receiver answered", body = ApiResponse<WebhookDelivery>),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Not a member of the active workspace", body = EmptyResponse),
+		(status = 404, description = "No such webhook the user manages", body = EmptyResponse),
+	)
+)]
+pub async fn test_webhook(
+	WorkspaceUser { user_id, workspace_id, .. }: WorkspaceUser,
+	State(pool): State<PgPool>,
+	State(config): State<Arc<Config>>,
+	Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+	let result: AppResult<WebhookDelivery> = async {
+		let client = webhook_service::http_client(&config)?;
+		webhook_service::send_test_event(&pool, &client, &user_id, &workspace_id, &id).await
+	}.await;
+
+	ApiResponse::from_result(result, StatusCode::OK).into_response()
+}
@@ -0,0 +1,267 @@
+use std::sync::Arc;
+use axum::{
+	extract::{Path, State},
+	http::StatusCode,
+	response::IntoResponse,
+	Json,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	auth::jwt::{generate_workspace_token, AuthUser},
+	config::Config,
+	models::{
+		response::{ApiResponse, EmptyResponse},
+		workspace::{
+			AcceptInvitationPayload, CreateInvitationPayload, CreateWorkspacePayload, Invitation,
+			UpdateMemberPayload, Workspace, WorkspaceMember, WorkspaceToken,
+		},
+	},
+	util::{
+		audit::AuditContext,
+		error::AppResult,
+		idempotency::NO_STORE,
+		workspace_service,
+	},
+};
+
+#[utoipa::path(
+	get,
+	path = "/workspaces",
+	tag = "workspaces",
+	security(("bearer" = [])),
+	responses(
+		(status = 200, description = "Workspaces the user belongs to, personal first", body = ApiResponse<Vec<Workspace>>),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+	)
+)]
+pub async fn get_workspaces(
+	AuthUser(user_id): AuthUser,
+	State(pool): State<PgPool>,
+) -> impl IntoResponse {
+	let result = workspace_service::list_workspaces(&pool, &user_id).await;
+	ApiResponse::from_result(result, StatusCode::OK).into_response()
+}
+
+#[utoipa::path(
+	post,
+	path = "/workspaces",
+	tag = "workspaces",
+	security(("bearer" = [])),
+	request_body = CreateWorkspacePayload,
+	responses(
+		(status = 201, description = "Created with the user as its owner", body = ApiResponse<Workspace>),
+		(status = 400, description = "Invalid name", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+	)
+)]
+pub async fn create_workspace(
+	AuthUser(user_id): AuthUser,
+	State(pool): State<PgPool>,
+	context: AuditContext,
+	Json(payload): Json<CreateWorkspacePayload>,
+) -> impl IntoResponse {
+	let result = workspace_service::create_workspace(&pool, &user_id, &payload, &context).await;
+	ApiResponse::from_result(result, StatusCode::CREATED).into_response()
+}
+
+/// Switching only issues a new token; the old one keeps acting in its workspace until it expires.
+#[utoipa::path(
+	post,
+	path = "/workspaces/{id}/switch",
+	tag = "workspaces",
+	security(("bearer" = [])),
+	params(("id" = Uuid, Path, description = "Id of the workspace")),
+	responses(
+		(status = 200, description = "A token with the workspace as the active one", body = ApiResponse<WorkspaceToken>),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 404, description = "No such workspace of the user's", body = EmptyResponse),
+	)
+)]
+pub async fn switch_workspace(
+	AuthUser(user_id): AuthUser,
+	State(pool): State<PgPool>,
+	Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+	let result: AppResult<WorkspaceToken> = async {
+		let workspace = workspace_service::fetch_workspace(&pool, &user_id, &id).await?;
+		let token = generate_workspace_token(user_id, workspace.id)?;
+		Ok(WorkspaceToken { token, workspace })
+	}.await;
+	(NO_STORE, ApiResponse::from_result(result, StatusCode::OK)).into_response()
+}
+
+#[utoipa::path(
+	get,
+	path = "/workspaces/{id}/members",
+	tag = "workspaces",
+	security(("bearer" = [])),
+	params(("id" = Uuid, Path, description = "Id of the workspace")),
+	responses(
+		(status = 200, description = "Members in the order they joined", body = ApiResponse<Vec<WorkspaceMember>>),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 404, description = "No such workspace of the user's", body = EmptyResponse),
+	)
+)]
+pub async fn get_members(
+	AuthUser(user_id): AuthUser,
+	State(pool): State<PgPool>,
+	Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+	let result = workspace_service::list_members(&pool, &user_id, &id).await;
+	ApiResponse::from_result(result, StatusCode::OK).into_response()
+}
+
+#[utoipa::path(
+	patch,
+	path = "/workspaces/{id}/members/{user_id}",
+	tag = "workspaces",
+	security(("bearer" = [])),
+	params(
+		("id" = Uuid, Path, description = "Id of the workspace"),
+		("user_id" = Uuid, Path, description = "Id of the member"),
+	),
+	request_body = UpdateMemberPayload,
+	responses(
+		(status = 200, description = "The member with their new role", body = ApiResponse<WorkspaceMember>),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "The user's role doesn't allow this change", body = EmptyResponse),
+		(status = 404, description = "No such workspace or member", body = EmptyResponse),
+		(status = 409, description = "The workspace would be left without an owner", body = EmptyResponse),
+	)
+)]
+pub async fn update_member(
+	AuthUser(user_id): AuthUser,
+	State(pool): State<PgPool>,
+	Path((id, member_id)): Path<(Uuid, Uuid)>,
+	context: AuditContext,
+	Json(payload): Json<UpdateMemberPayload>,
+) -> impl IntoResponse {
+	let result = workspace_service::update_member_role(&pool, &user_id, &id, &member_id, payload.role, &context).await;
+	ApiResponse::from_result(result, StatusCode::OK).into_response()
+}
+
+/// Members remove themselves to leave the workspace.
+#[utoipa::path(
+	delete,
+	path = "/workspaces/{id}/members/{user_id}",
+	tag = "workspaces",
+	security(("bearer" = [])),
+	params(
+		("id" = Uuid, Path, description = "Id of the workspace"),
+		("user_id" = Uuid, Path, description = "Id of the member"),
+	),
+	responses(
+		(status = 204, description = "Removed"),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "The user's role doesn't allow this, or it is the member's personal workspace", body = EmptyResponse),
+		(status = 404, description = "No such workspace or member", body = EmptyResponse),
+		(status = 409, description = "The workspace would be left without an owner", body = EmptyResponse),
+	)
+)]
+pub async fn remove_member(
+	AuthUser(user_id): AuthUser,
+	State(pool): State<PgPool>,
+	Path((id, member_id)): Path<(Uuid, Uuid)>,
+	context: AuditContext,
+) -> impl IntoResponse {
+	let result = workspace_service::remove_member(&pool, &user_id, &id, &member_id, &context).await;
+	ApiResponse::from_result(result, StatusCode::NO_CONTENT).into_response()
+}
+
+#[utoipa::path(
+	get,
+	path = "/workspaces/{id}/invitations",
+	tag = "workspaces",
+	security(("bearer" = [])),
+	params(("id" = Uuid, Path, description = "Id of the workspace")),
+	responses(
+		(status = 200, description = "Open invitations, newest first", body = ApiResponse<Vec<Invitation>>),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Only admins and owners see invitations", body = EmptyResponse),
+		(status = 404, description = "No such workspace of the user's", body = EmptyResponse),
+	)
+)]
+pub async fn get_invitations(
+	AuthUser(user_id): AuthUser,
+	State(pool): State<PgPool>,
+	Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+	let result = workspace_service::list_invitations(&pool, &user_id, &id).await;
+	ApiResponse::from_result(result, StatusCode::OK).into_response()
+}
+
+/// The invitee gets an email with the link to accept.
+#[utoipa::path(
+	post,
+	path = "/workspaces/{id}/invitations",
+	tag = "workspaces",
+	security(("bearer" = [])),
+	params(("id" = Uuid, Path, description = "Id of the workspace")),
+	request_body = CreateInvitationPayload,
+	responses(
+		(status = 201, description = "Invited", body = ApiResponse<Invitation>),
+		(status = 400, description = "Invalid email", body = EmptyResponse),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "The user's role doesn't allow inviting with this role", body = EmptyResponse),
+		(status = 404, description = "No such workspace of the user's", body = EmptyResponse),
+		(status = 409, description = "The address already belongs to a member", body = EmptyResponse),
+	)
+)]
+pub async fn create_invitation(
+	AuthUser(user_id): AuthUser,
+	State(pool): State<PgPool>,
+	State(config): State<Arc<Config>>,
+	Path(id): Path<Uuid>,
+	Json(payload): Json<CreateInvitationPayload>,
+) -> impl IntoResponse {
+	let result = workspace_service::create_invitation(&pool, &config, &user_id, &id, &payload).await;
+	ApiResponse::from_result(result, StatusCode::CREATED).into_response()
+}
+
+#[utoipa::path(
+	delete,
+	path = "/workspaces/{id}/invitations/{invitation_id}",
+	tag = "workspaces",
+	security(("bearer" = [])),
+	params(
+		("id" = Uuid, Path, description = "Id of the workspace"),
+		("invitation_id" = Uuid, Path, description = "Id of the invitation"),
+	),
+	responses(
+		(status = 204, description = "Revoked"),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 403, description = "Only admins and owners revoke invitations", body = EmptyResponse),
+		(status = 404, description = "No such open invitation", body = EmptyResponse),
+	)
+)]
+pub async fn revoke_invitation(
+	AuthUser(user_id): AuthUser,
+	State(pool): State<PgPool>,
+	Path((id, invitation_id)): Path<(Uuid, Uuid)>,
+) -> impl IntoResponse {
+	let result = workspace_service::revoke_invitation(&pool, &user_id, &id, &invitation_id).await;
+	ApiResponse::from_result(result, StatusCode::NO_CONTENT).into_response()
+}
+
+#[utoipa::path(
+	post,
+	path = "/invitations/accept",
+	tag = "workspaces",
+	security(("bearer" = [])),
+	request_body = AcceptInvitationPayload,
+	responses(
+		(status = 200, description = "The workspace joined", body = ApiResponse<Workspace>),
+		(status = 401, description = "Missing or invalid token", body = EmptyResponse),
+		(status = 404, description = "The invitation is invalid, expired, used, or for another address", body = EmptyResponse),
+	)
+)]
+pub async fn accept_invitation(
+	AuthUser(user_id): AuthUser,
+	State(pool): State<PgPool>,
+	context: AuditContext,
+	Json(payload): Json<AcceptInvitationPayload>,
+) -> impl IntoResponse {
+	let result = workspace_service::accept_invitation(&pool, &user_id, &payload.token, &context).await;
+	ApiResponse::from_result(result, StatusCode::OK).into_response()
+}
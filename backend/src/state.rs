@@ -0,0 +1,42 @@
+use std::sync::Arc;
+use axum::extract::FromRef;
+use sqlx::PgPool;
+use crate::{config::Config, util::events::EventHub};
+
+/// # AppState
+/// Shared state handed to every handler.
+/// Handlers that only need the database can keep extracting `State<PgPool>`.
+#[derive(Clone)]
+pub struct AppState {
+	pub pool: PgPool,
+	pub config: Arc<Config>,
+	pub events: EventHub,
+}
+
+impl AppState {
+	pub fn new(pool: PgPool, config: Config) -> Self {
+		Self {
+			pool,
+			config: Arc::new(config),
+			events: EventHub::new(),
+		}
+	}
+}
+
+impl FromRef<AppState> for PgPool {
+	fn from_ref(state: &AppState) -> Self {
+		state.pool.clone()
+	}
+}
+
+impl FromRef<AppState> for Arc<Config> {
+	fn from_ref(state: &AppState) -> Self {
+		state.config.clone()
+	}
+}
+
+impl FromRef<AppState> for EventHub {
+	fn from_ref(state: &AppState) -> Self {
+		state.events.clone()
+	}
+}
@@ -0,0 +1,144 @@
+use axum::{Router, middleware};
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use serde_json::json;
+use sqlx::PgPool;
+use tower::ServiceExt;
+use uuid::Uuid;
+use crate::{
+	config::Config,
+	models::{
+		audit::{AuditAction, AuditEvent, EntityType},
+		response::ApiResponse,
+		user::User,
+	},
+	routes,
+	state::AppState,
+	tests::support::{call, insert_admin, insert_tree, insert_user, parse},
+	util::{error::ErrorCode, request_id, tenant},
+};
+
+fn build_app(pool: PgPool) -> Router {
+	routes::router()
+		.layer(middleware::from_fn(request_id::propagate))
+		.with_state(AppState::new(pool, Config::default()))
+}
+
+async fn send(app: &Router, user: &User, method: Method, uri: &str) -> (StatusCode, Option<ApiResponse<Vec<AuditEvent>>>) {
+	let request = Request::builder()
+		.method(method)
+		.uri(uri)
+		// Versions are covered by the concurrency tests.
+		.header("If-Match", "*")
+		.header("X-Request-Id", "audit-test")
+		.header("X-Forwarded-For", "203.0.113.9");
+	parse(call(app, Some(user), request, None).await).await
+}
+
+#[sqlx::test]
+async fn test_signup_is_audited(pool: PgPool) {
+	let app = build_app(pool.clone());
+	let response = app
+		.oneshot(
+			Request::builder()
+				.method("POST")
+				.uri("/api/v1/auth/signup")
+				.header("Content-Type", "application/json")
+				.header("X-Request-Id", "signup-1")
+				.body(Body::from(json!({ "email": "new@example.com", "password": "SecurePassword123" }).to_string()))
+				.unwrap()
+		)
+		.await
+		.unwrap();
+	assert_eq!(response.status(), StatusCode::CREATED);
+
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	let (actor_id, entity_id, action, changes, request_id): (Option<Uuid>, Uuid, String, serde_json::Value, Option<String>) =
+		sqlx::query_as("SELECT actor_id, entity_id, action, changes, request_id FROM audit_events")
+			.fetch_one(&mut *tx)
+			.await
+			.unwrap();
+
+	assert_eq!(actor_id, Some(entity_id));
+	assert_eq!(action, "created");
+	assert_eq!(changes["email"], json!({ "before": null, "after": "new@example.com" }));
+	assert!(changes.get("password_hash").is_none());
+	assert_eq!(request_id.as_deref(), Some("signup-1"));
+}
+
+#[sqlx::test]
+async fn test_entity_history(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let stranger = insert_user(&pool, "stranger@example.com").await;
+	let project_id = insert_tree(&pool, &owner).await.project_id;
+	let app = build_app(pool);
+
+	send(&app, &owner, Method::DELETE, &format!("/api/v1/projects/{}", project_id)).await;
+	send(&app, &owner, Method::POST, &format!("/api/v1/trash/project/{}/restore", project_id)).await;
+
+	let uri = format!("/api/v1/audit/project/{}", project_id);
+	let (status, history) = send(&app, &owner, Method::GET, &uri).await;
+	assert_eq!(status, StatusCode::OK);
+
+	let events = history.unwrap().data.unwrap();
+	let actions: Vec<_> = events.iter().map(|event| event.action).collect();
+	assert_eq!(actions, vec![AuditAction::Restored, AuditAction::Deleted]);
+	assert!(events.iter().all(|event| event.actor_id == Some(owner.id)));
+	assert_eq!(events[0].changes["deleted_at"]["after"], json!(null));
+	assert_eq!(events[1].changes["deleted_at"]["before"], json!(null));
+	assert_eq!(events[1].request_id.as_deref(), Some("audit-test"));
+	assert_eq!(events[1].ip, None, "proxy headers are ignored unless trusted");
+
+	let (status, _) = send(&app, &stranger, Method::GET, &uri).await;
+	assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test]
+async fn test_admin_audit_log(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let admin = insert_admin(&pool, "admin@example.com").await;
+	let project_id = insert_tree(&pool, &owner).await.project_id;
+	let app = build_app(pool);
+
+	send(&app, &owner, Method::DELETE, &format!("/api/v1/projects/{}", project_id)).await;
+
+	let (status, response) = send(&app, &owner, Method::GET, "/api/v1/admin/audit-events").await;
+	assert_eq!(status, StatusCode::FORBIDDEN);
+	assert_eq!(response.unwrap().code, Some(ErrorCode::Forbidden));
+
+	let uri = format!("/api/v1/admin/audit-events?actor_id={}&entity_type=project&action=deleted", owner.id);
+	let (status, response) = send(&app, &admin, Method::GET, &uri).await;
+	assert_eq!(status, StatusCode::OK);
+
+	let events = response.unwrap().data.unwrap();
+	assert_eq!(events.len(), 1);
+	assert_eq!(events[0].entity_type, EntityType::Project);
+	assert_eq!(events[0].entity_id, project_id);
+
+	let (_, response) = send(&app, &admin, Method::GET, "/api/v1/admin/audit-events?action=restored").await;
+	assert!(response.unwrap().data.unwrap().is_empty());
+}
+
+#[sqlx::test]
+async fn test_audit_events_are_append_only(pool: PgPool) {
+	sqlx::query("INSERT INTO audit_events (entity_type, entity_id, action) VALUES ('user', gen_random_uuid(), 'created')")
+		.execute(&pool)
+		.await
+		.unwrap();
+
+	// Row-level security has no policy for updates or deletes, so an ordinary role changes nothing;
+	// the triggers refuse it to roles that skip row-level security, and refuse TRUNCATE to everyone.
+	for statement in ["UPDATE audit_events SET action = 'deleted'", "DELETE FROM audit_events", "TRUNCATE audit_events"] {
+		let mut tx = tenant::bypass(&pool).await.unwrap();
+		let result = sqlx::query(statement).execute(&mut *tx).await;
+		assert!(result.is_err() || result.unwrap().rows_affected() == 0, "{} should change nothing", statement);
+		tx.commit().await.ok();
+	}
+
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	let actions: Vec<String> = sqlx::query_scalar("SELECT action FROM audit_events")
+		.fetch_all(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!(actions, vec!["created"]);
+}
@@ -0,0 +1,126 @@
+use axum::http::{header, Method, Request, StatusCode};
+use axum::response::Response;
+use serde_json::json;
+use sqlx::PgPool;
+use crate::{
+	models::{project::Project, response::ApiResponse},
+	tests::support::{build_app, call, insert_tree, insert_user, insert_user_with_password, PASSWORD},
+	util::{error::ErrorCode, tenant},
+};
+
+async fn error_code(response: Response) -> Option<ErrorCode> {
+	let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+	serde_json::from_slice::<ApiResponse<()>>(&body).unwrap().code
+}
+
+fn etag(response: &Response) -> String {
+	response.headers()[header::ETAG].to_str().unwrap().to_owned()
+}
+
+#[sqlx::test]
+async fn test_get_returns_version_etag_and_304(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let project_id = insert_tree(&pool, &owner).await.project_id;
+	let app = build_app(pool.clone());
+	let uri = format!("/api/v1/projects/{}", project_id);
+
+	let response = call(&app, Some(&owner), Request::builder().uri(&uri), None).await;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(etag(&response), "\"1\"");
+	let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+	let project: ApiResponse<Project> = serde_json::from_slice(&body).unwrap();
+	assert_eq!(project.data.unwrap().version, 1);
+
+	let response = call(&app, Some(&owner), Request::builder().uri(&uri).header("If-None-Match", "\"1\""), None).await;
+	assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+	assert_eq!(etag(&response), "\"1\"");
+	let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+	assert!(body.is_empty());
+
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	sqlx::query("UPDATE projects SET name = 'Webshop' WHERE id = $1")
+		.bind(project_id)
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+
+	let response = call(&app, Some(&owner), Request::builder().uri(&uri).header("If-None-Match", "\"1\""), None).await;
+	assert_eq!(response.status(), StatusCode::OK, "the update bumped the version");
+	assert_eq!(etag(&response), "\"2\"");
+}
+
+#[sqlx::test]
+async fn test_lists_get_weak_etags(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	insert_tree(&pool, &owner).await;
+	let app = build_app(pool.clone());
+
+	let response = call(&app, Some(&owner), Request::builder().uri("/api/v1/projects"), None).await;
+	let tag = etag(&response);
+	assert!(tag.starts_with("W/\""));
+
+	let request = Request::builder().uri("/api/v1/projects").header("If-None-Match", &tag);
+	let response = call(&app, Some(&owner), request, None).await;
+	assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+	insert_tree(&pool, &owner).await;
+	let request = Request::builder().uri("/api/v1/projects").header("If-None-Match", &tag);
+	let response = call(&app, Some(&owner), request, None).await;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_ne!(etag(&response), tag);
+}
+
+#[sqlx::test]
+async fn test_delete_requires_current_version(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let project_id = insert_tree(&pool, &owner).await.project_id;
+	let app = build_app(pool.clone());
+	let uri = format!("/api/v1/projects/{}", project_id);
+	let delete = || Request::builder().method(Method::DELETE).uri(&uri);
+
+	let response = call(&app, Some(&owner), delete(), None).await;
+	assert_eq!(response.status(), StatusCode::PRECONDITION_REQUIRED);
+	assert_eq!(error_code(response).await, Some(ErrorCode::PreconditionRequired));
+
+	let response = call(&app, Some(&owner), delete().header("If-Match", "W/\"1\""), None).await;
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+	// Someone else renamed the project after this client fetched version 1.
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	sqlx::query("UPDATE projects SET name = 'Webshop' WHERE id = $1")
+		.bind(project_id)
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+
+	let response = call(&app, Some(&owner), delete().header("If-Match", "\"1\""), None).await;
+	assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+	assert_eq!(error_code(response).await, Some(ErrorCode::VersionMismatch));
+
+	let response = call(&app, Some(&owner), delete().header("If-Match", "\"2\""), None).await;
+	assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+	let response = call(&app, Some(&owner), delete().header("If-Match", "\"3\""), None).await;
+	assert_eq!(response.status(), StatusCode::NOT_FOUND, "a missing row is not reported as a version conflict");
+}
+
+#[sqlx::test]
+async fn test_second_writer_gets_412(pool: PgPool) {
+	let user = insert_user_with_password(&pool, "user@example.com").await;
+	let app = build_app(pool);
+
+	let response = call(&app, Some(&user), Request::builder().uri("/api/v1/me"), None).await;
+	let version = etag(&response);
+
+	let change = |new_password: &str| Some(json!({ "old_password": PASSWORD, "new_password": new_password }));
+	let put = || Request::builder().method(Method::PUT).uri("/api/v1/me/password").header("If-Match", &version);
+
+	let response = call(&app, Some(&user), put(), change("FirstDevice123")).await;
+	assert_eq!(response.status(), StatusCode::NO_CONTENT);
+	assert_eq!(etag(&response), "\"2\"");
+
+	let response = call(&app, Some(&user), put(), change("SecondDevice123")).await;
+	assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+}
@@ -0,0 +1,118 @@
+use axum::Router;
+use axum::http::{Method, Request, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use crate::{
+	models::{response::ApiResponse, user::{EmailChange, User}},
+	tests::support::{build_app, call, insert_user_with_password as insert_user, mailed_token, parse, PASSWORD},
+	util::{error::ErrorCode, tenant},
+};
+
+async fn email_of(pool: &PgPool, user: &User) -> String {
+	sqlx::query_scalar("SELECT email FROM users WHERE id = $1")
+		.bind(user.id)
+		.fetch_one(pool)
+		.await
+		.unwrap()
+}
+
+/// POSTs `body`, with a session if `user` is given; the links in the emails are followed without one.
+async fn send<T: DeserializeOwned>(
+	app: &Router,
+	user: Option<&User>,
+	uri: &str,
+	body: Value,
+) -> (StatusCode, Option<ApiResponse<T>>) {
+	parse(call(app, user, Request::builder().method(Method::POST).uri(uri), Some(body)).await).await
+}
+
+/// Requests the change and returns the tokens of the confirmation and undo links from the queued emails.
+async fn request_change(app: &Router, pool: &PgPool, user: &User, new_email: &str) -> (String, String) {
+	let body = json!({ "new_email": new_email, "password": PASSWORD });
+	let (status, change) = send::<EmailChange>(app, Some(user), "/api/v1/me/email", body).await;
+	assert_eq!(status, StatusCode::ACCEPTED);
+	assert_eq!(change.unwrap().data.unwrap().new_email, new_email);
+
+	(mailed_token(pool, new_email).await, mailed_token(pool, &user.email).await)
+}
+
+#[sqlx::test]
+async fn test_email_switches_after_confirmation(pool: PgPool) {
+	let user = insert_user(&pool, "old@example.com").await;
+	let app = build_app(pool.clone());
+
+	let body = json!({ "new_email": "new@example.com", "password": "WrongPassword123" });
+	let (status, response) = send::<()>(&app, Some(&user), "/api/v1/me/email", body).await;
+	assert_eq!(status, StatusCode::UNAUTHORIZED);
+	assert_eq!(response.unwrap().code, Some(ErrorCode::IncorrectPassword));
+	let (status, _) = send::<()>(&app, None, "/api/v1/me/email", json!({ "new_email": "new@example.com", "password": PASSWORD })).await;
+	assert_eq!(status, StatusCode::UNAUTHORIZED, "a session is required");
+
+	let (confirm, _) = request_change(&app, &pool, &user, "new@example.com").await;
+	assert_eq!(email_of(&pool, &user).await, "old@example.com", "nothing switches before confirmation");
+
+	let (status, _) = send::<()>(&app, None, "/api/v1/email-changes/confirm", json!({ "token": confirm })).await;
+	assert_eq!(status, StatusCode::NO_CONTENT);
+	assert_eq!(email_of(&pool, &user).await, "new@example.com");
+
+	let (status, response) = send::<()>(&app, None, "/api/v1/email-changes/confirm", json!({ "token": confirm })).await;
+	assert_eq!(status, StatusCode::NOT_FOUND, "links work once");
+	assert_eq!(response.unwrap().code, Some(ErrorCode::EmailChangeInvalid));
+
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	let changes: Value = sqlx::query_scalar(
+		"SELECT changes FROM audit_events WHERE entity_type = 'user' AND entity_id = $1 AND actor_id = $1"
+	)
+		.bind(user.id)
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!(changes["email"], json!({ "before": "old@example.com", "after": "new@example.com" }));
+}
+
+#[sqlx::test]
+async fn test_email_uniqueness_is_rechecked(pool: PgPool) {
+	let user = insert_user(&pool, "old@example.com").await;
+	insert_user(&pool, "taken@example.com").await;
+	let app = build_app(pool.clone());
+
+	let body = json!({ "new_email": "taken@example.com", "password": PASSWORD });
+	let (status, response) = send::<()>(&app, Some(&user), "/api/v1/me/email", body).await;
+	assert_eq!(status, StatusCode::UNAUTHORIZED);
+	assert_eq!(response.unwrap().code, Some(ErrorCode::EmailTaken));
+
+	let (confirm, _) = request_change(&app, &pool, &user, "free@example.com").await;
+	insert_user(&pool, "free@example.com").await;
+	let (_, response) = send::<()>(&app, None, "/api/v1/email-changes/confirm", json!({ "token": confirm })).await;
+	assert_eq!(response.unwrap().code, Some(ErrorCode::EmailTaken), "taken while waiting for confirmation");
+	assert_eq!(email_of(&pool, &user).await, "old@example.com");
+}
+
+#[sqlx::test]
+async fn test_old_address_can_undo(pool: PgPool) {
+	let user = insert_user(&pool, "old@example.com").await;
+	let app = build_app(pool.clone());
+
+	let (first_confirm, _) = request_change(&app, &pool, &user, "first@example.com").await;
+	let (confirm, undo) = request_change(&app, &pool, &user, "hijacker@example.com").await;
+	let (status, _) = send::<()>(&app, None, "/api/v1/email-changes/confirm", json!({ "token": first_confirm })).await;
+	assert_eq!(status, StatusCode::NOT_FOUND, "a new request replaces the pending one");
+
+	let (status, _) = send::<()>(&app, None, "/api/v1/email-changes/confirm", json!({ "token": confirm })).await;
+	assert_eq!(status, StatusCode::NO_CONTENT);
+	assert_eq!(email_of(&pool, &user).await, "hijacker@example.com");
+
+	let (status, _) = send::<()>(&app, None, "/api/v1/email-changes/undo", json!({ "token": undo })).await;
+	assert_eq!(status, StatusCode::NO_CONTENT);
+	assert_eq!(email_of(&pool, &user).await, "old@example.com");
+	let (status, _) = send::<()>(&app, None, "/api/v1/email-changes/undo", json!({ "token": undo })).await;
+	assert_eq!(status, StatusCode::NOT_FOUND);
+
+	let (confirm, undo) = request_change(&app, &pool, &user, "pending@example.com").await;
+	let (status, _) = send::<()>(&app, None, "/api/v1/email-changes/undo", json!({ "token": undo })).await;
+	assert_eq!(status, StatusCode::NO_CONTENT);
+	let (status, _) = send::<()>(&app, None, "/api/v1/email-changes/confirm", json!({ "token": confirm })).await;
+	assert_eq!(status, StatusCode::NOT_FOUND, "an undone change can't be confirmed");
+	assert_eq!(email_of(&pool, &user).await, "old@example.com");
+}
@@ -0,0 +1,117 @@
+use axum::{Router, middleware, routing::{get, post}};
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use serde_json::json;
+use sqlx::PgPool;
+use tower::ServiceExt;
+use crate::{
+	config::Config,
+	models::response::ApiResponse,
+	routes::{auth::signup, user},
+	state::AppState,
+	util::{error::ErrorCode, problem::{self, ProblemDetails}}
+};
+
+fn build_app(pool: PgPool) -> Router {
+	Router::new()
+		.route("/signup", post(signup))
+		.route("/me", get(user::get_me))
+		.layer(middleware::from_fn(problem::negotiate))
+		.with_state(AppState::new(pool, Config::default()))
+}
+
+fn signup_request(accept: &str) -> Request<Body> {
+	Request::builder()
+		.method("POST")
+		.uri("/signup")
+		.header("Content-Type", "application/json")
+		.header("Accept", accept)
+		.body(Body::from(json!({
+			"email": "test@example.com",
+			"password": "short"
+		}).to_string()))
+		.unwrap()
+}
+
+#[sqlx::test]
+async fn test_validation_error_details(pool: PgPool) {
+	let app = build_app(pool);
+	let response = app.oneshot(signup_request("application/json")).await.unwrap();
+
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+	let body = axum::body::to_bytes(response.into_body(), 8 * 1024).await.unwrap();
+	let api_response: ApiResponse<()> = serde_json::from_slice(&body).unwrap();
+
+	assert_eq!(api_response.code, Some(ErrorCode::ValidationFailed));
+	assert!(api_response.details.iter().all(|field| field.field == "password"));
+	assert!(api_response.details.iter().any(|field| field.code == ErrorCode::PasswordTooShort));
+}
+
+#[sqlx::test]
+async fn test_problem_json_response(pool: PgPool) {
+	let app = build_app(pool);
+	let response = app.oneshot(signup_request("application/problem+json")).await.unwrap();
+
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+	assert_eq!(response.headers()["content-type"], "application/problem+json");
+
+	let body = axum::body::to_bytes(response.into_body(), 8 * 1024).await.unwrap();
+	let problem: ProblemDetails = serde_json::from_slice(&body).unwrap();
+
+	assert_eq!(problem.status, 400);
+	assert_eq!(problem.code, ErrorCode::ValidationFailed);
+	assert_eq!(problem.problem_type, "urn:kvitter:problem:validation.failed");
+	assert!(!problem.errors.is_empty());
+}
+
+#[sqlx::test]
+async fn test_extractor_rejection_has_code(pool: PgPool) {
+	let app = build_app(pool);
+	let response = app
+		.oneshot(
+			Request::builder()
+				.uri("/me")
+				.header("Accept", "application/problem+json")
+				.body(Body::empty())
+				.unwrap()
+		)
+		.await
+		.unwrap();
+
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+	let body = axum::body::to_bytes(response.into_body(), 8 * 1024).await.unwrap();
+	let problem: ProblemDetails = serde_json::from_slice(&body).unwrap();
+
+	assert_eq!(problem.code, ErrorCode::MissingToken);
+}
+
+#[sqlx::test]
+async fn test_internal_error_is_generic(pool: PgPool) {
+	pool.close().await;
+	let app = build_app(pool);
+	let response = app
+		.oneshot(
+			Request::builder()
+				.method("POST")
+				.uri("/signup")
+				.header("Content-Type", "application/json")
+				.body(Body::from(json!({
+					"email": "test@example.com",
+					"password": "SecurePassword123"
+				}).to_string()))
+				.unwrap()
+		)
+		.await
+		.unwrap();
+
+	assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+	let body = axum::body::to_bytes(response.into_body(), 8 * 1024).await.unwrap();
+	let api_response: ApiResponse<()> = serde_json::from_slice(&body).unwrap();
+	let error_id = api_response.error_id.expect("internal errors carry an error id");
+
+	assert_eq!(api_response.code, Some(ErrorCode::Database));
+	assert_eq!(api_response.error, Some(format!("Internal server error (error id: {})", error_id)));
+}
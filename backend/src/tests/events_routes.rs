@@ -0,0 +1,152 @@
+use std::time::Duration;
+use axum::Router;
+use axum::body::BodyDataStream;
+use axum::http::{header, Request, StatusCode};
+use futures_util::StreamExt;
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	config::Config,
+	models::{audit::{AuditAction, EntityType}, event::DomainEvent, user::User},
+	routes,
+	state::AppState,
+	tests::support::{self, call, insert_client, insert_job, insert_user, log_time, personal_workspace},
+	util::{events, tenant, trash_service},
+};
+
+async fn build_app(pool: PgPool) -> Router {
+	let state = AppState::new(pool, Config::default());
+	events::spawn_listener(state.pool.clone(), state.events.clone()).await.unwrap();
+	routes::router().with_state(state)
+}
+
+/// A project of `owner` with one job; returns both ids.
+async fn insert_project(pool: &PgPool, owner: &User) -> (Uuid, Uuid) {
+	let client_id = insert_client(pool, owner, "Acme").await;
+	let project_id = support::insert_project(pool, owner, client_id, "Website").await;
+
+	(project_id, insert_job(pool, project_id, "Design").await)
+}
+
+async fn subscribe(app: &Router, user: &User) -> BodyDataStream {
+	let response = call(app, Some(user), Request::builder().uri("/api/v1/events"), None).await;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.headers()[header::CONTENT_TYPE], "text/event-stream");
+
+	response.into_body().into_data_stream()
+}
+
+/// The next `change` event on the stream, skipping keep-alive comments.
+async fn next_change(stream: &mut BodyDataStream) -> DomainEvent {
+	let mut buffer = String::new();
+	loop {
+		let chunk = tokio::time::timeout(Duration::from_secs(5), stream.next())
+			.await
+			.expect("no event within 5 seconds")
+			.unwrap()
+			.unwrap();
+		buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+
+		while let Some(end) = buffer.find("\n\n") {
+			let frame = buffer[..end].to_owned();
+			buffer.drain(..end + 2);
+			if frame.lines().any(|line| line == "event: change") {
+				let data = frame.lines().find_map(|line| line.strip_prefix("data: ")).unwrap();
+				return serde_json::from_str(data).unwrap();
+			}
+		}
+	}
+}
+
+#[sqlx::test]
+async fn test_teammates_see_each_others_changes(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let teammate = insert_user(&pool, "teammate@example.com").await;
+	let (project_id, job_id) = insert_project(&pool, &owner).await;
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	sqlx::query("INSERT INTO project_members (project_id, user_id, role) VALUES ($1, $2, 'member')")
+		.bind(project_id)
+		.bind(teammate.id)
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+	let app = build_app(pool.clone()).await;
+	let mut stream = subscribe(&app, &owner).await;
+
+	let entry_id = log_time(&pool, &teammate, job_id).await;
+	assert_eq!(next_change(&mut stream).await, DomainEvent {
+		entity_type: EntityType::TimeEntry,
+		entity_id: entry_id,
+		action: AuditAction::Created,
+		project_id,
+		workspace_id: personal_workspace(&pool, &owner).await,
+		version: 1,
+	});
+
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	sqlx::query("UPDATE time_entries SET deleted_at = NOW() WHERE id = $1")
+		.bind(entry_id)
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+	let event = next_change(&mut stream).await;
+	assert_eq!((event.entity_id, event.action, event.version), (entry_id, AuditAction::Deleted, 2));
+}
+
+#[sqlx::test]
+async fn test_events_are_scoped_to_the_users_projects(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let outsider = insert_user(&pool, "outsider@example.com").await;
+	let (_, job_id) = insert_project(&pool, &owner).await;
+	let (own_project, own_job) = insert_project(&pool, &outsider).await;
+	let app = build_app(pool.clone()).await;
+	let mut stream = subscribe(&app, &outsider).await;
+
+	log_time(&pool, &owner, job_id).await;
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	sqlx::query("UPDATE jobs SET name = 'Build' WHERE id = $1")
+		.bind(own_job)
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+
+	let event = next_change(&mut stream).await;
+	assert_eq!((event.entity_id, event.project_id), (own_job, own_project), "the other project's entry was skipped");
+	assert_eq!(event.action, AuditAction::Updated);
+}
+
+#[sqlx::test]
+async fn test_purges_are_announced(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let (project_id, job_id) = insert_project(&pool, &owner).await;
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	sqlx::query("UPDATE projects SET deleted_at = NOW() - INTERVAL '60 days' WHERE id = $1")
+		.bind(project_id)
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+	let app = build_app(pool.clone()).await;
+	let mut stream = subscribe(&app, &owner).await;
+
+	trash_service::purge_expired(&pool, Duration::from_secs(30 * 24 * 60 * 60)).await.unwrap();
+
+	let mut purged = Vec::new();
+	for _ in 0..2 {
+		let event = next_change(&mut stream).await;
+		assert_eq!(event.action, AuditAction::Purged);
+		purged.push(event.entity_id);
+	}
+	assert_eq!(purged, vec![job_id, project_id]);
+}
+
+#[sqlx::test]
+async fn test_events_require_a_token(pool: PgPool) {
+	let app = build_app(pool).await;
+
+	let response = call(&app, None, Request::builder().uri("/api/v1/events"), None).await;
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,89 @@
+use axum::{Router, routing::get};
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use sqlx::PgPool;
+use tower::ServiceExt;
+use crate::{
+	config::{Config, MailerConfig, StorageConfig},
+	models::{health::{HealthStatus, ReadinessReport}, response::ApiResponse},
+	routes::health,
+	state::AppState
+};
+
+fn build_app(pool: PgPool, config: Config) -> Router {
+	Router::new()
+		.route("/health/live", get(health::live))
+		.route("/health/ready", get(health::ready))
+		.with_state(AppState::new(pool, config))
+}
+
+async fn get_readiness(app: Router) -> (StatusCode, ReadinessReport) {
+	let response = app
+		.oneshot(Request::builder().uri("/health/ready").body(Body::empty()).unwrap())
+		.await
+		.unwrap();
+	let status = response.status();
+	let body = axum::body::to_bytes(response.into_body(), 8 * 1024).await.unwrap();
+	let api_response: ApiResponse<ReadinessReport> = serde_json::from_slice(&body).unwrap();
+
+	(status, api_response.data.unwrap())
+}
+
+#[sqlx::test]
+async fn test_liveness(pool: PgPool) {
+	let app = build_app(pool, Config::default());
+	let response = app
+		.oneshot(Request::builder().uri("/health/live").body(Body::empty()).unwrap())
+		.await
+		.unwrap();
+
+	assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[sqlx::test]
+async fn test_readiness_ok(pool: PgPool) {
+	let (status, report) = get_readiness(build_app(pool, Config::default())).await;
+
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!(report.status, HealthStatus::Ok);
+	assert_eq!(report.components["database"].status, HealthStatus::Ok);
+	assert_eq!(report.components["migrations"].status, HealthStatus::Ok);
+	assert_eq!(report.components["storage"].status, HealthStatus::Disabled);
+	assert_eq!(report.components["mailer"].status, HealthStatus::Disabled);
+}
+
+#[sqlx::test]
+async fn test_readiness_database_down(pool: PgPool) {
+	pool.close().await;
+	let (status, report) = get_readiness(build_app(pool, Config::default())).await;
+
+	assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+	assert_eq!(report.status, HealthStatus::Error);
+	assert_eq!(report.components["database"].status, HealthStatus::Error);
+	assert!(report.components["database"].detail.as_deref().unwrap().contains("error id"));
+}
+
+#[sqlx::test]
+async fn test_readiness_misconfigured_components(pool: PgPool) {
+	let config = Config {
+		storage: Some(StorageConfig { root: "/nonexistent/kvitter".into() }),
+		mailer: Some(MailerConfig {
+			smtp_host: "localhost".into(),
+			smtp_port: None,
+			from_address: None,
+			username: None,
+			password: None,
+		}),
+		..Config::default()
+	};
+	let (status, report) = get_readiness(build_app(pool, config)).await;
+
+	assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+	assert_eq!(report.components["database"].status, HealthStatus::Ok);
+	assert_eq!(report.components["storage"].status, HealthStatus::Error);
+	assert_eq!(report.components["mailer"].status, HealthStatus::Error);
+
+	let detail = report.components["storage"].detail.as_deref().unwrap();
+	assert!(detail.starts_with("Storage is not writable (error id: "), "{}", detail);
+	assert!(!detail.contains("/nonexistent") && !detail.contains("No such file"), "the cause stays in the log");
+}
@@ -0,0 +1,217 @@
+use std::panic::AssertUnwindSafe;
+use axum::{Router, middleware, routing::post};
+use axum::http::{Method, Request, StatusCode};
+use axum::response::Response;
+use futures_util::FutureExt;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use tower::ServiceExt;
+use crate::{
+	config::Config,
+	models::{response::ApiResponse, user::User},
+	routes,
+	state::AppState,
+	tests::support::{bearer, call, insert_user},
+	util::{error::ErrorCode, idempotency},
+};
+
+async fn panicking() -> StatusCode {
+	panic!("handler bug")
+}
+
+fn build_app(pool: PgPool) -> Router {
+	let state = AppState::new(pool, Config::default());
+	routes::router()
+		.route("/panic", post(panicking))
+		.layer(middleware::from_fn_with_state(state.clone(), idempotency::handle))
+		.with_state(state)
+}
+
+async fn create_workspace(app: &Router, user: &User, key: &str, name: &str) -> Response {
+	let request = Request::builder().method(Method::POST).uri("/api/v1/workspaces").header("Idempotency-Key", key);
+	call(app, Some(user), request, Some(json!({ "name": name }))).await
+}
+
+/// The key a request for `name` would be stored under.
+fn fingerprint(name: &str) -> String {
+	hex::encode(Sha256::digest(format!("POST\n/api/v1/workspaces\n{}", json!({ "name": name }))))
+}
+
+async fn body(response: Response) -> Value {
+	let bytes = axum::body::to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+	serde_json::from_slice(&bytes).unwrap()
+}
+
+async fn error_code(response: Response) -> Option<ErrorCode> {
+	serde_json::from_value::<ApiResponse<()>>(body(response).await).unwrap().code
+}
+
+async fn workspace_count(pool: &PgPool) -> i64 {
+	sqlx::query_scalar("SELECT COUNT(*) FROM workspaces WHERE NOT personal").fetch_one(pool).await.unwrap()
+}
+
+async fn key_count(pool: &PgPool) -> i64 {
+	sqlx::query_scalar("SELECT COUNT(*) FROM idempotency_keys").fetch_one(pool).await.unwrap()
+}
+
+#[sqlx::test]
+async fn test_retry_replays_the_first_response(pool: PgPool) {
+	let user = insert_user(&pool, "retry@example.com").await;
+	let app = build_app(pool.clone());
+
+	let first = create_workspace(&app, &user, "create-1", "Agency").await;
+	assert_eq!(first.status(), StatusCode::CREATED);
+	assert!(first.headers().get("idempotent-replayed").is_none());
+	let first = body(first).await;
+
+	let retry = create_workspace(&app, &user, "create-1", "Agency").await;
+	assert_eq!(retry.status(), StatusCode::CREATED);
+	assert_eq!(retry.headers()["idempotent-replayed"], "true");
+	assert_eq!(retry.headers()["content-type"], "application/json");
+	assert_eq!(body(retry).await, first, "the same workspace comes back");
+
+	assert_eq!(workspace_count(&pool).await, 1);
+}
+
+#[sqlx::test]
+async fn test_key_reused_for_another_request(pool: PgPool) {
+	let user = insert_user(&pool, "reuse@example.com").await;
+	let app = build_app(pool.clone());
+
+	create_workspace(&app, &user, "create-1", "Agency").await;
+	let response = create_workspace(&app, &user, "create-1", "Studio").await;
+	assert_eq!(response.status(), StatusCode::CONFLICT);
+	assert_eq!(error_code(response).await, Some(ErrorCode::IdempotencyKeyReused));
+	assert_eq!(workspace_count(&pool).await, 1);
+}
+
+#[sqlx::test]
+async fn test_key_in_progress(pool: PgPool) {
+	let user = insert_user(&pool, "slow@example.com").await;
+	let app = build_app(pool.clone());
+	sqlx::query(
+		"INSERT INTO idempotency_keys (user_id, key, fingerprint, expires_at) VALUES ($1, 'slow', $2, NOW() + INTERVAL '1 hour')"
+	)
+		.bind(user.id)
+		.bind(fingerprint("Agency"))
+		.execute(&pool)
+		.await
+		.unwrap();
+
+	let response = create_workspace(&app, &user, "slow", "Agency").await;
+	assert_eq!(response.status(), StatusCode::CONFLICT);
+	assert_eq!(error_code(response).await, Some(ErrorCode::IdempotencyKeyInProgress));
+}
+
+#[sqlx::test]
+async fn test_abandoned_key_is_taken_over(pool: PgPool) {
+	let user = insert_user(&pool, "abandoned@example.com").await;
+	let app = build_app(pool.clone());
+	sqlx::query(
+		"INSERT INTO idempotency_keys (user_id, key, fingerprint, created_at, expires_at) \
+		VALUES ($1, 'crashed', $2, NOW() - INTERVAL '10 minutes', NOW() + INTERVAL '1 hour')"
+	)
+		.bind(user.id)
+		.bind(fingerprint("Agency"))
+		.execute(&pool)
+		.await
+		.unwrap();
+
+	let response = create_workspace(&app, &user, "crashed", "Agency").await;
+	assert_eq!(response.status(), StatusCode::CREATED);
+	assert!(response.headers().get("idempotent-replayed").is_none());
+	assert_eq!(workspace_count(&pool).await, 1);
+}
+
+#[sqlx::test]
+async fn test_expired_key_is_reused(pool: PgPool) {
+	let user = insert_user(&pool, "late@example.com").await;
+	let app = build_app(pool.clone());
+	sqlx::query(
+		"INSERT INTO idempotency_keys (user_id, key, fingerprint, status, expires_at) \
+		VALUES ($1, 'old', 'other', 201, NOW() - INTERVAL '1 second')"
+	)
+		.bind(user.id)
+		.execute(&pool)
+		.await
+		.unwrap();
+
+	let response = create_workspace(&app, &user, "old", "Agency").await;
+	assert_eq!(response.status(), StatusCode::CREATED);
+	assert!(response.headers().get("idempotent-replayed").is_none());
+	assert_eq!(workspace_count(&pool).await, 1);
+}
+
+#[sqlx::test]
+async fn test_keys_are_scoped_per_user(pool: PgPool) {
+	let app = build_app(pool.clone());
+
+	for email in ["a@example.com", "b@example.com"] {
+		let user = insert_user(&pool, email).await;
+		let response = create_workspace(&app, &user, "shared", "Agency").await;
+		assert_eq!(response.status(), StatusCode::CREATED);
+		assert!(response.headers().get("idempotent-replayed").is_none());
+	}
+
+	assert_eq!(workspace_count(&pool).await, 2);
+	assert_eq!(key_count(&pool).await, 2);
+}
+
+#[sqlx::test]
+async fn test_anonymous_requests_are_refused(pool: PgPool) {
+	let app = build_app(pool.clone());
+
+	let request = Request::builder().method(Method::POST).uri("/api/v1/auth/signup").header("Idempotency-Key", "1");
+	let body = json!({ "email": "anonymous@example.com", "password": "SecurePassword123" });
+	let response = call(&app, None, request, Some(body)).await;
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+	let users: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users").fetch_one(&pool).await.unwrap();
+	assert_eq!((users, key_count(&pool).await), (0, 0));
+}
+
+#[sqlx::test]
+async fn test_credentials_are_not_stored(pool: PgPool) {
+	let user = insert_user(&pool, "switch@example.com").await;
+	let app = build_app(pool.clone());
+	let workspace: Value = body(create_workspace(&app, &user, "create-1", "Agency").await).await;
+	let uri = format!("/api/v1/workspaces/{}/switch", workspace["data"]["id"].as_str().unwrap());
+
+	for _ in 0..2 {
+		let request = Request::builder().method(Method::POST).uri(&uri).header("Idempotency-Key", "switch-1");
+		let response = call(&app, Some(&user), request, None).await;
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(response.headers()["cache-control"], "no-store");
+		assert!(response.headers().get("idempotent-replayed").is_none(), "a fresh token every time");
+	}
+
+	assert_eq!(key_count(&pool).await, 1, "only the workspace's key is kept");
+}
+
+#[sqlx::test]
+async fn test_panicking_handler_releases_the_key(pool: PgPool) {
+	let user = insert_user(&pool, "panic@example.com").await;
+	let app = build_app(pool.clone());
+	let request = Request::builder()
+		.method(Method::POST)
+		.uri("/panic")
+		.header("Authorization", bearer(&user))
+		.header("Idempotency-Key", "panic-1")
+		.body(axum::body::Body::empty())
+		.unwrap();
+
+	let outcome = AssertUnwindSafe(app.oneshot(request)).catch_unwind().await;
+	assert!(outcome.is_err());
+	assert_eq!(key_count(&pool).await, 0);
+}
+
+#[sqlx::test]
+async fn test_invalid_key(pool: PgPool) {
+	let user = insert_user(&pool, "invalid@example.com").await;
+	let app = build_app(pool.clone());
+
+	let response = create_workspace(&app, &user, "not a key", "Agency").await;
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+	assert_eq!(workspace_count(&pool).await, 0);
+}
@@ -0,0 +1,279 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	config::Config,
+	models::background_job::{BackgroundJob, JobStatus},
+	tests::support::{build_app, insert_admin, insert_user, send},
+	util::{
+		error::{AppError, AppResult, ErrorCode},
+		job_queue::{self, Job, JobContext, JobRegistry},
+		mailer::{self, SendEmail},
+	},
+};
+
+/// Fails its first `failures` attempts.
+#[derive(Serialize, Deserialize)]
+struct Flaky {
+	failures: i32,
+}
+
+#[async_trait]
+impl Job for Flaky {
+	const KIND: &'static str = "test.flaky";
+	const MAX_ATTEMPTS: i32 = 3;
+
+	async fn run(self, context: &JobContext) -> AppResult<()> {
+		match context.attempt <= self.failures {
+			true => Err(AppError::BadRequest(ErrorCode::BadRequest, format!("attempt {} failed", context.attempt))),
+			false => Ok(()),
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+struct Sleepy {
+	millis: u64,
+}
+
+#[async_trait]
+impl Job for Sleepy {
+	const KIND: &'static str = "test.sleepy";
+
+	async fn run(self, _context: &JobContext) -> AppResult<()> {
+		tokio::time::sleep(Duration::from_millis(self.millis)).await;
+		Ok(())
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+struct Panicky {}
+
+#[async_trait]
+impl Job for Panicky {
+	const KIND: &'static str = "test.panicky";
+
+	async fn run(self, _context: &JobContext) -> AppResult<()> {
+		panic!("handler bug");
+	}
+}
+
+fn registry() -> JobRegistry {
+	JobRegistry::default()
+		.register::<SendEmail>()
+		.register::<Flaky>()
+		.register::<Sleepy>()
+		.register::<Panicky>()
+}
+
+fn config() -> Arc<Config> {
+	Arc::new(Config { job_timeout: Duration::from_millis(200), ..Config::default() })
+}
+
+async fn fetch_job(pool: &PgPool, id: Uuid) -> BackgroundJob {
+	sqlx::query_as::<_, BackgroundJob>("SELECT * FROM background_jobs WHERE id = $1")
+		.bind(id)
+		.fetch_one(pool)
+		.await
+		.unwrap()
+}
+
+/// Makes a queued job due right away instead of waiting out its backoff.
+async fn make_due(pool: &PgPool, id: Uuid) {
+	sqlx::query("UPDATE background_jobs SET run_at = NOW() WHERE id = $1")
+		.bind(id)
+		.execute(pool)
+		.await
+		.unwrap();
+}
+
+#[sqlx::test]
+async fn test_successful_job(pool: PgPool) {
+	let id = job_queue::enqueue(&pool, &Flaky { failures: 0 }, None).await.unwrap();
+
+	let ran = job_queue::run_next(&pool, &config(), &registry()).await.unwrap();
+	assert_eq!(ran, Some(id));
+
+	let job = fetch_job(&pool, id).await;
+	assert_eq!(job.status, JobStatus::Succeeded);
+	assert_eq!(job.attempts, 1);
+	assert_eq!(job.payload, Some(json!({ "failures": 0 })));
+	assert!(job.finished_at.is_some());
+
+	assert_eq!(job_queue::run_next(&pool, &config(), &registry()).await.unwrap(), None);
+}
+
+#[sqlx::test]
+async fn test_failures_back_off_then_dead_letter(pool: PgPool) {
+	let id = job_queue::enqueue(&pool, &Flaky { failures: 10 }, None).await.unwrap();
+
+	job_queue::run_next(&pool, &config(), &registry()).await.unwrap();
+	let job = fetch_job(&pool, id).await;
+	assert_eq!(job.status, JobStatus::Queued);
+	assert_eq!(job.last_error.as_deref(), Some("Bad request: attempt 1 failed"));
+	assert!(job.run_at > Utc::now() + chrono::Duration::seconds(5));
+
+	// Backing off, so there's nothing due.
+	assert_eq!(job_queue::run_next(&pool, &config(), &registry()).await.unwrap(), None);
+
+	for _ in 0..2 {
+		make_due(&pool, id).await;
+		job_queue::run_next(&pool, &config(), &registry()).await.unwrap();
+	}
+	let job = fetch_job(&pool, id).await;
+	assert_eq!(job.status, JobStatus::Dead);
+	assert_eq!(job.attempts, Flaky::MAX_ATTEMPTS);
+	assert_eq!(job.last_error.as_deref(), Some("Bad request: attempt 3 failed"));
+
+	make_due(&pool, id).await;
+	assert_eq!(job_queue::run_next(&pool, &config(), &registry()).await.unwrap(), None);
+}
+
+#[sqlx::test]
+async fn test_timeouts_and_panics_are_failures(pool: PgPool) {
+	let sleepy = job_queue::enqueue(&pool, &Sleepy { millis: 5_000 }, None).await.unwrap();
+	let panicky = job_queue::enqueue(&pool, &Panicky {}, None).await.unwrap();
+
+	job_queue::run_next(&pool, &config(), &registry()).await.unwrap();
+	job_queue::run_next(&pool, &config(), &registry()).await.unwrap();
+
+	let job = fetch_job(&pool, sleepy).await;
+	assert_eq!(job.status, JobStatus::Queued);
+	assert!(job.last_error.unwrap().contains("timed out"));
+
+	let job = fetch_job(&pool, panicky).await;
+	assert_eq!(job.status, JobStatus::Queued);
+	assert_eq!(job.last_error.as_deref(), Some("Job panicked"));
+}
+
+#[sqlx::test]
+async fn test_scheduled_and_unknown_jobs(pool: PgPool) {
+	let later = Utc::now() + chrono::Duration::hours(1);
+	job_queue::enqueue(&pool, &Flaky { failures: 0 }, Some(later)).await.unwrap();
+	assert_eq!(job_queue::run_next(&pool, &config(), &registry()).await.unwrap(), None);
+
+	let unknown: Uuid = sqlx::query_scalar(
+		"INSERT INTO background_jobs (kind, payload, max_attempts) VALUES ('test.retired', '{}', 5) RETURNING id"
+	)
+		.fetch_one(&pool)
+		.await
+		.unwrap();
+	assert_eq!(job_queue::run_next(&pool, &config(), &registry()).await.unwrap(), Some(unknown));
+
+	let job = fetch_job(&pool, unknown).await;
+	assert_eq!(job.status, JobStatus::Dead);
+	assert_eq!(job.attempts, 1);
+	assert_eq!(job.last_error.as_deref(), Some("No handler for job kind test.retired"));
+}
+
+#[sqlx::test]
+async fn test_concurrent_workers_run_each_job_once(pool: PgPool) {
+	for _ in 0..6 {
+		job_queue::enqueue(&pool, &Sleepy { millis: 20 }, None).await.unwrap();
+	}
+
+	let workers = (0..4).map(|_| {
+		let pool = pool.clone();
+		tokio::spawn(async move {
+			let mut ran = Vec::new();
+			while let Some(id) = job_queue::run_next(&pool, &config(), &registry()).await.unwrap() {
+				ran.push(id);
+			}
+			ran
+		})
+	});
+	let mut ran = Vec::new();
+	for worker in workers.collect::<Vec<_>>() {
+		ran.extend(worker.await.unwrap());
+	}
+
+	assert_eq!(ran.len(), 6);
+	assert_eq!(ran.iter().collect::<HashSet<_>>().len(), 6);
+	let attempts: Vec<i32> = sqlx::query_scalar("SELECT attempts FROM background_jobs WHERE status = 'succeeded'")
+		.fetch_all(&pool)
+		.await
+		.unwrap();
+	assert_eq!(attempts, vec![1; 6]);
+}
+
+#[sqlx::test]
+async fn test_expired_lease_is_reclaimed(pool: PgPool) {
+	let id = job_queue::enqueue(&pool, &Flaky { failures: 0 }, None).await.unwrap();
+	// A worker claimed it and died.
+	sqlx::query("UPDATE background_jobs SET status = 'running', attempts = 1, locked_until = NOW() + INTERVAL '1 minute'")
+		.execute(&pool)
+		.await
+		.unwrap();
+	assert_eq!(job_queue::run_next(&pool, &config(), &registry()).await.unwrap(), None);
+
+	sqlx::query("UPDATE background_jobs SET locked_until = NOW() - INTERVAL '1 second'")
+		.execute(&pool)
+		.await
+		.unwrap();
+	assert_eq!(job_queue::run_next(&pool, &config(), &registry()).await.unwrap(), Some(id));
+
+	let job = fetch_job(&pool, id).await;
+	assert_eq!(job.status, JobStatus::Succeeded);
+	assert_eq!(job.attempts, 2);
+}
+
+#[sqlx::test]
+async fn test_admin_lists_and_retries_dead_jobs(pool: PgPool) {
+	let user = insert_user(&pool, "user@example.com").await;
+	let admin = insert_admin(&pool, "admin@example.com").await;
+	let dead = job_queue::enqueue(&pool, &Flaky { failures: 0 }, None).await.unwrap();
+	let queued = job_queue::enqueue(&pool, &Flaky { failures: 0 }, None).await.unwrap();
+	sqlx::query("UPDATE background_jobs SET status = 'dead', attempts = 3, last_error = 'boom' WHERE id = $1")
+		.bind(dead)
+		.execute(&pool)
+		.await
+		.unwrap();
+	let app = build_app(pool.clone());
+
+	let (status, _) = send::<()>(&app, &user, Method::GET, "/api/v1/admin/jobs", None).await;
+	assert_eq!(status, StatusCode::FORBIDDEN);
+	let (status, _) = send::<()>(&app, &user, Method::POST, &format!("/api/v1/admin/jobs/{}/retry", dead), None).await;
+	assert_eq!(status, StatusCode::FORBIDDEN);
+
+	let (status, response) = send::<Vec<BackgroundJob>>(&app, &admin, Method::GET, "/api/v1/admin/jobs?status=dead", None).await;
+	assert_eq!(status, StatusCode::OK);
+	let jobs = response.unwrap().data.unwrap();
+	assert_eq!(jobs.iter().map(|job| job.id).collect::<Vec<_>>(), vec![dead]);
+	assert_eq!(jobs[0].last_error.as_deref(), Some("boom"));
+
+	let (status, _) = send::<()>(&app, &admin, Method::POST, &format!("/api/v1/admin/jobs/{}/retry", queued), None).await;
+	assert_eq!(status, StatusCode::NOT_FOUND);
+
+	let (status, response) = send::<BackgroundJob>(&app, &admin, Method::POST, &format!("/api/v1/admin/jobs/{}/retry", dead), None).await;
+	assert_eq!(status, StatusCode::OK);
+	let job = response.unwrap().data.unwrap();
+	assert_eq!(job.status, JobStatus::Queued);
+	assert_eq!(job.attempts, 0);
+
+	job_queue::run_next(&pool, &config(), &registry()).await.unwrap();
+	job_queue::run_next(&pool, &config(), &registry()).await.unwrap();
+	assert_eq!(fetch_job(&pool, dead).await.status, JobStatus::Succeeded);
+}
+
+#[sqlx::test]
+async fn test_email_payloads_are_hidden_and_deleted_once_sent(pool: PgPool) {
+	let admin = insert_admin(&pool, "admin@example.com").await;
+	let email = SendEmail { to: "user@example.com".into(), subject: "Confirm".into(), body: "token=secret".into() };
+	mailer::send(&pool, &email).await.unwrap();
+	let app = build_app(pool.clone());
+
+	let (_, response) = send::<Vec<BackgroundJob>>(&app, &admin, Method::GET, "/api/v1/admin/jobs?kind=mail.send", None).await;
+	let jobs = response.unwrap().data.unwrap();
+	assert_eq!(jobs.len(), 1);
+	assert_eq!(jobs[0].payload, None);
+
+	job_queue::run_next(&pool, &config(), &registry()).await.unwrap();
+	let job = fetch_job(&pool, jobs[0].id).await;
+	assert_eq!(job.status, JobStatus::Succeeded);
+	assert_eq!(job.payload, None, "the links in the email are gone");
+}
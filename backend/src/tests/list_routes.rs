@@ -0,0 +1,196 @@
+use axum::Router;
+use axum::http::{Request, StatusCode};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::NaiveDate;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	auth::workspace::WORKSPACE_HEADER,
+	models::{project::Project, response::ApiResponse, time_entry::TimeEntry, user::User},
+	tests::support::{self, build_app, call, insert_client, insert_job, insert_user, parse},
+	util::{error::ErrorCode, tenant},
+};
+
+async fn insert_project(pool: &PgPool, owner: &User, client_id: Uuid, name: &str, status: &str) -> Uuid {
+	let project_id = support::insert_project(pool, owner, client_id, name).await;
+	let mut tx = tenant::bypass(pool).await.unwrap();
+	sqlx::query("UPDATE projects SET status = $1::project_status WHERE id = $2")
+		.bind(status)
+		.bind(project_id)
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+	project_id
+}
+
+async fn insert_time_entries(pool: &PgPool, user: &User, job_id: Uuid, first: NaiveDate, days: i32) {
+	let mut tx = tenant::bypass(pool).await.unwrap();
+	sqlx::query(
+		"INSERT INTO time_entries (job_id, user_id, time_spent, entry_date)
+		SELECT $1, $2, INTERVAL '90 minutes', $3 + day FROM generate_series(0, $4 - 1) AS day"
+	)
+		.bind(job_id)
+		.bind(user.id)
+		.bind(first)
+		.bind(days)
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+}
+
+async fn get<T: serde::de::DeserializeOwned>(app: &Router, user: &User, uri: &str) -> (StatusCode, ApiResponse<T>) {
+	get_in(app, user, None, uri).await
+}
+
+/// Like `get`, acting in `workspace_id` rather than the user's personal workspace.
+async fn get_in<T: serde::de::DeserializeOwned>(
+	app: &Router,
+	user: &User,
+	workspace_id: Option<Uuid>,
+	uri: &str,
+) -> (StatusCode, ApiResponse<T>) {
+	let mut request = Request::builder().uri(uri);
+	if let Some(workspace_id) = workspace_id {
+		request = request.header(WORKSPACE_HEADER, workspace_id.to_string());
+	}
+	let (status, response) = parse(call(app, Some(user), request, None).await).await;
+
+	(status, response.unwrap())
+}
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+	NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+#[sqlx::test]
+async fn test_time_entries_walk_all_pages(pool: PgPool) {
+	let user = insert_user(&pool, "pages@example.com").await;
+	let client_id = insert_client(&pool, &user, "Acme").await;
+	let project_id = insert_project(&pool, &user, client_id, "Website", "ACTIVE").await;
+	let job_id = insert_job(&pool, project_id, "Work").await;
+	insert_time_entries(&pool, &user, job_id, date(2025, 1, 1), 25).await;
+	let app = build_app(pool);
+
+	let mut seen = Vec::new();
+	let mut cursor: Option<String> = None;
+	loop {
+		let uri = match &cursor {
+			Some(cursor) => format!("/api/v1/time-entries?limit=10&cursor={}", cursor),
+			None => "/api/v1/time-entries?limit=10".into(),
+		};
+		let (status, page) = get::<Vec<TimeEntry>>(&app, &user, &uri).await;
+		assert_eq!(status, StatusCode::OK);
+
+		let entries = page.data.unwrap();
+		assert!(entries.len() <= 10);
+		seen.extend(entries);
+
+		match page.next_cursor {
+			Some(next) => cursor = Some(next),
+			None => break,
+		}
+	}
+
+	assert_eq!(seen.len(), 25);
+	assert_eq!(seen[0].entry_date, date(2025, 1, 25));
+	assert_eq!(seen[0].time_spent_seconds, 90 * 60);
+	assert!(seen.windows(2).all(|pair| pair[0].entry_date > pair[1].entry_date));
+}
+
+#[sqlx::test]
+async fn test_time_entries_filters(pool: PgPool) {
+	let user = insert_user(&pool, "filters@example.com").await;
+	let other = insert_user(&pool, "other@example.com").await;
+	let acme = insert_client(&pool, &user, "Acme").await;
+	let globex = insert_client(&pool, &user, "Globex").await;
+	let acme_job = insert_job(&pool, insert_project(&pool, &user, acme, "Website", "ACTIVE").await, "Work").await;
+	let globex_job = insert_job(&pool, insert_project(&pool, &user, globex, "Shop", "ACTIVE").await, "Work").await;
+	insert_time_entries(&pool, &user, acme_job, date(2025, 3, 1), 10).await;
+	insert_time_entries(&pool, &user, globex_job, date(2025, 3, 1), 5).await;
+	insert_time_entries(&pool, &other, acme_job, date(2025, 3, 1), 7).await;
+	let app = build_app(pool);
+
+	let (_, all) = get::<Vec<TimeEntry>>(&app, &user, "/api/v1/time-entries").await;
+	assert_eq!(all.data.unwrap().len(), 15);
+
+	let uri = format!("/api/v1/time-entries?client_id={}&from=2025-03-03&to=2025-03-05&sort=entry_date&order=asc", acme);
+	let (status, filtered) = get::<Vec<TimeEntry>>(&app, &user, &uri).await;
+	assert_eq!(status, StatusCode::OK);
+
+	let entries = filtered.data.unwrap();
+	let dates: Vec<_> = entries.iter().map(|entry| entry.entry_date).collect();
+	assert_eq!(dates, vec![date(2025, 3, 3), date(2025, 3, 4), date(2025, 3, 5)]);
+	assert!(entries.iter().all(|entry| entry.job_id == acme_job));
+}
+
+#[sqlx::test]
+async fn test_projects_sorted_and_filtered(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let member = insert_user(&pool, "member@example.com").await;
+	let stranger = insert_user(&pool, "stranger@example.com").await;
+	let client_id = insert_client(&pool, &owner, "Acme").await;
+	for (name, status) in [("Charlie", "ACTIVE"), ("Alpha", "ACTIVE"), ("Bravo", "ARCHIVED")] {
+		insert_project(&pool, &owner, client_id, name, status).await;
+	}
+	let workspace_id: Uuid = sqlx::query_scalar(
+		"INSERT INTO workspace_members (workspace_id, user_id) \
+		SELECT id, $2 FROM workspaces WHERE personal AND created_by = $1 RETURNING workspace_id"
+	)
+		.bind(owner.id)
+		.bind(member.id)
+		.fetch_one(&pool)
+		.await
+		.unwrap();
+	let app = build_app(pool);
+
+	let (_, own) = get::<Vec<Project>>(&app, &member, "/api/v1/projects").await;
+	assert!(own.data.unwrap().is_empty(), "the member's personal workspace is active by default");
+
+	let uri = "/api/v1/projects?sort=name&order=asc&limit=2";
+	let (_, first) = get_in::<Vec<Project>>(&app, &member, Some(workspace_id), uri).await;
+	let names: Vec<_> = first.data.unwrap().into_iter().map(|project| project.name).collect();
+	assert_eq!(names, vec!["Alpha", "Bravo"]);
+
+	let uri = format!("/api/v1/projects?sort=name&order=asc&limit=2&cursor={}", first.next_cursor.unwrap());
+	let (_, second) = get_in::<Vec<Project>>(&app, &member, Some(workspace_id), &uri).await;
+	let names: Vec<_> = second.data.unwrap().into_iter().map(|project| project.name).collect();
+	assert_eq!(names, vec!["Charlie"]);
+	assert!(second.next_cursor.is_none());
+
+	let (_, active) = get::<Vec<Project>>(&app, &owner, "/api/v1/projects?status=ACTIVE").await;
+	assert_eq!(active.data.unwrap().len(), 2);
+
+	let (_, none) = get::<Vec<Project>>(&app, &stranger, "/api/v1/projects").await;
+	assert!(none.data.unwrap().is_empty());
+
+	let (status, _) = get_in::<()>(&app, &stranger, Some(workspace_id), "/api/v1/projects").await;
+	assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test]
+async fn test_invalid_list_queries(pool: PgPool) {
+	let user = insert_user(&pool, "invalid@example.com").await;
+	let app = build_app(pool);
+	let tampered = URL_SAFE_NO_PAD.encode(
+		json!({ "sort": "entry_date", "order": "desc", "value": "last tuesday", "id": Uuid::new_v4() }).to_string()
+	);
+	let tampered = format!("/api/v1/time-entries?cursor={}", tampered);
+
+	let cases = [
+		("/api/v1/projects?sort=password_hash", ErrorCode::InvalidQuery),
+		("/api/v1/projects?status=DELETED", ErrorCode::InvalidQuery),
+		("/api/v1/projects?limit=1000", ErrorCode::ValidationFailed),
+		("/api/v1/time-entries?from=2025-02-01&to=2025-01-01", ErrorCode::ValidationFailed),
+		("/api/v1/time-entries?cursor=garbage", ErrorCode::InvalidCursor),
+		(&tampered, ErrorCode::InvalidCursor),
+	];
+
+	for (uri, code) in cases {
+		let (status, response) = get::<()>(&app, &user, uri).await;
+		assert_eq!(status, StatusCode::BAD_REQUEST, "{}", uri);
+		assert_eq!(response.code, Some(code), "{}", uri);
+	}
+}
@@ -0,0 +1,48 @@
+use axum::{Router, middleware, routing::get};
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use sqlx::PgPool;
+use tower::ServiceExt;
+use crate::{
+	config::Config,
+	routes::{health, metrics},
+	state::AppState,
+	util::metrics::{handle, track_requests}
+};
+
+fn build_app(pool: PgPool) -> Router {
+	handle();
+
+	Router::new()
+		.route("/health/live", get(health::live))
+		.route("/metrics", get(metrics::render))
+		.layer(middleware::from_fn(track_requests))
+		.with_state(AppState::new(pool, Config::default()))
+}
+
+#[sqlx::test]
+async fn test_metrics_endpoint(pool: PgPool) {
+	let app = build_app(pool);
+
+	let live_response = app
+		.clone()
+		.oneshot(Request::builder().uri("/health/live").body(Body::empty()).unwrap())
+		.await
+		.unwrap();
+	assert_eq!(live_response.status(), StatusCode::OK);
+
+	let response = app
+		.oneshot(Request::builder().uri("/metrics").body(Body::empty()).unwrap())
+		.await
+		.unwrap();
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/plain"));
+
+	let body = axum::body::to_bytes(response.into_body(), 64 * 1024).await.unwrap();
+	let text = String::from_utf8(body.to_vec()).unwrap();
+
+	assert!(text.contains(r#"http_requests_total{method="GET",route="/health/live",status="200"}"#));
+	assert!(text.contains("http_request_duration_seconds_bucket"));
+	assert!(text.contains(r#"db_pool_connections{state="idle"}"#));
+	assert!(text.contains("signups_total"));
+}
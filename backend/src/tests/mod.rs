@@ -1 +1,22 @@
-mod user_routes;
\ No newline at end of file
+mod audit_routes;
+mod concurrency_routes;
+mod email_change_routes;
+mod error_routes;
+mod events_routes;
+mod health_routes;
+mod idempotency_routes;
+mod job_routes;
+mod list_routes;
+mod metrics_routes;
+mod openapi;
+mod request_id_routes;
+mod schedule_routes;
+mod search_routes;
+mod support;
+mod sync_routes;
+mod tenant_routes;
+mod trash_routes;
+mod user_routes;
+mod version_routes;
+mod webhook_routes;
+mod workspace_routes;
\ No newline at end of file
@@ -0,0 +1,31 @@
+use std::{env, fs, path::PathBuf};
+use utoipa::OpenApi;
+use crate::openapi::ApiDoc;
+
+fn spec_path() -> PathBuf {
+	PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("openapi.json")
+}
+
+/// Fails when handler annotations or models change without regenerating the committed spec.
+/// Run with `UPDATE_OPENAPI=1 cargo test openapi` to rewrite `openapi.json`.
+#[test]
+fn test_openapi_spec_is_up_to_date() {
+	let generated = ApiDoc::openapi().to_pretty_json().unwrap() + "\n";
+
+	if env::var("UPDATE_OPENAPI").is_ok() {
+		fs::write(spec_path(), &generated).unwrap();
+		return;
+	}
+
+	let committed = fs::read_to_string(spec_path()).unwrap_or_default();
+	assert!(
+		committed == generated,
+		"openapi.json is out of date, regenerate it with `UPDATE_OPENAPI=1 cargo test openapi`"
+	);
+}
+
+#[test]
+fn test_openapi_version() {
+	let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+	assert!(spec["openapi"].as_str().unwrap().starts_with("3.1"));
+}
@@ -0,0 +1,85 @@
+use axum::{Router, middleware, routing::get};
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use sqlx::PgPool;
+use tower::ServiceExt;
+use crate::{
+	config::Config,
+	models::{response::ApiResponse, user::PublicUser},
+	routes::{health, user},
+	state::AppState,
+	util::request_id
+};
+
+fn build_app(pool: PgPool) -> Router {
+	Router::new()
+		.route("/health/live", get(health::live))
+		.route("/me", get(user::get_me))
+		.layer(middleware::from_fn(request_id::propagate))
+		.with_state(AppState::new(pool, Config::default()))
+}
+
+#[sqlx::test]
+async fn test_request_id_is_generated(pool: PgPool) {
+	let app = build_app(pool);
+	let response = app
+		.oneshot(Request::builder().uri("/health/live").body(Body::empty()).unwrap())
+		.await
+		.unwrap();
+
+	assert_eq!(response.status(), StatusCode::OK);
+	let header = response.headers()["x-request-id"].to_str().unwrap();
+	assert!(uuid::Uuid::parse_str(header).is_ok());
+}
+
+#[sqlx::test]
+async fn test_request_id_in_error_response(pool: PgPool) {
+	let app = build_app(pool);
+	let response = app
+		.oneshot(
+			Request::builder()
+				.uri("/me")
+				.header("X-Request-Id", "client-supplied-id")
+				.body(Body::empty())
+				.unwrap()
+		)
+		.await
+		.unwrap();
+
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+	assert_eq!(response.headers()["x-request-id"], "client-supplied-id");
+
+	let body = axum::body::to_bytes(response.into_body(), 8 * 1024).await.unwrap();
+	let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+	assert_eq!(json["request_id"], "client-supplied-id");
+}
+
+#[sqlx::test]
+async fn test_request_id_in_api_response(pool: PgPool) {
+	let app = build_app(pool);
+	let token = crate::auth::jwt::generate_jwt_token(&crate::models::user::User {
+		id: uuid::Uuid::new_v4(),
+		email: "missing@example.com".into(),
+		password_hash: String::new(),
+		created_at: chrono::Utc::now().naive_utc(),
+		version: 1,
+		profile: Default::default(),
+	}).unwrap();
+	let response = app
+		.oneshot(
+			Request::builder()
+				.uri("/me")
+				.header("Authorization", format!("Bearer {}", token))
+				.header("X-Request-Id", "lookup-42")
+				.body(Body::empty())
+				.unwrap()
+		)
+		.await
+		.unwrap();
+
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+	let body = axum::body::to_bytes(response.into_body(), 8 * 1024).await.unwrap();
+	let api_response: ApiResponse<PublicUser> = serde_json::from_slice(&body).unwrap();
+	assert_eq!(api_response.request_id.as_deref(), Some("lookup-42"));
+}
@@ -0,0 +1,158 @@
+use std::sync::Arc;
+use axum::http::{Method, StatusCode};
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	config::Config,
+	models::{
+		background_job::JobStatus,
+		schedule::{RunTrigger, ScheduledTask, ScheduledTaskRun},
+	},
+	tests::support::{build_app, insert_admin, insert_user, send},
+	util::{job_queue, scheduler},
+};
+
+async fn task_id(pool: &PgPool, name: &str) -> Uuid {
+	sqlx::query_scalar("SELECT id FROM scheduled_tasks WHERE name = $1")
+		.bind(name)
+		.fetch_one(pool)
+		.await
+		.unwrap()
+}
+
+async fn make_due(pool: &PgPool, name: &str) {
+	sqlx::query("UPDATE scheduled_tasks SET next_run_at = NOW() - INTERVAL '1 second' WHERE name = $1")
+		.bind(name)
+		.execute(pool)
+		.await
+		.unwrap();
+}
+
+async fn queued_kinds(pool: &PgPool) -> Vec<String> {
+	sqlx::query_scalar("SELECT kind FROM background_jobs ORDER BY created_at")
+		.fetch_all(pool)
+		.await
+		.unwrap()
+}
+
+#[sqlx::test]
+async fn test_due_tasks_queue_jobs_and_advance(pool: PgPool) {
+	let registry = job_queue::registry();
+	let mut conn = pool.acquire().await.unwrap();
+
+	// Seeded tasks start out due.
+	assert_eq!(scheduler::fire_due(&mut conn, &registry).await.unwrap(), 2);
+	assert_eq!(scheduler::fire_due(&mut conn, &registry).await.unwrap(), 0);
+	let mut kinds = queued_kinds(&pool).await;
+	kinds.sort();
+	assert_eq!(kinds, vec!["idempotency_keys.cleanup", "trash.purge"]);
+
+	let task: ScheduledTask = sqlx::query_as("SELECT * FROM scheduled_tasks WHERE name = 'purge-trash'")
+		.fetch_one(&pool)
+		.await
+		.unwrap();
+	assert!(task.next_run_at > Utc::now());
+	assert!(task.next_run_at <= Utc::now() + Duration::hours(1));
+	assert!(task.last_run_at.is_some());
+
+	sqlx::query("UPDATE scheduled_tasks SET enabled = FALSE WHERE name = 'purge-trash'")
+		.execute(&pool)
+		.await
+		.unwrap();
+	make_due(&pool, "purge-trash").await;
+	make_due(&pool, "cleanup-idempotency-keys").await;
+	assert_eq!(scheduler::fire_due(&mut conn, &registry).await.unwrap(), 1);
+	assert_eq!(queued_kinds(&pool).await.iter().filter(|kind| *kind == "trash.purge").count(), 1);
+}
+
+#[sqlx::test]
+async fn test_task_without_handler_is_skipped(pool: PgPool) {
+	sqlx::query("UPDATE scheduled_tasks SET next_run_at = NOW() + INTERVAL '1 hour'")
+		.execute(&pool)
+		.await
+		.unwrap();
+	sqlx::query("INSERT INTO scheduled_tasks (name, schedule, job_kind) VALUES ('retired', '0 0 * * * *', 'retired.kind')")
+		.execute(&pool)
+		.await
+		.unwrap();
+	let mut conn = pool.acquire().await.unwrap();
+
+	assert_eq!(scheduler::fire_due(&mut conn, &job_queue::registry()).await.unwrap(), 1);
+	assert!(queued_kinds(&pool).await.is_empty());
+	// Moved on rather than retried every tick.
+	assert_eq!(scheduler::fire_due(&mut conn, &job_queue::registry()).await.unwrap(), 0);
+}
+
+#[sqlx::test]
+async fn test_one_leader_at_a_time(pool: PgPool) {
+	let leader = scheduler::try_lead(&pool).await.unwrap().expect("first replica leads");
+	assert!(scheduler::try_lead(&pool).await.unwrap().is_none());
+
+	leader.close().await.unwrap();
+	let leader = scheduler::try_lead(&pool).await.unwrap().expect("lock released with the connection");
+	leader.close().await.unwrap();
+}
+
+#[sqlx::test]
+async fn test_admin_runs_task_now(pool: PgPool) {
+	let user = insert_user(&pool, "user@example.com").await;
+	let admin = insert_admin(&pool, "admin@example.com").await;
+	let id = task_id(&pool, "purge-trash").await;
+	let app = build_app(pool.clone());
+
+	let (status, _) = send::<Value>(&app, &user, Method::POST, &format!("/api/v1/admin/schedules/{}/run", id), None).await;
+	assert_eq!(status, StatusCode::FORBIDDEN);
+	let (status, _) = send::<Value>(&app, &admin, Method::POST, &format!("/api/v1/admin/schedules/{}/run", Uuid::new_v4()), None).await;
+	assert_eq!(status, StatusCode::NOT_FOUND);
+
+	let (status, response) = send::<Value>(&app, &admin, Method::POST, &format!("/api/v1/admin/schedules/{}/run", id), None).await;
+	assert_eq!(status, StatusCode::ACCEPTED);
+	let run: ScheduledTaskRun = serde_json::from_value(response.unwrap().data.unwrap()).unwrap();
+	assert_eq!(run.trigger, RunTrigger::Manual);
+	assert_eq!(run.triggered_by, Some(admin.id));
+	assert_eq!(run.job_status, Some(JobStatus::Queued));
+
+	let config = Arc::new(Config::default());
+	assert_eq!(job_queue::run_next(&pool, &config, &job_queue::registry()).await.unwrap(), run.job_id);
+
+	let (status, response) = send::<Value>(&app, &admin, Method::GET, &format!("/api/v1/admin/schedules/{}/runs", id), None).await;
+	assert_eq!(status, StatusCode::OK);
+	let runs: Vec<ScheduledTaskRun> = serde_json::from_value(response.unwrap().data.unwrap()).unwrap();
+	assert_eq!(runs.len(), 1);
+	assert_eq!(runs[0].job_status, Some(JobStatus::Succeeded));
+	assert!(runs[0].finished_at.is_some());
+
+	let (_, response) = send::<Value>(&app, &admin, Method::GET, &format!("/api/v1/admin/schedules/{}/runs?trigger=schedule", id), None).await;
+	assert_eq!(response.unwrap().data.unwrap(), json!([]));
+}
+
+#[sqlx::test]
+async fn test_admin_updates_schedule(pool: PgPool) {
+	let user = insert_user(&pool, "user@example.com").await;
+	let admin = insert_admin(&pool, "admin@example.com").await;
+	let id = task_id(&pool, "purge-trash").await;
+	let app = build_app(pool.clone());
+	let uri = format!("/api/v1/admin/schedules/{}", id);
+
+	let (status, _) = send::<Value>(&app, &user, Method::GET, "/api/v1/admin/schedules", None).await;
+	assert_eq!(status, StatusCode::FORBIDDEN);
+	let (status, response) = send::<Value>(&app, &admin, Method::GET, "/api/v1/admin/schedules", None).await;
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!(response.unwrap().data.unwrap().as_array().unwrap().len(), 2);
+
+	let (status, response) = send::<Value>(&app, &admin, Method::PATCH, &uri, Some(json!({ "schedule": "every night" }))).await;
+	assert_eq!(status, StatusCode::BAD_REQUEST);
+	assert_eq!(response.unwrap().details[0].field, "schedule");
+	let (status, _) = send::<Value>(&app, &admin, Method::PATCH, &uri, Some(json!({ "schedule": "0 0 0 1 1 * 2001" }))).await;
+	assert_eq!(status, StatusCode::BAD_REQUEST);
+
+	let (status, response) = send::<Value>(&app, &admin, Method::PATCH, &uri, Some(json!({ "schedule": "15 3 * * *", "enabled": false }))).await;
+	assert_eq!(status, StatusCode::OK);
+	let task: ScheduledTask = serde_json::from_value(response.unwrap().data.unwrap()).unwrap();
+	assert_eq!(task.schedule, "15 3 * * *");
+	assert!(!task.enabled);
+	assert_eq!(task.next_run_at, scheduler::next_run("15 3 * * *", task.next_run_at - Duration::days(1)).unwrap());
+	assert!(task.next_run_at > Utc::now());
+}
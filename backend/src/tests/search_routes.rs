@@ -0,0 +1,103 @@
+use axum::Router;
+use axum::http::{Request, StatusCode};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	models::{response::ApiResponse, search::{SearchKind, SearchResult}, user::User},
+	tests::support::{build_app, call, insert_client, insert_job, insert_project, insert_user, parse},
+	util::{error::ErrorCode, tenant},
+};
+
+/// One client, project and job owned by `owner`, plus a time entry with `entry` as its description.
+async fn insert_workspace(pool: &PgPool, owner: &User, client: &str, project: &str, entry: &str) -> Uuid {
+	let client_id = insert_client(pool, owner, client).await;
+	let project_id = insert_project(pool, owner, client_id, project).await;
+	let job_id = insert_job(pool, project_id, "Backend work").await;
+	let mut tx = tenant::bypass(pool).await.unwrap();
+	sqlx::query(
+		"INSERT INTO time_entries (job_id, user_id, time_spent, description, entry_date)
+		VALUES ($1, $2, INTERVAL '2 hours', $3, CURRENT_DATE)"
+	)
+		.bind(job_id)
+		.bind(owner.id)
+		.bind(entry)
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+
+	project_id
+}
+
+async fn search(app: &Router, user: &User, query: &str) -> (StatusCode, ApiResponse<Vec<SearchResult>>) {
+	let request = Request::builder().uri(format!("/api/v1/search?{}", query));
+	let (status, response) = parse(call(app, Some(user), request, None).await).await;
+
+	(status, response.unwrap())
+}
+
+#[sqlx::test]
+async fn test_search_finds_time_entry(pool: PgPool) {
+	let user = insert_user(&pool, "search@example.com").await;
+	let project_id = insert_workspace(&pool, &user, "Acme", "Website", "Finished the server migration to <b>new</b> host").await;
+	let app = build_app(pool);
+
+	let (status, response) = search(&app, &user, "q=server%20migration").await;
+	assert_eq!(status, StatusCode::OK);
+
+	let results = response.data.unwrap();
+	assert_eq!(results.len(), 1);
+	assert_eq!(results[0].kind, SearchKind::TimeEntry);
+	assert_eq!(results[0].project_id, Some(project_id));
+	assert_eq!(results[0].title, "Backend work");
+	assert!(results[0].highlight.contains("<mark>server</mark> <mark>migration</mark>"));
+	assert!(!results[0].highlight.contains("<b>"));
+}
+
+#[sqlx::test]
+async fn test_search_ranks_and_filters_by_kind(pool: PgPool) {
+	let user = insert_user(&pool, "kinds@example.com").await;
+	insert_workspace(&pool, &user, "Acme", "Acme webshop", "Call with Acme about invoices").await;
+	let app = build_app(pool);
+
+	let (_, response) = search(&app, &user, "q=acme").await;
+	let kinds: Vec<_> = response.data.unwrap().into_iter().map(|result| result.kind).collect();
+	assert_eq!(kinds.len(), 3);
+	assert_eq!(kinds[2], SearchKind::TimeEntry, "name matches rank above description matches");
+
+	let (_, response) = search(&app, &user, "q=acme&type=client").await;
+	let results = response.data.unwrap();
+	assert_eq!(results.len(), 1);
+	assert_eq!(results[0].kind, SearchKind::Client);
+}
+
+#[sqlx::test]
+async fn test_search_is_scoped_to_user(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let stranger = insert_user(&pool, "stranger@example.com").await;
+	insert_workspace(&pool, &owner, "Initech", "Initech intranet", "Initech kickoff").await;
+	let app = build_app(pool);
+
+	let (_, response) = search(&app, &owner, "q=initech").await;
+	assert_eq!(response.data.unwrap().len(), 3);
+
+	let (status, response) = search(&app, &stranger, "q=initech").await;
+	assert_eq!(status, StatusCode::OK);
+	assert!(response.data.unwrap().is_empty());
+}
+
+#[sqlx::test]
+async fn test_search_rejects_empty_query(pool: PgPool) {
+	let user = insert_user(&pool, "empty@example.com").await;
+	let app = build_app(pool);
+
+	for query in ["q=%20%20", "", "q=x&limit=0"] {
+		let (status, response) = search(&app, &user, query).await;
+		assert_eq!(status, StatusCode::BAD_REQUEST, "{}", query);
+		assert_eq!(response.code, Some(ErrorCode::ValidationFailed));
+	}
+
+	let (status, response) = search(&app, &user, "q=x&type=invoice").await;
+	assert_eq!(status, StatusCode::BAD_REQUEST);
+	assert_eq!(response.code, Some(ErrorCode::InvalidQuery));
+}
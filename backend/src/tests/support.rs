@@ -0,0 +1,221 @@
+//! Fixtures and request helpers shared by the route tests. Fixtures write tenant rows through
+//! `tenant::bypass`, so the suite also runs as a role that row-level security applies to.
+use axum::Router;
+use axum::body::Body;
+use axum::http::{request, Method, Request, StatusCode};
+use axum::response::Response;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use sqlx::PgPool;
+use tower::ServiceExt;
+use uuid::Uuid;
+use crate::{
+	auth::{hash::hash_password, jwt::generate_jwt_token},
+	config::Config,
+	models::{response::ApiResponse, user::User},
+	routes,
+	state::AppState,
+	util::tenant,
+};
+
+/// The password of users from `insert_user_with_password`.
+pub const PASSWORD: &str = "SecurePassword123";
+
+/// The whole API with the default config, mounted as in production.
+pub fn build_app(pool: PgPool) -> Router {
+	build_app_with(pool, Config::default())
+}
+
+pub fn build_app_with(pool: PgPool, config: Config) -> Router {
+	routes::router().with_state(AppState::new(pool, config))
+}
+
+/// A user who can't log in; tests act as them through a token.
+pub async fn insert_user(pool: &PgPool, email: &str) -> User {
+	insert_user_row(pool, email, "", false).await
+}
+
+pub async fn insert_admin(pool: &PgPool, email: &str) -> User {
+	insert_user_row(pool, email, "", true).await
+}
+
+/// A user whose password is `PASSWORD`, for routes that ask for it. Hashing is slow, so only where needed.
+pub async fn insert_user_with_password(pool: &PgPool, email: &str) -> User {
+	insert_user_row(pool, email, &hash_password(PASSWORD).unwrap(), false).await
+}
+
+async fn insert_user_row(pool: &PgPool, email: &str, password_hash: &str, is_admin: bool) -> User {
+	sqlx::query_as::<_, User>("INSERT INTO users (email, password_hash, is_admin) VALUES ($1, $2, $3) RETURNING *")
+		.bind(email)
+		.bind(password_hash)
+		.bind(is_admin)
+		.fetch_one(pool)
+		.await
+		.unwrap()
+}
+
+pub async fn personal_workspace(pool: &PgPool, user: &User) -> Uuid {
+	sqlx::query_scalar("SELECT id FROM workspaces WHERE personal AND created_by = $1")
+		.bind(user.id)
+		.fetch_one(pool)
+		.await
+		.unwrap()
+}
+
+/// A client in the owner's personal workspace.
+pub async fn insert_client(pool: &PgPool, owner: &User, name: &str) -> Uuid {
+	let mut tx = tenant::bypass(pool).await.unwrap();
+	let id = sqlx::query_scalar(
+		"INSERT INTO clients (name, workspace_id) SELECT $1, id FROM workspaces WHERE personal AND created_by = $2 RETURNING id"
+	)
+		.bind(name)
+		.bind(owner.id)
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+
+	id
+}
+
+/// A project in the owner's personal workspace.
+pub async fn insert_project(pool: &PgPool, owner: &User, client_id: Uuid, name: &str) -> Uuid {
+	let mut tx = tenant::bypass(pool).await.unwrap();
+	let id = sqlx::query_scalar(
+		"INSERT INTO projects (name, client_id, is_fixed_price, created_by) VALUES ($1, $2, false, $3) RETURNING id"
+	)
+		.bind(name)
+		.bind(client_id)
+		.bind(owner.id)
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+
+	id
+}
+
+/// A project with a client of its own in `workspace_id`.
+pub async fn insert_project_in(pool: &PgPool, owner: &User, workspace_id: Uuid, name: &str) -> Uuid {
+	let mut tx = tenant::bypass(pool).await.unwrap();
+	let client_id: Uuid = sqlx::query_scalar("INSERT INTO clients (name, workspace_id) VALUES ('Acme', $1) RETURNING id")
+		.bind(workspace_id)
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	let id = sqlx::query_scalar(
+		"INSERT INTO projects (name, client_id, is_fixed_price, created_by, workspace_id) \
+		VALUES ($1, $2, false, $3, $4) RETURNING id"
+	)
+		.bind(name)
+		.bind(client_id)
+		.bind(owner.id)
+		.bind(workspace_id)
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+
+	id
+}
+
+pub async fn insert_job(pool: &PgPool, project_id: Uuid, name: &str) -> Uuid {
+	let mut tx = tenant::bypass(pool).await.unwrap();
+	let id = sqlx::query_scalar("INSERT INTO jobs (project_id, name, is_fixed_price) VALUES ($1, $2, false) RETURNING id")
+		.bind(project_id)
+		.bind(name)
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+
+	id
+}
+
+/// An hour on `job_id` today.
+pub async fn log_time(pool: &PgPool, user: &User, job_id: Uuid) -> Uuid {
+	let mut tx = tenant::bypass(pool).await.unwrap();
+	let id = sqlx::query_scalar(
+		"INSERT INTO time_entries (job_id, user_id, time_spent, entry_date) VALUES ($1, $2, '1 hour', CURRENT_DATE) RETURNING id"
+	)
+		.bind(job_id)
+		.bind(user.id)
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+
+	id
+}
+
+/// One row of each kind under each other, as created by `insert_tree`.
+pub struct Tree {
+	pub client_id: Uuid,
+	pub project_id: Uuid,
+	pub job_id: Uuid,
+	pub entry_id: Uuid,
+}
+
+/// Client "Acme", project "Website", job "Design" and an hour logged by the owner, in their personal workspace.
+pub async fn insert_tree(pool: &PgPool, owner: &User) -> Tree {
+	let client_id = insert_client(pool, owner, "Acme").await;
+	let project_id = insert_project(pool, owner, client_id, "Website").await;
+	let job_id = insert_job(pool, project_id, "Design").await;
+	let entry_id = log_time(pool, owner, job_id).await;
+
+	Tree { client_id, project_id, job_id, entry_id }
+}
+
+/// The token of the link in the latest email queued for `to`.
+pub async fn mailed_token(pool: &PgPool, to: &str) -> String {
+	let body: String = sqlx::query_scalar(
+		"SELECT payload->>'body' FROM background_jobs WHERE kind = 'mail.send' AND payload->>'to' = $1 \
+		ORDER BY created_at DESC LIMIT 1"
+	)
+		.bind(to)
+		.fetch_one(pool)
+		.await
+		.unwrap();
+	let token = body.split("token=").nth(1).unwrap();
+
+	token.split_whitespace().next().unwrap().to_string()
+}
+
+pub fn bearer(user: &User) -> String {
+	format!("Bearer {}", generate_jwt_token(user).unwrap())
+}
+
+/// Sends `request`, as `user` if given, with `body` as JSON.
+pub async fn call(app: &Router, user: Option<&User>, request: request::Builder, body: Option<Value>) -> Response {
+	let mut request = request.header("Content-Type", "application/json");
+	if let Some(user) = user {
+		request = request.header("Authorization", bearer(user));
+	}
+	let body = body.map(|body| Body::from(body.to_string())).unwrap_or_default();
+
+	app.clone().oneshot(request.body(body).unwrap()).await.unwrap()
+}
+
+/// The status and the response envelope; `None` for bodies that aren't one, e.g. a 204.
+pub async fn parse<T: DeserializeOwned>(response: Response) -> (StatusCode, Option<ApiResponse<T>>) {
+	let status = response.status();
+	let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+
+	(status, serde_json::from_slice(&body).ok())
+}
+
+/// The `data` of a successful response.
+pub async fn data<T: DeserializeOwned>(response: Response) -> T {
+	let (status, parsed) = parse::<T>(response).await;
+	parsed.and_then(|parsed| parsed.data).unwrap_or_else(|| panic!("no data in the {} response", status))
+}
+
+pub async fn send<T: DeserializeOwned>(
+	app: &Router,
+	user: &User,
+	method: Method,
+	uri: &str,
+	body: Option<Value>,
+) -> (StatusCode, Option<ApiResponse<T>>) {
+	parse(call(app, Some(user), Request::builder().method(method).uri(uri), body).await).await
+}
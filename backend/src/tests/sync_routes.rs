@@ -0,0 +1,252 @@
+use axum::Router;
+use axum::http::{Method, StatusCode};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	models::{
+		sync::{SyncChanges, SyncKind, SyncOutcome, SyncRecord, SyncResult},
+		user::User,
+	},
+	tests::support::{build_app, insert_tree, insert_user, send, Tree},
+	util::{error::ErrorCode, tenant},
+};
+
+async fn pull(app: &Router, user: &User, since: Option<&str>) -> SyncChanges {
+	let uri = match since {
+		Some(since) => format!("/api/v1/sync?since={}", since),
+		None => "/api/v1/sync".into(),
+	};
+	let (status, response) = send::<SyncChanges>(app, user, Method::GET, &uri, None).await;
+	assert_eq!(status, StatusCode::OK);
+	response.unwrap().data.unwrap()
+}
+
+async fn push(app: &Router, user: &User, changes: Value) -> Vec<SyncResult> {
+	let (status, response) = send::<Vec<SyncResult>>(app, user, Method::POST, "/api/v1/sync", Some(json!({ "changes": changes }))).await;
+	assert_eq!(status, StatusCode::OK);
+	response.unwrap().data.unwrap()
+}
+
+fn ids(records: &[SyncRecord]) -> Vec<Uuid> {
+	records.iter().map(|record| record.id).collect()
+}
+
+#[sqlx::test]
+async fn test_first_pull_is_a_full_copy(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let other = insert_user(&pool, "other@example.com").await;
+	let Tree { client_id, project_id, job_id, entry_id } = insert_tree(&pool, &owner).await;
+	insert_tree(&pool, &other).await;
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	sqlx::query("UPDATE jobs SET name = 'Design v2' WHERE id = $1")
+		.bind(job_id)
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+	let app = build_app(pool);
+
+	let changes = pull(&app, &owner, None).await;
+	assert!(changes.reset);
+	assert!(changes.deleted.is_empty());
+	assert_eq!(ids(&changes.created), vec![client_id, project_id, entry_id], "only the owner's rows, parents first");
+	assert_eq!(ids(&changes.updated), vec![job_id]);
+
+	let entry = &changes.created[2];
+	assert_eq!(entry.kind, SyncKind::TimeEntry);
+	assert_eq!(entry.data["time_spent_seconds"], 3600);
+	assert!(entry.data.get("search_vector").is_none());
+	assert!(entry.data.get("time_spent").is_none());
+}
+
+#[sqlx::test]
+async fn test_delta_pull_returns_changes_since_cursor(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let Tree { project_id, job_id, entry_id, .. } = insert_tree(&pool, &owner).await;
+	let app = build_app(pool.clone());
+
+	let cursor = pull(&app, &owner, None).await.cursor;
+	let changes = pull(&app, &owner, Some(&cursor)).await;
+	assert!(!changes.reset);
+	assert!(changes.created.is_empty() && changes.updated.is_empty() && changes.deleted.is_empty());
+
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	sqlx::query("UPDATE projects SET name = 'Webshop' WHERE id = $1")
+		.bind(project_id)
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	sqlx::query("UPDATE time_entries SET deleted_at = NOW() WHERE id = $1")
+		.bind(entry_id)
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	let new_job: Uuid = sqlx::query_scalar("INSERT INTO jobs (project_id, name, is_fixed_price) VALUES ($1, 'Build', false) RETURNING id")
+		.bind(project_id)
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+
+	let changes = pull(&app, &owner, Some(&cursor)).await;
+	assert_eq!(ids(&changes.created), vec![new_job]);
+	assert_eq!(ids(&changes.updated), vec![project_id]);
+	assert_eq!(changes.updated[0].data["name"], "Webshop");
+	assert_eq!(ids(&changes.deleted), vec![entry_id]);
+	assert!(!ids(&changes.updated).contains(&job_id));
+
+	let changes = pull(&app, &owner, Some(&changes.cursor)).await;
+	assert!(changes.created.is_empty() && changes.updated.is_empty() && changes.deleted.is_empty());
+}
+
+#[sqlx::test]
+async fn test_stale_or_malformed_cursor(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	insert_tree(&pool, &owner).await;
+	let app = build_app(pool);
+
+	let stale = URL_SAFE_NO_PAD.encode(json!({ "xmin": 1, "issued_at": Utc::now() - Duration::days(365) }).to_string());
+	let changes = pull(&app, &owner, Some(&stale)).await;
+	assert!(changes.reset, "tombstones older than the retention may be gone");
+	assert_eq!(changes.created.len(), 4);
+
+	let (status, response) = send::<()>(&app, &owner, Method::GET, "/api/v1/sync?since=garbage", None).await;
+	assert_eq!(status, StatusCode::BAD_REQUEST);
+	assert_eq!(response.unwrap().code, Some(ErrorCode::InvalidCursor));
+}
+
+#[sqlx::test]
+async fn test_push_creates_rows_under_client_ids(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let app = build_app(pool.clone());
+	let [client_id, project_id, job_id, entry_id] = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+
+	let results = push(&app, &owner, json!([
+		{ "kind": "client", "id": client_id, "op": "upsert", "data": { "name": "Offline Ltd" } },
+		{ "kind": "project", "id": project_id, "op": "upsert",
+			"data": { "name": "Cabin", "client_id": client_id, "is_fixed_price": true, "total_budget": "1200.50",
+				"created_by": Uuid::new_v4(), "version": 99 } },
+		{ "kind": "job", "id": job_id, "op": "upsert", "data": { "project_id": project_id, "name": "Roof", "is_fixed_price": false } },
+		{ "kind": "time_entry", "id": entry_id, "op": "upsert",
+			"data": { "job_id": job_id, "time_spent_seconds": 1800, "entry_date": "2026-10-01" } },
+	])).await;
+	assert!(results.iter().all(|result| result.outcome == SyncOutcome::Applied), "{:?}", results);
+	assert!(results.iter().all(|result| result.version == Some(1)));
+
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	let created_by: Uuid = sqlx::query_scalar("SELECT created_by FROM projects WHERE id = $1")
+		.bind(project_id)
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!(created_by, owner.id, "ownership is not up to the client");
+
+	let changes = pull(&app, &owner, None).await;
+	assert_eq!(ids(&changes.created), vec![client_id, project_id, job_id, entry_id]);
+	assert_eq!(changes.created[3].data["time_spent_seconds"], 1800);
+
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	let audited: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM audit_events WHERE action = 'created'")
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!(audited, 4);
+}
+
+#[sqlx::test]
+async fn test_push_conflict_keeps_server_copy(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let Tree { project_id, entry_id, .. } = insert_tree(&pool, &owner).await;
+	let app = build_app(pool.clone());
+
+	// Edited on the server after the device last synced version 1.
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	sqlx::query("UPDATE projects SET name = 'Server name' WHERE id = $1")
+		.bind(project_id)
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+
+	let results = push(&app, &owner, json!([
+		{ "kind": "project", "id": project_id, "op": "upsert", "base_version": 1, "data": { "name": "Device name" } },
+		{ "kind": "time_entry", "id": entry_id, "op": "delete" },
+		{ "kind": "time_entry", "id": entry_id, "op": "upsert", "base_version": 1, "data": { "description": "Meeting" } },
+	])).await;
+
+	assert_eq!(results[0].outcome, SyncOutcome::Conflict);
+	let server = results[0].server.as_ref().unwrap();
+	assert_eq!(server.version, 2);
+	assert_eq!(server.data["name"], "Server name");
+	assert_eq!(results[1].outcome, SyncOutcome::Conflict, "a delete needs a base version too");
+	assert_eq!(results[2].outcome, SyncOutcome::Applied);
+	assert_eq!(results[2].version, Some(2));
+
+	let results = push(&app, &owner, json!([
+		{ "kind": "project", "id": project_id, "op": "upsert", "base_version": 2, "data": { "name": "Device name" } },
+		{ "kind": "time_entry", "id": entry_id, "op": "delete", "base_version": 2 },
+	])).await;
+	assert!(results.iter().all(|result| result.outcome == SyncOutcome::Applied), "{:?}", results);
+
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	let (name, deleted): (String, bool) = sqlx::query_as(
+		"SELECT p.name, t.deleted_at IS NOT NULL FROM projects p, time_entries t WHERE p.id = $1 AND t.id = $2"
+	)
+		.bind(project_id)
+		.bind(entry_id)
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!(name, "Device name");
+	assert!(deleted);
+}
+
+#[sqlx::test]
+async fn test_push_rejects_foreign_and_invalid_changes(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let other = insert_user(&pool, "other@example.com").await;
+	let Tree { project_id, job_id, .. } = insert_tree(&pool, &owner).await;
+	let Tree { project_id: foreign_project, .. } = insert_tree(&pool, &other).await;
+	let app = build_app(pool.clone());
+	let new_job = Uuid::new_v4();
+
+	let results = push(&app, &owner, json!([
+		{ "kind": "project", "id": foreign_project, "op": "upsert", "base_version": 1, "data": { "name": "Mine now" } },
+		{ "kind": "job", "id": job_id, "op": "upsert", "base_version": 1, "data": { "project_id": foreign_project } },
+		{ "kind": "job", "id": new_job, "op": "upsert", "data": { "project_id": project_id, "name": "Bad", "is_fixed_price": false, "status": "BOGUS" } },
+		{ "kind": "job", "id": Uuid::new_v4(), "op": "upsert", "data": { "project_id": project_id } },
+		{ "kind": "job", "id": job_id, "op": "upsert", "base_version": 1, "data": { "name": "Still applied" } },
+	])).await;
+
+	let outcomes = results.iter().map(|result| result.outcome).collect::<Vec<_>>();
+	assert_eq!(outcomes, vec![
+		SyncOutcome::Rejected,
+		SyncOutcome::Rejected,
+		SyncOutcome::Rejected,
+		SyncOutcome::Rejected,
+		SyncOutcome::Applied,
+	]);
+	assert!(results[..4].iter().all(|result| result.error.is_some()));
+
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	let names: Vec<String> = sqlx::query_scalar("SELECT name FROM projects ORDER BY name")
+		.fetch_all(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!(names, vec!["Website", "Website"]);
+	let (name, project): (String, Uuid) = sqlx::query_as("SELECT name, project_id FROM jobs WHERE id = $1")
+		.bind(job_id)
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!((name.as_str(), project), ("Still applied", project_id), "the rejected move was rolled back");
+	let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM jobs WHERE id = $1)")
+		.bind(new_job)
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	assert!(!exists);
+}
@@ -0,0 +1,238 @@
+use std::time::Duration;
+use axum::http::{Method, Request, StatusCode};
+use serde_json::{json, Value};
+use sqlx::{postgres::PgPoolOptions, Executor, PgPool};
+use uuid::Uuid;
+use crate::{
+	models::{project::Project, sync::{SyncChanges, SyncOutcome, SyncResult}, user::User},
+	tests::support::{build_app, call, data, insert_tree, insert_user, personal_workspace},
+	util::{tenant, trash_service},
+};
+
+/// An ordinary role, for when the test database connects as a superuser: superusers skip row-level security.
+const ROLE: &str = "kvitter_tenant_test";
+
+/// A pool on the same database whose connections act as an ordinary role, like the API does in production.
+/// One connection, so consecutive transactions are known to share it.
+async fn restricted_pool(pool: &PgPool) -> PgPool {
+	let exempt: bool = sqlx::query_scalar("SELECT rolsuper OR rolbypassrls FROM pg_roles WHERE rolname = current_user")
+		.fetch_one(pool)
+		.await
+		.unwrap();
+	if exempt {
+		// Roles are shared by every database of the cluster, and the tests run in parallel.
+		pool.execute(format!(
+			"DO $$ BEGIN CREATE ROLE {} NOLOGIN; EXCEPTION WHEN duplicate_object OR unique_violation THEN NULL; END $$",
+			ROLE,
+		).as_str()).await.unwrap();
+		pool.execute(format!(
+			"GRANT USAGE ON SCHEMA public TO {role}; \
+			GRANT SELECT, INSERT, UPDATE, DELETE ON ALL TABLES IN SCHEMA public TO {role}; \
+			GRANT USAGE, SELECT ON ALL SEQUENCES IN SCHEMA public TO {role}",
+			role = ROLE,
+		).as_str()).await.unwrap();
+	}
+
+	PgPoolOptions::new()
+		.max_connections(1)
+		.after_connect(move |conn, _| Box::pin(async move {
+			if exempt {
+				conn.execute(format!("SET ROLE {}", ROLE).as_str()).await?;
+			}
+			Ok(())
+		}))
+		.connect_with(pool.connect_options().as_ref().clone())
+		.await
+		.unwrap()
+}
+
+struct Tenant {
+	user: User,
+	workspace_id: Uuid,
+	project_id: Uuid,
+	job_id: Uuid,
+}
+
+/// A user with a client, project, job, milestone and time entry in their personal workspace.
+async fn insert_tenant(pool: &PgPool, email: &str) -> Tenant {
+	let user = insert_user(pool, email).await;
+	let workspace_id = personal_workspace(pool, &user).await;
+	let tree = insert_tree(pool, &user).await;
+	let mut tx = tenant::bypass(pool).await.unwrap();
+	sqlx::query("INSERT INTO milestones (project_id, description, amount) VALUES ($1, 'Q1', 100)")
+		.bind(tree.project_id)
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+
+	Tenant { user, workspace_id, project_id: tree.project_id, job_id: tree.job_id }
+}
+
+fn is_policy_violation(err: sqlx::Error) -> bool {
+	err.as_database_error().and_then(|db| db.code()).as_deref() == Some("42501")
+}
+
+#[sqlx::test]
+async fn test_unfiltered_reads_only_see_the_workspace(pool: PgPool) {
+	let alice = insert_tenant(&pool, "alice@example.com").await;
+	let bob = insert_tenant(&pool, "bob@example.com").await;
+	let restricted = restricted_pool(&pool).await;
+
+	let mut tx = tenant::begin(&restricted, &alice.user.id, &alice.workspace_id).await.unwrap();
+	for table in ["clients", "projects", "jobs", "milestones", "time_entries"] {
+		let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+			.fetch_one(&mut *tx)
+			.await
+			.unwrap();
+		assert_eq!(count, 1, "{}", table);
+	}
+	let projects: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM projects")
+		.fetch_all(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!(projects, vec![alice.project_id]);
+	let found: Option<Uuid> = sqlx::query_scalar("SELECT id FROM jobs WHERE id = $1")
+		.bind(bob.job_id)
+		.fetch_optional(&mut *tx)
+		.await
+		.unwrap();
+	assert!(found.is_none(), "not even by id");
+	tx.commit().await.unwrap();
+
+	for table in ["clients", "projects", "project_members", "jobs", "milestones", "time_entries", "audit_events"] {
+		let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+			.fetch_one(&restricted)
+			.await
+			.unwrap();
+		assert_eq!(count, 0, "without a tenant, {} look empty", table);
+	}
+
+	let mut tx = tenant::bypass(&restricted).await.unwrap();
+	let all: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM projects")
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!(all, 2, "system work asks to see everything");
+}
+
+#[sqlx::test]
+async fn test_audit_log_is_append_only_for_tenants(pool: PgPool) {
+	let alice = insert_tenant(&pool, "alice@example.com").await;
+	let restricted = restricted_pool(&pool).await;
+
+	let mut tx = tenant::begin(&restricted, &alice.user.id, &alice.workspace_id).await.unwrap();
+	sqlx::query("INSERT INTO audit_events (entity_type, entity_id, action) VALUES ('project', $1, 'update')")
+		.bind(alice.project_id)
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	let seen: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM audit_events")
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!(seen, 0);
+	tx.commit().await.unwrap();
+
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	let recorded: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM audit_events WHERE entity_id = $1")
+		.bind(alice.project_id)
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!(recorded, 1);
+}
+
+#[sqlx::test]
+async fn test_trash_purge_reaches_every_workspace(pool: PgPool) {
+	let alice = insert_tenant(&pool, "alice@example.com").await;
+	let bob = insert_tenant(&pool, "bob@example.com").await;
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	sqlx::query("UPDATE projects SET deleted_at = NOW() - INTERVAL '60 days'")
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+	let restricted = restricted_pool(&pool).await;
+
+	trash_service::purge_expired(&restricted, Duration::from_secs(30 * 24 * 3600)).await.unwrap();
+
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	let left: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM projects WHERE id IN ($1, $2)")
+		.bind(alice.project_id)
+		.bind(bob.project_id)
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!(left, 0);
+}
+
+#[sqlx::test]
+async fn test_unfiltered_writes_only_touch_the_workspace(pool: PgPool) {
+	let alice = insert_tenant(&pool, "alice@example.com").await;
+	let bob = insert_tenant(&pool, "bob@example.com").await;
+	let restricted = restricted_pool(&pool).await;
+
+	let mut tx = tenant::begin(&restricted, &alice.user.id, &alice.workspace_id).await.unwrap();
+	let renamed = sqlx::query("UPDATE projects SET name = 'Renamed'")
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!(renamed.rows_affected(), 1);
+	let deleted = sqlx::query("DELETE FROM time_entries")
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!(deleted.rows_affected(), 1);
+	tx.commit().await.unwrap();
+
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	let (name, entries): (String, i64) = sqlx::query_as(
+		"SELECT p.name, (SELECT COUNT(*) FROM time_entries t JOIN jobs j ON j.id = t.job_id WHERE j.project_id = p.id) \
+		FROM projects p WHERE p.id = $1"
+	)
+		.bind(bob.project_id)
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!((name.as_str(), entries), ("Website", 1), "bob's rows are untouched");
+
+	let attempts = [
+		("INSERT INTO clients (name, workspace_id) VALUES ('Sneaky', $1)", bob.workspace_id),
+		("INSERT INTO jobs (project_id, name, is_fixed_price) VALUES ($1, 'Sneaky', false)", bob.project_id),
+		("UPDATE jobs SET project_id = $1", bob.project_id),
+	];
+	for (sql, target) in attempts {
+		let mut tx = tenant::begin(&restricted, &alice.user.id, &alice.workspace_id).await.unwrap();
+		let err = sqlx::query(sql).bind(target).execute(&mut *tx).await.unwrap_err();
+		assert!(is_policy_violation(err), "{}", sql);
+	}
+}
+
+#[sqlx::test]
+async fn test_api_works_under_row_level_security(pool: PgPool) {
+	let alice = insert_tenant(&pool, "alice@example.com").await;
+	insert_tenant(&pool, "bob@example.com").await;
+	let app = build_app(restricted_pool(&pool).await);
+	let send = |method: Method, uri: &str, body: Option<Value>| {
+		call(&app, Some(&alice.user), Request::builder().method(method).uri(uri).header("If-Match", "*"), body)
+	};
+
+	let projects: Vec<Project> = data(send(Method::GET, "/api/v1/projects", None).await).await;
+	assert_eq!(projects.iter().map(|project| project.id).collect::<Vec<_>>(), vec![alice.project_id]);
+
+	let changes: SyncChanges = data(send(Method::GET, "/api/v1/sync", None).await).await;
+	assert_eq!(changes.created.len(), 5);
+
+	let changes = json!({ "changes": [
+		{ "kind": "job", "id": Uuid::new_v4(), "op": "upsert",
+			"data": { "project_id": alice.project_id, "name": "Audit", "is_fixed_price": false } },
+	] });
+	let results: Vec<SyncResult> = data(send(Method::POST, "/api/v1/sync", Some(changes)).await).await;
+	assert_eq!(results[0].outcome, SyncOutcome::Applied, "{:?}", results);
+
+	let response = send(Method::DELETE, &format!("/api/v1/projects/{}", alice.project_id), None).await;
+	assert_eq!(response.status(), StatusCode::NO_CONTENT);
+	let response = send(Method::POST, &format!("/api/v1/trash/project/{}/restore", alice.project_id), None).await;
+	assert_eq!(response.status(), StatusCode::NO_CONTENT);
+}
@@ -0,0 +1,171 @@
+use std::time::Duration;
+use axum::Router;
+use axum::http::{Method, Request, StatusCode};
+use serde::de::DeserializeOwned;
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	models::{project::Project, response::ApiResponse, time_entry::TimeEntry, trash::{TrashItem, TrashKind}, user::User},
+	tests::support::{build_app, call, insert_job, insert_project_in, insert_tree, insert_user, log_time, parse, Tree},
+	util::{audit::AuditContext, error::AppError, tenant, trash_service::purge_expired, user_service},
+};
+
+/// Sends without a body and with `If-Match: *`; versions are covered by the concurrency tests.
+async fn send<T: DeserializeOwned>(app: &Router, user: &User, method: Method, uri: &str) -> (StatusCode, Option<ApiResponse<T>>) {
+	parse(call(app, Some(user), Request::builder().method(method).uri(uri).header("If-Match", "*"), None).await).await
+}
+
+#[sqlx::test]
+async fn test_delete_and_restore_project(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let project_id = insert_tree(&pool, &owner).await.project_id;
+	let app = build_app(pool);
+
+	let (status, _) = send::<()>(&app, &owner, Method::DELETE, &format!("/api/v1/projects/{}", project_id)).await;
+	assert_eq!(status, StatusCode::NO_CONTENT);
+
+	let (_, projects) = send::<Vec<Project>>(&app, &owner, Method::GET, "/api/v1/projects").await;
+	assert!(projects.unwrap().data.unwrap().is_empty());
+	let (_, entries) = send::<Vec<TimeEntry>>(&app, &owner, Method::GET, "/api/v1/time-entries").await;
+	assert!(entries.unwrap().data.unwrap().is_empty(), "entries of a deleted project are hidden");
+
+	let (_, trash) = send::<Vec<TrashItem>>(&app, &owner, Method::GET, "/api/v1/trash").await;
+	let trash = trash.unwrap().data.unwrap();
+	assert_eq!(trash.len(), 1);
+	assert_eq!(trash[0].kind, TrashKind::Project);
+	assert_eq!(trash[0].id, project_id);
+	assert_eq!(trash[0].purge_at - trash[0].deleted_at, chrono::Duration::days(30));
+
+	let uri = format!("/api/v1/trash/project/{}/restore", project_id);
+	let (status, _) = send::<()>(&app, &owner, Method::POST, &uri).await;
+	assert_eq!(status, StatusCode::NO_CONTENT);
+
+	let (_, entries) = send::<Vec<TimeEntry>>(&app, &owner, Method::GET, "/api/v1/time-entries").await;
+	assert_eq!(entries.unwrap().data.unwrap().len(), 1);
+
+	let (status, _) = send::<()>(&app, &owner, Method::POST, &uri).await;
+	assert_eq!(status, StatusCode::NOT_FOUND, "a restored row is no longer in the trash");
+}
+
+#[sqlx::test]
+async fn test_only_owner_can_delete_or_restore(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let stranger = insert_user(&pool, "stranger@example.com").await;
+	let Tree { project_id, entry_id, .. } = insert_tree(&pool, &owner).await;
+	let app = build_app(pool);
+
+	let (status, _) = send::<()>(&app, &stranger, Method::DELETE, &format!("/api/v1/projects/{}", project_id)).await;
+	assert_eq!(status, StatusCode::NOT_FOUND);
+
+	let (status, _) = send::<()>(&app, &owner, Method::DELETE, &format!("/api/v1/time-entries/{}", entry_id)).await;
+	assert_eq!(status, StatusCode::NO_CONTENT);
+
+	let (_, trash) = send::<Vec<TrashItem>>(&app, &stranger, Method::GET, "/api/v1/trash").await;
+	assert!(trash.unwrap().data.unwrap().is_empty());
+
+	let uri = format!("/api/v1/trash/time_entry/{}/restore", entry_id);
+	let (status, _) = send::<()>(&app, &stranger, Method::POST, &uri).await;
+	assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test]
+async fn test_purge_removes_expired_rows_with_children(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let expired = insert_tree(&pool, &owner).await.project_id;
+	let recent = insert_tree(&pool, &owner).await.project_id;
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	sqlx::query("UPDATE projects SET deleted_at = NOW() - INTERVAL '40 days' WHERE id = $1")
+		.bind(expired)
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	sqlx::query("UPDATE projects SET deleted_at = NOW() - INTERVAL '1 day' WHERE id = $1")
+		.bind(recent)
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+
+	let purged = purge_expired(&pool, Duration::from_secs(30 * 24 * 60 * 60)).await.unwrap();
+	assert_eq!(purged, 3, "the project, its job and its time entry");
+
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	let remaining: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM projects")
+		.fetch_all(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!(remaining, vec![recent]);
+}
+
+#[sqlx::test]
+async fn test_deleted_user_is_hidden_until_purged(pool: PgPool) {
+	let user = insert_user(&pool, "leaving@example.com").await;
+	insert_tree(&pool, &user).await;
+
+	user_service::delete_user_by_uuid(&pool, &user.id, &AuditContext::default()).await.unwrap();
+	let result = user_service::fetch_user_by_email(&pool, "leaving@example.com").await;
+	assert!(matches!(result, Err(AppError::NotFound(..))));
+	assert!(user_service::is_email_unique(&pool, "leaving@example.com").await.is_err());
+
+	sqlx::query("UPDATE users SET deleted_at = NOW() - INTERVAL '31 days'")
+		.execute(&pool)
+		.await
+		.unwrap();
+	purge_expired(&pool, Duration::from_secs(30 * 24 * 60 * 60)).await.unwrap();
+
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	let users: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users").fetch_one(&mut *tx).await.unwrap();
+	let projects: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM projects").fetch_one(&mut *tx).await.unwrap();
+	let workspaces: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM workspaces").fetch_one(&mut *tx).await.unwrap();
+	assert_eq!((users, projects, workspaces), (0, 0, 0), "the personal workspace goes with its data");
+	assert!(user_service::is_email_unique(&pool, "leaving@example.com").await.is_ok());
+}
+
+#[sqlx::test]
+async fn test_purged_member_leaves_shared_projects_behind(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let member = insert_user(&pool, "leaving@example.com").await;
+	let shared: Uuid = sqlx::query_scalar(
+		"WITH w AS (INSERT INTO workspaces (name, created_by) VALUES ('Studio', $1) RETURNING id) \
+		INSERT INTO workspace_members (workspace_id, user_id, role) \
+		SELECT id, $1, 'OWNER'::workspace_role FROM w UNION ALL SELECT id, $2, 'MEMBER' FROM w RETURNING workspace_id"
+	)
+		.bind(owner.id)
+		.bind(member.id)
+		.fetch_one(&pool)
+		.await
+		.unwrap();
+	let project_id = insert_project_in(&pool, &member, shared, "Team site").await;
+	let job_id = insert_job(&pool, project_id, "Design").await;
+	let owners_entry = log_time(&pool, &owner, job_id).await;
+	log_time(&pool, &member, job_id).await;
+	insert_tree(&pool, &member).await;
+
+	user_service::delete_user_by_uuid(&pool, &member.id, &AuditContext::default()).await.unwrap();
+	sqlx::query("UPDATE users SET deleted_at = NOW() - INTERVAL '31 days' WHERE id = $1")
+		.bind(member.id)
+		.execute(&pool)
+		.await
+		.unwrap();
+	purge_expired(&pool, Duration::from_secs(30 * 24 * 60 * 60)).await.unwrap();
+
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	let projects: Vec<(Uuid, Option<Uuid>)> = sqlx::query_as("SELECT id, created_by FROM projects")
+		.fetch_all(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!(projects, vec![(project_id, None)], "the shared project stays, the personal one goes");
+	let entries: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM time_entries").fetch_all(&mut *tx).await.unwrap();
+	assert_eq!(entries, vec![owners_entry], "only the purged member's own time goes");
+	let workspaces: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM workspaces WHERE created_by IS NULL OR personal = false")
+		.fetch_all(&pool)
+		.await
+		.unwrap();
+	assert_eq!(workspaces, vec![shared]);
+	let members: Vec<Uuid> = sqlx::query_scalar("SELECT user_id FROM workspace_members WHERE workspace_id = $1")
+		.bind(shared)
+		.fetch_all(&pool)
+		.await
+		.unwrap();
+	assert_eq!(members, vec![owner.id]);
+}
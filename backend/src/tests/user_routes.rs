@@ -1,12 +1,18 @@
-use axum::{Router, routing::{post, get, put, delete}, extract::State};
+use axum::{Router, routing::{post, get, put}};
 use axum::body::Body;
 use axum::http::{Request, StatusCode};
-use jsonwebtoken::TokenData;
 use serde_json::json;
-use sqlx::{PgPool};
+use sqlx::{migrate::Migrator, PgPool};
 use tower::ServiceExt;
 use dotenvy::from_filename;
-use crate::{models::{response::ApiResponse, user::{PublicUser, RegisterPayload}}, routes::{auth::{health_check, login, signup, AuthResponse}, user}, util::error::AppResult};
+use crate::{
+	config::Config,
+	models::{response::ApiResponse, user::{DateFormat, PublicUser}},
+	tests::support::insert_user_with_password,
+	util::{error::ErrorCode, tenant},
+	routes::{auth::{login, signup, AuthResponse}, health, user},
+	state::AppState
+};
 
 #[ctor::ctor]
 fn init() {
@@ -18,10 +24,10 @@ fn build_app(pool: PgPool) -> Router {
 		.without_v07_checks()
 		.route("/signup", post(signup))
 		.route("/login", post(login))
-		.route("/health", get(health_check))
-		.route("/me", get(user::get_me))
+		.route("/health", get(health::live))
+		.route("/me", get(user::get_me).patch(user::update_me).delete(user::delete_me))
 		.route("/me/password", put(user::change_password))
-		.with_state(pool)
+		.with_state(AppState::new(pool, Config::default()))
 }
 
 #[sqlx::test]
@@ -328,6 +334,7 @@ async fn test_change_password(pool: PgPool) {
 			Request::builder()
 				.method("PUT")
 				.uri("/me/password")
+				.header("If-Match", "*")
 				.header("Authorization", format!("Bearer {}", token))
 				.header("Content-Type", "application/json")
 				.body(Body::from(change_password_payload.to_string()))
@@ -386,6 +393,7 @@ async fn test_change_password(pool: PgPool) {
 			Request::builder()
 				.method("PUT")
 				.uri("/me/password")
+				.header("If-Match", "*")
 				.header("Authorization", format!("Bearer {}", new_token))
 				.header("Content-Type", "application/json")
 				.body(Body::from(json!({
@@ -405,6 +413,7 @@ async fn test_change_password(pool: PgPool) {
 			Request::builder()
 				.method("PUT")
 				.uri("/me/password")
+				.header("If-Match", "*")
 				.header("Authorization", format!("Bearer {}", new_token))
 				.header("Content-Type", "application/json")
 				.body(Body::from(json!({
@@ -418,4 +427,246 @@ async fn test_change_password(pool: PgPool) {
 
 	assert_eq!(change_password_error_response.status(), StatusCode::BAD_REQUEST);
 
-}
\ No newline at end of file
+}
+
+/// Signs up and logs in `email`, returning the token.
+async fn signup_and_login(app: &Router, email: &str) -> String {
+	let payload = json!({ "email": email, "password": "SecurePassword123" });
+	for uri in ["/signup", "/login"] {
+		let response = app
+			.clone()
+			.oneshot(
+				Request::builder()
+					.method("POST")
+					.uri(uri)
+					.header("Content-Type", "application/json")
+					.body(Body::from(payload.to_string()))
+					.unwrap(),
+			)
+			.await
+			.unwrap();
+		assert!(response.status().is_success(), "{}", uri);
+		if uri == "/login" {
+			let body = axum::body::to_bytes(response.into_body(), 8 * 1024).await.unwrap();
+			let auth: ApiResponse<AuthResponse> = serde_json::from_slice(&body).unwrap();
+			return auth.data.unwrap().token;
+		}
+	}
+	unreachable!()
+}
+
+async fn patch_me(app: &Router, token: &str, if_match: &str, body: serde_json::Value) -> (StatusCode, Option<ApiResponse<PublicUser>>) {
+	let response = app
+		.clone()
+		.oneshot(
+			Request::builder()
+				.method("PATCH")
+				.uri("/me")
+				.header("If-Match", if_match)
+				.header("Authorization", format!("Bearer {}", token))
+				.header("Content-Type", "application/json")
+				.body(Body::from(body.to_string()))
+				.unwrap(),
+		)
+		.await
+		.unwrap();
+	let status = response.status();
+	let body = axum::body::to_bytes(response.into_body(), 8 * 1024).await.unwrap();
+
+	(status, serde_json::from_slice(&body).ok())
+}
+
+#[sqlx::test]
+async fn test_update_profile(pool: PgPool) {
+	let app = build_app(pool.clone());
+	let token = signup_and_login(&app, "profile@example.com").await;
+
+	let (status, response) = patch_me(&app, &token, "*", json!({
+		"display_name": " Ada Lovelace ",
+		"locale": "nb-NO",
+		"timezone": "Europe/Oslo",
+		"currency": "nok",
+		"date_format": "day_month_year",
+		"org_number": "923 609 016",
+		"bank_account": "NO93 8601 1117 947",
+		"city": "Oslo",
+		"country": "NO"
+	})).await;
+	assert_eq!(status, StatusCode::OK);
+	let user = response.unwrap().data.unwrap();
+	assert_eq!(user.profile.display_name.as_deref(), Some("Ada Lovelace"));
+	assert_eq!(user.profile.timezone, "Europe/Oslo");
+	assert_eq!(user.profile.currency, "NOK");
+	assert_eq!(user.profile.date_format, DateFormat::DayMonthYear);
+	assert_eq!(user.profile.bank_account.as_deref(), Some("NO93 8601 1117 947"));
+
+	let (status, _) = patch_me(&app, &token, "\"1\"", json!({ "city": "Bergen" })).await;
+	assert_eq!(status, StatusCode::PRECONDITION_FAILED, "the first update bumped the version");
+
+	let if_match = format!("\"{}\"", user.version);
+	let (status, response) = patch_me(&app, &token, &if_match, json!({ "city": "", "display_name": "Ada" })).await;
+	assert_eq!(status, StatusCode::OK);
+	let user = response.unwrap().data.unwrap();
+	assert_eq!(user.profile.city, None, "an empty string clears the field");
+	assert_eq!(user.profile.display_name.as_deref(), Some("Ada"));
+	assert_eq!(user.profile.locale, "nb-NO", "fields left out stay as they are");
+let mut tx = tenant::bypass(&pool).await.unwrap();
+
+	let changes: Vec<serde_json::Value> = sqlx::query_scalar(
+		"SELECT changes FROM audit_events WHERE entity_id = $1 AND action = 'updated' ORDER BY occurred_at"
+	)
+		.bind(user.id)
+		.fetch_all(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!(changes.len(), 2);
+	assert_eq!(changes[1]["city"], json!({ "before": "Oslo", "after": null }));
+}
+
+#[sqlx::test]
+async fn test_update_profile_validation(pool: PgPool) {
+	let app = build_app(pool);
+	let token = signup_and_login(&app, "invalid@example.com").await;
+
+	let (status, response) = patch_me(&app, &token, "*", json!({
+		"timezone": "Mars/Olympus_Mons",
+		"currency": "",
+		"bank_account": "NO94 8601 1117 947",
+		"display_name": "Ada"
+	})).await;
+	assert_eq!(status, StatusCode::BAD_REQUEST);
+	let fields: Vec<String> = response.unwrap().details.into_iter().map(|field| field.field).collect();
+	assert_eq!(fields, vec!["timezone", "currency", "bank_account"]);
+
+	let (status, _) = patch_me(&app, &token, "*", json!({ "date_format": "yesterday" })).await;
+	assert!(status.is_client_error());
+}
+
+#[sqlx::test]
+async fn test_delete_me(pool: PgPool) {
+	let app = build_app(pool.clone());
+	let token = signup_and_login(&app, "leaving@example.com").await;
+
+	let send = |method: &'static str| {
+		let request = Request::builder()
+			.method(method)
+			.uri("/me")
+			.header("Authorization", format!("Bearer {}", token))
+			.body(Body::empty())
+			.unwrap();
+		app.clone().oneshot(request)
+	};
+	assert_eq!(send("DELETE").await.unwrap().status(), StatusCode::NO_CONTENT);
+	assert_eq!(send("GET").await.unwrap().status(), StatusCode::NOT_FOUND);
+	assert_eq!(send("DELETE").await.unwrap().status(), StatusCode::NO_CONTENT, "deleting twice is a no-op");
+
+	let (status, _) = post_credentials(&app, "/login", "leaving@example.com").await;
+	assert_eq!(status, StatusCode::UNAUTHORIZED);
+	let deleted: bool = sqlx::query_scalar("SELECT deleted_at IS NOT NULL FROM users WHERE email = 'leaving@example.com'")
+		.fetch_one(&pool)
+		.await
+		.unwrap();
+	assert!(deleted, "kept in the trash until the purge");
+}
+
+async fn post_credentials(app: &Router, uri: &str, email: &str) -> (StatusCode, ApiResponse<serde_json::Value>) {
+	let response = app
+		.clone()
+		.oneshot(
+			Request::builder()
+				.method("POST")
+				.uri(uri)
+				.header("Content-Type", "application/json")
+				.body(Body::from(json!({ "email": email, "password": "SecurePassword123" }).to_string()))
+				.unwrap(),
+		)
+		.await
+		.unwrap();
+	let status = response.status();
+	let body = axum::body::to_bytes(response.into_body(), 8 * 1024).await.unwrap();
+
+	(status, serde_json::from_slice(&body).unwrap())
+}
+
+#[sqlx::test]
+async fn test_email_is_case_insensitive(pool: PgPool) {
+	let app = build_app(pool.clone());
+
+	let (status, _) = post_credentials(&app, "/signup", " Bob@Example.COM ").await;
+	assert_eq!(status, StatusCode::CREATED);
+	let stored: String = sqlx::query_scalar("SELECT email FROM users").fetch_one(&pool).await.unwrap();
+	assert_eq!(stored, "Bob@example.com", "trimmed, with a lowercase domain");
+
+	let (status, response) = post_credentials(&app, "/signup", "bob@example.com").await;
+	assert_eq!(status, StatusCode::UNAUTHORIZED);
+	assert_eq!(response.code, Some(ErrorCode::EmailTaken));
+	let (status, _) = post_credentials(&app, "/login", "BOB@EXAMPLE.COM").await;
+	assert_eq!(status, StatusCode::OK);
+
+	let (status, response) = post_credentials(&app, "/signup", "bob@example").await;
+	assert_eq!(status, StatusCode::BAD_REQUEST);
+	assert_eq!(response.details[0].code, ErrorCode::EmailInvalid);
+	let (status, _) = post_credentials(&app, "/login", "bob@example").await;
+	assert_eq!(status, StatusCode::UNAUTHORIZED, "no such account");
+
+	let duplicate = sqlx::query("INSERT INTO users (email, password_hash) VALUES ('BOB@example.com', '')")
+		.execute(&pool)
+		.await;
+	assert!(duplicate.is_err(), "the unique index ignores case");
+}
+
+#[sqlx::test]
+async fn test_legacy_addresses_can_still_log_in(pool: PgPool) {
+	let app = build_app(pool.clone());
+
+	// Stored before addresses were checked this strictly on signup.
+	for email in ["\"john smith\"@example.com", "root@localhost", "admin@[10.0.0.1]", "ops@example.123"] {
+		insert_user_with_password(&pool, email).await;
+
+		let (status, _) = post_credentials(&app, "/login", &format!(" {} ", email.to_uppercase())).await;
+		assert_eq!(status, StatusCode::OK, "{}", email);
+		let (status, response) = post_credentials(&app, "/signup", email).await;
+		assert_eq!(status, StatusCode::BAD_REQUEST, "{}", email);
+		assert_eq!(response.details[0].code, ErrorCode::EmailInvalid);
+	}
+}
+
+#[sqlx::test(migrations = false)]
+async fn test_email_collisions_block_the_unique_index(pool: PgPool) {
+	const COLLISIONS: i64 = 20261018230000;
+	let migrator: Migrator = sqlx::migrate!("./migrations");
+	let run = |version: i64| {
+		let migration = migrator.iter().find(|migration| migration.version == version).unwrap();
+		sqlx::raw_sql(&migration.sql).execute(&pool)
+	};
+	for migration in migrator.iter().filter(|migration| migration.version < COLLISIONS) {
+		run(migration.version).await.unwrap();
+	}
+	sqlx::query(
+		"INSERT INTO users (email, password_hash, created_at) VALUES \
+		('Ann@example.com', '', '2025-01-01'), ('ann@EXAMPLE.com', '', '2025-02-01'), ('solo@Example.COM', '', '2025-03-01')"
+	)
+		.execute(&pool)
+		.await
+		.unwrap();
+
+	run(COLLISIONS).await.unwrap();
+	let (email, emails): (String, Vec<String>) = sqlx::query_as("SELECT email, emails FROM email_collisions")
+		.fetch_one(&pool)
+		.await
+		.unwrap();
+	assert_eq!(email, "ann@example.com");
+	assert_eq!(emails, vec!["Ann@example.com", "ann@EXAMPLE.com"], "oldest first, left as they were");
+	let solo: String = sqlx::query_scalar("SELECT email FROM users WHERE email ILIKE 'solo@%'")
+		.fetch_one(&pool)
+		.await
+		.unwrap();
+	assert_eq!(solo, "solo@example.com");
+
+	let err = run(COLLISIONS + 100).await.unwrap_err();
+	assert!(err.to_string().contains("share an email address"), "{}", err);
+
+	sqlx::query("DELETE FROM users WHERE email = 'ann@EXAMPLE.com'").execute(&pool).await.unwrap();
+	sqlx::query("DELETE FROM email_collisions").execute(&pool).await.unwrap();
+	run(COLLISIONS + 100).await.unwrap();
+}
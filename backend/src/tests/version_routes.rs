@@ -0,0 +1,69 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use sqlx::PgPool;
+use tower::ServiceExt;
+use crate::tests::support::build_app;
+
+#[sqlx::test]
+async fn test_v1_routes(pool: PgPool) {
+	let app = build_app(pool);
+	let response = app
+		.oneshot(Request::builder().uri("/api/v1/health/live").body(Body::empty()).unwrap())
+		.await
+		.unwrap();
+
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(response.headers().get("deprecation").is_none());
+	assert!(response.headers().get("sunset").is_none());
+}
+
+#[sqlx::test]
+async fn test_legacy_routes_are_deprecated(pool: PgPool) {
+	let app = build_app(pool);
+
+	for uri in ["/health", "/health/live", "/health/ready"] {
+		let response = app
+			.clone()
+			.oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+			.await
+			.unwrap();
+
+		assert_ne!(response.status(), StatusCode::NOT_FOUND, "{} should still be served", uri);
+		assert!(response.headers()["deprecation"].to_str().unwrap().starts_with('@'));
+		assert!(response.headers()["sunset"].to_str().unwrap().ends_with("GMT"));
+		assert_eq!(
+			response.headers()["link"],
+			format!("</api/v1{}>; rel=\"successor-version\"", uri).as_str()
+		);
+	}
+}
+
+#[sqlx::test]
+async fn test_later_routes_are_only_versioned(pool: PgPool) {
+	let app = build_app(pool);
+
+	for uri in ["/me", "/sync", "/workspaces", "/events", "/webhooks", "/admin/jobs"] {
+		let response = app
+			.clone()
+			.oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+			.await
+			.unwrap();
+
+		assert_eq!(response.status(), StatusCode::NOT_FOUND, "{} is only served under a version", uri);
+	}
+}
+
+#[sqlx::test]
+async fn test_openapi_is_versioned(pool: PgPool) {
+	let app = build_app(pool);
+	let response = app
+		.oneshot(Request::builder().uri("/api/v1/openapi.json").body(Body::empty()).unwrap())
+		.await
+		.unwrap();
+
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let body = axum::body::to_bytes(response.into_body(), 256 * 1024).await.unwrap();
+	let spec: serde_json::Value = serde_json::from_slice(&body).unwrap();
+	assert_eq!(spec["servers"][0]["url"], "/api/v1");
+}
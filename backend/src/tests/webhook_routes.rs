@@ -0,0 +1,349 @@
+use std::sync::{Arc, Mutex, atomic::{AtomicU16, Ordering}};
+use axum::{Router, routing::post};
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, Method, Request, StatusCode};
+use axum::response::Response;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	config::Config,
+	models::{
+		response::ApiResponse,
+		user::User,
+		webhook::{CreatedWebhook, DeliveryStatus, Webhook, WebhookDelivery, WebhookEventType},
+	},
+	tests::support::{
+		self, build_app, build_app_with, call, data, insert_client, insert_project, insert_project_in, insert_user, log_time,
+		parse,
+	},
+	util::webhook_service::{self, DISABLE_AFTER_FAILURES, MAX_ATTEMPTS},
+};
+
+/// Local stand-in for a customer's endpoint: records every request and answers with `status`
+/// and a body that must not end up in the delivery log.
+#[derive(Clone)]
+struct Receiver {
+	url: String,
+	status: Arc<AtomicU16>,
+	requests: Arc<Mutex<Vec<(HeaderMap, Bytes)>>>,
+}
+
+impl Receiver {
+	async fn start() -> Self {
+		let status = Arc::new(AtomicU16::new(200));
+		let requests = Arc::new(Mutex::new(Vec::new()));
+		let app = Router::new()
+			.route("/hook", post(|State(receiver): State<Receiver>, headers: HeaderMap, body: Bytes| async move {
+				receiver.requests.lock().unwrap().push((headers, body));
+				(StatusCode::from_u16(receiver.status.load(Ordering::SeqCst)).unwrap(), "internal details")
+			}));
+		let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let receiver = Receiver {
+			url: format!("http://{}/hook", listener.local_addr().unwrap()),
+			status,
+			requests,
+		};
+		let app = app.with_state(receiver.clone());
+		tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+		receiver
+	}
+
+	fn answer_with(&self, status: StatusCode) {
+		self.status.store(status.as_u16(), Ordering::SeqCst);
+	}
+
+	fn received(&self) -> Vec<(HeaderMap, Value)> {
+		self.requests.lock().unwrap()
+			.iter()
+			.map(|(headers, body)| (headers.clone(), serde_json::from_slice(body).unwrap()))
+			.collect()
+	}
+}
+
+/// A project of `owner` with one job; returns the job id.
+async fn insert_job(pool: &PgPool, owner: &User) -> Uuid {
+	let client_id = insert_client(pool, owner, "Acme").await;
+	let project_id = insert_project(pool, owner, client_id, "Website").await;
+	support::insert_job(pool, project_id, "Design").await
+}
+
+async fn send(app: &Router, user: &User, method: Method, uri: &str, body: Option<Value>) -> Response {
+	call(app, Some(user), Request::builder().method(method).uri(uri), body).await
+}
+
+/// Like `send`, acting in `workspace_id` rather than the user's personal workspace.
+async fn send_in(app: &Router, user: &User, workspace_id: Uuid, method: Method, uri: &str, body: Option<Value>) -> Response {
+	let request = Request::builder().method(method).uri(uri).header("X-Workspace-Id", workspace_id.to_string());
+	call(app, Some(user), request, body).await
+}
+
+async fn subscribe(app: &Router, user: &User, url: &str, events: Value) -> CreatedWebhook {
+	let response = send(app, user, Method::POST, "/api/v1/webhooks", Some(json!({ "url": url, "events": events }))).await;
+	assert_eq!(response.status(), StatusCode::CREATED);
+	data(response).await
+}
+
+/// The receivers listen on localhost.
+fn config() -> Config {
+	Config { webhook_allow_private_targets: true, ..Config::default() }
+}
+
+async fn dispatch(pool: &PgPool) -> usize {
+	dispatch_with(pool, &config()).await
+}
+
+async fn dispatch_with(pool: &PgPool, config: &Config) -> usize {
+	let client = webhook_service::http_client(config).unwrap();
+	webhook_service::dispatch_due(pool, &client).await.unwrap()
+}
+
+async fn deliveries(app: &Router, user: &User, webhook_id: Uuid) -> Vec<WebhookDelivery> {
+	let response = send(app, user, Method::GET, &format!("/api/v1/webhooks/{}/deliveries", webhook_id), None).await;
+	assert_eq!(response.status(), StatusCode::OK);
+	data(response).await
+}
+
+#[sqlx::test]
+async fn test_subscribe_validates_and_hides_the_secret(pool: PgPool) {
+	let user = insert_user(&pool, "user@example.com").await;
+	let app = build_app_with(pool, config());
+
+	let created = subscribe(&app, &user, "https://hooks.example.com/kvitter", json!(["time_entry.created"])).await;
+	assert!(created.secret.starts_with("whsec_"));
+	assert_eq!(created.webhook.events, vec![WebhookEventType::TimeEntryCreated]);
+
+	let response = send(&app, &user, Method::GET, "/api/v1/webhooks", None).await;
+	let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+	assert!(!String::from_utf8_lossy(&body).contains(&created.secret));
+	let listed: ApiResponse<Vec<Webhook>> = serde_json::from_slice(&body).unwrap();
+	assert_eq!(listed.data.unwrap().len(), 1);
+
+	let response = send(&app, &user, Method::POST, "/api/v1/webhooks", Some(json!({ "url": "ftp://example.com", "events": [] }))).await;
+	let (status, response) = parse::<()>(response).await;
+	assert_eq!(status, StatusCode::BAD_REQUEST);
+	let fields = response.unwrap().details.into_iter().map(|error| error.field).collect::<Vec<_>>();
+	assert_eq!(fields, vec!["url", "events"]);
+}
+
+#[sqlx::test]
+async fn test_events_are_delivered_signed_to_project_members(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let outsider = insert_user(&pool, "outsider@example.com").await;
+	let job_id = insert_job(&pool, &owner).await;
+	let receiver = Receiver::start().await;
+	let app = build_app_with(pool.clone(), config());
+	let webhook = subscribe(&app, &owner, &receiver.url, json!(["time_entry.created"])).await;
+	let other = subscribe(&app, &outsider, &receiver.url, json!(["time_entry.created"])).await;
+
+	let entry_id = log_time(&pool, &owner, job_id).await;
+	assert_eq!(dispatch(&pool).await, 1, "only the project owner's webhook");
+
+	let received = receiver.received();
+	let (headers, payload) = &received[0];
+	assert_eq!(payload["type"], "time_entry.created");
+	assert_eq!(payload["data"]["id"], json!(entry_id));
+	assert_eq!(payload["data"]["time_spent_seconds"], 3600);
+	assert_eq!(headers["kvitter-event"], "time_entry.created");
+	assert_eq!(headers["kvitter-delivery"], payload["id"].as_str().unwrap());
+
+	let signature = headers["kvitter-signature"].to_str().unwrap();
+	let timestamp = signature.strip_prefix("t=").unwrap().split(',').next().unwrap().parse::<i64>().unwrap();
+	let body = receiver.requests.lock().unwrap()[0].1.clone();
+	assert_eq!(signature, webhook_service::sign(&webhook.secret, timestamp, &body));
+
+	let log = deliveries(&app, &owner, webhook.webhook.id).await;
+	assert_eq!(log.len(), 1);
+	assert_eq!((log[0].status, log[0].attempts, log[0].response_status), (DeliveryStatus::Succeeded, 1, Some(200)));
+	assert!(deliveries(&app, &outsider, other.webhook.id).await.is_empty());
+	assert_eq!(dispatch(&pool).await, 0, "nothing is sent twice");
+}
+
+#[sqlx::test]
+async fn test_workspace_webhooks_hear_of_their_workspace_only(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let admin = insert_user(&pool, "admin@example.com").await;
+	let member = insert_user(&pool, "member@example.com").await;
+	let studio: Uuid = sqlx::query_scalar(
+		"WITH w AS (INSERT INTO workspaces (name, created_by) VALUES ('Studio', $1) RETURNING id) \
+		INSERT INTO workspace_members (workspace_id, user_id, role) \
+		SELECT id, $1, 'OWNER'::workspace_role FROM w UNION ALL SELECT id, $2, 'ADMIN' FROM w \
+		UNION ALL SELECT id, $3, 'MEMBER' FROM w RETURNING workspace_id"
+	)
+		.bind(owner.id)
+		.bind(admin.id)
+		.bind(member.id)
+		.fetch_one(&pool)
+		.await
+		.unwrap();
+	let shared_job = support::insert_job(&pool, insert_project_in(&pool, &owner, studio, "Team site").await, "Design").await;
+	let own_job = insert_job(&pool, &owner).await;
+	let receiver = Receiver::start().await;
+	let app = build_app_with(pool.clone(), config());
+	let body = |events: Value| Some(json!({ "url": receiver.url, "events": events, "scope": "workspace" }));
+
+	let response = send_in(&app, &member, studio, Method::POST, "/api/v1/webhooks", body(json!(["time_entry.created"]))).await;
+	assert_eq!(response.status(), StatusCode::FORBIDDEN, "members can't add the workspace's webhooks");
+	let response = send_in(&app, &admin, studio, Method::POST, "/api/v1/webhooks", body(json!(["user.signed_up"]))).await;
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+	let response = send_in(&app, &admin, studio, Method::POST, "/api/v1/webhooks", body(json!(["time_entry.created"]))).await;
+	assert_eq!(response.status(), StatusCode::CREATED);
+	let webhook: CreatedWebhook = data(response).await;
+	assert_eq!(webhook.webhook.workspace_id, Some(studio));
+
+	let entry_id = log_time(&pool, &owner, shared_job).await;
+	log_time(&pool, &owner, own_job).await;
+	assert_eq!(dispatch(&pool).await, 1, "only the studio's entry");
+	assert_eq!(receiver.received()[0].1["data"]["id"], json!(entry_id));
+
+	let listed: Vec<Webhook> = data(send(&app, &admin, Method::GET, "/api/v1/webhooks", None).await).await;
+	assert!(listed.is_empty(), "only listed in the studio");
+	let listed: Vec<Webhook> = data(send_in(&app, &admin, studio, Method::GET, "/api/v1/webhooks", None).await).await;
+	assert_eq!(listed.len(), 1);
+	let listed: Vec<Webhook> = data(send_in(&app, &member, studio, Method::GET, "/api/v1/webhooks", None).await).await;
+	assert!(listed.is_empty(), "nor to members");
+
+	sqlx::query("DELETE FROM workspace_members WHERE workspace_id = $1 AND user_id = $2")
+		.bind(studio)
+		.bind(admin.id)
+		.execute(&pool)
+		.await
+		.unwrap();
+	log_time(&pool, &owner, shared_job).await;
+	assert_eq!(dispatch(&pool).await, 0, "not after whoever added it left");
+}
+
+#[sqlx::test]
+async fn test_failed_deliveries_back_off_and_give_up(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let job_id = insert_job(&pool, &owner).await;
+	let receiver = Receiver::start().await;
+	receiver.answer_with(StatusCode::SERVICE_UNAVAILABLE);
+	let app = build_app_with(pool.clone(), config());
+	let webhook = subscribe(&app, &owner, &receiver.url, json!(["time_entry.created"])).await;
+
+	log_time(&pool, &owner, job_id).await;
+	assert_eq!(dispatch(&pool).await, 1);
+	assert_eq!(dispatch(&pool).await, 0, "the retry is not due yet");
+
+	let delivery = &deliveries(&app, &owner, webhook.webhook.id).await[0];
+	assert_eq!((delivery.status, delivery.attempts, delivery.response_status), (DeliveryStatus::Pending, 1, Some(503)));
+	assert_eq!(delivery.error.as_deref(), Some("Receiver answered 503 Service Unavailable"));
+	let wait = delivery.next_attempt_at - chrono::Utc::now();
+	assert!((25..=30).contains(&wait.num_seconds()), "first retry after 30 seconds, got {}", wait);
+
+	sqlx::query("UPDATE webhook_deliveries SET attempts = $1, next_attempt_at = NOW()")
+		.bind(MAX_ATTEMPTS - 1)
+		.execute(&pool)
+		.await
+		.unwrap();
+	assert_eq!(dispatch(&pool).await, 1);
+	let delivery = &deliveries(&app, &owner, webhook.webhook.id).await[0];
+	assert_eq!((delivery.status, delivery.attempts), (DeliveryStatus::Failed, MAX_ATTEMPTS));
+	assert_eq!(receiver.received().len(), 2);
+}
+
+#[sqlx::test]
+async fn test_webhook_is_disabled_after_repeated_failures(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let job_id = insert_job(&pool, &owner).await;
+	let receiver = Receiver::start().await;
+	receiver.answer_with(StatusCode::INTERNAL_SERVER_ERROR);
+	let app = build_app_with(pool.clone(), config());
+	let webhook = subscribe(&app, &owner, &receiver.url, json!(["time_entry.created"])).await;
+	let uri = format!("/api/v1/webhooks/{}", webhook.webhook.id);
+	sqlx::query("UPDATE webhooks SET consecutive_failures = $1")
+		.bind(DISABLE_AFTER_FAILURES - 1)
+		.execute(&pool)
+		.await
+		.unwrap();
+
+	log_time(&pool, &owner, job_id).await;
+	dispatch(&pool).await;
+	let listed: Vec<Webhook> = data(send(&app, &owner, Method::GET, "/api/v1/webhooks", None).await).await;
+	assert!(listed[0].disabled_at.is_some());
+
+	log_time(&pool, &owner, job_id).await;
+	sqlx::query("UPDATE webhook_deliveries SET next_attempt_at = NOW()").execute(&pool).await.unwrap();
+	assert_eq!(dispatch(&pool).await, 0, "disabled webhooks get nothing");
+	let queued: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM webhook_deliveries").fetch_one(&pool).await.unwrap();
+	assert_eq!(queued, 1, "nor are new events queued for them");
+
+	receiver.answer_with(StatusCode::NO_CONTENT);
+	let response = send(&app, &owner, Method::PATCH, &uri, Some(json!({ "enabled": true }))).await;
+	let enabled: Webhook = data(response).await;
+	assert_eq!((enabled.disabled_at, enabled.consecutive_failures), (None, 0));
+	assert_eq!(dispatch(&pool).await, 1, "the pending delivery resumes");
+	assert_eq!(deliveries(&app, &owner, webhook.webhook.id).await[0].status, DeliveryStatus::Succeeded);
+}
+
+#[sqlx::test]
+async fn test_send_test_event(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let stranger = insert_user(&pool, "stranger@example.com").await;
+	let receiver = Receiver::start().await;
+	let app = build_app_with(pool.clone(), config());
+	let webhook = subscribe(&app, &owner, &receiver.url, json!(["milestone.completed"])).await;
+	let uri = format!("/api/v1/webhooks/{}/test", webhook.webhook.id);
+
+	let response = send(&app, &owner, Method::POST, &uri, None).await;
+	assert_eq!(response.status(), StatusCode::OK);
+	let delivery: WebhookDelivery = data(response).await;
+	assert_eq!((delivery.event_type, delivery.status), (WebhookEventType::Ping, DeliveryStatus::Succeeded));
+	assert_eq!(receiver.received()[0].1["data"]["webhook_id"], json!(webhook.webhook.id));
+
+	let response = send(&app, &stranger, Method::POST, &uri, None).await;
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+	let response = send(&app, &stranger, Method::DELETE, &format!("/api/v1/webhooks/{}", webhook.webhook.id), None).await;
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test]
+async fn test_signups_reach_administrators_only(pool: PgPool) {
+	let admin = insert_user(&pool, "admin@example.com").await;
+	let user = insert_user(&pool, "user@example.com").await;
+	sqlx::query("UPDATE users SET is_admin = true WHERE id = $1").bind(admin.id).execute(&pool).await.unwrap();
+	let receiver = Receiver::start().await;
+	let app = build_app_with(pool.clone(), config());
+	subscribe(&app, &admin, &receiver.url, json!(["user.signed_up"])).await;
+	subscribe(&app, &user, &receiver.url, json!(["user.signed_up"])).await;
+
+	let newcomer = insert_user(&pool, "newcomer@example.com").await;
+	assert_eq!(dispatch(&pool).await, 1);
+	let payload = &receiver.received()[0].1;
+	assert_eq!(payload["data"]["email"], "newcomer@example.com");
+	assert_eq!(payload["data"]["id"], json!(newcomer.id));
+	assert!(payload["data"].get("password_hash").is_none());
+}
+
+#[sqlx::test]
+async fn test_private_targets_are_refused(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let job_id = insert_job(&pool, &owner).await;
+	let receiver = Receiver::start().await;
+	let app = build_app(pool.clone());
+
+	let by_name = receiver.url.replace("127.0.0.1", "localhost");
+	for url in [receiver.url.as_str(), &by_name, "http://169.254.169.254/latest/meta-data/"] {
+		let body = json!({ "url": url, "events": ["time_entry.created"] });
+		let (status, response) = parse::<()>(send(&app, &owner, Method::POST, "/api/v1/webhooks", Some(body)).await).await;
+		assert_eq!(status, StatusCode::BAD_REQUEST, "{}", url);
+		assert_eq!(response.unwrap().details[0].field, "url");
+	}
+
+	// Names can change what they point to after registration, so every delivery checks again.
+	let allowed = build_app_with(pool.clone(), config());
+	let literal = subscribe(&allowed, &owner, &receiver.url, json!(["time_entry.created"])).await;
+	let named = subscribe(&allowed, &owner, &by_name, json!(["time_entry.created"])).await;
+	log_time(&pool, &owner, job_id).await;
+
+	assert_eq!(dispatch_with(&pool, &Config::default()).await, 2);
+	assert!(receiver.received().is_empty());
+	for webhook in [literal, named] {
+		let delivery = &deliveries(&app, &owner, webhook.webhook.id).await[0];
+		assert_eq!((delivery.status, delivery.response_status), (DeliveryStatus::Pending, None));
+		assert_eq!(delivery.error.as_deref(), Some("Webhook URLs must point to a public address"));
+	}
+}
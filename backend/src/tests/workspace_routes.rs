@@ -0,0 +1,204 @@
+use axum::Router;
+use axum::http::{Method, Request, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use sqlx::{migrate::Migrator, PgPool};
+use uuid::Uuid;
+use crate::{
+	auth::{jwt::generate_jwt_token, workspace::WORKSPACE_HEADER},
+	models::{
+		project::Project,
+		response::ApiResponse,
+		user::User,
+		workspace::{Invitation, Workspace, WorkspaceMember, WorkspaceRole, WorkspaceToken},
+	},
+	tests::support::{build_app, call, insert_project_in, insert_user, mailed_token, parse, personal_workspace, send},
+	util::{error::ErrorCode, tenant},
+};
+
+/// Sends with a raw `token`, e.g. one from a workspace switch, and `workspace_id` in the workspace header.
+async fn send_as<T: DeserializeOwned>(
+	app: &Router,
+	token: &str,
+	workspace_id: Option<Uuid>,
+	method: Method,
+	uri: &str,
+	body: Option<Value>,
+) -> (StatusCode, Option<ApiResponse<T>>) {
+	let mut request = Request::builder()
+		.method(method)
+		.uri(uri)
+		.header("Authorization", format!("Bearer {}", token));
+	if let Some(workspace_id) = workspace_id {
+		request = request.header(WORKSPACE_HEADER, workspace_id.to_string());
+	}
+
+	parse(call(app, None, request, body).await).await
+}
+
+/// Creates a shared workspace owned by `owner` and has `invitee` join it with `role`.
+async fn share_workspace(app: &Router, pool: &PgPool, owner: &User, invitee: &User, role: &str) -> Uuid {
+	let (status, created) = send::<Workspace>(app, owner, Method::POST, "/api/v1/workspaces", Some(json!({ "name": "Studio" }))).await;
+	assert_eq!(status, StatusCode::CREATED);
+	let workspace = created.unwrap().data.unwrap();
+	assert_eq!(workspace.role, WorkspaceRole::Owner);
+
+	let uri = format!("/api/v1/workspaces/{}/invitations", workspace.id);
+	let body = json!({ "email": invitee.email, "role": role });
+	let (status, invited) = send::<Value>(app, owner, Method::POST, &uri, Some(body)).await;
+	assert_eq!(status, StatusCode::CREATED);
+	assert!(invited.unwrap().data.unwrap().get("token").is_none(), "only the invitee sees the token");
+	let token = mailed_token(pool, &invitee.email).await;
+
+	let (status, joined) = send::<Workspace>(app, invitee, Method::POST, "/api/v1/invitations/accept", Some(json!({ "token": token }))).await;
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!(joined.unwrap().data.unwrap().id, workspace.id);
+
+	workspace.id
+}
+
+#[sqlx::test]
+async fn test_every_user_has_a_personal_workspace(pool: PgPool) {
+	let user = insert_user(&pool, "personal@example.com").await;
+	let app = build_app(pool);
+
+	let (status, response) = send::<Vec<Workspace>>(&app, &user, Method::GET, "/api/v1/workspaces", None).await;
+	assert_eq!(status, StatusCode::OK);
+	let workspaces = response.unwrap().data.unwrap();
+	assert_eq!(workspaces.len(), 1);
+	assert!(workspaces[0].personal);
+	assert_eq!(workspaces[0].role, WorkspaceRole::Owner);
+}
+
+#[sqlx::test]
+async fn test_invited_member_works_in_the_shared_workspace(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let member = insert_user(&pool, "member@example.com").await;
+	let stranger = insert_user(&pool, "stranger@example.com").await;
+	let app = build_app(pool.clone());
+	let workspace_id = share_workspace(&app, &pool, &owner, &member, "MEMBER").await;
+	let project_id = insert_project_in(&pool, &owner, workspace_id, "Shared").await;
+
+	let member_token = generate_jwt_token(&member).unwrap();
+	let (status, projects) = send_as::<Vec<Project>>(&app, &member_token, Some(workspace_id), Method::GET, "/api/v1/projects", None).await;
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!(projects.unwrap().data.unwrap().iter().map(|project| project.id).collect::<Vec<_>>(), vec![project_id]);
+
+	let (_, personal) = send::<Vec<Project>>(&app, &member, Method::GET, "/api/v1/projects", None).await;
+	assert!(personal.unwrap().data.unwrap().is_empty(), "without a header the personal workspace is active");
+
+	let uri = format!("/api/v1/workspaces/{}/switch", workspace_id);
+	let (status, switched) = send::<WorkspaceToken>(&app, &member, Method::POST, &uri, None).await;
+	assert_eq!(status, StatusCode::OK);
+	let token = switched.unwrap().data.unwrap().token;
+	let (_, projects) = send_as::<Vec<Project>>(&app, &token, None, Method::GET, "/api/v1/projects", None).await;
+	assert_eq!(projects.unwrap().data.unwrap().len(), 1, "the switched token carries the workspace");
+
+	let stranger_token = generate_jwt_token(&stranger).unwrap();
+	let (status, denied) = send_as::<()>(&app, &stranger_token, Some(workspace_id), Method::GET, "/api/v1/projects", None).await;
+	assert_eq!(status, StatusCode::FORBIDDEN);
+	assert_eq!(denied.unwrap().code, Some(ErrorCode::NotWorkspaceMember));
+
+	let (status, _) = send::<WorkspaceToken>(&app, &stranger, Method::POST, &uri, None).await;
+	assert_eq!(status, StatusCode::NOT_FOUND);
+
+	let request = Request::builder().uri("/api/v1/projects").header(WORKSPACE_HEADER, "not-a-uuid");
+	let response = call(&app, Some(&member), request, None).await;
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test]
+async fn test_roles_limit_what_members_manage(pool: PgPool) {
+	let owner = insert_user(&pool, "owner@example.com").await;
+	let member = insert_user(&pool, "member@example.com").await;
+	let admin = insert_user(&pool, "admin@example.com").await;
+	let app = build_app(pool.clone());
+	let workspace_id = share_workspace(&app, &pool, &owner, &member, "MEMBER").await;
+
+	let uri = format!("/api/v1/workspaces/{}/invitations", workspace_id);
+	let (status, _) = send::<()>(&app, &member, Method::POST, &uri, Some(json!({ "email": admin.email }))).await;
+	assert_eq!(status, StatusCode::FORBIDDEN, "members don't invite");
+
+	let (status, _) = send::<Invitation>(&app, &owner, Method::POST, &uri, Some(json!({ "email": member.email }))).await;
+	assert_eq!(status, StatusCode::CONFLICT, "already a member");
+
+	let (status, invited) = send::<Invitation>(&app, &owner, Method::POST, &uri, Some(json!({ "email": admin.email, "role": "ADMIN" }))).await;
+	assert_eq!(status, StatusCode::CREATED);
+	assert_eq!(invited.unwrap().data.unwrap().email, admin.email);
+	let token = mailed_token(&pool, &admin.email).await;
+	let (status, _) = send::<()>(&app, &member, Method::POST, "/api/v1/invitations/accept", Some(json!({ "token": token }))).await;
+	assert_eq!(status, StatusCode::NOT_FOUND, "invitations are for the invited address only");
+	let (status, _) = send::<Workspace>(&app, &admin, Method::POST, "/api/v1/invitations/accept", Some(json!({ "token": token }))).await;
+	assert_eq!(status, StatusCode::OK);
+	let (status, _) = send::<()>(&app, &admin, Method::POST, "/api/v1/invitations/accept", Some(json!({ "token": token }))).await;
+	assert_eq!(status, StatusCode::NOT_FOUND, "an invitation is used once");
+
+	let member_uri = format!("/api/v1/workspaces/{}/members/{}", workspace_id, member.id);
+	let owner_uri = format!("/api/v1/workspaces/{}/members/{}", workspace_id, owner.id);
+	let (status, _) = send::<()>(&app, &admin, Method::PATCH, &owner_uri, Some(json!({ "role": "MEMBER" }))).await;
+	assert_eq!(status, StatusCode::FORBIDDEN, "only owners revoke ownership");
+	let (status, promoted) = send::<WorkspaceMember>(&app, &admin, Method::PATCH, &member_uri, Some(json!({ "role": "ADMIN" }))).await;
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!(promoted.unwrap().data.unwrap().role, WorkspaceRole::Admin);
+
+	let (status, response) = send::<()>(&app, &owner, Method::PATCH, &owner_uri, Some(json!({ "role": "ADMIN" }))).await;
+	assert_eq!(status, StatusCode::CONFLICT);
+	assert_eq!(response.unwrap().code, Some(ErrorCode::LastOwner));
+	let (status, _) = send::<()>(&app, &owner, Method::DELETE, &owner_uri, None).await;
+	assert_eq!(status, StatusCode::CONFLICT, "the last owner can't leave");
+
+	let (status, _) = send::<()>(&app, &member, Method::DELETE, &member_uri, None).await;
+	assert_eq!(status, StatusCode::NO_CONTENT, "members may leave");
+	let (_, members) = send::<Vec<WorkspaceMember>>(&app, &owner, Method::GET, &format!("/api/v1/workspaces/{}/members", workspace_id), None).await;
+	let ids = members.unwrap().data.unwrap().into_iter().map(|member| member.user_id).collect::<Vec<_>>();
+	assert_eq!(ids, vec![owner.id, admin.id]);
+
+	let personal = personal_workspace(&pool, &owner).await;
+	let (status, _) = send::<()>(&app, &owner, Method::DELETE, &format!("/api/v1/workspaces/{}/members/{}", personal, owner.id), None).await;
+	assert_eq!(status, StatusCode::FORBIDDEN, "nobody leaves their personal workspace");
+}
+
+#[sqlx::test(migrations = false)]
+async fn test_clients_without_a_workspace_are_adopted_or_removed(pool: PgPool) {
+	const ADOPTION: i64 = 20261018240600;
+	let migrator: Migrator = sqlx::migrate!("./migrations");
+	for migration in migrator.iter().filter(|migration| migration.version < ADOPTION) {
+		sqlx::raw_sql(&migration.sql).execute(&pool).await.unwrap();
+	}
+	let user = insert_user(&pool, "owner@example.com").await;
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	let (created, ownerless): (Uuid, Uuid) = sqlx::query_as(
+		"WITH c AS (INSERT INTO clients (name) VALUES ('Created'), ('Ownerless') RETURNING id, name) \
+		SELECT (SELECT id FROM c WHERE name = 'Created'), (SELECT id FROM c WHERE name = 'Ownerless')"
+	)
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	sqlx::query("INSERT INTO audit_events (actor_id, entity_type, entity_id, action) VALUES ($1, 'client', $2, 'created')")
+		.bind(user.id)
+		.bind(created)
+		.execute(&mut *tx)
+		.await
+		.unwrap();
+	tx.commit().await.unwrap();
+
+	let migration = migrator.iter().find(|migration| migration.version == ADOPTION).unwrap();
+	sqlx::raw_sql(&migration.sql).execute(&pool).await.unwrap();
+
+	let mut tx = tenant::bypass(&pool).await.unwrap();
+	let clients: Vec<(Uuid, Uuid)> = sqlx::query_as("SELECT id, workspace_id FROM clients")
+		.fetch_all(&mut *tx)
+		.await
+		.unwrap();
+	assert_eq!(clients, vec![(created, personal_workspace(&pool, &user).await)]);
+	let purged: bool = sqlx::query_scalar(
+		"SELECT EXISTS (SELECT 1 FROM audit_events WHERE entity_id = $1 AND action = 'purged')"
+	)
+		.bind(ownerless)
+		.fetch_one(&mut *tx)
+		.await
+		.unwrap();
+	assert!(purged);
+	let err = sqlx::query("INSERT INTO clients (name) VALUES ('Unassigned')").execute(&mut *tx).await.unwrap_err();
+	assert!(err.to_string().contains("workspace_id"), "{}", err);
+}
@@ -0,0 +1,138 @@
+use std::{net::{IpAddr, SocketAddr}, sync::Arc};
+use axum::{
+	extract::{ConnectInfo, FromRef, FromRequestParts},
+	http::request::Parts,
+};
+use serde_json::{json, Map, Value};
+use sqlx::PgConnection;
+use uuid::Uuid;
+use crate::{
+	auth::jwt::AuthUser,
+	config::Config,
+	models::audit::{AuditAction, EntityType},
+	util::{error::{AppError, AppResult}, request_id},
+};
+
+/// Fields whose values never end up in the audit log, only the fact that they changed.
+const REDACTED_FIELDS: &[&str] = &["password_hash"];
+const REDACTED: &str = "[redacted]";
+
+/// # AuditContext
+/// Who made a change and from where, attached to every audit event of a request.
+/// As an extractor it never rejects: anonymous requests simply have no `actor_id`.
+#[derive(Clone, Debug, Default)]
+pub struct AuditContext {
+	pub actor_id: Option<Uuid>,
+	pub request_id: Option<String>,
+	pub ip: Option<IpAddr>,
+}
+
+impl AuditContext {
+	pub fn with_actor(mut self, actor_id: Uuid) -> Self {
+		self.actor_id = Some(actor_id);
+		self
+	}
+}
+
+impl<S> FromRequestParts<S> for AuditContext
+where
+	Arc<Config>: FromRef<S>,
+	S: Send + Sync,
+{
+	type Rejection = AppError;
+
+	async fn from_request_parts(parts: &mut Parts, state: &S) -> AppResult<Self> {
+		let config = Arc::<Config>::from_ref(state);
+		let actor_id = AuthUser::from_request_parts(parts, state).await
+			.ok()
+			.map(|AuthUser(user_id)| user_id);
+		let forwarded = parts.headers.get("x-forwarded-for")
+			.filter(|_| config.trust_proxy_headers)
+			.and_then(|value| value.to_str().ok())
+			.and_then(|value| value.split(',').next())
+			.and_then(|ip| ip.trim().parse().ok());
+		let ip = forwarded.or_else(|| parts.extensions
+			.get::<ConnectInfo<SocketAddr>>()
+			.map(|ConnectInfo(addr)| addr.ip()));
+
+		Ok(Self { actor_id, request_id: request_id::current(), ip })
+	}
+}
+
+/// Appends one event. Pass the transaction that makes the change so both commit or neither does.
+pub async fn record(
+	conn: &mut PgConnection,
+	context: &AuditContext,
+	entity_type: EntityType,
+	entity_id: Uuid,
+	action: AuditAction,
+	changes: Value,
+) -> AppResult<()> {
+	sqlx::query(
+		"INSERT INTO audit_events (actor_id, entity_type, entity_id, action, changes, request_id, ip)
+		VALUES ($1, $2, $3, $4, $5, $6, $7::inet)"
+	)
+		.bind(context.actor_id)
+		.bind(entity_type)
+		.bind(entity_id)
+		.bind(action)
+		.bind(changes)
+		.bind(&context.request_id)
+		.bind(context.ip.map(|ip| ip.to_string()))
+		.execute(conn)
+		.await
+		.map_err(|err| AppError::database("Failed to write audit event", err))?;
+
+	Ok(())
+}
+
+/// `{"field": {"before": .., "after": ..}}` for every top-level field that differs between two
+/// JSON objects. `Value::Null` stands for "did not exist", as on create.
+pub fn diff(before: &Value, after: &Value) -> Value {
+	let empty = Map::new();
+	let before = before.as_object().unwrap_or(&empty);
+	let after = after.as_object().unwrap_or(&empty);
+	let mut changes = Map::new();
+
+	for field in before.keys().chain(after.keys()) {
+		let old = before.get(field).unwrap_or(&Value::Null);
+		let new = after.get(field).unwrap_or(&Value::Null);
+
+		if old == new || changes.contains_key(field) {
+			continue;
+		}
+		let change = match REDACTED_FIELDS.contains(&field.as_str()) {
+			true => json!({ "before": redact(old), "after": redact(new) }),
+			false => json!({ "before": old, "after": new }),
+		};
+		changes.insert(field.clone(), change);
+	}
+	Value::Object(changes)
+}
+
+fn redact(value: &Value) -> Value {
+	match value {
+		Value::Null => Value::Null,
+		_ => Value::String(REDACTED.into()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_diff_only_lists_changed_fields() {
+		let before = json!({ "name": "Website", "status": "ACTIVE" });
+		let after = json!({ "name": "Website", "status": "ARCHIVED" });
+
+		assert_eq!(diff(&before, &after), json!({ "status": { "before": "ACTIVE", "after": "ARCHIVED" } }));
+		assert_eq!(diff(&Value::Null, &json!({ "name": "Acme" })), json!({ "name": { "before": null, "after": "Acme" } }));
+	}
+
+	#[test]
+	fn test_diff_redacts_secrets() {
+		let changes = diff(&json!({ "password_hash": "$argon2id$old" }), &json!({ "password_hash": "$argon2id$new" }));
+		assert_eq!(changes, json!({ "password_hash": { "before": REDACTED, "after": REDACTED } }));
+	}
+}
@@ -0,0 +1,144 @@
+use chrono::Days;
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+use crate::{
+	models::audit::{AuditEvent, AuditFilter, AuditSort, EntityType, HistoryFilter},
+	util::{
+		error::{AppError, AppResult, ErrorCode},
+		pagination::{ListFilter, ListQuery, Page},
+		tenant,
+		workspace_service::{IN_WORKSPACE, MANAGES_PROJECT},
+	},
+};
+
+const AUDIT_COLUMNS: &str = "a.id, a.occurred_at, a.actor_id, a.entity_type, a.entity_id, a.action, \
+	a.changes, a.request_id, host(a.ip) AS ip";
+
+impl EntityType {
+	fn table(&self) -> &'static str {
+		match self {
+			EntityType::User => "users",
+			EntityType::Client => "clients",
+			EntityType::Project => "projects",
+			EntityType::Job => "jobs",
+			EntityType::TimeEntry => "time_entries",
+			EntityType::Milestone => "milestones",
+			EntityType::Workspace => "workspaces",
+		}
+	}
+
+	/// Everything of the active workspace, except other members' time entries on projects the user doesn't
+	/// manage. Deleted rows count too: their history is still of interest.
+	/// Expects the row as `x` and the acting member in a `me` CTE.
+	pub fn visible_to_me(&self) -> String {
+		match self {
+			EntityType::User => "x.id = me.id".into(),
+			EntityType::Workspace => "x.id = me.workspace_id".into(),
+			EntityType::Client | EntityType::Project => "x.workspace_id = me.workspace_id".into(),
+			EntityType::Job | EntityType::Milestone => format!(
+				"EXISTS (SELECT 1 FROM projects p WHERE p.id = x.project_id AND {})", IN_WORKSPACE,
+			),
+			EntityType::TimeEntry => format!(
+				"EXISTS (SELECT 1 FROM jobs j JOIN projects p ON p.id = j.project_id \
+				WHERE j.id = x.job_id AND (x.user_id = me.id AND {} OR {}))",
+				IN_WORKSPACE,
+				MANAGES_PROJECT,
+			),
+		}
+	}
+}
+
+async fn can_view_history(
+	pool: &PgPool,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	entity_type: EntityType,
+	entity_id: &Uuid,
+) -> AppResult<bool> {
+	let sql = format!(
+		"WITH me AS (SELECT u.id, u.is_admin, m.workspace_id, m.role FROM users u \
+			LEFT JOIN workspace_members m ON m.user_id = u.id AND m.workspace_id = $3 \
+			WHERE u.id = $2 AND u.deleted_at IS NULL) \
+		SELECT EXISTS (SELECT 1 FROM me WHERE me.is_admin) \
+			OR EXISTS (SELECT 1 FROM {} x, me WHERE x.id = $1 AND ({}))",
+		entity_type.table(),
+		entity_type.visible_to_me(),
+	);
+
+	let mut tx = tenant::begin(pool, user_id, workspace_id).await?;
+	sqlx::query_scalar::<_, bool>(&sql)
+		.bind(entity_id)
+		.bind(user_id)
+		.bind(workspace_id)
+		.fetch_one(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to check audit access", err))
+}
+
+/// Every recorded change to one entity, for users who can see it in the active workspace and for admins.
+pub async fn list_entity_history(
+	pool: &PgPool,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	entity_type: EntityType,
+	entity_id: &Uuid,
+	query: &ListQuery<HistoryFilter>,
+) -> AppResult<Page<AuditEvent>> {
+	if !can_view_history(pool, user_id, workspace_id, entity_type, entity_id).await? {
+		return Err(AppError::NotFound(ErrorCode::NotFound, "No history found for this entity".into()));
+	}
+
+	let mut builder = QueryBuilder::new(format!("SELECT {} FROM audit_events a WHERE a.entity_type = ", AUDIT_COLUMNS));
+	builder.push_bind(entity_type)
+		.push(" AND a.entity_id = ")
+		.push_bind(*entity_id);
+	query.push_page(&mut builder, "a.id");
+
+	fetch_page(pool, builder, query).await
+}
+
+/// The whole audit log, for admins.
+pub async fn list_audit_events(pool: &PgPool, query: &ListQuery<AuditFilter>) -> AppResult<Page<AuditEvent>> {
+	let filter = &query.filter;
+	let mut builder = QueryBuilder::new(format!("SELECT {} FROM audit_events a WHERE TRUE", AUDIT_COLUMNS));
+
+	if let Some(actor_id) = filter.actor_id {
+		builder.push(" AND a.actor_id = ").push_bind(actor_id);
+	}
+	if let Some(entity_type) = filter.entity_type {
+		builder.push(" AND a.entity_type = ").push_bind(entity_type);
+	}
+	if let Some(entity_id) = filter.entity_id {
+		builder.push(" AND a.entity_id = ").push_bind(entity_id);
+	}
+	if let Some(action) = filter.action {
+		builder.push(" AND a.action = ").push_bind(action);
+	}
+	if let Some(request_id) = &filter.request_id {
+		builder.push(" AND a.request_id = ").push_bind(request_id.clone());
+	}
+	if let Some(from) = filter.from {
+		builder.push(" AND a.occurred_at >= ").push_bind(from.and_time(Default::default()).and_utc());
+	}
+	if let Some(to) = filter.to.and_then(|to| to.checked_add_days(Days::new(1))) {
+		builder.push(" AND a.occurred_at < ").push_bind(to.and_time(Default::default()).and_utc());
+	}
+	query.push_page(&mut builder, "a.id");
+
+	fetch_page(pool, builder, query).await
+}
+
+async fn fetch_page<F: ListFilter<Sort = AuditSort>>(
+	pool: &PgPool,
+	mut builder: QueryBuilder<'_, Postgres>,
+	query: &ListQuery<F>,
+) -> AppResult<Page<AuditEvent>> {
+	// The log is only readable past row-level security; callers have checked access already.
+	let mut tx = tenant::bypass(pool).await?;
+	let rows = builder.build_query_as::<AuditEvent>()
+		.fetch_all(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to list audit events", err))?;
+
+	Ok(query.paginate(rows))
+}
@@ -0,0 +1,87 @@
+use axum::{
+	extract::{Request, State},
+	http::{HeaderName, HeaderValue},
+	middleware::Next,
+	response::Response,
+};
+use chrono::{DateTime, Utc};
+
+static DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+static SUNSET: HeaderName = HeaderName::from_static("sunset");
+static LINK: HeaderName = HeaderName::from_static("link");
+
+/// # Deprecation
+/// Describes when a group of routes was deprecated and when it stops working.
+/// `successor_prefix` is prepended to the request path to point clients at the replacement.
+#[derive(Clone, Debug)]
+pub struct Deprecation {
+	pub deprecated_at: DateTime<Utc>,
+	pub sunset: Option<DateTime<Utc>>,
+	pub successor_prefix: Option<&'static str>,
+}
+
+impl Deprecation {
+	/// `Deprecation: @<unix seconds>` as defined by RFC 9745.
+	fn deprecation_value(&self) -> HeaderValue {
+		HeaderValue::from_str(&format!("@{}", self.deprecated_at.timestamp()))
+			.expect("timestamp is ASCII")
+	}
+
+	/// `Sunset: <HTTP-date>` as defined by RFC 8594.
+	fn sunset_value(&self) -> Option<HeaderValue> {
+		self.sunset.map(|sunset| {
+			HeaderValue::from_str(&sunset.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+				.expect("HTTP date is ASCII")
+		})
+	}
+
+	fn link_value(&self, path: &str) -> Option<HeaderValue> {
+		self.successor_prefix
+			.and_then(|prefix| {
+				HeaderValue::from_str(&format!("<{}{}>; rel=\"successor-version\"", prefix, path)).ok()
+			})
+	}
+}
+
+/// Adds `Deprecation`, `Sunset` and `Link` headers to every response of the routes it wraps.
+/// Use with `middleware::from_fn_with_state(deprecation, deprecation::mark)`.
+pub async fn mark(
+	State(deprecation): State<Deprecation>,
+	request: Request,
+	next: Next,
+) -> Response {
+	let link = deprecation.link_value(request.uri().path());
+	let mut response = next.run(request).await;
+	let headers = response.headers_mut();
+
+	headers.insert(DEPRECATION.clone(), deprecation.deprecation_value());
+	if let Some(sunset) = deprecation.sunset_value() {
+		headers.insert(SUNSET.clone(), sunset);
+	}
+	if let Some(link) = link {
+		headers.append(LINK.clone(), link);
+	}
+	response
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chrono::TimeZone;
+
+	#[test]
+	fn test_header_values() {
+		let deprecation = Deprecation {
+			deprecated_at: Utc.with_ymd_and_hms(2026, 10, 18, 0, 0, 0).unwrap(),
+			sunset: Some(Utc.with_ymd_and_hms(2027, 4, 18, 0, 0, 0).unwrap()),
+			successor_prefix: Some("/api/v1"),
+		};
+
+		assert_eq!(deprecation.deprecation_value(), "@1792281600");
+		assert_eq!(deprecation.sunset_value().unwrap(), "Sun, 18 Apr 2027 00:00:00 GMT");
+		assert_eq!(
+			deprecation.link_value("/me").unwrap(),
+			"</api/v1/me>; rel=\"successor-version\""
+		);
+	}
+}
@@ -0,0 +1,225 @@
+use chrono::Duration;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+use crate::{
+	auth::hash::verify_password,
+	config::Config,
+	models::{
+		audit::{AuditAction, EntityType},
+		user::{ChangeEmailPayload, EmailChange},
+	},
+	util::{
+		audit::{self, AuditContext},
+		error::{AppError, AppResult, ErrorCode, FieldError},
+		mailer::{self, SendEmail},
+		user_service::{fetch_user_by_uuid, is_email_unique},
+		validation::normalize_email,
+	},
+};
+
+/// How long the link sent to the new address can be followed.
+const CONFIRM_PERIOD: Duration = Duration::hours(24);
+/// How long the old address can undo a change, confirmed or not.
+const UNDO_PERIOD: Duration = Duration::days(7);
+
+fn change_invalid() -> AppError {
+	AppError::NotFound(ErrorCode::EmailChangeInvalid, "The link is invalid, expired or already used".into())
+}
+
+fn hash_token(token: &str) -> String {
+	hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn new_token() -> String {
+	format!("eml_{}", hex::encode(rand::random::<[u8; 32]>()))
+}
+
+/// Starts moving the account to `payload.new_email`, replacing any change still waiting for confirmation.
+/// Nothing switches yet: the new address gets a link to confirm, the old one a notice with a link to undo.
+pub async fn request_email_change(
+	pool: &PgPool,
+	config: &Config,
+	user_id: &Uuid,
+	payload: &ChangeEmailPayload,
+) -> AppResult<EmailChange> {
+	let new_email = normalize_email("new_email", &payload.new_email)?;
+	let new_email = new_email.as_str();
+
+	let user = fetch_user_by_uuid(pool, user_id).await?;
+	let is_valid = verify_password(&payload.password, &user.password_hash)
+		.map_err(|err| AppError::internal_from("Stored password hash is invalid", err))?;
+	if !is_valid {
+		return Err(AppError::Auth(ErrorCode::IncorrectPassword, "Current password is incorrect".into()));
+	}
+	if new_email.to_lowercase() == user.email.to_lowercase() {
+		return Err(AppError::Validation(vec![
+			FieldError::new("new_email", ErrorCode::ValidationFailed, "This is already the address of the account"),
+		]));
+	}
+	is_email_unique(pool, new_email).await?;
+
+	let (confirm_token, undo_token) = (new_token(), new_token());
+	let mut tx = pool.begin().await
+		.map_err(|err| AppError::database("Failed to start transaction", err))?;
+	sqlx::query("DELETE FROM email_changes WHERE user_id = $1 AND confirmed_at IS NULL AND undone_at IS NULL")
+		.bind(user_id)
+		.execute(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to replace pending email change", err))?;
+	let change = sqlx::query_as::<_, EmailChange>(
+		"INSERT INTO email_changes (user_id, old_email, new_email, confirm_token_hash, undo_token_hash, expires_at) \
+		VALUES ($1, $2, $3, $4, $5, NOW() + make_interval(secs => $6)) \
+		RETURNING id, new_email, created_at, expires_at"
+	)
+		.bind(user_id)
+		.bind(&user.email)
+		.bind(new_email)
+		.bind(hash_token(&confirm_token))
+		.bind(hash_token(&undo_token))
+		.bind(CONFIRM_PERIOD.num_seconds() as f64)
+		.fetch_one(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to request email change", err))?;
+
+	mailer::send(&mut *tx, &SendEmail {
+		to: new_email.to_string(),
+		subject: "Confirm your new email address".into(),
+		body: format!(
+			"Follow this link within {} hours to use this address for your Kvitter account:\n\n{}/email-changes/confirm?token={}\n\n\
+			If you didn't ask for this, ignore this email.",
+			CONFIRM_PERIOD.num_hours(), config.app_url, confirm_token,
+		),
+	}).await?;
+	mailer::send(&mut *tx, &SendEmail {
+		to: user.email.clone(),
+		subject: "Your email address is being changed".into(),
+		body: format!(
+			"Someone asked to change the email address of your Kvitter account to {}.\n\n\
+			If it wasn't you, follow this link within {} days to keep this address, then change your password:\n\n\
+			{}/email-changes/undo?token={}",
+			new_email, UNDO_PERIOD.num_days(), config.app_url, undo_token,
+		),
+	}).await?;
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to request email change", err))?;
+
+	Ok(change)
+}
+
+/// Switches the account to the new address behind `token`, if it is still free and the account
+/// still has the address the change was requested from.
+pub async fn confirm_email_change(pool: &PgPool, token: &str, context: &AuditContext) -> AppResult<()> {
+	let mut tx = pool.begin().await
+		.map_err(|err| AppError::database("Failed to start transaction", err))?;
+	let (change_id, user_id, old_email, new_email) = sqlx::query_as::<_, (Uuid, Uuid, String, String)>(
+		"SELECT id, user_id, old_email, new_email FROM email_changes \
+		WHERE confirm_token_hash = $1 AND confirmed_at IS NULL AND undone_at IS NULL AND expires_at > NOW() \
+		FOR UPDATE"
+	)
+		.bind(hash_token(token))
+		.fetch_optional(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to confirm email change", err))?
+		.ok_or_else(change_invalid)?;
+
+	// Someone may have signed up with the address since it was requested.
+	is_email_unique(pool, &new_email).await?;
+	let switched = set_email(&mut tx, &user_id, Some(&old_email), &new_email).await?;
+	if !switched {
+		return Err(change_invalid());
+	}
+	sqlx::query("UPDATE email_changes SET confirmed_at = NOW() WHERE id = $1")
+		.bind(change_id)
+		.execute(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to confirm email change", err))?;
+	record_email_change(&mut tx, context, &user_id, &old_email, &new_email).await?;
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to confirm email change", err))?;
+
+	Ok(())
+}
+
+/// Cancels the change behind `token`, and every change requested after it. When one of them went through,
+/// the account gets the old address back.
+pub async fn undo_email_change(pool: &PgPool, token: &str, context: &AuditContext) -> AppResult<()> {
+	let mut tx = pool.begin().await
+		.map_err(|err| AppError::database("Failed to start transaction", err))?;
+	let (user_id, old_email) = sqlx::query_as::<_, (Uuid, String)>(
+		"SELECT user_id, old_email FROM email_changes \
+		WHERE undo_token_hash = $1 AND undone_at IS NULL AND created_at > NOW() - make_interval(secs => $2) \
+		FOR UPDATE"
+	)
+		.bind(hash_token(token))
+		.bind(UNDO_PERIOD.num_seconds() as f64)
+		.fetch_optional(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to undo email change", err))?
+		.ok_or_else(change_invalid)?;
+
+	let any_confirmed = sqlx::query_scalar::<_, Option<bool>>(
+		"WITH undone AS ( \
+			UPDATE email_changes x SET undone_at = NOW() FROM email_changes c \
+			WHERE c.undo_token_hash = $1 AND x.user_id = c.user_id AND x.created_at >= c.created_at \
+				AND x.undone_at IS NULL \
+			RETURNING x.confirmed_at \
+		) SELECT bool_or(confirmed_at IS NOT NULL) FROM undone"
+	)
+		.bind(hash_token(token))
+		.fetch_one(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to undo email change", err))?
+		.unwrap_or(false);
+
+	if any_confirmed {
+		let user = fetch_user_by_uuid(pool, &user_id).await?;
+		if user.email != old_email {
+			is_email_unique(pool, &old_email).await?;
+			set_email(&mut tx, &user_id, None, &old_email).await?;
+			record_email_change(&mut tx, context, &user_id, &user.email, &old_email).await?;
+		}
+	}
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to undo email change", err))?;
+
+	Ok(())
+}
+
+/// Returns whether the account was updated: it must still exist, and have `expected` if given.
+/// The unique index has the last word on addresses taken in the meantime.
+async fn set_email(conn: &mut PgConnection, user_id: &Uuid, expected: Option<&str>, email: &str) -> AppResult<bool> {
+	let updated = sqlx::query(
+		"UPDATE users SET email = $1 WHERE id = $2 AND deleted_at IS NULL AND ($3::text IS NULL OR lower(email) = lower($3))"
+	)
+		.bind(email)
+		.bind(user_id)
+		.bind(expected)
+		.execute(conn)
+		.await
+		.map_err(|err| match err.as_database_error().is_some_and(|db| db.is_unique_violation()) {
+			true => AppError::Auth(ErrorCode::EmailTaken, "Email is already taken".into()),
+			false => AppError::database("Failed to change email", err),
+		})?;
+
+	Ok(updated.rows_affected() > 0)
+}
+
+/// The links are followed without a session, so the account itself is recorded as the actor.
+async fn record_email_change(
+	conn: &mut PgConnection,
+	context: &AuditContext,
+	user_id: &Uuid,
+	before: &str,
+	after: &str,
+) -> AppResult<()> {
+	audit::record(
+		conn,
+		&context.clone().with_actor(*user_id),
+		EntityType::User,
+		*user_id,
+		AuditAction::Updated,
+		audit::diff(&json!({ "email": before }), &json!({ "email": after })),
+	).await
+}
@@ -1,46 +1,343 @@
-use axum::{http::StatusCode, response::{IntoResponse, Response}, Json};
+use axum::{http::StatusCode, response::{IntoResponse, Response}};
 use serde::{Serialize, Deserialize};
-use serde_json::json;
 use thiserror::Error;
-use tracing::error;
-use console::style;
+use tracing::{error, info, warn};
+use ts_rs::TS;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use crate::models::response::ApiResponse;
 
-#[derive(Error, Debug, Serialize, Deserialize)]
+/// Declares `ErrorCode` with each wire string written once, for serde, the generated types and `as_str`.
+macro_rules! error_codes {
+	($($variant:ident => $code:tt,)*) => {
+		/// # ErrorCode
+		/// Stable, machine-readable identifier sent with every error.
+		/// Clients switch on these instead of the English message, so existing codes must never be renamed.
+		#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema, TS)]
+		#[ts(export)]
+		pub enum ErrorCode {
+			$(
+				#[serde(rename = $code)]
+				$variant,
+			)*
+		}
+
+		impl ErrorCode {
+			#[cfg(test)]
+			const ALL: &[ErrorCode] = &[$(ErrorCode::$variant),*];
+
+			pub fn as_str(&self) -> &'static str {
+				match self {
+					$(ErrorCode::$variant => $code,)*
+				}
+			}
+		}
+	};
+}
+
+error_codes! {
+	MissingToken => "auth.missing_token",
+	InvalidToken => "auth.invalid_token",
+	InvalidCredentials => "auth.invalid_credentials",
+	IncorrectPassword => "auth.incorrect_password",
+	TokenGenerationFailed => "auth.token_generation_failed",
+	Forbidden => "auth.forbidden",
+	UserNotFound => "user.not_found",
+	EmailTaken => "user.email_taken",
+	EmailChangeInvalid => "user.email_change_invalid",
+	NotWorkspaceMember => "workspace.not_member",
+	LastOwner => "workspace.last_owner",
+	AlreadyMember => "workspace.already_member",
+	InvitationInvalid => "workspace.invitation_invalid",
+	ValidationFailed => "validation.failed",
+	EmailInvalid => "email.invalid",
+	PasswordEmpty => "password.empty",
+	PasswordTooShort => "password.too_short",
+	PasswordTooLong => "password.too_long",
+	PasswordMissingUppercase => "password.missing_uppercase",
+	PasswordMissingLowercase => "password.missing_lowercase",
+	PasswordMissingDigit => "password.missing_digit",
+	PasswordMissingSymbol => "password.missing_symbol",
+	PasswordTooManyRepeats => "password.too_many_repeats",
+	PasswordTooWeak => "password.too_weak",
+	PasswordBreached => "password.breached",
+	BadRequest => "request.bad_request",
+	InvalidQuery => "request.invalid_query",
+	InvalidCursor => "request.invalid_cursor",
+	PreconditionRequired => "request.precondition_required",
+	IdempotencyKeyReused => "request.idempotency_key_reused",
+	IdempotencyKeyInProgress => "request.idempotency_key_in_progress",
+	NotFound => "resource.not_found",
+	VersionMismatch => "resource.version_mismatch",
+	Database => "database.error",
+	Internal => "internal.error",
+	Misconfigured => "internal.misconfigured",
+}
+
+impl std::fmt::Display for ErrorCode {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+/// # FieldError
+/// One failed rule on one input field, carried by `AppError::Validation`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct FieldError {
+	pub field: String,
+	pub code: ErrorCode,
+	pub message: String,
+}
+
+impl FieldError {
+	pub fn new(field: &str, code: ErrorCode, message: impl Into<String>) -> Self {
+		Self {
+			field: field.into(),
+			code,
+			message: message.into(),
+		}
+	}
+}
+
+/// # InternalError
+/// Diagnostic context for failures that are our fault.
+/// `context` and `source` only reach the logs; clients get a generic message and the `id`.
+#[derive(Debug)]
+pub struct InternalError {
+	pub id: Uuid,
+	pub code: ErrorCode,
+	pub context: String,
+	pub source: Option<Box<dyn std::error::Error + Send + Sync>>,
+}
+
+impl InternalError {
+	pub fn new(code: ErrorCode, context: impl Into<String>) -> Self {
+		Self {
+			id: Uuid::new_v4(),
+			code,
+			context: context.into(),
+			source: None,
+		}
+	}
+
+	pub fn with_source(mut self, source: impl std::error::Error + Send + Sync + 'static) -> Self {
+		self.source = Some(Box::new(source));
+		self
+	}
+
+	/// `Display` of every error below this one, outermost first.
+	pub fn source_chain(&self) -> Vec<String> {
+		let mut chain = Vec::new();
+		let mut current = self.source.as_deref().map(|err| err as &(dyn std::error::Error + 'static));
+
+		while let Some(err) = current {
+			chain.push(err.to_string());
+			current = err.source();
+		}
+		chain
+	}
+}
+
+impl std::fmt::Display for InternalError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(&self.context)
+	}
+}
+
+impl std::error::Error for InternalError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		self.source.as_deref().map(|err| err as &(dyn std::error::Error + 'static))
+	}
+}
+
+/// `Display` is meant for logs and includes internal context.
+/// Use `message` or `public_message` for anything sent to a client.
+#[derive(Error, Debug)]
 pub enum AppError {
-	#[error("Authentication failed: {0}")]
-	Auth(String),
+	#[error("Authentication failed: {1}")]
+	Auth(ErrorCode, String),
 	#[error("Database error: {0}")]
-	Database(String),
-	#[error("Invalid input: {0}")]
-	Validation(String),
+	Database(#[source] InternalError),
+	#[error("Invalid input: {}", join_messages(.0))]
+	Validation(Vec<FieldError>),
 	#[error("Internal error: {0}")]
-	Internal(String),
-	#[error("Not found: {0}")]
-	NotFound(String),
-	#[error("Bad request: {0}")]
-	BadRequest(String),
-	#[error("Forbidden: {0}")]
-	Forbidden(String),
+	Internal(#[source] InternalError),
+	#[error("Not found: {1}")]
+	NotFound(ErrorCode, String),
+	#[error("Bad request: {1}")]
+	BadRequest(ErrorCode, String),
+	#[error("Forbidden: {1}")]
+	Forbidden(ErrorCode, String),
+	#[error("Conflict: {1}")]
+	Conflict(ErrorCode, String),
+	#[error("Precondition failed: {1}")]
+	PreconditionFailed(ErrorCode, String),
+	#[error("Precondition required: {1}")]
+	PreconditionRequired(ErrorCode, String),
+}
+
+const INTERNAL_MESSAGE: &str = "Internal server error";
+
+fn join_messages(fields: &[FieldError]) -> String {
+	fields.iter()
+		.map(|field| field.message.as_str())
+		.collect::<Vec<_>>()
+		.join("; ")
 }
 
 impl AppError {
-	pub fn log(&self) {
-		let (level, color) = match self {
-			AppError::Internal(_) | AppError::Database(_) => ("ERROR", console::Color::Red),
-			AppError::Auth(_) | AppError::Forbidden(_) => ("WARN", console::Color::Yellow),
-			_ => ("INFO", console::Color::White),
+	#[cfg(test)]
+	pub fn internal(context: impl Into<String>) -> Self {
+		AppError::Internal(InternalError::new(ErrorCode::Internal, context))
+	}
+
+	pub fn internal_from(
+		context: impl Into<String>,
+		source: impl std::error::Error + Send + Sync + 'static,
+	) -> Self {
+		AppError::Internal(InternalError::new(ErrorCode::Internal, context).with_source(source))
+	}
+
+	pub fn database(context: impl Into<String>, source: sqlx::Error) -> Self {
+		AppError::Database(InternalError::new(ErrorCode::Database, context).with_source(source))
+	}
+
+	/// Variant name, used as a metric label.
+	pub fn kind(&self) -> &'static str {
+		match self {
+			AppError::Auth(..) => "Auth",
+			AppError::Database(..) => "Database",
+			AppError::Validation(_) => "Validation",
+			AppError::Internal(..) => "Internal",
+			AppError::NotFound(..) => "NotFound",
+			AppError::BadRequest(..) => "BadRequest",
+			AppError::Forbidden(..) => "Forbidden",
+			AppError::Conflict(..) => "Conflict",
+			AppError::PreconditionFailed(..) => "PreconditionFailed",
+			AppError::PreconditionRequired(..) => "PreconditionRequired",
+		}
+	}
+
+	pub fn code(&self) -> ErrorCode {
+		match self {
+			AppError::Validation(_) => ErrorCode::ValidationFailed,
+			AppError::Database(internal) | AppError::Internal(internal) => internal.code,
+			AppError::Auth(code, _)
+			| AppError::NotFound(code, _)
+			| AppError::BadRequest(code, _)
+			| AppError::Forbidden(code, _)
+			| AppError::Conflict(code, _)
+			| AppError::PreconditionFailed(code, _)
+			| AppError::PreconditionRequired(code, _) => *code,
+		}
+	}
+
+	/// Client-facing message without the variant prefix.
+	/// Internal failures never expose their context here.
+	#[cfg(test)]
+	pub fn message(&self) -> String {
+		match self {
+			AppError::Validation(fields) => join_messages(fields),
+			AppError::Database(_) | AppError::Internal(_) => INTERNAL_MESSAGE.into(),
+			AppError::Auth(_, msg)
+			| AppError::NotFound(_, msg)
+			| AppError::BadRequest(_, msg)
+			| AppError::Forbidden(_, msg)
+			| AppError::Conflict(_, msg)
+			| AppError::PreconditionFailed(_, msg)
+			| AppError::PreconditionRequired(_, msg) => msg.clone(),
+		}
+	}
+
+	/// Client-facing equivalent of `Display`.
+	pub fn public_message(&self) -> String {
+		match self {
+			AppError::Database(internal) | AppError::Internal(internal) =>
+				format!("{} (error id: {})", INTERNAL_MESSAGE, internal.id),
+			_ => self.to_string(),
+		}
+	}
+
+	/// Identifier a client can quote to find the matching log entry.
+	pub fn error_id(&self) -> Option<Uuid> {
+		match self {
+			AppError::Database(internal) | AppError::Internal(internal) => Some(internal.id),
+			_ => None,
+		}
+	}
+
+	pub fn details(&self) -> &[FieldError] {
+		match self {
+			AppError::Validation(fields) => fields,
+			_ => &[],
+		}
+	}
+
+	pub fn status_code(&self) -> StatusCode {
+		match self {
+			AppError::Auth(..) => StatusCode::UNAUTHORIZED,
+			AppError::Database(..) => StatusCode::INTERNAL_SERVER_ERROR,
+			AppError::Validation(_) => StatusCode::BAD_REQUEST,
+			AppError::Internal(..) => StatusCode::INTERNAL_SERVER_ERROR,
+			AppError::NotFound(..) => StatusCode::NOT_FOUND,
+			AppError::BadRequest(..) => StatusCode::BAD_REQUEST,
+			AppError::Forbidden(..) => StatusCode::FORBIDDEN,
+			AppError::Conflict(..) => StatusCode::CONFLICT,
+			AppError::PreconditionFailed(..) => StatusCode::PRECONDITION_FAILED,
+			AppError::PreconditionRequired(..) => StatusCode::PRECONDITION_REQUIRED,
+		}
+	}
+
+	/// Inverse of `status_code`, `code` and `message`, used to turn a serialized error back into a variant.
+	/// 400 and 500 are shared by two variants each and are told apart by the code.
+	/// Internal errors come back with their public message as context, since that is all a client ever sees.
+	#[cfg(test)]
+	pub fn from_parts(
+		status: StatusCode,
+		code: ErrorCode,
+		message: String,
+		details: Vec<FieldError>,
+		error_id: Option<Uuid>,
+	) -> Self {
+		let internal = |code| {
+			let mut internal = InternalError::new(code, message.clone());
+			internal.id = error_id.unwrap_or(internal.id);
+			internal
 		};
 
-		let err_msg = format!("{}", self);
-		let formatted = style(format!("[{}] {}", level, err_msg)).fg(color);
+		match (status, code) {
+			(StatusCode::UNAUTHORIZED, _) => AppError::Auth(code, message),
+			(StatusCode::FORBIDDEN, _) => AppError::Forbidden(code, message),
+			(StatusCode::NOT_FOUND, _) => AppError::NotFound(code, message),
+			(StatusCode::CONFLICT, _) => AppError::Conflict(code, message),
+			(StatusCode::PRECONDITION_FAILED, _) => AppError::PreconditionFailed(code, message),
+			(StatusCode::PRECONDITION_REQUIRED, _) => AppError::PreconditionRequired(code, message),
+			(StatusCode::BAD_REQUEST, ErrorCode::ValidationFailed) => AppError::Validation(details),
+			(StatusCode::BAD_REQUEST, _) => AppError::BadRequest(code, message),
+			(_, ErrorCode::Database) => AppError::Database(internal(code)),
+			_ => AppError::Internal(internal(code)),
+		}
+	}
+
+	/// Emits one structured event; the request span adds `request_id`, method and uri.
+	pub fn log(&self) {
+		let error_type = self.kind();
+		let code = self.code().as_str();
 
-		error!(
-			error_type = format!("{:?}", self).split('(').next().unwrap(),
-			message = %err_msg,
-			"Request failed"
-		);
-		
-		eprintln!("{}", formatted);
+		match self {
+			AppError::Internal(internal) | AppError::Database(internal) => error!(
+				error_type,
+				code,
+				error_id = %internal.id,
+				error = %self,
+				causes = ?internal.source_chain(),
+				"Request failed"
+			),
+			AppError::Auth(..) | AppError::Forbidden(..) =>
+				warn!(error_type, code, error = %self, "Request failed"),
+			_ => info!(error_type, code, error = %self, "Request failed"),
+		}
 	}
 }
 
@@ -48,29 +345,7 @@ pub type AppResult<T> = Result<T, AppError>;
 
 impl IntoResponse for AppError {
 	fn into_response(self) -> Response {
-		let (status, message) = match &self {
-			AppError::Auth(msg) => 
-				(StatusCode::UNAUTHORIZED, 
-					"Authentication failed: ".to_string() + &msg
-				),
-			AppError::Internal(msg) => 
-				(StatusCode::INTERNAL_SERVER_ERROR, 
-					"Internal server error: ".to_string() + &msg
-				),
-			AppError::NotFound(msg) => 
-				(StatusCode::NOT_FOUND, "Not found: ".to_string() + &msg),
-			AppError::BadRequest(msg) => 
-				(StatusCode::BAD_REQUEST, "Bad request: ".to_string() + &msg),
-			AppError::Forbidden(msg) => 
-				(StatusCode::FORBIDDEN, "Forbidden: ".to_string() + &msg),
-			AppError::Database(msg) => 
-				(StatusCode::INTERNAL_SERVER_ERROR, "Database error: ".to_string() + &msg),
-			AppError::Validation(msg) => 
-				(StatusCode::BAD_REQUEST, "Validation error: ".to_string() + &msg),
-		};
-
-		self.log();
-		(status, Json(json!({ "error": message }))).into_response()
+		ApiResponse::<()>::error(&self).into_response()
 	}
 }
 
@@ -80,7 +355,59 @@ mod tests {
 
 	#[test]
 	fn test_app_error_logging() {
-		let error = AppError::Auth("Invalid credentials".into());
+		let error = AppError::Auth(ErrorCode::InvalidCredentials, "Invalid credentials".into());
 		error.log();
 	}
-}
\ No newline at end of file
+
+	#[test]
+	fn test_error_code_serialization() {
+		for code in ErrorCode::ALL {
+			assert_eq!(serde_json::to_value(code).unwrap(), serde_json::json!(code.as_str()));
+			assert_eq!(serde_json::from_value::<ErrorCode>(serde_json::json!(code.as_str())).unwrap(), *code);
+		}
+	}
+
+	#[test]
+	fn test_from_parts_round_trip() {
+		let errors = vec![
+			AppError::Auth(ErrorCode::InvalidCredentials, "Invalid credentials".into()),
+			AppError::database("Fetching user", sqlx::Error::PoolTimedOut),
+			AppError::internal("Oops"),
+			AppError::NotFound(ErrorCode::UserNotFound, "User not found".into()),
+			AppError::BadRequest(ErrorCode::BadRequest, "Malformed".into()),
+			AppError::Forbidden(ErrorCode::Forbidden, "No access".into()),
+			AppError::Conflict(ErrorCode::IdempotencyKeyReused, "Reused".into()),
+			AppError::PreconditionFailed(ErrorCode::VersionMismatch, "Stale".into()),
+			AppError::PreconditionRequired(ErrorCode::PreconditionRequired, "If-Match missing".into()),
+			AppError::Validation(vec![
+				FieldError::new("password", ErrorCode::PasswordTooShort, "Too short"),
+			]),
+		];
+
+		for error in errors {
+			let rebuilt = AppError::from_parts(
+				error.status_code(),
+				error.code(),
+				error.message(),
+				error.details().to_vec(),
+				error.error_id(),
+			);
+			assert_eq!(rebuilt.kind(), error.kind());
+			assert_eq!(rebuilt.public_message(), error.public_message());
+		}
+	}
+
+	#[test]
+	fn test_internal_error_is_not_exposed() {
+		let error = AppError::database("Fetching user 42", sqlx::Error::PoolTimedOut);
+		let public = error.public_message();
+
+		assert!(!public.contains("user 42"));
+		assert!(!public.contains("pool"));
+		assert!(public.contains(&error.error_id().unwrap().to_string()));
+		assert!(error.to_string().contains("Fetching user 42"));
+
+		let AppError::Database(internal) = &error else { unreachable!() };
+		assert_eq!(internal.source_chain().len(), 1);
+	}
+}
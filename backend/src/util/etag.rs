@@ -0,0 +1,200 @@
+use axum::{
+	body::{self, Body},
+	extract::{FromRequestParts, Request},
+	http::{header, request::Parts, HeaderMap, HeaderValue, Method, StatusCode},
+	middleware::Next,
+	response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use crate::{
+	models::response::ApiResponse,
+	util::error::{AppError, AppResult, ErrorCode},
+};
+
+/// Headers a 304 keeps from the response it replaces (RFC 9110, section 15.4.5).
+const NOT_MODIFIED_HEADERS: [header::HeaderName; 5] = [
+	header::ETAG,
+	header::CACHE_CONTROL,
+	header::CONTENT_LOCATION,
+	header::EXPIRES,
+	header::VARY,
+];
+
+/// Strong validator of a row version, sent as `ETag` and expected back in `If-Match`.
+pub fn version_tag(version: i64) -> HeaderValue {
+	HeaderValue::from_str(&format!("\"{}\"", version)).expect("a quoted number is a valid header value")
+}
+
+/// Weak validator over a response body, for responses that aren't a single versioned row.
+fn body_tag(body: &[u8]) -> HeaderValue {
+	let digest = Sha256::digest(body);
+	HeaderValue::from_str(&format!("W/\"{}\"", hex::encode(&digest[..16]))).expect("hex is a valid header value")
+}
+
+pub fn version_mismatch() -> AppError {
+	AppError::PreconditionFailed(
+		ErrorCode::VersionMismatch,
+		"The resource was changed in the meantime; fetch it again and reapply your change".into(),
+	)
+}
+
+/// # IfMatch
+/// Row versions a client is willing to overwrite, from the `If-Match` header.
+/// Changing or deleting a versioned row requires the header so no client overwrites blindly;
+/// `*` is the explicit way to skip the check.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IfMatch {
+	Any,
+	Versions(Vec<i64>),
+}
+
+impl IfMatch {
+	/// Weak tags never match here (strong comparison), so they are rejected as malformed.
+	pub fn parse(value: &str) -> Option<Self> {
+		if value.trim() == "*" {
+			return Some(IfMatch::Any);
+		}
+
+		value.split(',')
+			.map(|tag| tag.trim().strip_prefix('"')?.strip_suffix('"')?.parse().ok())
+			.collect::<Option<Vec<i64>>>()
+			.filter(|versions| !versions.is_empty())
+			.map(IfMatch::Versions)
+	}
+
+	pub fn matches(&self, version: i64) -> bool {
+		match self {
+			IfMatch::Any => true,
+			IfMatch::Versions(versions) => versions.contains(&version),
+		}
+	}
+
+	pub fn check(&self, version: i64) -> AppResult<()> {
+		match self.matches(version) {
+			true => Ok(()),
+			false => Err(version_mismatch()),
+		}
+	}
+
+	/// Bind as `$n::bigint[]` and test with `($n IS NULL OR x.version = ANY($n))`,
+	/// so the check happens in the same statement as the write.
+	pub fn versions(&self) -> Option<Vec<i64>> {
+		match self {
+			IfMatch::Any => None,
+			IfMatch::Versions(versions) => Some(versions.clone()),
+		}
+	}
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for IfMatch {
+	type Rejection = AppError;
+
+	async fn from_request_parts(parts: &mut Parts, _state: &S) -> AppResult<Self> {
+		let values = parts.headers.get_all(header::IF_MATCH)
+			.iter()
+			.map(|value| value.to_str().unwrap_or_default())
+			.collect::<Vec<_>>();
+
+		if values.is_empty() {
+			return Err(AppError::PreconditionRequired(
+				ErrorCode::PreconditionRequired,
+				"Send the resource's ETag in If-Match to change it".into(),
+			));
+		}
+		IfMatch::parse(&values.join(","))
+			.ok_or_else(|| AppError::BadRequest(ErrorCode::BadRequest, "If-Match must be * or a list of quoted ETags".into()))
+	}
+}
+
+/// Envelope for `result` carrying the ETag of the row version on success.
+pub fn versioned<T: Serialize>(result: AppResult<(i64, T)>, status: StatusCode) -> Response {
+	match result {
+		Ok((version, data)) => (
+			[(header::ETAG, version_tag(version))],
+			ApiResponse::with_status(data, status),
+		).into_response(),
+		Err(err) => ApiResponse::<T>::error(&err).into_response(),
+	}
+}
+
+/// Whether `If-None-Match` lists `etag`, using weak comparison as RFC 9110 asks for GET.
+fn none_match(condition: &HeaderValue, etag: &HeaderValue) -> bool {
+	let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_owned();
+	let (Ok(condition), Ok(etag)) = (condition.to_str(), etag.to_str()) else {
+		return false;
+	};
+
+	condition.trim() == "*" || condition.split(',').any(|tag| opaque(tag) == opaque(etag))
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+	headers.get(header::CONTENT_TYPE)
+		.and_then(|value| value.to_str().ok())
+		.is_some_and(|value| value.starts_with("application/json"))
+}
+
+/// Answers conditional GETs. Successful JSON responses without an ETag of their own get a weak one
+/// over the body, so lists qualify too, and a matching `If-None-Match` turns any of them into a bodiless 304.
+pub async fn conditional(request: Request, next: Next) -> Response {
+	if request.method() != Method::GET && request.method() != Method::HEAD {
+		return next.run(request).await;
+	}
+
+	let condition = request.headers().get(header::IF_NONE_MATCH).cloned();
+	let response = next.run(request).await;
+
+	if response.status() != StatusCode::OK {
+		return response;
+	}
+
+	let (mut parts, body) = response.into_parts();
+	let body = match parts.headers.contains_key(header::ETAG) || !is_json(&parts.headers) {
+		true => body,
+		false => match body::to_bytes(body, usize::MAX).await {
+			Ok(bytes) => {
+				parts.headers.insert(header::ETAG, body_tag(&bytes));
+				Body::from(bytes)
+			}
+			Err(err) => return AppError::internal_from("Failed to buffer response body", err).into_response(),
+		},
+	};
+
+	match (condition, parts.headers.get(header::ETAG)) {
+		(Some(condition), Some(etag)) if none_match(&condition, etag) => {
+			let mut not_modified = StatusCode::NOT_MODIFIED.into_response();
+			for name in NOT_MODIFIED_HEADERS {
+				if let Some(value) = parts.headers.get(&name) {
+					not_modified.headers_mut().insert(name, value.clone());
+				}
+			}
+			not_modified
+		}
+		_ => Response::from_parts(parts, body),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_if_match() {
+		assert_eq!(IfMatch::parse("*"), Some(IfMatch::Any));
+		assert_eq!(IfMatch::parse("\"3\""), Some(IfMatch::Versions(vec![3])));
+		assert_eq!(IfMatch::parse("\"3\", \"4\""), Some(IfMatch::Versions(vec![3, 4])));
+		assert_eq!(IfMatch::parse("W/\"3\""), None);
+		assert_eq!(IfMatch::parse("3"), None);
+		assert_eq!(IfMatch::parse(""), None);
+	}
+
+	#[test]
+	fn test_none_match_is_weak() {
+		let etag = HeaderValue::from_static("\"3\"");
+
+		assert!(none_match(&HeaderValue::from_static("W/\"3\""), &etag));
+		assert!(none_match(&HeaderValue::from_static("\"1\", \"3\""), &etag));
+		assert!(none_match(&HeaderValue::from_static("*"), &etag));
+		assert!(!none_match(&HeaderValue::from_static("\"4\""), &etag));
+	}
+}
@@ -0,0 +1,138 @@
+use std::time::Duration;
+use sqlx::{postgres::PgListener, PgPool};
+use tokio::{sync::broadcast::{self, error::RecvError}, time::Instant};
+use tracing::warn;
+use uuid::Uuid;
+use crate::{
+	models::event::DomainEvent,
+	util::{error::AppError, workspace_service},
+};
+
+/// Filled by the `notify_change` trigger.
+pub const CHANNEL: &str = "domain_events";
+/// Events a slow subscriber may fall behind before it is told to resync.
+const CAPACITY: usize = 1024;
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+/// How long a stream goes on trusting the membership it was opened with.
+const MEMBERSHIP_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What the listener hands to the streams of this replica.
+#[derive(Clone, Debug)]
+pub enum Signal {
+	Change(DomainEvent),
+	/// Events may have been missed, so whatever the subscriber shows should be refetched.
+	Resync,
+}
+
+/// # EventHub
+/// Fans the notifications received by this replica out to its `/events` streams.
+#[derive(Clone)]
+pub struct EventHub {
+	sender: broadcast::Sender<Signal>,
+}
+
+impl EventHub {
+	pub fn new() -> Self {
+		Self { sender: broadcast::channel(CAPACITY).0 }
+	}
+
+	/// Signals for a stream of `user_id`, whose membership of `workspace_id` has just been checked.
+	pub fn subscribe(&self, user_id: Uuid, workspace_id: Uuid) -> Subscription {
+		Subscription {
+			receiver: self.sender.subscribe(),
+			user_id,
+			workspace_id,
+			checked_at: Instant::now(),
+		}
+	}
+
+	fn publish(&self, signal: Signal) {
+		// Nobody listening is fine.
+		let _ = self.sender.send(signal);
+	}
+}
+
+impl Default for EventHub {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Starts listening on `CHANNEL` and keeps passing notifications to `hub` for the lifetime of the process.
+/// Returns once the listener is in place. A lost connection is re-established and reported as a resync,
+/// since notifications sent in between are gone. Stops when the pool is closed.
+pub async fn spawn_listener(pool: PgPool, hub: EventHub) -> Result<(), sqlx::Error> {
+	let mut listener = PgListener::connect_with(&pool).await?;
+	listener.listen(CHANNEL).await?;
+
+	tokio::spawn(async move {
+		let closed = pool.close_event();
+		tokio::pin!(closed);
+		loop {
+			let received = tokio::select! {
+				received = listener.try_recv() => received,
+				// Hand the connection back so the pool can shut down.
+				_ = &mut closed => break,
+			};
+			match received {
+				Ok(Some(notification)) => match serde_json::from_str::<DomainEvent>(notification.payload()) {
+					Ok(event) => hub.publish(Signal::Change(event)),
+					Err(err) => warn!(error = %err, payload = notification.payload(), "Ignoring malformed change notification"),
+				},
+				Ok(None) => hub.publish(Signal::Resync),
+				Err(err) => {
+					AppError::database("Failed to receive change notifications", err).log();
+					tokio::time::sleep(RETRY_DELAY).await;
+				}
+			}
+		}
+	});
+
+	Ok(())
+}
+
+/// # Subscription
+/// What one stream passes on: changes in its workspace, for as long as the user is a member of it.
+/// Events are filtered by the workspace they carry; membership is read again at most once every
+/// `MEMBERSHIP_CHECK_INTERVAL`, when an event is due.
+pub struct Subscription {
+	receiver: broadcast::Receiver<Signal>,
+	user_id: Uuid,
+	workspace_id: Uuid,
+	checked_at: Instant,
+}
+
+impl Subscription {
+	/// The next signal for the subscriber. `None` once the hub is gone or the user has left the workspace.
+	pub async fn next(&mut self, pool: &PgPool) -> Option<Signal> {
+		loop {
+			match self.receiver.recv().await {
+				Ok(Signal::Change(event)) if event.workspace_id != self.workspace_id => continue,
+				Ok(Signal::Change(event)) => match self.still_member(pool).await {
+					true => return Some(Signal::Change(event)),
+					false => return None,
+				},
+				Ok(Signal::Resync) | Err(RecvError::Lagged(_)) => return Some(Signal::Resync),
+				Err(RecvError::Closed) => return None,
+			}
+		}
+	}
+
+	/// A failed check keeps the stream going on the last answer and is tried again with the next event.
+	async fn still_member(&mut self, pool: &PgPool) -> bool {
+		if self.checked_at.elapsed() < MEMBERSHIP_CHECK_INTERVAL {
+			return true;
+		}
+
+		match workspace_service::active_membership(pool, &self.user_id, Some(self.workspace_id)).await {
+			Ok(membership) => {
+				self.checked_at = Instant::now();
+				membership.is_some()
+			}
+			Err(err) => {
+				err.log();
+				true
+			}
+		}
+	}
+}
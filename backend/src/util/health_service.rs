@@ -0,0 +1,134 @@
+use std::{collections::HashSet, time::{Duration, Instant}};
+use sqlx::PgPool;
+use tracing::error;
+use uuid::Uuid;
+use crate::{
+	config::{Config, MailerConfig, StorageConfig},
+	models::health::ComponentHealth,
+};
+
+static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./migrations");
+
+/// The report is public, so the error itself, which can name hosts, roles or paths, only goes to the log.
+/// The error id links the two, as for failed requests.
+fn failed(component: &str, summary: &str, err: impl std::fmt::Display) -> ComponentHealth {
+	let error_id = Uuid::new_v4();
+	error!(%error_id, component, error = %err, "{}", summary);
+	ComponentHealth::error(format!("{} (error id: {})", summary, error_id))
+}
+
+pub async fn check_database(pool: &PgPool, timeout: Duration) -> ComponentHealth {
+	let started = Instant::now();
+	let result = tokio::time::timeout(
+		timeout,
+		sqlx::query_scalar::<_, i32>("SELECT 1").fetch_one(pool),
+	).await;
+
+	match result {
+		Ok(Ok(_)) => ComponentHealth::ok(),
+		Ok(Err(err)) => failed("database", "Query failed", err),
+		Err(_) => ComponentHealth::error(format!("Timed out after {}ms", timeout.as_millis())),
+	}.with_latency(started.elapsed().as_millis() as u64)
+}
+
+/// Compares the migrations embedded at compile time with the ones recorded in `_sqlx_migrations`.
+pub async fn check_migrations(pool: &PgPool, timeout: Duration) -> ComponentHealth {
+	let result = tokio::time::timeout(
+		timeout,
+		sqlx::query_scalar::<_, i64>("SELECT version FROM _sqlx_migrations WHERE success")
+			.fetch_all(pool),
+	).await;
+	let applied: HashSet<i64> = match result {
+		Ok(Ok(versions)) => versions.into_iter().collect(),
+		Ok(Err(err)) => return failed("migrations", "Could not read migrations", err),
+		Err(_) => return ComponentHealth::error(format!("Timed out after {}ms", timeout.as_millis())),
+	};
+	let pending = MIGRATOR.iter()
+		.filter(|migration| migration.migration_type.is_up_migration())
+		.filter(|migration| !applied.contains(&migration.version))
+		.count();
+
+	match pending {
+		0 => ComponentHealth::ok(),
+		n => ComponentHealth::error(format!("{} pending migration(s)", n)),
+	}
+}
+
+/// Writes and removes a probe file to make sure the storage root is usable.
+pub async fn check_storage(storage: Option<&StorageConfig>, timeout: Duration) -> ComponentHealth {
+	let Some(storage) = storage else {
+		return ComponentHealth::disabled("STORAGE_DIR is not set");
+	};
+	let probe = storage.root.join(format!(".health-{}", Uuid::new_v4()));
+	let started = Instant::now();
+	let result = tokio::time::timeout(timeout, async {
+		tokio::fs::write(&probe, b"ok").await?;
+		tokio::fs::remove_file(&probe).await
+	}).await;
+
+	match result {
+		Ok(Ok(())) => ComponentHealth::ok(),
+		Ok(Err(err)) => failed("storage", "Storage is not writable", err),
+		Err(_) => ComponentHealth::error(format!("Timed out after {}ms", timeout.as_millis())),
+	}.with_latency(started.elapsed().as_millis() as u64)
+}
+
+pub fn check_mailer(mailer: Option<&MailerConfig>) -> ComponentHealth {
+	let Some(mailer) = mailer else {
+		return ComponentHealth::disabled("SMTP_HOST is not set");
+	};
+
+	match (&mailer.smtp_port, &mailer.from_address) {
+		(None, _) => ComponentHealth::error("SMTP_PORT is missing or invalid"),
+		(_, None) => ComponentHealth::error("MAIL_FROM is not set"),
+		(_, Some(from)) if !from.contains('@') =>
+			ComponentHealth::error("MAIL_FROM is not a valid address"),
+		_ => ComponentHealth::ok(),
+	}
+}
+
+pub async fn check_all(pool: &PgPool, config: &Config) -> Vec<(&'static str, ComponentHealth)> {
+	let timeout = config.health_timeout;
+	let (database, migrations, storage) = tokio::join!(
+		check_database(pool, timeout),
+		check_migrations(pool, timeout),
+		check_storage(config.storage.as_ref(), timeout),
+	);
+
+	vec![
+		("database", database),
+		("migrations", migrations),
+		("storage", storage),
+		("mailer", check_mailer(config.mailer.as_ref())),
+	]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::models::health::HealthStatus;
+
+	#[test]
+	fn test_check_mailer() {
+		assert_eq!(check_mailer(None).status, HealthStatus::Disabled);
+
+		let mut mailer = MailerConfig {
+			smtp_host: "localhost".into(),
+			smtp_port: Some(25),
+			from_address: None,
+			username: None,
+			password: None,
+		};
+		assert_eq!(check_mailer(Some(&mailer)).status, HealthStatus::Error);
+
+		mailer.from_address = Some("noreply@kvitter.app".into());
+		assert_eq!(check_mailer(Some(&mailer)).status, HealthStatus::Ok);
+	}
+
+	#[tokio::test]
+	async fn test_check_storage_missing_dir() {
+		let storage = StorageConfig { root: "/nonexistent/kvitter".into() };
+		let health = check_storage(Some(&storage), Duration::from_secs(1)).await;
+		assert_eq!(health.status, HealthStatus::Error);
+	}
+}
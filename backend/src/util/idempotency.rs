@@ -0,0 +1,290 @@
+use std::{collections::HashMap, panic::AssertUnwindSafe, sync::Arc, time::Duration};
+use async_trait::async_trait;
+use axum::{
+	body::{self, Body},
+	extract::{FromRequestParts, Request, State},
+	http::{header, HeaderName, HeaderValue, Method, StatusCode},
+	middleware::Next,
+	response::{IntoResponse, Response},
+};
+use futures_util::FutureExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use tracing::info;
+use uuid::Uuid;
+use crate::{
+	auth::jwt::AuthUser,
+	config::Config,
+	util::{
+		error::{AppError, AppResult, ErrorCode},
+		job_queue::{Job, JobContext},
+	},
+};
+
+pub static IDEMPOTENCY_KEY_HEADER: HeaderName = HeaderName::from_static("idempotency-key");
+pub static REPLAYED_HEADER: HeaderName = HeaderName::from_static("idempotent-replayed");
+const MAX_KEY_LEN: usize = 255;
+/// Same as axum's default body limit, which the handlers would enforce anyway.
+const MAX_BODY_LEN: usize = 2 * 1024 * 1024;
+/// Response headers worth replaying; the rest (request id, deprecation, ...) are set anew per request.
+const STORED_HEADERS: [HeaderName; 3] = [header::CONTENT_TYPE, header::ETAG, header::LOCATION];
+/// A key still without a response after this long belongs to a request that died, e.g. with its replica,
+/// and is taken over by the next one.
+const IN_PROGRESS_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Marks a response that carries a credential, like a token or a signing secret. It is never stored for
+/// replay; the key is released instead, so a retry runs the request again.
+pub const NO_STORE: [(HeaderName, &str); 1] = [(header::CACHE_CONTROL, "no-store")];
+
+/// What a stored key says about the request that claimed it.
+#[derive(sqlx::FromRow)]
+struct StoredResponse {
+	fingerprint: String,
+	status: Option<i16>,
+	headers: sqlx::types::Json<HashMap<String, String>>,
+	body: Option<Vec<u8>>,
+}
+
+impl StoredResponse {
+	fn replay(self) -> Response {
+		let status = self.status
+			.and_then(|status| StatusCode::from_u16(status as u16).ok())
+			.unwrap_or(StatusCode::OK);
+		let mut response = (status, self.body.unwrap_or_default()).into_response();
+		let headers = response.headers_mut();
+
+		headers.remove(header::CONTENT_TYPE);
+		for (name, value) in self.headers.0 {
+			if let (Ok(name), Ok(value)) = (HeaderName::try_from(name), HeaderValue::try_from(value)) {
+				headers.insert(name, value);
+			}
+		}
+		headers.insert(REPLAYED_HEADER.clone(), HeaderValue::from_static("true"));
+		response
+	}
+}
+
+fn parse_key(value: &HeaderValue) -> AppResult<String> {
+	value.to_str().ok()
+		.filter(|key| !key.is_empty() && key.len() <= MAX_KEY_LEN)
+		.filter(|key| key.chars().all(|c| c.is_ascii_graphic()))
+		.map(str::to_owned)
+		.ok_or_else(|| AppError::BadRequest(
+			ErrorCode::BadRequest,
+			format!("Idempotency-Key must be 1 to {} printable ASCII characters", MAX_KEY_LEN),
+		))
+}
+
+fn fingerprint(method: &Method, path: &str, body: &[u8]) -> String {
+	let mut hasher = Sha256::new();
+	hasher.update(method.as_str());
+	hasher.update(b"\n");
+	hasher.update(path);
+	hasher.update(b"\n");
+	hasher.update(body);
+	hex::encode(hasher.finalize())
+}
+
+/// Claims `key` for a new request. Expired and abandoned keys are taken over; returns `None` while the key is live.
+async fn claim(
+	pool: &PgPool,
+	user_id: Uuid,
+	key: &str,
+	fingerprint: &str,
+	config: &Config,
+) -> AppResult<Option<Uuid>> {
+	sqlx::query_scalar::<_, Uuid>(
+		"INSERT INTO idempotency_keys (user_id, key, fingerprint, expires_at) \
+		VALUES ($1, $2, $3, NOW() + make_interval(secs => $4)) \
+		ON CONFLICT (user_id, key) DO UPDATE SET fingerprint = EXCLUDED.fingerprint, status = NULL, \
+			headers = '{}', body = NULL, created_at = NOW(), expires_at = EXCLUDED.expires_at \
+			WHERE idempotency_keys.expires_at <= NOW() \
+				OR idempotency_keys.status IS NULL AND idempotency_keys.created_at <= NOW() - make_interval(secs => $5) \
+		RETURNING id"
+	)
+		.bind(user_id)
+		.bind(key)
+		.bind(fingerprint)
+		.bind(config.idempotency_ttl.as_secs_f64())
+		.bind(IN_PROGRESS_TIMEOUT.as_secs_f64())
+		.fetch_optional(pool)
+		.await
+		.map_err(|err| AppError::database("Failed to claim idempotency key", err))
+}
+
+async fn fetch(pool: &PgPool, user_id: Uuid, key: &str) -> AppResult<Option<StoredResponse>> {
+	sqlx::query_as::<_, StoredResponse>(
+		"SELECT fingerprint, status, headers, body FROM idempotency_keys WHERE user_id = $1 AND key = $2"
+	)
+		.bind(user_id)
+		.bind(key)
+		.fetch_optional(pool)
+		.await
+		.map_err(|err| AppError::database("Failed to fetch idempotency key", err))
+}
+
+fn is_no_store(response: &Response) -> bool {
+	response.headers().get_all(header::CACHE_CONTROL)
+		.iter()
+		.filter_map(|value| value.to_str().ok())
+		.any(|value| value.split(',').any(|directive| directive.trim().eq_ignore_ascii_case("no-store")))
+}
+
+/// Keeps the response for replay. Server errors and responses marked `NO_STORE` release the key instead,
+/// so the retry gets another go, and so does a response that couldn't be stored.
+async fn complete(pool: &PgPool, id: Uuid, response: Response) -> AppResult<Response> {
+	if response.status().is_server_error() || is_no_store(&response) {
+		release(pool, id).await?;
+		return Ok(response);
+	}
+
+	let (parts, body) = response.into_parts();
+	let bytes = match body::to_bytes(body, usize::MAX).await {
+		Ok(bytes) => bytes,
+		Err(err) => {
+			release(pool, id).await?;
+			return Err(AppError::internal_from("Failed to buffer response body", err));
+		}
+	};
+	let headers = STORED_HEADERS.iter()
+		.filter_map(|name| Some((name.to_string(), parts.headers.get(name)?.to_str().ok()?.to_owned())))
+		.collect::<HashMap<_, _>>();
+
+	let stored = sqlx::query("UPDATE idempotency_keys SET status = $2, headers = $3, body = $4 WHERE id = $1")
+		.bind(id)
+		.bind(parts.status.as_u16() as i16)
+		.bind(sqlx::types::Json(headers))
+		.bind(bytes.as_ref())
+		.execute(pool)
+		.await;
+	if let Err(err) = stored {
+		// The request went through; only its replay is lost.
+		AppError::database("Failed to store idempotent response", err).log();
+		release(pool, id).await?;
+	}
+
+	Ok(Response::from_parts(parts, Body::from(bytes)))
+}
+
+async fn release(pool: &PgPool, id: Uuid) -> AppResult<()> {
+	sqlx::query("DELETE FROM idempotency_keys WHERE id = $1")
+		.bind(id)
+		.execute(pool)
+		.await
+		.map_err(|err| AppError::database("Failed to release idempotency key", err))?;
+
+	Ok(())
+}
+
+/// Honors `Idempotency-Key` on authenticated POST and PATCH requests. The first request with a key runs
+/// and its response is stored per user for `idempotency_ttl`; repeats get that response back with
+/// `Idempotent-Replayed: true`. Reusing a key for a different request, or while the first is still running,
+/// is a 409. Anonymous requests with a key are refused: they would share one key space.
+pub async fn handle(
+	State(pool): State<PgPool>,
+	State(config): State<Arc<Config>>,
+	request: Request,
+	next: Next,
+) -> Response {
+	if request.method() != Method::POST && request.method() != Method::PATCH {
+		return next.run(request).await;
+	}
+	let Some(key) = request.headers().get(&IDEMPOTENCY_KEY_HEADER).cloned() else {
+		return next.run(request).await;
+	};
+
+	let result: AppResult<Response> = async {
+		let key = parse_key(&key)?;
+		let (mut parts, body) = request.into_parts();
+		let AuthUser(user_id) = AuthUser::from_request_parts(&mut parts, &()).await
+			.map_err(|_| AppError::BadRequest(
+				ErrorCode::BadRequest,
+				"Idempotency-Key is only accepted on authenticated requests".into(),
+			))?;
+		let bytes = body::to_bytes(body, MAX_BODY_LEN).await
+			.map_err(|_| AppError::BadRequest(ErrorCode::BadRequest, "Request body is too large".into()))?;
+		let path = parts.uri.path_and_query().map(|path| path.as_str()).unwrap_or_default();
+		let fingerprint = fingerprint(&parts.method, path, &bytes);
+
+		if let Some(id) = claim(&pool, user_id, &key, &fingerprint, &config).await? {
+			let response = AssertUnwindSafe(next.run(Request::from_parts(parts, Body::from(bytes))))
+				.catch_unwind()
+				.await;
+			return match response {
+				Ok(response) => complete(&pool, id, response).await,
+				Err(panic) => {
+					if let Err(err) = release(&pool, id).await {
+						err.log();
+					}
+					std::panic::resume_unwind(panic)
+				}
+			};
+		}
+
+		// A missing row means the first request just failed and released the key; it's as busy as a running one.
+		match fetch(&pool, user_id, &key).await? {
+			Some(stored) if stored.fingerprint != fingerprint => Err(AppError::Conflict(
+				ErrorCode::IdempotencyKeyReused,
+				"This Idempotency-Key was already used for a different request".into(),
+			)),
+			Some(stored) if stored.status.is_some() => Ok(stored.replay()),
+			_ => Err(AppError::Conflict(
+				ErrorCode::IdempotencyKeyInProgress,
+				"A request with this Idempotency-Key is still being processed".into(),
+			)),
+		}
+	}.await;
+
+	result.unwrap_or_else(IntoResponse::into_response)
+}
+
+/// Deletes the keys past their `expires_at`, returning how many there were.
+pub async fn delete_expired(pool: &PgPool) -> AppResult<u64> {
+	let done = sqlx::query("DELETE FROM idempotency_keys WHERE expires_at <= NOW()")
+		.execute(pool)
+		.await
+		.map_err(|err| AppError::database("Failed to delete expired idempotency keys", err))?;
+
+	Ok(done.rows_affected())
+}
+
+/// Background job running `delete_expired` once.
+#[derive(Serialize, Deserialize)]
+pub struct CleanupIdempotencyKeys {}
+
+#[async_trait]
+impl Job for CleanupIdempotencyKeys {
+	const KIND: &'static str = "idempotency_keys.cleanup";
+
+	async fn run(self, context: &JobContext) -> AppResult<()> {
+		let deleted = delete_expired(&context.pool).await?;
+		if deleted > 0 {
+			info!(deleted, "Deleted expired idempotency keys");
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_key() {
+		assert_eq!(parse_key(&HeaderValue::from_static("retry-42")).unwrap(), "retry-42");
+		assert!(parse_key(&HeaderValue::from_static("")).is_err());
+		assert!(parse_key(&HeaderValue::from_static("has space")).is_err());
+		assert!(parse_key(&HeaderValue::from_str(&"k".repeat(MAX_KEY_LEN + 1)).unwrap()).is_err());
+	}
+
+	#[test]
+	fn test_fingerprint_covers_method_path_and_body() {
+		let base = fingerprint(&Method::POST, "/time-entries", b"{}");
+
+		assert_eq!(base, fingerprint(&Method::POST, "/time-entries", b"{}"));
+		assert_ne!(base, fingerprint(&Method::PATCH, "/time-entries", b"{}"));
+		assert_ne!(base, fingerprint(&Method::POST, "/projects", b"{}"));
+		assert_ne!(base, fingerprint(&Method::POST, "/time-entries", b"{\"a\":1}"));
+	}
+}
@@ -0,0 +1,301 @@
+use std::{collections::HashMap, panic::AssertUnwindSafe, sync::Arc, time::Duration};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::{future::BoxFuture, FutureExt};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use sqlx::{PgExecutor, PgPool, QueryBuilder};
+use tracing::{info, warn};
+use uuid::Uuid;
+use crate::{
+	config::Config,
+	models::background_job::{BackgroundJob, JobFilter},
+	util::{
+		error::{AppError, AppResult, ErrorCode},
+		idempotency::CleanupIdempotencyKeys,
+		mailer::SendEmail,
+		pagination::{ListQuery, Page},
+		trash_service::PurgeTrash,
+	},
+};
+
+const JOB_COLUMNS: &str = "x.id, x.kind, CASE WHEN x.sensitive THEN NULL ELSE x.payload END AS payload, x.status, x.attempts, x.max_attempts, x.run_at, x.last_error, \
+	x.created_at, x.finished_at";
+const FIRST_RETRY: Duration = Duration::from_secs(10);
+const MAX_RETRY: Duration = Duration::from_secs(60 * 60);
+/// On top of `job_timeout` before another worker may claim a running job.
+const LEASE_MARGIN: Duration = Duration::from_secs(60);
+const MAX_ERROR_LEN: usize = 2000;
+
+/// # Job
+/// A kind of background work. The value is the payload: it is stored as JSON when enqueued
+/// and deserialized again for the worker that runs it.
+#[async_trait]
+pub trait Job: Serialize + DeserializeOwned + Send + Sync + 'static {
+	/// Identifies the handler in the table, so it must stay the same across deploys.
+	const KIND: &'static str;
+	/// Attempts before the job is dead-lettered.
+	const MAX_ATTEMPTS: i32 = 5;
+	/// The payload holds secrets, like the links in an email. The job API never shows it,
+	/// and it is deleted once the job succeeds.
+	const SENSITIVE: bool = false;
+
+	/// Errors are retried with backoff. Jobs may run more than once, e.g. when a worker dies mid-attempt,
+	/// so the work should be safe to repeat.
+	async fn run(self, context: &JobContext) -> AppResult<()>;
+}
+
+/// What a job gets to work with.
+#[derive(Clone)]
+pub struct JobContext {
+	pub pool: PgPool,
+	pub config: Arc<Config>,
+	/// 1 on the first attempt.
+	#[allow(dead_code, reason = "part of the job API for jobs that back off or give up early; none do yet")]
+	pub attempt: i32,
+}
+
+type Handler = Arc<dyn Fn(Value, JobContext) -> BoxFuture<'static, AppResult<()>> + Send + Sync>;
+
+#[derive(Clone)]
+struct Registered {
+	handler: Handler,
+	max_attempts: i32,
+	sensitive: bool,
+}
+
+/// # JobRegistry
+/// The handlers a worker knows, by `Job::KIND`.
+#[derive(Clone, Default)]
+pub struct JobRegistry {
+	handlers: HashMap<&'static str, Registered>,
+}
+
+impl JobRegistry {
+	pub fn register<J: Job>(mut self) -> Self {
+		let handler: Handler = Arc::new(|payload, context| Box::pin(async move {
+			let job = serde_json::from_value::<J>(payload)
+				.map_err(|err| AppError::internal_from(format!("Malformed {} payload", J::KIND), err))?;
+			job.run(&context).await
+		}));
+		self.handlers.insert(J::KIND, Registered { handler, max_attempts: J::MAX_ATTEMPTS, sensitive: J::SENSITIVE });
+		self
+	}
+
+	pub fn knows(&self, kind: &str) -> bool {
+		self.handlers.contains_key(kind)
+	}
+}
+
+/// Every job this backend can run.
+pub fn registry() -> JobRegistry {
+	JobRegistry::default()
+		.register::<PurgeTrash>()
+		.register::<CleanupIdempotencyKeys>()
+		.register::<SendEmail>()
+}
+
+/// Queues `job` to run at `run_at`, or right away. Pass a transaction to queue it only if the transaction commits.
+pub async fn enqueue<'c, J: Job>(
+	executor: impl PgExecutor<'c>,
+	job: &J,
+	run_at: Option<DateTime<Utc>>,
+) -> AppResult<Uuid> {
+	let payload = serde_json::to_value(job)
+		.map_err(|err| AppError::internal_from(format!("Failed to serialize {} payload", J::KIND), err))?;
+
+	sqlx::query_scalar::<_, Uuid>(
+		"INSERT INTO background_jobs (kind, payload, max_attempts, sensitive, run_at) \
+		VALUES ($1, $2, $3, $4, COALESCE($5, NOW())) RETURNING id"
+	)
+		.bind(J::KIND)
+		.bind(payload)
+		.bind(J::MAX_ATTEMPTS)
+		.bind(J::SENSITIVE)
+		.bind(run_at)
+		.fetch_one(executor)
+		.await
+		.map_err(|err| AppError::database(format!("Failed to enqueue {}", J::KIND), err))
+}
+
+/// Queues a job by kind with an untyped payload, for callers that only know both at runtime like the scheduler.
+pub async fn enqueue_kind<'c>(
+	executor: impl PgExecutor<'c>,
+	registry: &JobRegistry,
+	kind: &str,
+	payload: &Value,
+) -> AppResult<Uuid> {
+	let Some(registered) = registry.handlers.get(kind) else {
+		return Err(AppError::BadRequest(ErrorCode::BadRequest, format!("Unknown job kind {}", kind)));
+	};
+
+	sqlx::query_scalar::<_, Uuid>(
+		"INSERT INTO background_jobs (kind, payload, max_attempts, sensitive) VALUES ($1, $2, $3, $4) RETURNING id"
+	)
+		.bind(kind)
+		.bind(payload)
+		.bind(registered.max_attempts)
+		.bind(registered.sensitive)
+		.fetch_one(executor)
+		.await
+		.map_err(|err| AppError::database(format!("Failed to enqueue {}", kind), err))
+}
+
+/// Doubles from `FIRST_RETRY` with every failed attempt, up to `MAX_RETRY`.
+fn backoff(attempts: i32) -> Duration {
+	let exponent = attempts.saturating_sub(1).clamp(0, 20) as u32;
+	FIRST_RETRY.saturating_mul(2u32.pow(exponent)).min(MAX_RETRY)
+}
+
+#[derive(sqlx::FromRow)]
+struct Claimed {
+	id: Uuid,
+	kind: String,
+	payload: Value,
+	attempts: i32,
+	max_attempts: i32,
+}
+
+/// Claims the oldest due job, or a running one whose worker is gone, and runs it.
+/// Returns the id of the job it ran, or `None` when there was nothing to do.
+pub async fn run_next(pool: &PgPool, config: &Arc<Config>, registry: &JobRegistry) -> AppResult<Option<Uuid>> {
+	let claimed = sqlx::query_as::<_, Claimed>(
+		"UPDATE background_jobs SET status = 'running', attempts = attempts + 1, \
+			locked_until = NOW() + make_interval(secs => $1) \
+		WHERE id = ( \
+			SELECT id FROM background_jobs \
+			WHERE (status = 'queued' AND run_at <= NOW()) OR (status = 'running' AND locked_until < NOW()) \
+			ORDER BY run_at LIMIT 1 FOR UPDATE SKIP LOCKED \
+		) RETURNING id, kind, payload, attempts, max_attempts"
+	)
+		.bind((config.job_timeout + LEASE_MARGIN).as_secs_f64())
+		.fetch_optional(pool)
+		.await
+		.map_err(|err| AppError::database("Failed to claim background job", err))?;
+
+	let Some(job) = claimed else {
+		return Ok(None);
+	};
+
+	let Some(Registered { handler, .. }) = registry.handlers.get(job.kind.as_str()) else {
+		let error = format!("No handler for job kind {}", job.kind);
+		finish(pool, &job, Err(error), true).await?;
+		return Ok(Some(job.id));
+	};
+
+	let context = JobContext { pool: pool.clone(), config: config.clone(), attempt: job.attempts };
+	let run = AssertUnwindSafe(handler(job.payload.clone(), context)).catch_unwind();
+	let outcome = match tokio::time::timeout(config.job_timeout, run).await {
+		Ok(Ok(Ok(()))) => Ok(()),
+		Ok(Ok(Err(err))) => Err(err.to_string()),
+		Ok(Err(_)) => Err("Job panicked".to_owned()),
+		Err(_) => Err(format!("Job timed out after {:?}", config.job_timeout)),
+	};
+	if let Err(error) = &outcome {
+		warn!(job_id = %job.id, kind = job.kind, attempt = job.attempts, error, "Background job failed");
+	}
+	finish(pool, &job, outcome, false).await?;
+
+	Ok(Some(job.id))
+}
+
+/// Marks the attempt as done: succeeded, retried after a backoff, or dead once out of attempts.
+async fn finish(pool: &PgPool, job: &Claimed, outcome: Result<(), String>, dead: bool) -> AppResult<()> {
+	let query = match outcome {
+		Ok(()) => sqlx::query(
+			"UPDATE background_jobs SET status = 'succeeded', locked_until = NULL, last_error = NULL, finished_at = NOW(), \
+				payload = CASE WHEN sensitive THEN NULL ELSE payload END \
+			WHERE id = $1"
+		)
+			.bind(job.id),
+		Err(mut error) => {
+			error.truncate(error.floor_char_boundary(MAX_ERROR_LEN));
+			let dead = dead || job.attempts >= job.max_attempts;
+			sqlx::query(
+				"UPDATE background_jobs SET locked_until = NULL, last_error = $2, \
+					status = CASE WHEN $3 THEN 'dead' ELSE 'queued' END, \
+					finished_at = CASE WHEN $3 THEN NOW() END, \
+					run_at = CASE WHEN $3 THEN run_at ELSE NOW() + make_interval(secs => $4) END \
+				WHERE id = $1"
+			)
+				.bind(job.id)
+				.bind(error)
+				.bind(dead)
+				.bind(backoff(job.attempts).as_secs_f64())
+		}
+	};
+
+	query.execute(pool)
+		.await
+		.map_err(|err| AppError::database("Failed to record background job outcome", err))?;
+
+	Ok(())
+}
+
+/// Starts `job_concurrency` workers that run due jobs for the lifetime of the process.
+pub fn spawn_workers(pool: PgPool, config: Arc<Config>, registry: JobRegistry) {
+	if config.job_concurrency > 0 {
+		info!(workers = config.job_concurrency, "Starting background job workers");
+	}
+
+	for _ in 0..config.job_concurrency {
+		let (pool, config, registry) = (pool.clone(), config.clone(), registry.clone());
+		tokio::spawn(async move {
+			loop {
+				match run_next(&pool, &config, &registry).await {
+					Ok(Some(_)) => {}
+					Ok(None) => tokio::time::sleep(config.job_poll_interval).await,
+					Err(err) => {
+						err.log();
+						tokio::time::sleep(config.job_poll_interval).await;
+					}
+				}
+			}
+		});
+	}
+}
+
+pub async fn list_jobs(pool: &PgPool, query: &ListQuery<JobFilter>) -> AppResult<Page<BackgroundJob>> {
+	let mut builder = QueryBuilder::new(format!("SELECT {} FROM background_jobs x WHERE TRUE", JOB_COLUMNS));
+	if let Some(status) = query.filter.status {
+		builder.push(" AND x.status = ").push_bind(status);
+	}
+	if let Some(kind) = &query.filter.kind {
+		builder.push(" AND x.kind = ").push_bind(kind.clone());
+	}
+	query.push_page(&mut builder, "x.id");
+
+	let rows = builder.build_query_as::<BackgroundJob>()
+		.fetch_all(pool)
+		.await
+		.map_err(|err| AppError::database("Failed to list background jobs", err))?;
+
+	Ok(query.paginate(rows))
+}
+
+/// Gives a dead job a fresh set of attempts, starting now.
+pub async fn retry_job(pool: &PgPool, job_id: &Uuid) -> AppResult<BackgroundJob> {
+	sqlx::query_as::<_, BackgroundJob>(&format!(
+		"UPDATE background_jobs x SET status = 'queued', attempts = 0, run_at = NOW(), finished_at = NULL \
+		WHERE x.id = $1 AND x.status = 'dead' RETURNING {}",
+		JOB_COLUMNS,
+	))
+		.bind(job_id)
+		.fetch_optional(pool)
+		.await
+		.map_err(|err| AppError::database("Failed to retry background job", err))?
+		.ok_or_else(|| AppError::NotFound(ErrorCode::NotFound, "No dead job with this id".into()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_backoff_doubles_up_to_the_cap() {
+		assert_eq!(backoff(1), Duration::from_secs(10));
+		assert_eq!(backoff(2), Duration::from_secs(20));
+		assert_eq!(backoff(4), Duration::from_secs(80));
+		assert_eq!(backoff(30), MAX_RETRY);
+	}
+}
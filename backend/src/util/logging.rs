@@ -0,0 +1,22 @@
+use tracing_subscriber::{fmt, EnvFilter};
+use crate::config::{Config, LogFormat};
+
+/// Installs the global subscriber.
+/// `RUST_LOG` takes precedence over `LOG_LEVEL` so a single instance can be debugged without a config change.
+pub fn init(config: &Config) {
+	let filter = EnvFilter::try_from_default_env()
+		.or_else(|_| EnvFilter::try_new(&config.log_level))
+		.unwrap_or_else(|_| EnvFilter::new("info"));
+
+	match config.log_format {
+		LogFormat::Json => fmt()
+			.json()
+			.with_current_span(true)
+			.with_span_list(false)
+			.with_env_filter(filter)
+			.init(),
+		LogFormat::Text => fmt()
+			.with_env_filter(filter)
+			.init(),
+	}
+}
@@ -0,0 +1,91 @@
+use async_trait::async_trait;
+use lettre::{
+	message::{header::ContentType, Mailbox},
+	transport::smtp::authentication::Credentials,
+	AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use tracing::info;
+use crate::{
+	config::MailerConfig,
+	util::{
+		error::{AppError, AppResult, ErrorCode, InternalError},
+		job_queue::{self, Job, JobContext},
+	},
+};
+
+/// # SendEmail
+/// A plain-text email, sent by a background job so a slow or unreachable SMTP server never holds up a request.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SendEmail {
+	pub to: String,
+	pub subject: String,
+	pub body: String,
+}
+
+/// Queues `email`. Pass a transaction to send it only if the change it is about commits.
+pub async fn send<'c>(executor: impl PgExecutor<'c>, email: &SendEmail) -> AppResult<()> {
+	job_queue::enqueue(executor, email, None).await?;
+	Ok(())
+}
+
+#[async_trait]
+impl Job for SendEmail {
+	const KIND: &'static str = "mail.send";
+	const MAX_ATTEMPTS: i32 = 8;
+	const SENSITIVE: bool = true;
+
+	async fn run(self, context: &JobContext) -> AppResult<()> {
+		let Some(mailer) = &context.config.mailer else {
+			// The body stays out of the log: it may hold links that act on the recipient's account.
+			info!(to = %self.to, subject = %self.subject, "SMTP_HOST is not set, email not sent");
+			return Ok(());
+		};
+
+		let from = mailer.from_address.as_deref()
+			.and_then(|from| from.parse::<Mailbox>().ok())
+			.ok_or_else(|| misconfigured("MAIL_FROM is not set or not a valid address"))?;
+		let to = self.to.parse::<Mailbox>()
+			.map_err(|err| AppError::internal_from("Invalid recipient address", err))?;
+		let message = Message::builder()
+			.from(from)
+			.to(to)
+			.subject(self.subject)
+			.header(ContentType::TEXT_PLAIN)
+			.body(self.body)
+			.map_err(|err| AppError::internal_from("Failed to build email", err))?;
+
+		transport(mailer)?
+			.send(message)
+			.await
+			.map_err(|err| AppError::internal_from("Failed to send email", err))?;
+		Ok(())
+	}
+}
+
+/// Implicit TLS on port 465, none for a server on the same host, and STARTTLS otherwise.
+fn transport(mailer: &MailerConfig) -> AppResult<AsyncSmtpTransport<Tokio1Executor>> {
+	let host = mailer.smtp_host.as_str();
+	let builder = match mailer.smtp_port {
+		Some(465) => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+		_ if matches!(host, "localhost" | "127.0.0.1" | "::1") =>
+			Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)),
+		_ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+	}
+		.map_err(|err| AppError::Internal(InternalError::new(ErrorCode::Misconfigured, "Invalid SMTP_HOST").with_source(err)))?;
+	let builder = match mailer.smtp_port {
+		Some(port) => builder.port(port),
+		None => builder,
+	};
+	let builder = match (&mailer.username, &mailer.password) {
+		(Some(username), Some(password)) => builder.credentials(Credentials::new(username.clone(), password.clone())),
+		_ => builder,
+	};
+
+	Ok(builder.build())
+}
+
+fn misconfigured(context: &str) -> AppError {
+	AppError::Internal(InternalError::new(ErrorCode::Misconfigured, context))
+}
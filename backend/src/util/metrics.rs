@@ -0,0 +1,115 @@
+use std::{sync::OnceLock, time::{Duration, Instant}};
+use axum::{
+	extract::{MatchedPath, Request},
+	middleware::Next,
+	response::Response,
+};
+use metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram, Unit};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use sqlx::PgPool;
+
+const LATENCY_BUCKETS: &[f64] = &[
+	0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Installs the global Prometheus recorder on first use and returns its handle.
+/// Safe to call more than once, which the test suite relies on.
+pub fn handle() -> &'static PrometheusHandle {
+	HANDLE.get_or_init(|| {
+		let handle = PrometheusBuilder::new()
+			.set_buckets_for_metric(Matcher::Suffix("_seconds".into()), LATENCY_BUCKETS)
+			.expect("latency buckets are not empty")
+			.install_recorder()
+			.expect("failed to install metrics recorder");
+
+		describe();
+		handle
+	})
+}
+
+/// Drains histogram buffers periodically so memory stays bounded between scrapes.
+pub fn spawn_upkeep() {
+	let handle = handle();
+
+	tokio::spawn(async move {
+		let mut interval = tokio::time::interval(UPKEEP_INTERVAL);
+		loop {
+			interval.tick().await;
+			handle.run_upkeep();
+		}
+	});
+}
+
+fn describe() {
+	describe_counter!("http_requests_total", "HTTP requests by route, method and status code");
+	describe_histogram!("http_request_duration_seconds", Unit::Seconds, "HTTP request latency by route and method");
+	describe_counter!("app_errors_total", "Error responses by AppError variant and status code");
+	describe_gauge!("db_pool_connections", "Open database connections by state");
+	describe_gauge!("db_pool_max_connections", "Configured upper bound of the database pool");
+	describe_histogram!("password_hash_duration_seconds", Unit::Seconds, "Time spent in Argon2 by operation");
+	describe_counter!("signups_total", "Accounts created");
+	describe_counter!("logins_total", "Login attempts by outcome");
+	describe_counter!("time_entries_created_total", "Time entries created");
+
+	// Domain counters are registered up front so they are exported as 0 before the first event.
+	counter!("signups_total").absolute(0);
+	counter!("time_entries_created_total").absolute(0);
+}
+
+/// Records a request count and latency per matched route.
+/// Unmatched paths share one label so random URLs can't blow up the series count.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+	let route = request.extensions()
+		.get::<MatchedPath>()
+		.map(|path| path.as_str().to_owned())
+		.unwrap_or_else(|| "unmatched".into());
+	let method = request.method().to_string();
+	let started = Instant::now();
+
+	let response = next.run(request).await;
+	let status = response.status().as_u16().to_string();
+
+	counter!("http_requests_total", "method" => method.clone(), "route" => route.clone(), "status" => status)
+		.increment(1);
+	histogram!("http_request_duration_seconds", "method" => method, "route" => route)
+		.record(started.elapsed().as_secs_f64());
+
+	response
+}
+
+pub fn record_app_error(kind: &'static str, status: u16) {
+	counter!("app_errors_total", "kind" => kind, "status" => status.to_string()).increment(1);
+}
+
+pub fn record_pool(pool: &PgPool) {
+	let size = pool.size() as f64;
+	let idle = pool.num_idle() as f64;
+
+	gauge!("db_pool_connections", "state" => "idle").set(idle);
+	gauge!("db_pool_connections", "state" => "active").set(size - idle);
+	gauge!("db_pool_max_connections").set(pool.options().get_max_connections() as f64);
+}
+
+pub fn record_password_hash(operation: &'static str, elapsed: Duration) {
+	histogram!("password_hash_duration_seconds", "operation" => operation).record(elapsed.as_secs_f64());
+}
+
+pub fn record_signup() {
+	counter!("signups_total").increment(1);
+}
+
+pub fn record_login(success: bool) {
+	let outcome = match success {
+		true => "success",
+		false => "failure",
+	};
+
+	counter!("logins_total", "outcome" => outcome).increment(1);
+}
+
+pub fn record_time_entry_created() {
+	counter!("time_entries_created_total").increment(1);
+}
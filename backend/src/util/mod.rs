@@ -1,4 +1,28 @@
+pub mod audit;
+pub mod audit_service;
 pub mod error;
+pub mod etag;
+pub mod events;
 pub mod db_service;
+pub mod email_change_service;
+pub mod deprecation;
+pub mod health_service;
+pub mod idempotency;
+pub mod job_queue;
+pub mod logging;
+pub mod mailer;
+pub mod metrics;
+pub mod pagination;
+pub mod problem;
+pub mod project_service;
+pub mod request_id;
+pub mod scheduler;
+pub mod search_service;
+pub mod sync_service;
+pub mod tenant;
+pub mod time_entry_service;
+pub mod trash_service;
 pub mod user_service;
+pub mod webhook_service;
+pub mod workspace_service;
 pub mod validation;
\ No newline at end of file
@@ -0,0 +1,368 @@
+use axum::{extract::{FromRequestParts, Query}, http::{request::Parts, Uri}};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sqlx::{Postgres, QueryBuilder};
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use crate::util::error::{AppError, AppResult, ErrorCode, FieldError};
+
+pub const DEFAULT_LIMIT: u32 = 50;
+pub const MAX_LIMIT: u32 = 200;
+
+#[derive(Serialize, Deserialize, ToSchema, TS, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+#[ts(export)]
+pub enum SortOrder {
+	Asc,
+	#[default]
+	Desc,
+}
+
+impl SortOrder {
+	fn keyword(&self) -> &'static str {
+		match self {
+			SortOrder::Asc => "ASC",
+			SortOrder::Desc => "DESC",
+		}
+	}
+
+	/// Row comparison that selects the rows after the cursor in this order.
+	fn comparison(&self) -> &'static str {
+		match self {
+			SortOrder::Asc => ">",
+			SortOrder::Desc => "<",
+		}
+	}
+}
+
+/// # SortField
+/// Whitelist of columns a list can be ordered by, implemented by one enum per endpoint.
+/// Only `column` ever reaches the SQL text, so clients can't sort by arbitrary expressions.
+/// Sort columns must be `NOT NULL`; the row id breaks ties.
+pub trait SortField: Copy + Default + PartialEq + Serialize + DeserializeOwned + Send {
+	fn column(&self) -> &'static str;
+	/// Type of the column, which the cursor value is parsed as.
+	fn sql_type(&self) -> SortType;
+}
+
+/// Column types a list can be sorted by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortType {
+	Text,
+	Date,
+	Timestamp,
+}
+
+impl SortType {
+	/// Parses the text form written by `Keyset::keyset_value`; `None` for a tampered cursor.
+	fn parse(&self, value: String) -> Option<SortValue> {
+		match self {
+			SortType::Text => Some(SortValue::Text(value)),
+			SortType::Date => value.parse().ok().map(SortValue::Date),
+			SortType::Timestamp => DateTime::parse_from_rfc3339(&value)
+				.ok()
+				.map(|at| SortValue::Timestamp(at.with_timezone(&Utc))),
+		}
+	}
+}
+
+/// A cursor's sort value, typed so it binds as the column's type.
+#[derive(Debug, PartialEq)]
+enum SortValue {
+	Text(String),
+	Date(NaiveDate),
+	Timestamp(DateTime<Utc>),
+}
+
+/// Endpoint-specific query parameters, read from the same query string as `PageParams`.
+pub trait ListFilter: DeserializeOwned + Send {
+	type Sort: SortField;
+
+	fn sort(&self) -> Self::Sort;
+
+	fn validate(&self) -> AppResult<()> {
+		Ok(())
+	}
+}
+
+/// Implemented by list rows so the last row on a page can become the next cursor.
+pub trait Keyset {
+	type Sort: SortField;
+
+	fn keyset_id(&self) -> Uuid;
+	/// Text form of the sort column, parseable as its `SortField::sql_type`.
+	fn keyset_value(&self, sort: Self::Sort) -> String;
+}
+
+/// Query parameters shared by every list endpoint.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageParams {
+	/// Page size, 1 to 200. Defaults to 50.
+	pub limit: Option<u32>,
+	/// `next_cursor` of the previous page.
+	pub cursor: Option<String>,
+	pub order: Option<SortOrder>,
+}
+
+/// Opaque to clients. The sort field and order are part of it so a cursor
+/// can't be replayed against a differently ordered list.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Cursor {
+	sort: serde_json::Value,
+	order: SortOrder,
+	value: String,
+	id: Uuid,
+}
+
+impl Cursor {
+	fn encode(&self) -> String {
+		URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).expect("cursor serializes"))
+	}
+
+	fn decode(raw: &str) -> AppResult<Self> {
+		URL_SAFE_NO_PAD.decode(raw).ok()
+			.and_then(|bytes| serde_json::from_slice(&bytes).ok())
+			.ok_or(AppError::BadRequest(ErrorCode::InvalidCursor, "Malformed cursor".into()))
+	}
+}
+
+/// Like axum's `Query`, but rejects with an `AppError` so the response carries an error code.
+pub fn parse_query<T: DeserializeOwned>(uri: &Uri) -> AppResult<T> {
+	Query::try_from_uri(uri)
+		.map(|Query(value)| value)
+		.map_err(|rejection| AppError::BadRequest(ErrorCode::InvalidQuery, rejection.body_text()))
+}
+
+/// Rejects a `from`/`to` filter pair whose end lies before its start.
+pub fn validate_date_range(from: Option<NaiveDate>, to: Option<NaiveDate>) -> AppResult<()> {
+	match (from, to) {
+		(Some(from), Some(to)) if from > to => Err(AppError::Validation(vec![FieldError::new(
+			"to",
+			ErrorCode::InvalidQuery,
+			"The end of the date range lies before its start",
+		)])),
+		_ => Ok(()),
+	}
+}
+
+pub struct Page<T> {
+	pub items: Vec<T>,
+	pub next_cursor: Option<String>,
+}
+
+/// # ListQuery
+/// Extractor for list endpoints: page size, keyset cursor, sort order and the typed filter `F`.
+pub struct ListQuery<F: ListFilter> {
+	pub limit: u32,
+	pub order: SortOrder,
+	pub sort: F::Sort,
+	pub filter: F,
+	after: Option<(SortValue, Uuid)>,
+}
+
+impl<F: ListFilter> ListQuery<F> {
+	fn from_params(params: PageParams, filter: F) -> AppResult<Self> {
+		filter.validate()?;
+
+		let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+		if !(1..=MAX_LIMIT).contains(&limit) {
+			return Err(AppError::Validation(vec![FieldError::new(
+				"limit",
+				ErrorCode::InvalidQuery,
+				format!("Limit must be between 1 and {}", MAX_LIMIT),
+			)]));
+		}
+
+		let sort = filter.sort();
+		let order = params.order.unwrap_or_default();
+		let after = match params.cursor.as_deref() {
+			None | Some("") => None,
+			Some(raw) => {
+				let cursor = Cursor::decode(raw)?;
+				if cursor.sort != serde_json::json!(sort) || cursor.order != order {
+					return Err(AppError::BadRequest(
+						ErrorCode::InvalidCursor,
+						"Cursor belongs to a list with a different sort or order".into(),
+					));
+				}
+				let value = sort.sql_type().parse(cursor.value).ok_or(AppError::BadRequest(
+					ErrorCode::InvalidCursor,
+					"Malformed cursor".into(),
+				))?;
+				Some((value, cursor.id))
+			}
+		};
+
+		Ok(Self { limit, order, sort, filter, after })
+	}
+
+	/// Appends the keyset condition, `ORDER BY` and `LIMIT`.
+	/// The builder must end inside a `WHERE` clause; one extra row is fetched to detect a next page.
+	pub fn push_page(&self, builder: &mut QueryBuilder<'_, Postgres>, id_column: &str) {
+		let column = self.sort.column();
+
+		if let Some((value, id)) = &self.after {
+			builder.push(format_args!(" AND ({}, {}) {} (", column, id_column, self.order.comparison()));
+			match value {
+				SortValue::Text(value) => builder.push_bind(value.clone()),
+				SortValue::Date(value) => builder.push_bind(*value),
+				SortValue::Timestamp(value) => builder.push_bind(*value),
+			};
+			builder.push(", ").push_bind(*id).push(")");
+		}
+
+		let order = self.order.keyword();
+		builder.push(format_args!(" ORDER BY {} {}, {} {} LIMIT ", column, order, id_column, order))
+			.push_bind(self.limit as i64 + 1);
+	}
+
+	/// Trims the extra row fetched by `push_page` and turns the last remaining row into the next cursor.
+	pub fn paginate<T: Keyset<Sort = F::Sort>>(&self, mut rows: Vec<T>) -> Page<T> {
+		let has_more = rows.len() > self.limit as usize;
+		rows.truncate(self.limit as usize);
+
+		let next_cursor = match (has_more, rows.last()) {
+			(true, Some(last)) => Some(Cursor {
+				sort: serde_json::json!(self.sort),
+				order: self.order,
+				value: last.keyset_value(self.sort),
+				id: last.keyset_id(),
+			}.encode()),
+			_ => None,
+		};
+
+		Page { items: rows, next_cursor }
+	}
+}
+
+impl<F, S> FromRequestParts<S> for ListQuery<F>
+where
+	F: ListFilter,
+	S: Send + Sync,
+{
+	type Rejection = AppError;
+
+	async fn from_request_parts(parts: &mut Parts, _state: &S) -> AppResult<Self> {
+		Self::from_params(parse_query(&parts.uri)?, parse_query(&parts.uri)?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+	#[serde(rename_all = "snake_case")]
+	enum TestSort {
+		#[default]
+		CreatedAt,
+		Name,
+	}
+
+	impl SortField for TestSort {
+		fn column(&self) -> &'static str {
+			match self {
+				TestSort::CreatedAt => "created_at",
+				TestSort::Name => "name",
+			}
+		}
+
+		fn sql_type(&self) -> SortType {
+			match self {
+				TestSort::CreatedAt => SortType::Timestamp,
+				TestSort::Name => SortType::Text,
+			}
+		}
+	}
+
+	#[derive(Deserialize)]
+	struct TestFilter {
+		sort: Option<TestSort>,
+	}
+
+	impl ListFilter for TestFilter {
+		type Sort = TestSort;
+
+		fn sort(&self) -> TestSort {
+			self.sort.unwrap_or_default()
+		}
+	}
+
+	struct Row(Uuid, String);
+
+	impl Keyset for Row {
+		type Sort = TestSort;
+
+		fn keyset_id(&self) -> Uuid {
+			self.0
+		}
+
+		fn keyset_value(&self, _sort: TestSort) -> String {
+			self.1.clone()
+		}
+	}
+
+	fn query(limit: Option<u32>, cursor: Option<String>, sort: Option<TestSort>) -> AppResult<ListQuery<TestFilter>> {
+		ListQuery::from_params(PageParams { limit, cursor, order: None }, TestFilter { sort })
+	}
+
+	#[test]
+	fn test_limit_bounds() {
+		assert_eq!(query(None, None, None).unwrap().limit, DEFAULT_LIMIT);
+		assert!(matches!(query(Some(0), None, None), Err(AppError::Validation(_))));
+		assert!(matches!(query(Some(MAX_LIMIT + 1), None, None), Err(AppError::Validation(_))));
+	}
+
+	#[test]
+	fn test_next_cursor_round_trip() {
+		let list = query(Some(2), None, Some(TestSort::Name)).unwrap();
+		let rows = vec![
+			Row(Uuid::new_v4(), "a".into()),
+			Row(Uuid::new_v4(), "b".into()),
+			Row(Uuid::new_v4(), "c".into()),
+		];
+		let last_id = rows[1].0;
+		let page = list.paginate(rows);
+
+		assert_eq!(page.items.len(), 2);
+		let cursor = page.next_cursor.expect("a third row means another page");
+		let next = query(Some(2), Some(cursor.clone()), Some(TestSort::Name)).unwrap();
+		assert_eq!(next.after, Some((SortValue::Text("b".into()), last_id)));
+
+		let resorted = query(Some(2), Some(cursor), None);
+		assert_eq!(resorted.err().map(|err| err.code()), Some(ErrorCode::InvalidCursor));
+	}
+
+	#[test]
+	fn test_last_page_has_no_cursor() {
+		let list = query(Some(2), None, None).unwrap();
+		let page = list.paginate(vec![Row(Uuid::new_v4(), "a".into())]);
+		assert!(page.next_cursor.is_none());
+	}
+
+	#[test]
+	fn test_malformed_cursor() {
+		let result = query(None, Some("not-a-cursor".into()), None);
+		assert_eq!(result.err().map(|err| err.code()), Some(ErrorCode::InvalidCursor));
+	}
+
+	#[test]
+	fn test_tampered_cursor_value() {
+		let cursor = |value: &str| Cursor {
+			sort: serde_json::json!(TestSort::CreatedAt),
+			order: SortOrder::Desc,
+			value: value.into(),
+			id: Uuid::new_v4(),
+		}.encode();
+
+		let result = query(None, Some(cursor("yesterday')) OR true --")), None);
+		assert_eq!(result.err().map(|err| err.code()), Some(ErrorCode::InvalidCursor));
+
+		let at = Utc::now();
+		let list = query(None, Some(cursor(&at.to_rfc3339())), None).unwrap();
+		assert_eq!(list.after.map(|(value, _)| value), Some(SortValue::Timestamp(at)));
+	}
+}
@@ -0,0 +1,61 @@
+use axum::{
+	extract::Request,
+	http::header,
+	middleware::Next,
+	response::Response,
+};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use crate::util::error::{ErrorCode, FieldError};
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+tokio::task_local! {
+	static PREFERS_PROBLEM_JSON: bool;
+}
+
+/// # ProblemDetails
+/// RFC 7807 body, extended with the stable error `code`, field `errors` and the `request_id`.
+#[derive(Serialize, Deserialize, Debug, ToSchema, TS)]
+#[ts(export)]
+pub struct ProblemDetails {
+	#[serde(rename = "type")]
+	pub problem_type: String,
+	pub title: String,
+	pub status: u16,
+	pub detail: String,
+	pub code: ErrorCode,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	#[ts(as = "Option<_>", optional)]
+	pub errors: Vec<FieldError>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[ts(optional)]
+	pub error_id: Option<Uuid>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[ts(optional)]
+	pub request_id: Option<String>,
+}
+
+impl ProblemDetails {
+	pub fn type_uri(code: ErrorCode) -> String {
+		format!("urn:kvitter:problem:{}", code)
+	}
+}
+
+/// Whether the client asked for `application/problem+json` on the current request.
+pub fn preferred() -> bool {
+	PREFERS_PROBLEM_JSON.try_with(|prefers| *prefers).unwrap_or(false)
+}
+
+/// Records the client's `Accept` preference so error responses built deep in handlers can honor it.
+pub async fn negotiate(request: Request, next: Next) -> Response {
+	let prefers = request.headers()
+		.get_all(header::ACCEPT)
+		.iter()
+		.filter_map(|value| value.to_str().ok())
+		.any(|value| value.contains(PROBLEM_JSON));
+
+	PREFERS_PROBLEM_JSON.scope(prefers, next.run(request)).await
+}
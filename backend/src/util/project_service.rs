@@ -0,0 +1,76 @@
+use sqlx::{PgPool, QueryBuilder};
+use uuid::Uuid;
+use crate::{
+	models::project::{Project, ProjectFilter},
+	util::{
+		error::{AppError, AppResult, ErrorCode},
+		pagination::{ListQuery, Page},
+		tenant,
+	},
+};
+
+const PROJECT_COLUMNS: &str = "p.id, p.name, p.description, p.client_id, p.total_budget, \
+	p.default_hourly_rate, p.is_fixed_price, p.start_date, p.end_date, p.status, p.created_at, p.created_by, p.workspace_id, p.version";
+
+/// Projects of the workspace, for its members.
+pub async fn list_projects(
+	pool: &PgPool,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	query: &ListQuery<ProjectFilter>,
+) -> AppResult<Page<Project>> {
+	let filter = &query.filter;
+	let mut builder = QueryBuilder::new(format!(
+		"SELECT {} FROM projects p \
+		JOIN clients c ON c.id = p.client_id AND c.deleted_at IS NULL \
+		WHERE p.deleted_at IS NULL AND p.workspace_id = ",
+		PROJECT_COLUMNS,
+	));
+	builder.push_bind(*workspace_id)
+		.push(" AND EXISTS (SELECT 1 FROM workspace_members m WHERE m.workspace_id = p.workspace_id AND m.user_id = ")
+		.push_bind(*user_id)
+		.push(")");
+
+	if let Some(status) = filter.status {
+		builder.push(" AND p.status = ").push_bind(status);
+	}
+	if let Some(client_id) = filter.client_id {
+		builder.push(" AND p.client_id = ").push_bind(client_id);
+	}
+	if let Some(from) = filter.from {
+		builder.push(" AND p.start_date >= ").push_bind(from);
+	}
+	if let Some(to) = filter.to {
+		builder.push(" AND p.start_date <= ").push_bind(to);
+	}
+	query.push_page(&mut builder, "p.id");
+
+	let mut tx = tenant::begin(pool, user_id, workspace_id).await?;
+	let rows = builder.build_query_as::<Project>()
+		.fetch_all(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to list projects", err))?;
+
+	Ok(query.paginate(rows))
+}
+
+/// One project of the workspace, for its members.
+pub async fn fetch_project(pool: &PgPool, user_id: &Uuid, workspace_id: &Uuid, project_id: &Uuid) -> AppResult<Project> {
+	let sql = format!(
+		"SELECT {} FROM projects p \
+		JOIN clients c ON c.id = p.client_id AND c.deleted_at IS NULL \
+		WHERE p.id = $1 AND p.deleted_at IS NULL AND p.workspace_id = $3 \
+			AND EXISTS (SELECT 1 FROM workspace_members m WHERE m.workspace_id = p.workspace_id AND m.user_id = $2)",
+		PROJECT_COLUMNS,
+	);
+
+	let mut tx = tenant::begin(pool, user_id, workspace_id).await?;
+	sqlx::query_as::<_, Project>(&sql)
+		.bind(project_id)
+		.bind(user_id)
+		.bind(workspace_id)
+		.fetch_optional(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to fetch project", err))?
+		.ok_or_else(|| AppError::NotFound(ErrorCode::NotFound, "Project not found".into()))
+}
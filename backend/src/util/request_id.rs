@@ -0,0 +1,84 @@
+use axum::{
+	extract::Request,
+	http::{HeaderName, HeaderValue},
+	middleware::Next,
+	response::Response,
+};
+use tracing::Span;
+use uuid::Uuid;
+
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+	static CURRENT_REQUEST_ID: RequestId;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+	/// Reuses the caller's id when it is short, printable ASCII, otherwise generates a new one.
+	fn from_header(value: Option<&HeaderValue>) -> Self {
+		value
+			.and_then(|v| v.to_str().ok())
+			.filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+			.filter(|id| id.chars().all(|c| c.is_ascii_graphic()))
+			.map(|id| RequestId(id.to_owned()))
+			.unwrap_or_else(|| RequestId(Uuid::new_v4().to_string()))
+	}
+}
+
+/// Id of the request being handled by the current task, if any.
+/// Lets error responses carry the id without threading it through every handler.
+pub fn current() -> Option<String> {
+	CURRENT_REQUEST_ID.try_with(|id| id.0.clone()).ok()
+}
+
+/// Accepts or generates an `X-Request-Id`, exposes it to handlers and echoes it on the response.
+/// Must run outside the trace layer so the id is available when the request span is created.
+pub async fn propagate(mut request: Request, next: Next) -> Response {
+	let request_id = RequestId::from_header(request.headers().get(&REQUEST_ID_HEADER));
+	let header_value = HeaderValue::from_str(&request_id.0)
+		.expect("request id is printable ASCII");
+
+	request.extensions_mut().insert(request_id.clone());
+
+	let mut response = CURRENT_REQUEST_ID.scope(request_id, next.run(request)).await;
+	response.headers_mut().insert(REQUEST_ID_HEADER.clone(), header_value);
+	response
+}
+
+/// Span factory for `tower_http::trace::TraceLayer`.
+pub fn make_span(request: &Request) -> Span {
+	let request_id = request.extensions()
+		.get::<RequestId>()
+		.map(|id| id.0.as_str())
+		.unwrap_or_default();
+
+	tracing::info_span!(
+		"request",
+		request_id = %request_id,
+		method = %request.method(),
+		uri = %request.uri(),
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_request_id_from_header() {
+		let accepted = HeaderValue::from_static("abc-123");
+		assert_eq!(RequestId::from_header(Some(&accepted)).0, "abc-123");
+
+		let too_long = HeaderValue::from_str(&"a".repeat(MAX_REQUEST_ID_LEN + 1)).unwrap();
+		assert_ne!(RequestId::from_header(Some(&too_long)).0.len(), MAX_REQUEST_ID_LEN + 1);
+
+		let with_space = HeaderValue::from_static("abc 123");
+		assert_ne!(RequestId::from_header(Some(&with_space)).0, "abc 123");
+
+		assert!(Uuid::parse_str(&RequestId::from_header(None).0).is_ok());
+	}
+}
@@ -0,0 +1,318 @@
+use std::{str::FromStr, sync::Arc};
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use serde_json::Value;
+use sqlx::{pool::PoolConnection, Acquire, PgConnection, PgPool, Postgres, QueryBuilder};
+use tracing::{info, warn};
+use uuid::Uuid;
+use crate::{
+	config::Config,
+	models::schedule::{
+		RunFilter, RunTrigger, ScheduleFilter, ScheduledTask, ScheduledTaskRun, UpdateScheduledTaskPayload,
+	},
+	util::{
+		error::{AppError, AppResult, ErrorCode, FieldError},
+		job_queue::{self, JobRegistry},
+		pagination::{ListQuery, Page},
+	},
+};
+
+/// Key of the session-level advisory lock held by the replica that fires scheduled tasks.
+pub const LEADER_LOCK: i64 = 0x6b76_6974_7465_7201;
+const TASK_COLUMNS: &str = "x.id, x.name, x.description, x.schedule, x.job_kind, x.payload, x.enabled, x.next_run_at, \
+	x.last_run_at, x.created_at";
+const RUN_COLUMNS: &str = "x.id, x.task_id, x.job_id, x.trigger, x.triggered_by, x.created_at, \
+	j.status AS job_status, j.last_error AS job_error, j.finished_at";
+
+/// Parses a cron expression. Five fields are the classic minute-level form and get a `0` seconds field.
+pub fn parse_schedule(expression: &str) -> Result<Schedule, cron::error::Error> {
+	match expression.split_whitespace().count() {
+		5 => Schedule::from_str(&format!("0 {}", expression)),
+		_ => Schedule::from_str(expression),
+	}
+}
+
+/// When `schedule` fires next after `after`, or `None` if it never does again.
+pub fn next_run(schedule: &str, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+	parse_schedule(schedule).ok()?.after(&after).next()
+}
+
+fn validate_schedule(schedule: &str) -> AppResult<()> {
+	match next_run(schedule, Utc::now()) {
+		Some(_) => Ok(()),
+		None => Err(AppError::Validation(vec![FieldError::new(
+			"schedule",
+			ErrorCode::ValidationFailed,
+			"Must be a cron expression that fires again, e.g. `0 3 * * *`",
+		)])),
+	}
+}
+
+fn not_found() -> AppError {
+	AppError::NotFound(ErrorCode::NotFound, "Scheduled task not found".into())
+}
+
+#[derive(sqlx::FromRow)]
+struct DueTask {
+	id: Uuid,
+	name: String,
+	schedule: String,
+	job_kind: String,
+	payload: Value,
+}
+
+/// Queues a job for every enabled task that is due and moves it to its next run.
+/// Runs missed while no replica was leading are not caught up; the task fires once and carries on.
+pub async fn fire_due(conn: &mut PgConnection, registry: &JobRegistry) -> AppResult<usize> {
+	let mut tx = conn.begin().await
+		.map_err(|err| AppError::database("Failed to start scheduler tick", err))?;
+
+	let due = sqlx::query_as::<_, DueTask>(
+		"SELECT id, name, schedule, job_kind, payload FROM scheduled_tasks \
+		WHERE enabled AND next_run_at <= NOW() FOR UPDATE SKIP LOCKED"
+	)
+		.fetch_all(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to fetch due scheduled tasks", err))?;
+
+	let now = Utc::now();
+	for task in &due {
+		// A task whose job kind is gone, or whose schedule stopped parsing, is skipped rather than retried every tick.
+		match registry.knows(&task.job_kind) {
+			true => {
+				let job_id = job_queue::enqueue_kind(&mut *tx, registry, &task.job_kind, &task.payload).await?;
+				insert_run(&mut tx, &task.id, &job_id, RunTrigger::Schedule, None).await?;
+			}
+			false => warn!(task = task.name, kind = task.job_kind, "Scheduled task has no job handler"),
+		}
+
+		let next_run_at = next_run(&task.schedule, now);
+		if next_run_at.is_none() {
+			warn!(task = task.name, schedule = task.schedule, "Scheduled task won't fire again; disabling it");
+		}
+		sqlx::query(
+			"UPDATE scheduled_tasks SET last_run_at = $2, next_run_at = COALESCE($3, next_run_at), enabled = $3 IS NOT NULL \
+			WHERE id = $1"
+		)
+			.bind(task.id)
+			.bind(now)
+			.bind(next_run_at)
+			.execute(&mut *tx)
+			.await
+			.map_err(|err| AppError::database("Failed to advance scheduled task", err))?;
+	}
+
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to commit scheduler tick", err))?;
+
+	Ok(due.len())
+}
+
+async fn insert_run(
+	conn: &mut PgConnection,
+	task_id: &Uuid,
+	job_id: &Uuid,
+	trigger: RunTrigger,
+	triggered_by: Option<&Uuid>,
+) -> AppResult<ScheduledTaskRun> {
+	sqlx::query_as::<_, ScheduledTaskRun>(&format!(
+		"WITH x AS ( \
+			INSERT INTO scheduled_task_runs (task_id, job_id, trigger, triggered_by) VALUES ($1, $2, $3, $4) RETURNING * \
+		) SELECT {} FROM x LEFT JOIN background_jobs j ON j.id = x.job_id",
+		RUN_COLUMNS,
+	))
+		.bind(task_id)
+		.bind(job_id)
+		.bind(trigger)
+		.bind(triggered_by)
+		.fetch_one(conn)
+		.await
+		.map_err(|err| AppError::database("Failed to record scheduled task run", err))
+}
+
+/// Takes the leader lock on a connection of its own if no other replica holds it.
+/// The lock lasts as long as the connection, so a crashed leader is replaced on the next tick.
+pub async fn try_lead(pool: &PgPool) -> AppResult<Option<PoolConnection<Postgres>>> {
+	let mut conn = pool.acquire().await
+		.map_err(|err| AppError::database("Failed to acquire scheduler connection", err))?;
+	let leading = sqlx::query_scalar::<_, bool>("SELECT pg_try_advisory_lock($1)")
+		.bind(LEADER_LOCK)
+		.fetch_one(&mut *conn)
+		.await
+		.map_err(|err| AppError::database("Failed to try the scheduler lock", err))?;
+
+	Ok(leading.then_some(conn))
+}
+
+/// Every `scheduler_interval`, each replica tries to become the leader; the leader fires due tasks.
+pub fn spawn_scheduler(pool: PgPool, config: Arc<Config>, registry: JobRegistry) {
+	tokio::spawn(async move {
+		let mut interval = tokio::time::interval(config.scheduler_interval);
+		let mut leader: Option<PoolConnection<Postgres>> = None;
+		loop {
+			interval.tick().await;
+
+			if leader.is_none() {
+				match try_lead(&pool).await {
+					Ok(Some(conn)) => {
+						info!("Leading the scheduler");
+						leader = Some(conn);
+					}
+					Ok(None) => continue,
+					Err(err) => {
+						err.log();
+						continue;
+					}
+				}
+			}
+
+			let Some(conn) = leader.as_mut() else { continue };
+			if let Err(err) = fire_due(conn, &registry).await {
+				err.log();
+				// Closing rather than returning the connection releases the lock for another replica.
+				if let Some(conn) = leader.take() {
+					let _ = conn.close().await;
+				}
+			}
+		}
+	});
+}
+
+pub async fn list_tasks(pool: &PgPool, query: &ListQuery<ScheduleFilter>) -> AppResult<Page<ScheduledTask>> {
+	let mut builder = QueryBuilder::new(format!("SELECT {} FROM scheduled_tasks x WHERE TRUE", TASK_COLUMNS));
+	query.push_page(&mut builder, "x.id");
+
+	let rows = builder.build_query_as::<ScheduledTask>()
+		.fetch_all(pool)
+		.await
+		.map_err(|err| AppError::database("Failed to list scheduled tasks", err))?;
+
+	Ok(query.paginate(rows))
+}
+
+/// A new schedule, or enabling the task, recomputes `next_run_at` from now.
+pub async fn update_task(
+	pool: &PgPool,
+	task_id: &Uuid,
+	payload: &UpdateScheduledTaskPayload,
+) -> AppResult<ScheduledTask> {
+	if let Some(schedule) = &payload.schedule {
+		validate_schedule(schedule)?;
+	}
+
+	let mut tx = pool.begin().await
+		.map_err(|err| AppError::database("Failed to start scheduled task update", err))?;
+	let current = sqlx::query_as::<_, ScheduledTask>(&format!(
+		"SELECT {} FROM scheduled_tasks x WHERE x.id = $1 FOR UPDATE",
+		TASK_COLUMNS,
+	))
+		.bind(task_id)
+		.fetch_optional(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to fetch scheduled task", err))?
+		.ok_or_else(not_found)?;
+
+	let schedule = payload.schedule.as_deref().unwrap_or(&current.schedule);
+	let enabled = payload.enabled.unwrap_or(current.enabled);
+	let next_run_at = match payload.schedule.is_some() || (enabled && !current.enabled) {
+		true => next_run(schedule, Utc::now()).ok_or_else(|| AppError::Validation(vec![FieldError::new(
+			"schedule",
+			ErrorCode::ValidationFailed,
+			"The schedule doesn't fire again; change it before enabling the task",
+		)]))?,
+		false => current.next_run_at,
+	};
+
+	let task = sqlx::query_as::<_, ScheduledTask>(&format!(
+		"UPDATE scheduled_tasks x SET schedule = $2, enabled = $3, next_run_at = $4 WHERE x.id = $1 RETURNING {}",
+		TASK_COLUMNS,
+	))
+		.bind(task_id)
+		.bind(schedule)
+		.bind(enabled)
+		.bind(next_run_at)
+		.fetch_one(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to update scheduled task", err))?;
+
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to commit scheduled task update", err))?;
+
+	Ok(task)
+}
+
+/// Queues the task's job right away, whether or not the task is enabled. Its schedule is left alone.
+pub async fn run_now(
+	pool: &PgPool,
+	registry: &JobRegistry,
+	task_id: &Uuid,
+	admin_id: &Uuid,
+) -> AppResult<ScheduledTaskRun> {
+	let mut tx = pool.begin().await
+		.map_err(|err| AppError::database("Failed to start scheduled task run", err))?;
+
+	let (job_kind, payload) = sqlx::query_as::<_, (String, Value)>(
+		"UPDATE scheduled_tasks SET last_run_at = NOW() WHERE id = $1 RETURNING job_kind, payload"
+	)
+		.bind(task_id)
+		.fetch_optional(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to fetch scheduled task", err))?
+		.ok_or_else(not_found)?;
+
+	let job_id = job_queue::enqueue_kind(&mut *tx, registry, &job_kind, &payload).await?;
+	let run = insert_run(&mut tx, task_id, &job_id, RunTrigger::Manual, Some(admin_id)).await?;
+
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to commit scheduled task run", err))?;
+
+	Ok(run)
+}
+
+pub async fn list_runs(
+	pool: &PgPool,
+	task_id: &Uuid,
+	query: &ListQuery<RunFilter>,
+) -> AppResult<Page<ScheduledTaskRun>> {
+	let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM scheduled_tasks WHERE id = $1)")
+		.bind(task_id)
+		.fetch_one(pool)
+		.await
+		.map_err(|err| AppError::database("Failed to fetch scheduled task", err))?;
+	if !exists {
+		return Err(not_found());
+	}
+
+	let mut builder = QueryBuilder::new(format!(
+		"SELECT {} FROM scheduled_task_runs x LEFT JOIN background_jobs j ON j.id = x.job_id WHERE x.task_id = ",
+		RUN_COLUMNS,
+	));
+	builder.push_bind(*task_id);
+	if let Some(trigger) = query.filter.trigger {
+		builder.push(" AND x.trigger = ").push_bind(trigger);
+	}
+	query.push_page(&mut builder, "x.id");
+
+	let rows = builder.build_query_as::<ScheduledTaskRun>()
+		.fetch_all(pool)
+		.await
+		.map_err(|err| AppError::database("Failed to list scheduled task runs", err))?;
+
+	Ok(query.paginate(rows))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chrono::TimeZone;
+
+	#[test]
+	fn test_parse_schedule() {
+		let after = Utc.with_ymd_and_hms(2026, 10, 18, 12, 34, 56).unwrap();
+		assert_eq!(next_run("0 3 * * *", after), Some(Utc.with_ymd_and_hms(2026, 10, 19, 3, 0, 0).unwrap()));
+		assert_eq!(next_run("30 0 * * * *", after), Some(Utc.with_ymd_and_hms(2026, 10, 18, 13, 0, 30).unwrap()));
+		assert_eq!(next_run("0 0 0 1 1 * 2020", after), None);
+		assert!(parse_schedule("every day").is_err());
+		assert!(parse_schedule("61 * * * *").is_err());
+	}
+}
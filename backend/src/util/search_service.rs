@@ -0,0 +1,126 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::{
+	models::search::{SearchKind, SearchResult},
+	util::{
+		error::{AppError, AppResult},
+		tenant,
+	},
+};
+
+// ts_headline doesn't escape the source text, so matches are marked with control
+// characters that typed text has no reason to contain, and swapped for tags after escaping.
+const MATCH_START: char = '\u{2}';
+const MATCH_STOP: char = '\u{3}';
+const HEADLINE_OPTIONS: &str = "StartSel=\"\u{2}\", StopSel=\"\u{3}\", MaxWords=25, MinWords=8, \
+	MaxFragments=2, FragmentDelimiter=\" … \"";
+
+// Every branch binds $1 = user id and reads the `q` and `visible` CTEs of `search`.
+// `visible` only holds live projects of live clients, which hides their deleted children too.
+const CLIENT_BRANCH: &str = "SELECT 'client' AS kind, c.id, NULL::uuid AS project_id, c.name AS title,
+		ts_headline('simple', concat_ws(' ', c.name, c.company_name, c.first_name, c.last_name), q.query, $3) AS highlight,
+		ts_rank(c.search_vector, q.query) AS rank
+	FROM clients c, q
+	WHERE c.deleted_at IS NULL AND c.search_vector @@ q.query
+		AND EXISTS (SELECT 1 FROM projects p JOIN visible v ON v.id = p.id WHERE p.client_id = c.id)";
+
+const PROJECT_BRANCH: &str = "SELECT 'project', p.id, p.id, p.name,
+		ts_headline('simple', concat_ws(' ', p.name, p.description), q.query, $3),
+		ts_rank(p.search_vector, q.query)
+	FROM projects p JOIN visible v ON v.id = p.id, q
+	WHERE p.search_vector @@ q.query";
+
+const JOB_BRANCH: &str = "SELECT 'job', j.id, j.project_id, j.name,
+		ts_headline('simple', concat_ws(' ', j.name, j.description), q.query, $3),
+		ts_rank(j.search_vector, q.query)
+	FROM jobs j JOIN visible v ON v.id = j.project_id, q
+	WHERE j.deleted_at IS NULL AND j.search_vector @@ q.query";
+
+const TIME_ENTRY_BRANCH: &str = "SELECT 'time_entry', t.id, j.project_id, j.name,
+		ts_headline('simple', coalesce(t.description, ''), q.query, $3),
+		ts_rank(t.search_vector, q.query)
+	FROM time_entries t
+		JOIN jobs j ON j.id = t.job_id AND j.deleted_at IS NULL
+		JOIN visible v ON v.id = j.project_id, q
+	WHERE t.user_id = $1 AND t.deleted_at IS NULL AND t.search_vector @@ q.query";
+
+fn branch(kind: SearchKind) -> &'static str {
+	match kind {
+		SearchKind::Client => CLIENT_BRANCH,
+		SearchKind::Project => PROJECT_BRANCH,
+		SearchKind::Job => JOB_BRANCH,
+		SearchKind::TimeEntry => TIME_ENTRY_BRANCH,
+	}
+}
+
+/// Ranked matches the user can see: projects of the active workspace,
+/// the clients and jobs of those projects, and their own time entries on them.
+pub async fn search(
+	pool: &PgPool,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	query: &str,
+	kind: Option<SearchKind>,
+	limit: u32,
+) -> AppResult<Vec<SearchResult>> {
+	let kinds = match kind {
+		Some(kind) => vec![kind],
+		None => vec![SearchKind::Client, SearchKind::Project, SearchKind::Job, SearchKind::TimeEntry],
+	};
+	let branches = kinds.into_iter().map(branch).collect::<Vec<_>>().join(" UNION ALL ");
+	let sql = format!(
+		"WITH q AS (SELECT websearch_to_tsquery('simple', $2) AS query),
+		visible AS (
+			SELECT p.id FROM projects p
+			JOIN clients c ON c.id = p.client_id AND c.deleted_at IS NULL
+			WHERE p.deleted_at IS NULL AND p.workspace_id = $5
+				AND EXISTS (SELECT 1 FROM workspace_members m WHERE m.workspace_id = p.workspace_id AND m.user_id = $1)
+		)
+		SELECT * FROM ({}) results ORDER BY rank DESC, id LIMIT $4",
+		branches,
+	);
+
+	let mut tx = tenant::begin(pool, user_id, workspace_id).await?;
+	let results = sqlx::query_as::<_, SearchResult>(&sql)
+		.bind(user_id)
+		.bind(query)
+		.bind(HEADLINE_OPTIONS)
+		.bind(limit as i64)
+		.bind(workspace_id)
+		.fetch_all(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to search", err))?;
+
+	Ok(results.into_iter()
+		.map(|result| SearchResult { highlight: mark_matches(&result.highlight), ..result })
+		.collect())
+}
+
+fn mark_matches(headline: &str) -> String {
+	let mut marked = String::with_capacity(headline.len() + 16);
+
+	for c in headline.chars() {
+		match c {
+			MATCH_START => marked.push_str("<mark>"),
+			MATCH_STOP => marked.push_str("</mark>"),
+			'&' => marked.push_str("&amp;"),
+			'<' => marked.push_str("&lt;"),
+			'>' => marked.push_str("&gt;"),
+			'"' => marked.push_str("&quot;"),
+			'\'' => marked.push_str("&#39;"),
+			c => marked.push(c),
+		}
+	}
+	marked
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_mark_matches_escapes_source_text() {
+		let headline = format!("<b>{}server{}</b> & co", MATCH_START, MATCH_STOP);
+		assert_eq!(mark_matches(&headline), "&lt;b&gt;<mark>server</mark>&lt;/b&gt; &amp; co");
+	}
+}
@@ -0,0 +1,539 @@
+use std::time::Duration;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{Connection, PgConnection, PgPool};
+use uuid::Uuid;
+use crate::{
+	models::{
+		audit::{AuditAction, EntityType},
+		sync::{SyncChange, SyncChanges, SyncKind, SyncOperation, SyncOutcome, SyncRecord, SyncResult},
+	},
+	util::{
+		audit::{self, AuditContext},
+		error::{AppError, AppResult, ErrorCode},
+		metrics,
+		tenant,
+		workspace_service::{member_cte, IN_WORKSPACE, MANAGES_PROJECT},
+	},
+};
+
+/// Columns that are bookkeeping rather than data and never leave the server as part of `data`.
+const INTERNAL_COLUMNS: &str = "ARRAY['search_vector', 'sync_xid', 'deleted_at', 'version']";
+
+/// A field clients may write: its key in `data`, its column, and the value expression
+/// over the `jsonb_populate_record` row `r` or, where the shapes differ, the raw payload `$3`.
+struct Field {
+	key: &'static str,
+	column: &'static str,
+	value: &'static str,
+}
+
+const fn field(key: &'static str, value: &'static str) -> Field {
+	Field { key, column: key, value }
+}
+
+const CLIENT_FIELDS: &[Field] = &[
+	field("name", "r.name"),
+	field("first_name", "r.first_name"),
+	field("last_name", "r.last_name"),
+	field("phone", "r.phone"),
+	field("company_name", "r.company_name"),
+	field("address_line1", "r.address_line1"),
+	field("address_line2", "r.address_line2"),
+	field("city", "r.city"),
+	field("postal_code", "r.postal_code"),
+	field("country", "r.country"),
+];
+
+const PROJECT_FIELDS: &[Field] = &[
+	field("name", "r.name"),
+	field("description", "r.description"),
+	field("client_id", "r.client_id"),
+	field("total_budget", "r.total_budget"),
+	field("default_hourly_rate", "r.default_hourly_rate"),
+	field("is_fixed_price", "r.is_fixed_price"),
+	field("start_date", "r.start_date"),
+	field("end_date", "r.end_date"),
+	field("status", "r.status"),
+];
+
+const JOB_FIELDS: &[Field] = &[
+	field("project_id", "r.project_id"),
+	field("name", "r.name"),
+	field("description", "r.description"),
+	field("budget", "r.budget"),
+	field("is_fixed_price", "r.is_fixed_price"),
+	field("status", "r.status"),
+];
+
+const TIME_ENTRY_FIELDS: &[Field] = &[
+	field("job_id", "r.job_id"),
+	Field {
+		key: "time_spent_seconds",
+		column: "time_spent",
+		value: "make_interval(secs => ($3->>'time_spent_seconds')::float8)",
+	},
+	field("description", "r.description"),
+	field("entry_date", "r.entry_date"),
+];
+
+const MILESTONE_FIELDS: &[Field] = &[
+	field("project_id", "r.project_id"),
+	field("description", "r.description"),
+	field("amount", "r.amount"),
+	field("due_date", "r.due_date"),
+	field("completed_at", "r.completed_at"),
+	field("status", "r.status"),
+];
+
+impl SyncKind {
+	fn table(&self) -> &'static str {
+		match self {
+			SyncKind::Client => "clients",
+			SyncKind::Project => "projects",
+			SyncKind::Job => "jobs",
+			SyncKind::TimeEntry => "time_entries",
+			SyncKind::Milestone => "milestones",
+		}
+	}
+
+	/// The row `x` as JSON, shaped like the REST representation.
+	fn data(&self) -> String {
+		match self {
+			SyncKind::TimeEntry => format!(
+				"(to_jsonb(x) - {} - 'time_spent') \
+				|| jsonb_build_object('time_spent_seconds', EXTRACT(EPOCH FROM x.time_spent)::BIGINT)",
+				INTERNAL_COLUMNS,
+			),
+			_ => format!("to_jsonb(x) - {}", INTERNAL_COLUMNS),
+		}
+	}
+
+	fn fields(&self) -> &'static [Field] {
+		match self {
+			SyncKind::Client => CLIENT_FIELDS,
+			SyncKind::Project => PROJECT_FIELDS,
+			SyncKind::Job => JOB_FIELDS,
+			SyncKind::TimeEntry => TIME_ENTRY_FIELDS,
+			SyncKind::Milestone => MILESTONE_FIELDS,
+		}
+	}
+
+	/// Columns a new row gets from the server no matter what the client sent, with their values over `me`.
+	fn server_columns(&self) -> &'static [(&'static str, &'static str)] {
+		match self {
+			SyncKind::Client => &[("workspace_id", "(SELECT workspace_id FROM me)")],
+			SyncKind::Project => &[("created_by", "(SELECT id FROM me)"), ("workspace_id", "(SELECT workspace_id FROM me)")],
+			SyncKind::TimeEntry => &[("user_id", "(SELECT id FROM me)")],
+			_ => &[],
+		}
+	}
+
+	/// Who may create, change and delete a row: the project creator and workspace admins, and the author
+	/// of a time entry on a project of the workspace. Clients are shared, so members may only edit one until
+	/// someone else bills a project to it. Checked before and after every write. Expects the row as `x` and a `me` CTE.
+	fn may_write(&self) -> String {
+		match self {
+			SyncKind::Client =>
+				"EXISTS (SELECT 1 FROM me WHERE me.workspace_id = x.workspace_id AND (me.role <> 'MEMBER' \
+					OR NOT EXISTS (SELECT 1 FROM projects p WHERE p.client_id = x.id AND p.created_by IS DISTINCT FROM me.id)))".into(),
+			SyncKind::Project => format!("EXISTS (SELECT 1 FROM projects p, me WHERE p.id = x.id AND {})", MANAGES_PROJECT),
+			SyncKind::Job | SyncKind::Milestone =>
+				format!("EXISTS (SELECT 1 FROM projects p, me WHERE p.id = x.project_id AND {})", MANAGES_PROJECT),
+			SyncKind::TimeEntry => format!(
+				"x.user_id = (SELECT id FROM me) AND EXISTS (SELECT 1 FROM jobs j \
+				JOIN projects p ON p.id = j.project_id, me WHERE j.id = x.job_id AND {})",
+				IN_WORKSPACE,
+			),
+		}
+	}
+
+	/// Rows that aren't hidden, neither themselves nor through a parent. A full copy only includes these.
+	fn live(&self) -> &'static str {
+		match self {
+			SyncKind::Client => "x.deleted_at IS NULL",
+			SyncKind::Project => "x.deleted_at IS NULL \
+				AND EXISTS (SELECT 1 FROM clients c WHERE c.id = x.client_id AND c.deleted_at IS NULL)",
+			SyncKind::Job | SyncKind::Milestone => "x.deleted_at IS NULL \
+				AND EXISTS (SELECT 1 FROM projects p JOIN clients c ON c.id = p.client_id \
+				WHERE p.id = x.project_id AND p.deleted_at IS NULL AND c.deleted_at IS NULL)",
+			SyncKind::TimeEntry => "x.deleted_at IS NULL \
+				AND EXISTS (SELECT 1 FROM jobs j JOIN projects p ON p.id = j.project_id JOIN clients c ON c.id = p.client_id \
+				WHERE j.id = x.job_id AND j.deleted_at IS NULL AND p.deleted_at IS NULL AND c.deleted_at IS NULL)",
+		}
+	}
+
+	fn record_columns(&self) -> String {
+		format!("'{}' AS kind, x.id, x.version, x.deleted_at, {} AS data", self.as_str(), self.data())
+	}
+}
+
+/// Opaque to clients. `xmin` is the oldest transaction that was still running when the cursor was issued.
+/// A cursor only continues the workspace it was issued for; older cursors name none.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct SyncCursor {
+	xmin: u64,
+	issued_at: DateTime<Utc>,
+	#[serde(default)]
+	workspace_id: Option<Uuid>,
+}
+
+impl SyncCursor {
+	fn encode(&self) -> String {
+		URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).expect("cursor serializes"))
+	}
+
+	fn decode(raw: &str) -> AppResult<Self> {
+		URL_SAFE_NO_PAD.decode(raw).ok()
+			.and_then(|bytes| serde_json::from_slice(&bytes).ok())
+			.ok_or(AppError::BadRequest(ErrorCode::InvalidCursor, "Malformed sync cursor".into()))
+	}
+}
+
+/// Changes in the workspace visible to the user since `since`, or a full copy without one.
+/// Deleted rows are purged after `retention`, so older cursors get a full copy with `reset` instead,
+/// and so do cursors of another workspace.
+pub async fn pull_changes(
+	pool: &PgPool,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	since: Option<&str>,
+	retention: Duration,
+) -> AppResult<SyncChanges> {
+	let retention = chrono::Duration::from_std(retention)
+		.map_err(|err| AppError::internal_from("Trash retention is out of range", err))?;
+	let now = Utc::now();
+	let since = since.map(SyncCursor::decode)
+		.transpose()?
+		.filter(|cursor| cursor.issued_at + retention > now && cursor.workspace_id == Some(*workspace_id));
+
+	// Taken before reading, so anything committed in between is merely sent twice.
+	let xmin = sqlx::query_scalar::<_, String>("SELECT pg_snapshot_xmin(pg_current_snapshot())::text")
+		.fetch_one(pool)
+		.await
+		.map_err(|err| AppError::database("Failed to read sync position", err))?
+		.parse::<u64>()
+		.map_err(|err| AppError::internal_from("Unexpected transaction id", err))?;
+
+	let branches = SyncKind::ALL.iter()
+		.map(|kind| format!(
+			"SELECT {} FROM {} x, me WHERE ({}) AND {}",
+			kind.record_columns(),
+			kind.table(),
+			EntityType::from(*kind).visible_to_me(),
+			match since {
+				Some(_) => "x.sync_xid >= $3::text::xid8",
+				None => kind.live(),
+			},
+		))
+		.collect::<Vec<_>>()
+		.join(" UNION ALL ");
+	let sql = format!("WITH me AS ({}) {}", member_cte("$1", "$2"), branches);
+
+	let mut query = sqlx::query_as::<_, SyncRecord>(&sql).bind(user_id).bind(workspace_id);
+	if let Some(since) = &since {
+		query = query.bind(since.xmin.to_string());
+	}
+	let mut tx = tenant::begin(pool, user_id, workspace_id).await?;
+	let records = query.fetch_all(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to read changes", err))?;
+
+	let mut changes = SyncChanges {
+		created: Vec::new(),
+		updated: Vec::new(),
+		deleted: Vec::new(),
+		cursor: SyncCursor { xmin, issued_at: now, workspace_id: Some(*workspace_id) }.encode(),
+		reset: since.is_none(),
+	};
+	for record in records {
+		match (record.deleted_at, record.version) {
+			(Some(_), _) => changes.deleted.push(record),
+			(None, 1) => changes.created.push(record),
+			(None, _) => changes.updated.push(record),
+		}
+	}
+
+	Ok(changes)
+}
+
+/// Why a single change didn't go through: either the change itself is at fault and the batch goes on,
+/// or something is broken and the whole batch fails.
+enum ApplyError {
+	Rejected(String),
+	Failed(AppError),
+}
+
+impl From<AppError> for ApplyError {
+	fn from(err: AppError) -> Self {
+		ApplyError::Failed(err)
+	}
+}
+
+/// Data exceptions (class 22) and constraint violations (class 23) are the client's doing.
+fn classify(context: &str, err: sqlx::Error) -> ApplyError {
+	let client_error = err.as_database_error()
+		.and_then(|db| db.code())
+		.is_some_and(|code| code.starts_with("22") || code.starts_with("23"));
+
+	match client_error {
+		true => ApplyError::Rejected(err.as_database_error().map(|db| db.message().to_owned()).unwrap_or_default()),
+		false => ApplyError::Failed(AppError::database(context.to_owned(), err)),
+	}
+}
+
+/// Row-level security refuses a row written into another workspace before `check_may_write` sees it.
+fn is_policy_violation(err: &sqlx::Error) -> bool {
+	err.as_database_error()
+		.is_some_and(|db| db.code().as_deref() == Some("42501") && db.message().contains("row-level security policy"))
+}
+
+fn not_allowed_there(kind: SyncKind) -> ApplyError {
+	ApplyError::Rejected(format!("Not allowed to write this {} there", kind.as_str().replace('_', " ")))
+}
+
+/// Applies each change in its own savepoint, in order, so parents can be created before their children.
+/// Policy: a change to an existing row needs the row's current version as `base_version`;
+/// otherwise the server's copy wins and is reported back as a conflict.
+pub async fn push_changes(
+	pool: &PgPool,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	changes: &[SyncChange],
+	context: &AuditContext,
+) -> AppResult<Vec<SyncResult>> {
+	let mut tx = tenant::begin(pool, user_id, workspace_id).await?;
+	let mut results = Vec::with_capacity(changes.len());
+	let mut entries_created = 0;
+
+	for change in changes {
+		let mut savepoint = tx.begin().await
+			.map_err(|err| AppError::database("Failed to start savepoint", err))?;
+		let result = match apply(&mut savepoint, user_id, workspace_id, change, context).await {
+			Ok(result) => result,
+			Err(ApplyError::Rejected(reason)) => SyncResult::rejected(change, reason),
+			Err(ApplyError::Failed(err)) => return Err(err),
+		};
+		let done = match result.outcome {
+			SyncOutcome::Applied => savepoint.commit().await,
+			_ => savepoint.rollback().await,
+		};
+		done.map_err(|err| AppError::database("Failed to finish savepoint", err))?;
+		// Rows start at version 1 and every update bumps it, so only an insert leaves an entry there.
+		if change.kind == SyncKind::TimeEntry && result.outcome == SyncOutcome::Applied && result.version == Some(1) {
+			entries_created += 1;
+		}
+		results.push(result);
+	}
+
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to apply changes", err))?;
+	for _ in 0..entries_created {
+		metrics::record_time_entry_created();
+	}
+
+	Ok(results)
+}
+
+async fn apply(
+	conn: &mut PgConnection,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	change: &SyncChange,
+	context: &AuditContext,
+) -> Result<SyncResult, ApplyError> {
+	let kind = change.kind;
+	let current = sqlx::query_as::<_, (i64, bool)>(&format!(
+		"WITH me AS ({}) SELECT x.version, {} FROM {} x WHERE x.id = $1 FOR UPDATE OF x",
+		member_cte("$2", "$3"),
+		kind.may_write(),
+		kind.table(),
+	))
+		.bind(change.id)
+		.bind(user_id)
+		.bind(workspace_id)
+		.fetch_optional(&mut *conn)
+		.await
+		.map_err(|err| classify("Failed to lock row", err))?;
+
+	match (current, change.op) {
+		(None, SyncOperation::Delete) => Ok(SyncResult::applied(change, None)),
+		(None, SyncOperation::Upsert) => insert(conn, user_id, workspace_id, change, context).await,
+		(Some((_, false)), _) => Err(ApplyError::Rejected(format!("Not allowed to change this {}", kind.as_str().replace('_', " ")))),
+		(Some((version, true)), _) if change.base_version != Some(version) => {
+			let server = fetch_record(conn, kind, &change.id).await?;
+			Ok(SyncResult::conflict(change, server))
+		}
+		(Some(_), SyncOperation::Upsert) => update(conn, user_id, workspace_id, change, context).await,
+		(Some(_), SyncOperation::Delete) => delete(conn, change, context).await,
+	}
+}
+
+/// The writable fields present in the change. Read-only fields such as `id` or `created_at` are ignored
+/// so clients can send back whole records.
+fn present_fields(change: &SyncChange) -> Vec<&'static Field> {
+	change.kind.fields()
+		.iter()
+		.filter(|field| change.data.get(field.key).is_some())
+		.collect()
+}
+
+async fn insert(
+	conn: &mut PgConnection,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	change: &SyncChange,
+	context: &AuditContext,
+) -> Result<SyncResult, ApplyError> {
+	let kind = change.kind;
+	let fields = present_fields(change);
+	let mut columns = vec!["id"];
+	let mut values = vec!["$1"];
+
+	for (column, value) in kind.server_columns() {
+		columns.push(column);
+		values.push(value);
+	}
+	columns.extend(fields.iter().map(|field| field.column));
+	values.extend(fields.iter().map(|field| field.value));
+
+	let sql = format!(
+		"WITH me AS ({}) INSERT INTO {table} ({}) \
+		SELECT {} FROM jsonb_populate_record(NULL::{table}, $3) r RETURNING version",
+		member_cte("$2", "$4"),
+		columns.join(", "),
+		values.join(", "),
+		table = kind.table(),
+	);
+	let version = sqlx::query_scalar::<_, i64>(&sql)
+		.bind(change.id)
+		.bind(user_id)
+		.bind(&change.data)
+		.bind(workspace_id)
+		.fetch_one(&mut *conn)
+		.await
+		.map_err(|err| match is_policy_violation(&err) {
+			true => not_allowed_there(kind),
+			false => classify("Failed to create row", err),
+		})?;
+
+	check_may_write(conn, user_id, workspace_id, change).await?;
+	let after = fetch_record(conn, kind, &change.id).await?;
+	audit::record(conn, context, kind.into(), change.id, AuditAction::Created, audit::diff(&Value::Null, &after.data)).await?;
+
+	Ok(SyncResult::applied(change, Some(version)))
+}
+
+async fn update(
+	conn: &mut PgConnection,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	change: &SyncChange,
+	context: &AuditContext,
+) -> Result<SyncResult, ApplyError> {
+	let kind = change.kind;
+	let fields = present_fields(change);
+	let before = fetch_record(conn, kind, &change.id).await?;
+
+	if fields.is_empty() {
+		return Ok(SyncResult::applied(change, Some(before.version)));
+	}
+
+	let sql = format!(
+		"UPDATE {table} x SET ({}) = \
+		(SELECT {} FROM jsonb_populate_record(NULL::{table}, $2) r) WHERE x.id = $1 RETURNING x.version",
+		fields.iter().map(|field| field.column).collect::<Vec<_>>().join(", "),
+		fields.iter().map(|field| field.value).collect::<Vec<_>>().join(", "),
+		table = kind.table(),
+	);
+	let version = sqlx::query_scalar::<_, i64>(&sql)
+		.bind(change.id)
+		.bind(&change.data)
+		.fetch_one(&mut *conn)
+		.await
+		.map_err(|err| match is_policy_violation(&err) {
+			true => not_allowed_there(kind),
+			false => classify("Failed to update row", err),
+		})?;
+
+	check_may_write(conn, user_id, workspace_id, change).await?;
+	let after = fetch_record(conn, kind, &change.id).await?;
+	audit::record(conn, context, kind.into(), change.id, AuditAction::Updated, audit::diff(&before.data, &after.data)).await?;
+
+	Ok(SyncResult::applied(change, Some(version)))
+}
+
+async fn delete(
+	conn: &mut PgConnection,
+	change: &SyncChange,
+	context: &AuditContext,
+) -> Result<SyncResult, ApplyError> {
+	let sql = format!(
+		"UPDATE {} x SET deleted_at = NOW() WHERE x.id = $1 AND x.deleted_at IS NULL RETURNING x.version, x.deleted_at",
+		change.kind.table(),
+	);
+	let deleted = sqlx::query_as::<_, (i64, DateTime<Utc>)>(&sql)
+		.bind(change.id)
+		.fetch_optional(&mut *conn)
+		.await
+		.map_err(|err| classify("Failed to delete row", err))?;
+
+	let Some((version, deleted_at)) = deleted else {
+		let current = fetch_record(conn, change.kind, &change.id).await?;
+		return Ok(SyncResult::applied(change, Some(current.version)));
+	};
+
+	let changes = json!({ "deleted_at": { "before": null, "after": deleted_at } });
+	audit::record(conn, context, change.kind.into(), change.id, AuditAction::Deleted, changes).await?;
+
+	Ok(SyncResult::applied(change, Some(version)))
+}
+
+/// Re-checks the rules on the row as written, so a change can't move a row somewhere the user has no say.
+async fn check_may_write(
+	conn: &mut PgConnection,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	change: &SyncChange,
+) -> Result<(), ApplyError> {
+	let allowed = sqlx::query_scalar::<_, bool>(&format!(
+		"WITH me AS ({}) SELECT {} FROM {} x WHERE x.id = $1",
+		member_cte("$2", "$3"),
+		change.kind.may_write(),
+		change.kind.table(),
+	))
+		.bind(change.id)
+		.bind(user_id)
+		.bind(workspace_id)
+		.fetch_one(&mut *conn)
+		.await
+		.map_err(|err| classify("Failed to check permissions", err))?;
+
+	match allowed {
+		true => Ok(()),
+		false => Err(not_allowed_there(change.kind)),
+	}
+}
+
+async fn fetch_record(conn: &mut PgConnection, kind: SyncKind, id: &Uuid) -> Result<SyncRecord, ApplyError> {
+	sqlx::query_as::<_, SyncRecord>(&format!("SELECT {} FROM {} x WHERE x.id = $1", kind.record_columns(), kind.table()))
+		.bind(id)
+		.fetch_one(conn)
+		.await
+		.map_err(|err| ApplyError::Failed(AppError::database("Failed to fetch row", err)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_cursor_round_trip() {
+		let cursor = SyncCursor { xmin: 1234, issued_at: Utc::now(), workspace_id: Some(Uuid::new_v4()) };
+
+		assert_eq!(SyncCursor::decode(&cursor.encode()).unwrap(), cursor);
+		assert!(SyncCursor::decode("not-a-cursor").is_err());
+	}
+}
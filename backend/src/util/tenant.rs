@@ -0,0 +1,39 @@
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use crate::util::error::{AppError, AppResult};
+
+/// Starts a transaction for a request by `user_id` acting in `workspace_id`. The row-level security
+/// policies read both back, so tenant tables only show and accept rows of that workspace for the rest
+/// of the transaction, whatever the queries in it filter on. Every query touching tenant data on
+/// behalf of a user should run in one; read-only work may simply drop it instead of committing.
+pub async fn begin(pool: &PgPool, user_id: &Uuid, workspace_id: &Uuid) -> AppResult<Transaction<'static, Postgres>> {
+	let mut tx = pool.begin().await
+		.map_err(|err| AppError::database("Failed to start transaction", err))?;
+
+	// Local to the transaction, so nothing leaks to the next user of the pooled connection.
+	sqlx::query(
+		"SELECT set_config('app.current_user_id', $1, true), set_config('app.current_workspace_id', $2, true)"
+	)
+		.bind(user_id.to_string())
+		.bind(workspace_id.to_string())
+		.execute(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to set the tenant of the transaction", err))?;
+
+	Ok(tx)
+}
+
+/// Starts a transaction that reaches the rows of every workspace, for work nobody does as a tenant:
+/// the trash purge, the admin views and other background work. Outside both this and `begin`, tenant
+/// tables look empty.
+pub async fn bypass(pool: &PgPool) -> AppResult<Transaction<'static, Postgres>> {
+	let mut tx = pool.begin().await
+		.map_err(|err| AppError::database("Failed to start transaction", err))?;
+
+	sqlx::query("SELECT set_config('app.bypass_rls', 'on', true)")
+		.execute(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to bypass row-level security", err))?;
+
+	Ok(tx)
+}
@@ -0,0 +1,81 @@
+use sqlx::{PgPool, QueryBuilder};
+use uuid::Uuid;
+use crate::{
+	models::time_entry::{TimeEntry, TimeEntryFilter},
+	util::{
+		error::{AppError, AppResult, ErrorCode},
+		pagination::{ListQuery, Page},
+		tenant,
+	},
+};
+
+const TIME_ENTRY_COLUMNS: &str = "t.id, t.job_id, t.user_id, \
+	EXTRACT(EPOCH FROM t.time_spent)::BIGINT AS time_spent_seconds, t.description, t.entry_date, t.created_at, t.version";
+
+/// The user's own time entries on projects of the workspace. Project and client filters go through the entry's job.
+pub async fn list_time_entries(
+	pool: &PgPool,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	query: &ListQuery<TimeEntryFilter>,
+) -> AppResult<Page<TimeEntry>> {
+	let filter = &query.filter;
+	let mut builder = QueryBuilder::new(format!(
+		"SELECT {} FROM time_entries t \
+		JOIN jobs j ON j.id = t.job_id AND j.deleted_at IS NULL \
+		JOIN projects p ON p.id = j.project_id AND p.deleted_at IS NULL \
+		JOIN clients c ON c.id = p.client_id AND c.deleted_at IS NULL \
+		WHERE t.deleted_at IS NULL AND t.user_id = ",
+		TIME_ENTRY_COLUMNS,
+	));
+	builder.push_bind(*user_id)
+		.push(" AND p.workspace_id = ")
+		.push_bind(*workspace_id);
+
+	if let Some(from) = filter.from {
+		builder.push(" AND t.entry_date >= ").push_bind(from);
+	}
+	if let Some(to) = filter.to {
+		builder.push(" AND t.entry_date <= ").push_bind(to);
+	}
+	if let Some(job_id) = filter.job_id {
+		builder.push(" AND t.job_id = ").push_bind(job_id);
+	}
+	if let Some(project_id) = filter.project_id {
+		builder.push(" AND j.project_id = ").push_bind(project_id);
+	}
+	if let Some(client_id) = filter.client_id {
+		builder.push(" AND p.client_id = ").push_bind(client_id);
+	}
+	query.push_page(&mut builder, "t.id");
+
+	let mut tx = tenant::begin(pool, user_id, workspace_id).await?;
+	let rows = builder.build_query_as::<TimeEntry>()
+		.fetch_all(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to list time entries", err))?;
+
+	Ok(query.paginate(rows))
+}
+
+/// One of the user's own time entries on a project of the workspace.
+pub async fn fetch_time_entry(pool: &PgPool, user_id: &Uuid, workspace_id: &Uuid, entry_id: &Uuid) -> AppResult<TimeEntry> {
+	let sql = format!(
+		"SELECT {} FROM time_entries t \
+		JOIN jobs j ON j.id = t.job_id AND j.deleted_at IS NULL \
+		JOIN projects p ON p.id = j.project_id AND p.deleted_at IS NULL \
+		JOIN clients c ON c.id = p.client_id AND c.deleted_at IS NULL \
+		WHERE t.id = $1 AND t.deleted_at IS NULL AND t.user_id = $2 AND p.workspace_id = $3",
+		TIME_ENTRY_COLUMNS,
+	);
+
+	let mut tx = tenant::begin(pool, user_id, workspace_id).await?;
+	sqlx::query_as::<_, TimeEntry>(&sql)
+		.bind(entry_id)
+		.bind(user_id)
+		.bind(workspace_id)
+		.fetch_optional(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to fetch time entry", err))?
+		.ok_or_else(|| AppError::NotFound(ErrorCode::NotFound, "Time entry not found".into()))
+}
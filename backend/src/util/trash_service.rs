@@ -0,0 +1,300 @@
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgPool, QueryBuilder};
+use tracing::info;
+use uuid::Uuid;
+use crate::{
+	models::{
+		audit::{AuditAction, EntityType},
+		trash::{TrashFilter, TrashItem, TrashKind},
+	},
+	util::{
+		audit::{self, AuditContext},
+		error::{AppError, AppResult, ErrorCode},
+		etag::{version_mismatch, IfMatch},
+		job_queue::{Job, JobContext},
+		pagination::{ListQuery, Page},
+		tenant,
+		workspace_service::{member_cte, IN_WORKSPACE, MANAGES_PROJECT},
+	},
+};
+
+impl TrashKind {
+	fn table(&self) -> &'static str {
+		match self {
+			TrashKind::Client => "clients",
+			TrashKind::Project => "projects",
+			TrashKind::Job => "jobs",
+			TrashKind::TimeEntry => "time_entries",
+			TrashKind::Milestone => "milestones",
+		}
+	}
+
+	fn title(&self) -> &'static str {
+		match self {
+			TrashKind::Client | TrashKind::Project | TrashKind::Job => "x.name",
+			TrashKind::TimeEntry => "concat_ws(': ', x.entry_date::text, x.description)",
+			TrashKind::Milestone => "x.description",
+		}
+	}
+
+	/// Who may delete and restore a row of this kind: the project creator or a workspace admin, or the
+	/// author of a time entry. Clients are shared, so a member may only delete one whose projects are all theirs.
+	/// Expects the row as `x` and the acting member in a `me` CTE.
+	fn owned_by_me(&self) -> String {
+		match self {
+			TrashKind::Client =>
+				"EXISTS (SELECT 1 FROM me WHERE me.workspace_id = x.workspace_id AND (me.role <> 'MEMBER' \
+					OR (EXISTS (SELECT 1 FROM projects p WHERE p.client_id = x.id AND p.created_by = me.id) \
+					AND NOT EXISTS (SELECT 1 FROM projects p WHERE p.client_id = x.id AND p.created_by IS DISTINCT FROM me.id))))".into(),
+			TrashKind::Project => format!("EXISTS (SELECT 1 FROM projects p, me WHERE p.id = x.id AND {})", MANAGES_PROJECT),
+			TrashKind::Job | TrashKind::Milestone =>
+				format!("EXISTS (SELECT 1 FROM projects p, me WHERE p.id = x.project_id AND {})", MANAGES_PROJECT),
+			TrashKind::TimeEntry => format!(
+				"EXISTS (SELECT 1 FROM jobs j JOIN projects p ON p.id = j.project_id, me \
+				WHERE j.id = x.job_id AND x.user_id = me.id AND {})",
+				IN_WORKSPACE,
+			),
+		}
+	}
+
+	fn not_found(&self) -> AppError {
+		AppError::NotFound(ErrorCode::NotFound, format!("No such {} found", self.as_str().replace('_', " ")))
+	}
+}
+
+/// Fails with 412 unless the row is still at a version `if_match` accepts.
+pub async fn soft_delete(
+	pool: &PgPool,
+	kind: TrashKind,
+	id: &Uuid,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	if_match: &IfMatch,
+	context: &AuditContext,
+) -> AppResult<()> {
+	set_deleted_at(pool, kind, id, user_id, workspace_id, if_match, context, AuditAction::Deleted).await
+}
+
+pub async fn restore(
+	pool: &PgPool,
+	kind: TrashKind,
+	id: &Uuid,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	context: &AuditContext,
+) -> AppResult<()> {
+	set_deleted_at(pool, kind, id, user_id, workspace_id, &IfMatch::Any, context, AuditAction::Restored).await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn set_deleted_at(
+	pool: &PgPool,
+	kind: TrashKind,
+	id: &Uuid,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	if_match: &IfMatch,
+	context: &AuditContext,
+	action: AuditAction,
+) -> AppResult<()> {
+	let (value, condition) = match action {
+		AuditAction::Restored => ("NULL", "IS NOT NULL"),
+		_ => ("NOW()", "IS NULL"),
+	};
+	let target = format!("x.id = $1 AND x.deleted_at {} AND {}", condition, kind.owned_by_me());
+	// The subquery in RETURNING reads the snapshot from before the update, i.e. the old value.
+	let sql = format!(
+		"WITH me AS ({}) \
+		UPDATE {table} x SET deleted_at = {} WHERE {} AND ($3::bigint[] IS NULL OR x.version = ANY($3)) \
+		RETURNING (SELECT deleted_at FROM {table} WHERE id = $1), x.deleted_at",
+		member_cte("$2", "$4"),
+		value,
+		target,
+		table = kind.table(),
+	);
+
+	let mut tx = tenant::begin(pool, user_id, workspace_id).await?;
+	let updated = sqlx::query_as::<_, (Option<DateTime<Utc>>, Option<DateTime<Utc>>)>(&sql)
+		.bind(id)
+		.bind(user_id)
+		.bind(if_match.versions())
+		.bind(workspace_id)
+		.fetch_optional(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to update deleted_at", err))?;
+
+	let Some((before, after)) = updated else {
+		// Nothing matched: tell a stale version apart from a row that isn't there for this user.
+		let exists = sqlx::query_scalar::<_, bool>(&format!(
+			"WITH me AS ({}) SELECT EXISTS (SELECT 1 FROM {} x WHERE {})",
+			member_cte("$2", "$3"),
+			kind.table(),
+			target,
+		))
+			.bind(id)
+			.bind(user_id)
+			.bind(workspace_id)
+			.fetch_one(&mut *tx)
+			.await
+			.map_err(|err| AppError::database("Failed to update deleted_at", err))?;
+
+		return Err(match exists {
+			true => version_mismatch(),
+			false => kind.not_found(),
+		});
+	};
+
+	let changes = json!({ "deleted_at": { "before": before, "after": after } });
+	audit::record(&mut tx, context, kind.into(), *id, action, changes).await?;
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to update deleted_at", err))?;
+
+	Ok(())
+}
+
+/// Deleted rows of the workspace the user may restore. Children hidden through a deleted parent aren't listed;
+/// they come back when the parent is restored.
+pub async fn list_trash(
+	pool: &PgPool,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	retention: Duration,
+	query: &ListQuery<TrashFilter>,
+) -> AppResult<Page<TrashItem>> {
+	let kinds = match query.filter.kind {
+		Some(kind) => vec![kind],
+		None => TrashKind::ALL.to_vec(),
+	};
+	let branches = kinds.iter()
+		.map(|kind| format!(
+			"SELECT '{}' AS kind, x.id, {} AS title, x.deleted_at FROM {} x WHERE x.deleted_at IS NOT NULL AND {}",
+			kind.as_str(),
+			kind.title(),
+			kind.table(),
+			kind.owned_by_me(),
+		))
+		.collect::<Vec<_>>()
+		.join(" UNION ALL ");
+
+	let mut builder = QueryBuilder::new("WITH me AS (SELECT user_id AS id, workspace_id, role FROM workspace_members WHERE user_id = ");
+	builder.push_bind(*user_id)
+		.push(" AND workspace_id = ")
+		.push_bind(*workspace_id)
+		.push(") SELECT trash.*, trash.deleted_at + make_interval(secs => ")
+		.push_bind(retention.as_secs_f64())
+		.push(format_args!(") AS purge_at FROM ({}) trash WHERE TRUE", branches));
+	query.push_page(&mut builder, "trash.id");
+
+	let mut tx = tenant::begin(pool, user_id, workspace_id).await?;
+	let rows = builder.build_query_as::<TrashItem>()
+		.fetch_all(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to list trash", err))?;
+
+	Ok(query.paginate(rows))
+}
+
+// Everything that goes with a purged row: a user takes their entries and their personal workspace along,
+// a workspace its clients and projects, a client its projects, a project its jobs, members and milestones,
+// and a job its entries. Projects a user created in shared workspaces belong to the workspace and stay.
+const USERS_GONE: &str = "SELECT id FROM users WHERE deleted_at < $1";
+
+fn workspaces_gone() -> String {
+	format!("SELECT id FROM workspaces WHERE personal AND created_by IN ({})", USERS_GONE)
+}
+
+fn clients_gone() -> String {
+	format!("deleted_at < $1 OR workspace_id IN ({})", workspaces_gone())
+}
+
+fn projects_gone() -> String {
+	format!(
+		"deleted_at < $1 OR workspace_id IN ({}) OR client_id IN (SELECT id FROM clients WHERE {})",
+		workspaces_gone(),
+		clients_gone(),
+	)
+}
+
+fn jobs_gone() -> String {
+	format!("deleted_at < $1 OR project_id IN (SELECT id FROM projects WHERE {})", projects_gone())
+}
+
+/// Wraps a purge `DELETE` so every removed row leaves a system `purged` event behind.
+fn purge_with_audit(entity_type: EntityType, delete: String) -> String {
+	format!(
+		"WITH gone AS ({} RETURNING id) \
+		INSERT INTO audit_events (entity_type, entity_id, action) SELECT '{}', id, 'purged' FROM gone",
+		delete,
+		entity_type.as_str(),
+	)
+}
+
+/// Permanently removes rows deleted before `now - retention`, children first so foreign keys hold.
+pub async fn purge_expired(pool: &PgPool, retention: Duration) -> AppResult<u64> {
+	let retention = chrono::Duration::from_std(retention)
+		.map_err(|err| AppError::internal_from("Trash retention is out of range", err))?;
+	let cutoff: DateTime<Utc> = Utc::now() - retention;
+	let statements = [
+		purge_with_audit(EntityType::TimeEntry, format!(
+			"DELETE FROM time_entries WHERE deleted_at < $1 OR user_id IN ({}) OR job_id IN (SELECT id FROM jobs WHERE {})",
+			USERS_GONE,
+			jobs_gone(),
+		)),
+		purge_with_audit(EntityType::Milestone, format!(
+			"DELETE FROM milestones WHERE deleted_at < $1 OR project_id IN (SELECT id FROM projects WHERE {})",
+			projects_gone(),
+		)),
+		purge_with_audit(EntityType::Job, format!("DELETE FROM jobs WHERE {}", jobs_gone())),
+		// Memberships aren't entities of their own; the project's event covers them.
+		format!(
+			"DELETE FROM project_members WHERE user_id IN ({}) OR project_id IN (SELECT id FROM projects WHERE {})",
+			USERS_GONE,
+			projects_gone(),
+		),
+		purge_with_audit(EntityType::Project, format!("DELETE FROM projects WHERE {}", projects_gone())),
+		format!("UPDATE projects SET created_by = NULL WHERE created_by IN ({})", USERS_GONE),
+		purge_with_audit(EntityType::Client, format!("DELETE FROM clients WHERE {}", clients_gone())),
+		// Memberships and invitations go with the workspace.
+		purge_with_audit(EntityType::Workspace, format!("DELETE FROM workspaces WHERE id IN ({})", workspaces_gone())),
+		purge_with_audit(EntityType::User, "DELETE FROM users WHERE deleted_at < $1".into()),
+	];
+
+	// Every workspace's trash at once.
+	let mut tx = tenant::bypass(pool).await?;
+	let mut purged = 0;
+
+	for statement in &statements {
+		purged += sqlx::query(statement)
+			.bind(cutoff)
+			.execute(&mut *tx)
+			.await
+			.map_err(|err| AppError::database("Failed to purge trash", err))?
+			.rows_affected();
+	}
+
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to commit trash purge", err))?;
+
+	Ok(purged)
+}
+
+/// Background job running `purge_expired` once with the configured retention.
+#[derive(Serialize, Deserialize)]
+pub struct PurgeTrash {}
+
+#[async_trait]
+impl Job for PurgeTrash {
+	const KIND: &'static str = "trash.purge";
+
+	async fn run(self, context: &JobContext) -> AppResult<()> {
+		let purged = purge_expired(&context.pool, context.config.trash_retention).await?;
+		if purged > 0 {
+			info!(purged, "Purged expired trash");
+		}
+		Ok(())
+	}
+}
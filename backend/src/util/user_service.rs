@@ -1,61 +1,98 @@
-use sqlx::PgPool;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
 use crate::{
-	util::error::{AppError, AppResult},
-	models::user::{PublicUser, User}
+	util::{
+		audit::{self, AuditContext},
+		error::{AppError, AppResult, ErrorCode, FieldError},
+		etag::{version_mismatch, IfMatch},
+		validation::validated,
+	},
+	models::{
+		audit::{AuditAction, EntityType},
+		user::{PublicUser, UpdateUserPayload, User},
+	}
 };
 
+const MAX_TEXT_LEN: usize = 100;
+const MAX_URL_LEN: usize = 2048;
+
+/// Addresses are compared case-insensitively, as the unique index on `lower(email)` does.
+/// Soft-deleted accounts keep their address until they are purged, so it can't be taken over in the meantime.
 pub async fn is_email_unique(pool: &PgPool, email: &str) -> AppResult<()> {
 	let count = sqlx::query_scalar::<_, i64>
-		("SELECT COUNT(*) FROM users WHERE email = $1")
+		("SELECT COUNT(*) FROM users WHERE lower(email) = lower($1)")
 		.bind(email)
 		.fetch_one(pool)
 		.await
-		.map_err(|_| AppError::Internal("Failed to check email uniqueness".into()))?;
+		.map_err(|err| AppError::database("Failed to check email uniqueness", err))?;
 
 	match count {
 		0 => Ok(()),
-		_ => Err(AppError::Auth("Email is already taken".into())),
+		_ => Err(AppError::Auth(ErrorCode::EmailTaken, "Email is already taken".into())),
 	}
 }
 
 pub async fn fetch_user_by_uuid(pool: &PgPool, user_id: &Uuid) -> AppResult<User> {
-	sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+	sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1 AND deleted_at IS NULL")
 		.bind(user_id)
 		.fetch_optional(pool)
 		.await
-		.map_err(|_| AppError::Internal("Error fetching user".into()))?
-		.ok_or(AppError::NotFound("User not found".into()))
+		.map_err(|err| AppError::database("Error fetching user", err))?
+		.ok_or(AppError::NotFound(ErrorCode::UserNotFound, "User not found".into()))
 }
 
 pub async fn fetch_user_by_email(pool: &PgPool, email: &str) -> AppResult<User> {
-	sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1")
+	sqlx::query_as::<_, User>("SELECT * FROM users WHERE lower(email) = lower($1) AND deleted_at IS NULL")
 		.bind(email)
 		.fetch_optional(pool)
 		.await
-		.map_err(|_| AppError::Internal("Error fetching user".into()))?
-		.ok_or(AppError::NotFound("User not found".into()))
+		.map_err(|err| AppError::database("Error fetching user", err))?
+		.ok_or(AppError::NotFound(ErrorCode::UserNotFound, "User not found".into()))
 }
 
-pub async fn delete_user_by_uuid(pool: &PgPool, user_id: &Uuid) -> AppResult<()> {
-	sqlx::query("DELETE FROM users WHERE id = $1")
+/// Marks the account deleted; the trash purge removes it and everything it owns after the retention period.
+pub async fn delete_user_by_uuid(pool: &PgPool, user_id: &Uuid, context: &AuditContext) -> AppResult<()> {
+	let mut tx = pool.begin().await
+		.map_err(|err| AppError::database("Failed to start transaction", err))?;
+	let deleted_at = sqlx::query_scalar::<_, DateTime<Utc>>(
+		"UPDATE users SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL RETURNING deleted_at"
+	)
 		.bind(user_id)
-		.execute(pool)
+		.fetch_optional(&mut *tx)
 		.await
-		.map_err(|_| AppError::Internal("Failed to delete user".into()))?;
-	
+		.map_err(|err| AppError::database("Failed to delete user", err))?;
+
+	if let Some(deleted_at) = deleted_at {
+		let changes = json!({ "deleted_at": { "before": null, "after": deleted_at } });
+		audit::record(&mut tx, context, EntityType::User, *user_id, AuditAction::Deleted, changes).await?;
+	}
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to delete user", err))?;
+
 	Ok(())
 }
 
-pub async fn update_user_password(pool: &PgPool, user_id: &Uuid, password_hash: &str) -> AppResult<()> {
-	sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2")
+/// Takes the connection of the caller's transaction so the change and its audit event commit together.
+/// Returns the new version, or a 412 if the user changed since the version in `if_match`.
+pub async fn update_user_password(
+	conn: &mut PgConnection,
+	user_id: &Uuid,
+	password_hash: &str,
+	if_match: &IfMatch,
+) -> AppResult<i64> {
+	sqlx::query_scalar::<_, i64>(
+		"UPDATE users SET password_hash = $1 WHERE id = $2 AND deleted_at IS NULL \
+		AND ($3::bigint[] IS NULL OR version = ANY($3)) RETURNING version"
+	)
 		.bind(password_hash)
 		.bind(user_id)
-		.execute(pool)
+		.bind(if_match.versions())
+		.fetch_optional(conn)
 		.await
-		.map_err(|_| AppError::Internal("Failed to update password".into()))?;
-	
-	Ok(())
+		.map_err(|err| AppError::database("Failed to update password", err))?
+		.ok_or_else(version_mismatch)
 }
 
 pub async fn fetch_and_map_by_uuid(pool: &PgPool, user_id: &Uuid) -> AppResult<PublicUser> {
@@ -64,8 +101,300 @@ pub async fn fetch_and_map_by_uuid(pool: &PgPool, user_id: &Uuid) -> AppResult<P
 		.map(|user| user.into())
 }
 
-pub async fn fetch_and_map_by_email(pool: &PgPool, email: &str) -> AppResult<PublicUser> {
-	fetch_user_by_email(pool, email)
+/// Returns the payload as it will be stored: text trimmed and codes upper-cased.
+/// Every field is checked, and all failures are reported at once.
+pub fn validate_profile(payload: &UpdateUserPayload) -> AppResult<UpdateUserPayload> {
+	let trim = |value: &Option<String>| value.as_deref().map(|value| value.trim().to_string());
+	let upper = |value: &Option<String>| trim(value).map(|value| value.to_uppercase());
+	let payload = UpdateUserPayload {
+		display_name: trim(&payload.display_name),
+		avatar_url: trim(&payload.avatar_url),
+		locale: trim(&payload.locale),
+		timezone: trim(&payload.timezone),
+		currency: upper(&payload.currency),
+		date_format: payload.date_format,
+		number_format: payload.number_format,
+		org_number: trim(&payload.org_number),
+		vat_number: upper(&payload.vat_number),
+		bank_account: upper(&payload.bank_account),
+		address_line1: trim(&payload.address_line1),
+		address_line2: trim(&payload.address_line2),
+		postal_code: upper(&payload.postal_code),
+		city: trim(&payload.city),
+		country: upper(&payload.country),
+	};
+	let clearable = |value: &Option<String>| value.clone().filter(|value| !value.is_empty());
+
+	validated([
+		clearable(&payload.display_name).and_then(|value| validate_text("display_name", &value)),
+		clearable(&payload.avatar_url).and_then(|value| validate_avatar_url(&value)),
+		payload.locale.as_deref().and_then(validate_locale),
+		payload.timezone.as_deref().and_then(validate_timezone),
+		payload.currency.as_deref().and_then(validate_currency),
+		clearable(&payload.org_number).and_then(|value| validate_reference("org_number", &value, 20)),
+		clearable(&payload.vat_number).and_then(|value| validate_reference("vat_number", &value, 20)),
+		clearable(&payload.bank_account).and_then(|value| validate_bank_account(&value)),
+		clearable(&payload.address_line1).and_then(|value| validate_text("address_line1", &value)),
+		clearable(&payload.address_line2).and_then(|value| validate_text("address_line2", &value)),
+		clearable(&payload.postal_code).and_then(|value| validate_reference("postal_code", &value, 12)),
+		clearable(&payload.city).and_then(|value| validate_text("city", &value)),
+		clearable(&payload.country).and_then(|value| validate_country(&value)),
+	])?;
+
+	Ok(payload)
+}
+
+fn profile_error(field: &str, message: impl Into<String>) -> Option<FieldError> {
+	Some(FieldError::new(field, ErrorCode::ValidationFailed, message))
+}
+
+fn validate_text(field: &str, value: &str) -> Option<FieldError> {
+	match value.chars().count() > MAX_TEXT_LEN {
+		true => profile_error(field, format!("Must be at most {} characters", MAX_TEXT_LEN)),
+		false => None,
+	}
+}
+
+fn validate_avatar_url(url: &str) -> Option<FieldError> {
+	let valid = url.len() <= MAX_URL_LEN
+		&& reqwest::Url::parse(url)
+			.is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.host().is_some());
+
+	match valid {
+		true => None,
+		false => profile_error(
+			"avatar_url",
+			format!("Must be an http or https URL of at most {} characters", MAX_URL_LEN),
+		),
+	}
+}
+
+/// A language with an optional script and region, e.g. `en`, `nb-NO` or `sr-Latn-RS`.
+fn validate_locale(locale: &str) -> Option<FieldError> {
+	let mut subtags = locale.split('-').peekable();
+	let language = subtags.next()
+		.is_some_and(|tag| (2..=3).contains(&tag.len()) && tag.chars().all(|c| c.is_ascii_lowercase()));
+	let script = subtags.next_if(|tag| tag.len() == 4)
+		.is_none_or(|tag| tag.chars().all(|c| c.is_ascii_alphabetic()) && tag.starts_with(|c: char| c.is_ascii_uppercase()));
+	let region = subtags.next()
+		.is_none_or(|tag| (tag.len() == 2 && tag.chars().all(|c| c.is_ascii_uppercase()))
+			|| (tag.len() == 3 && tag.chars().all(|c| c.is_ascii_digit())));
+
+	match language && script && region && subtags.next().is_none() {
+		true => None,
+		false => profile_error("locale", "Must be a language tag like en or nb-NO"),
+	}
+}
+
+fn validate_timezone(timezone: &str) -> Option<FieldError> {
+	match timezone.parse::<chrono_tz::Tz>() {
+		Ok(_) => None,
+		Err(_) => profile_error("timezone", "Must be an IANA time zone like Europe/Oslo"),
+	}
+}
+
+fn validate_currency(currency: &str) -> Option<FieldError> {
+	match currency.len() == 3 && currency.chars().all(|c| c.is_ascii_uppercase()) {
+		true => None,
+		false => profile_error("currency", "Must be an ISO 4217 currency code like EUR"),
+	}
+}
+
+fn validate_country(country: &str) -> Option<FieldError> {
+	match country.len() == 2 && country.chars().all(|c| c.is_ascii_uppercase()) {
+		true => None,
+		false => profile_error("country", "Must be an ISO 3166-1 alpha-2 country code like NO"),
+	}
+}
+
+/// Registration numbers and postal codes: letters and digits, optionally grouped by spaces, dashes or dots.
+fn validate_reference(field: &str, value: &str, max_len: usize) -> Option<FieldError> {
+	let valid = value.len() <= max_len
+		&& value.chars().any(|c| c.is_ascii_alphanumeric())
+		&& value.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, ' ' | '-' | '.'));
+
+	match valid {
+		true => None,
+		false => profile_error(
+			field,
+			format!("Must be at most {} letters and digits, optionally grouped by spaces, dashes or dots", max_len),
+		),
+	}
+}
+
+/// An IBAN when it starts with a country code, with its check digits verified; otherwise a domestic
+/// account number of digits, optionally grouped by spaces, dashes or dots.
+fn validate_bank_account(account: &str) -> Option<FieldError> {
+	let compact = account.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+	let is_iban = compact.starts_with(|c: char| c.is_ascii_alphabetic());
+	let valid = match is_iban {
+		true => is_valid_iban(&compact),
+		false => (4..=34).contains(&compact.len())
+			&& compact.chars().all(|c| c.is_ascii_digit() || matches!(c, '-' | '.')),
+	};
+
+	match (valid, is_iban) {
+		(true, _) => None,
+		(false, true) => profile_error("bank_account", "Not a valid IBAN"),
+		(false, false) => profile_error("bank_account", "Must be an IBAN or an account number of digits"),
+	}
+}
+
+/// Moves the country code and check digits to the end, reads letters as 10 to 35,
+/// and requires the resulting number to be 1 modulo 97.
+fn is_iban_checksum_valid(iban: &str) -> bool {
+	let (head, tail) = iban.split_at(4);
+	tail.chars().chain(head.chars())
+		.try_fold(0u32, |remainder, c| {
+			let digit = c.to_digit(36)?;
+			Some(match digit {
+				0..=9 => (remainder * 10 + digit) % 97,
+				_ => (remainder * 100 + digit) % 97,
+			})
+		})
+		== Some(1)
+}
+
+fn is_valid_iban(iban: &str) -> bool {
+	(15..=34).contains(&iban.len())
+		&& iban.chars().take(2).all(|c| c.is_ascii_uppercase())
+		&& iban.chars().skip(2).take(2).all(|c| c.is_ascii_digit())
+		&& iban.chars().all(|c| c.is_ascii_alphanumeric())
+		&& is_iban_checksum_valid(iban)
+}
+
+/// Applies a payload from `validate_profile`; empty strings clear optional fields.
+/// Takes the connection of the caller's transaction so the change and its audit event commit together.
+/// Returns the updated user, or a 412 if the user changed since the version in `if_match`.
+pub async fn update_user_profile(
+	conn: &mut PgConnection,
+	user_id: &Uuid,
+	payload: &UpdateUserPayload,
+	if_match: &IfMatch,
+) -> AppResult<User> {
+	let mut builder = QueryBuilder::<Postgres>::new("UPDATE users SET id = id");
+	let text_columns = [
+		("display_name", &payload.display_name),
+		("avatar_url", &payload.avatar_url),
+		("locale", &payload.locale),
+		("timezone", &payload.timezone),
+		("currency", &payload.currency),
+		("org_number", &payload.org_number),
+		("vat_number", &payload.vat_number),
+		("bank_account", &payload.bank_account),
+		("address_line1", &payload.address_line1),
+		("address_line2", &payload.address_line2),
+		("postal_code", &payload.postal_code),
+		("city", &payload.city),
+		("country", &payload.country),
+	];
+	for (column, value) in text_columns {
+		if let Some(value) = value {
+			builder.push(format_args!(", {} = ", column))
+				.push_bind(Some(value).filter(|value| !value.is_empty()));
+		}
+	}
+	if let Some(date_format) = payload.date_format {
+		builder.push(", date_format = ").push_bind(date_format);
+	}
+	if let Some(number_format) = payload.number_format {
+		builder.push(", number_format = ").push_bind(number_format);
+	}
+	builder.push(" WHERE id = ")
+		.push_bind(*user_id)
+		.push(" AND deleted_at IS NULL AND (")
+		.push_bind(if_match.versions())
+		.push("::bigint[] IS NULL OR version = ANY(")
+		.push_bind(if_match.versions())
+		.push(")) RETURNING *");
+
+	builder.build_query_as::<User>()
+		.fetch_optional(conn)
 		.await
-		.map(|user| user.into())
+		.map_err(|err| AppError::database("Failed to update profile", err))?
+		.ok_or_else(version_mismatch)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::models::user::DateFormat;
+
+	fn failed_fields(payload: UpdateUserPayload) -> Vec<String> {
+		match validate_profile(&payload) {
+			Ok(_) => vec![],
+			Err(err) => err.details().iter().map(|field| field.field.clone()).collect(),
+		}
+	}
+
+	#[test]
+	fn test_validate_profile_normalizes() {
+		let payload = validate_profile(&UpdateUserPayload {
+			display_name: Some("  Ada Lovelace ".into()),
+			currency: Some("nok".into()),
+			country: Some("no".into()),
+			bank_account: Some("no93 8601 1117 947".into()),
+			city: Some("   ".into()),
+			date_format: Some(DateFormat::DayMonthYear),
+			..Default::default()
+		}).unwrap();
+
+		assert_eq!(payload.display_name.as_deref(), Some("Ada Lovelace"));
+		assert_eq!(payload.currency.as_deref(), Some("NOK"));
+		assert_eq!(payload.country.as_deref(), Some("NO"));
+		assert_eq!(payload.bank_account.as_deref(), Some("NO93 8601 1117 947"));
+		assert_eq!(payload.city.as_deref(), Some(""), "blank clears");
+		assert_eq!(payload.date_format, Some(DateFormat::DayMonthYear));
+	}
+
+	#[test]
+	fn test_validate_profile_reports_every_field() {
+		let fields = failed_fields(UpdateUserPayload {
+			display_name: Some("x".repeat(MAX_TEXT_LEN + 1)),
+			avatar_url: Some("ftp://example.com/me.png".into()),
+			locale: Some("english".into()),
+			timezone: Some("Europe/Atlantis".into()),
+			currency: Some("EURO".into()),
+			bank_account: Some("NO94 8601 1117 947".into()),
+			postal_code: Some("#0150".into()),
+			country: Some("NOR".into()),
+			..Default::default()
+		});
+
+		assert_eq!(fields, vec![
+			"display_name", "avatar_url", "locale", "timezone", "currency", "bank_account", "postal_code", "country",
+		]);
+	}
+
+	#[test]
+	fn test_required_fields_cannot_be_cleared() {
+		let fields = failed_fields(UpdateUserPayload {
+			locale: Some(" ".into()),
+			timezone: Some("".into()),
+			currency: Some("".into()),
+			..Default::default()
+		});
+
+		assert_eq!(fields, vec!["locale", "timezone", "currency"]);
+	}
+
+	#[test]
+	fn test_validate_locale() {
+		for locale in ["en", "nb-NO", "sr-Latn-RS", "es-419", "haw"] {
+			assert!(validate_locale(locale).is_none(), "{}", locale);
+		}
+		for locale in ["", "EN", "nb_NO", "nb-no", "en-US-x", "e", "sr-latn-RS"] {
+			assert!(validate_locale(locale).is_some(), "{}", locale);
+		}
+	}
+
+	#[test]
+	fn test_validate_bank_account() {
+		for account in ["GB82 WEST 1234 5698 7654 32", "DE89370400440532013000", "1503.12.34567", "12345678"] {
+			assert!(validate_bank_account(account).is_none(), "{}", account);
+		}
+		for account in ["GB82 WEST 1234 5698 7654 33", "GB82", "123", "12ab34"] {
+			assert!(validate_bank_account(account).is_some(), "{}", account);
+		}
+	}
 }
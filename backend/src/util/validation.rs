@@ -1,66 +1,298 @@
-use crate::util::error::{AppError, AppResult};
-
-pub fn validate_password(password: &str) -> AppResult<()> {
-	let validators: Vec<fn(&str) -> AppResult<()>> = vec![
-		validate_password_length,
-		validate_password_uppercase,
-		validate_password_lowercase,
-		validate_password_digit,
-	];
+use std::path::Path;
+use sha1::{Digest, Sha1};
+use tracing::warn;
+use crate::{
+	config::PasswordPolicy,
+	util::error::{AppError, AppResult, ErrorCode, FieldError},
+};
+
+const MAX_EMAIL_LEN: usize = 254;
+const MAX_LOCAL_PART_LEN: usize = 64;
+/// Besides letters and digits, what RFC 5322 allows unquoted in the part before the `@`.
+const LOCAL_PART_SYMBOLS: &str = "!#$%&'*+/=?^_`{|}~-";
 
-	validators.iter()
-		.try_fold((), |_, validator| validator(password))
+/// Checks the syntax of an address and returns it as accounts store it: trimmed, with the domain lowercased
+/// and in its ASCII (IDNA) form. The part before the `@` keeps its case, but addresses are compared
+/// case-insensitively, so `Bob@Example.com` and `bob@example.com` are the same account.
+/// Quoted local parts and IP address domains are not accepted.
+pub fn normalize_email(field: &str, email: &str) -> AppResult<String> {
+	let invalid = || AppError::Validation(vec![
+		FieldError::new(field, ErrorCode::EmailInvalid, "Must be an email address like name@example.com"),
+	]);
+	let (local_part, domain) = email.trim().rsplit_once('@').ok_or_else(invalid)?;
+	let domain = idna::domain_to_ascii(domain).map_err(|_| invalid())?;
+	let email = format!("{}@{}", local_part, domain);
+
+	match is_valid_local_part(local_part) && is_valid_domain(&domain) && email.len() <= MAX_EMAIL_LEN {
+		true => Ok(email),
+		false => Err(invalid()),
+	}
 }
 
-fn validate_password_length(password: &str) -> AppResult<()> {
-	match password.len() {
-		0 => Err(AppError::BadRequest("Password cannot be empty".into())),
-		len if len < 8 => 
-			Err(AppError::BadRequest("Password must be at least 8 characters long".into())
-		),
-		len if len > 128 => Err(AppError::BadRequest("Password is too long".into())),
-		_ => Ok(()),
+/// Dot-separated atoms; letters may be non-ASCII, as internationalized mail allows.
+fn is_valid_local_part(local_part: &str) -> bool {
+	local_part.len() <= MAX_LOCAL_PART_LEN
+		&& local_part.split('.').all(|atom| {
+			!atom.is_empty() && atom.chars().all(|c| c.is_alphanumeric() || LOCAL_PART_SYMBOLS.contains(c))
+		})
+}
+
+/// At least two labels of letters, digits and inner hyphens, and a top-level domain that isn't a number.
+fn is_valid_domain(domain: &str) -> bool {
+	let labels = domain.split('.').collect::<Vec<_>>();
+	let is_valid_label = |label: &&str| (1..=63).contains(&label.len())
+		&& label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+		&& !label.starts_with('-')
+		&& !label.ends_with('-');
+
+	labels.len() >= 2
+		&& labels.iter().all(is_valid_label)
+		&& labels.last().is_some_and(|tld| !tld.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Runs every rule of `policy` and reports all failures at once, so a form can show them together.
+/// `user_inputs`, like the email address, make a password weaker when it contains them.
+/// The breach check looks the password up in local files only; nothing leaves the server.
+/// Both it and the strength estimate, which is quadratic in the length, only run on passwords of a valid length.
+pub async fn validate_password(policy: &PasswordPolicy, password: &str, user_inputs: &[&str]) -> AppResult<()> {
+	let length = validate_password_length(policy, password);
+	let length_ok = length.is_none();
+	let mut errors = vec![
+		length,
+		validate_password_class(policy.require_uppercase, password, |c| c.is_uppercase(),
+			ErrorCode::PasswordMissingUppercase, "Password must contain at least one uppercase letter"),
+		validate_password_class(policy.require_lowercase, password, |c| c.is_lowercase(),
+			ErrorCode::PasswordMissingLowercase, "Password must contain at least one lowercase letter"),
+		validate_password_class(policy.require_digit, password, |c| c.is_ascii_digit(),
+			ErrorCode::PasswordMissingDigit, "Password must contain at least one digit"),
+		validate_password_class(policy.require_symbol, password, |c| !c.is_alphanumeric(),
+			ErrorCode::PasswordMissingSymbol, "Password must contain at least one symbol or space"),
+		validate_password_repeats(policy, password),
+	];
+	if length_ok {
+		errors.push(validate_password_strength(policy, password, user_inputs));
+		if let Some(dir) = &policy.breached_dir
+			&& is_breached(dir, password).await?
+		{
+			errors.push(password_error(
+				ErrorCode::PasswordBreached,
+				"This password has appeared in a data breach; choose a different one",
+			));
+		}
 	}
+
+	validated(errors)
 }
 
-fn validate_password_uppercase(password: &str) -> AppResult<()> {
-	match password.chars().any(|c| c.is_uppercase()) {
+/// Collects the failed rules of several fields into one validation error, if there are any.
+pub fn validated(errors: impl IntoIterator<Item = Option<FieldError>>) -> AppResult<()> {
+	let errors = errors.into_iter().flatten().collect::<Vec<_>>();
+
+	match errors.is_empty() {
 		true => Ok(()),
-		false => Err(
-			AppError::BadRequest("Password must contain at least one uppercase letter".into())
-		),
+		false => Err(AppError::Validation(errors)),
 	}
 }
 
-fn validate_password_lowercase(password: &str) -> AppResult<()> {
-	match password.chars().any(|c| c.is_lowercase()) {
-		true => Ok(()),
-		false => Err(
-			AppError::BadRequest("Password must contain at least one lowercase letter".into())
+fn password_error(code: ErrorCode, message: &str) -> Option<FieldError> {
+	Some(FieldError::new("password", code, message))
+}
+
+fn validate_password_length(policy: &PasswordPolicy, password: &str) -> Option<FieldError> {
+	match password.chars().count() {
+		0 => password_error(ErrorCode::PasswordEmpty, "Password cannot be empty"),
+		len if len < policy.min_length => password_error(
+			ErrorCode::PasswordTooShort,
+			&format!("Password must be at least {} characters long", policy.min_length),
+		),
+		len if len > policy.max_length => password_error(
+			ErrorCode::PasswordTooLong,
+			&format!("Password must be at most {} characters long", policy.max_length),
 		),
+		_ => None,
 	}
 }
 
-fn validate_password_digit(password: &str) -> AppResult<()> {
-	match password.chars().any(|c| c.is_digit(10)) {
-		true => Ok(()),
-		false => Err(
-			AppError::BadRequest("Password must contain at least one digit".into())
+fn validate_password_class(
+	required: bool,
+	password: &str,
+	is_member: fn(char) -> bool,
+	code: ErrorCode,
+	message: &str,
+) -> Option<FieldError> {
+	match !required || password.chars().any(is_member) {
+		true => None,
+		false => password_error(code, message),
+	}
+}
+
+fn validate_password_repeats(policy: &PasswordPolicy, password: &str) -> Option<FieldError> {
+	let max = policy.max_repeats?;
+	let chars = password.chars().collect::<Vec<_>>();
+	let longest = chars.chunk_by(|a, b| a == b).map(<[char]>::len).max().unwrap_or(0);
+
+	match longest > max {
+		true => password_error(
+			ErrorCode::PasswordTooManyRepeats,
+			&format!("Password cannot repeat a character more than {} times in a row", max),
 		),
+		false => None,
+	}
+}
+
+fn validate_password_strength(policy: &PasswordPolicy, password: &str, user_inputs: &[&str]) -> Option<FieldError> {
+	let strength = zxcvbn::zxcvbn(password, user_inputs);
+	if u8::from(strength.score()) >= policy.min_strength {
+		return None;
 	}
+
+	let feedback: Vec<String> = strength.feedback()
+		.map(|feedback| {
+			let suggestions = feedback.suggestions().iter().map(ToString::to_string);
+			feedback.warning().map(|warning| warning.to_string()).into_iter().chain(suggestions).collect()
+		})
+		.unwrap_or_default();
+	password_error(
+		ErrorCode::PasswordTooWeak,
+		format!("Password is too easy to guess. {}", feedback.join(" ")).trim_end(),
+	)
+}
+
+/// Looks up the SHA-1 of the password in the range file of its first five hex digits, the format of
+/// k-anonymity breach APIs, so the files can be downloaded once and kept up to date offline.
+/// Lines with a count of 0 are padding. A missing range file counts as no match.
+async fn is_breached(dir: &Path, password: &str) -> AppResult<bool> {
+	let hash = hex::encode_upper(Sha1::digest(password.as_bytes()));
+	let (prefix, suffix) = hash.split_at(5);
+	let path = dir.join(format!("{}.txt", prefix));
+
+	let ranges = match tokio::fs::read_to_string(&path).await {
+		Ok(ranges) => ranges,
+		Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+			warn!(path = %path.display(), "Breached password range file is missing");
+			return Ok(false);
+		}
+		Err(err) => return Err(AppError::internal_from("Failed to read breached password range", err)),
+	};
+
+	Ok(ranges.lines()
+		.filter_map(|line| line.trim().split_once(':'))
+		.any(|(line_suffix, count)| line_suffix.eq_ignore_ascii_case(suffix) && count.trim() != "0"))
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
 
+	async fn codes(policy: &PasswordPolicy, password: &str) -> Vec<ErrorCode> {
+		match validate_password(policy, password, &["bob.smith@example.com"]).await {
+			Ok(()) => vec![],
+			Err(err) => err.details().iter().map(|field| field.code).collect(),
+		}
+	}
+
+	#[tokio::test]
+	async fn test_validate_password() {
+		let policy = PasswordPolicy::default();
+
+		assert!(!codes(&policy, "Valid1").await.is_empty());
+		assert!(!codes(&policy, "validpassword").await.is_empty());
+		assert!(!codes(&policy, "VALIDPASSWORD").await.is_empty());
+		assert!(!codes(&policy, "ValidPassword").await.is_empty());
+		assert_eq!(codes(&policy, "Valid1Password").await, vec![]);
+		assert_eq!(codes(&policy, "Valid1Password!").await, vec![]);
+	}
+
+	#[tokio::test]
+	async fn test_validate_password_reports_every_rule() {
+		assert_eq!(codes(&PasswordPolicy::default(), "short").await, vec![
+			ErrorCode::PasswordTooShort,
+			ErrorCode::PasswordMissingUppercase,
+			ErrorCode::PasswordMissingDigit,
+		]);
+	}
+
+	#[tokio::test]
+	async fn test_validate_password_skips_estimate_when_too_long() {
+		// Near the request body limit; estimating its strength would take hours.
+		let password = "Aa1".repeat(700_000);
+		let started = std::time::Instant::now();
+		assert_eq!(codes(&PasswordPolicy::default(), &password).await, vec![ErrorCode::PasswordTooLong]);
+		assert!(started.elapsed() < std::time::Duration::from_secs(5));
+	}
+
+	#[tokio::test]
+	async fn test_validate_password_follows_policy() {
+		let policy = PasswordPolicy { require_symbol: true, max_repeats: Some(2), ..PasswordPolicy::default() };
+		assert_eq!(codes(&policy, "Valid1Password").await, vec![ErrorCode::PasswordMissingSymbol]);
+		assert_eq!(codes(&policy, "Vaaalid1Password!").await, vec![ErrorCode::PasswordTooManyRepeats]);
+
+		let policy = PasswordPolicy { min_length: 4, max_length: 10, ..PasswordPolicy::default() };
+		assert_eq!(codes(&policy, "Valid1Password").await, vec![ErrorCode::PasswordTooLong]);
+
+		let lenient = PasswordPolicy {
+			require_uppercase: false,
+			require_digit: false,
+			min_strength: 0,
+			..PasswordPolicy::default()
+		};
+		assert_eq!(codes(&lenient, "password").await, vec![]);
+		assert_eq!(codes(&PasswordPolicy::default(), "Password1").await, vec![ErrorCode::PasswordTooWeak]);
+	}
+
+	#[tokio::test]
+	async fn test_validate_password_penalizes_user_inputs() {
+		let policy = PasswordPolicy::default();
+		assert!(validate_password(&policy, "Kvalmgrus1", &[]).await.is_ok());
+		let err = validate_password(&policy, "Kvalmgrus1", &["ola@example.com", "Kvalmgrus"]).await.unwrap_err();
+		assert_eq!(err.details().iter().map(|field| field.code).collect::<Vec<_>>(), vec![ErrorCode::PasswordTooWeak]);
+	}
+
+	#[tokio::test]
+	async fn test_validate_password_counts_characters() {
+		// 7 characters, but 14 bytes.
+		assert!(codes(&PasswordPolicy::default(), "Åøæ1Ŋħß").await.contains(&ErrorCode::PasswordTooShort));
+		let policy = PasswordPolicy { max_length: 12, ..PasswordPolicy::default() };
+		assert_eq!(codes(&policy, "Blåbærsyltø9").await, vec![]);
+	}
+
+	#[tokio::test]
+	async fn test_validate_password_rejects_breached() {
+		let dir = std::env::temp_dir().join(format!("breached-{}", uuid::Uuid::new_v4()));
+		std::fs::create_dir(&dir).unwrap();
+		let hash = hex::encode_upper(Sha1::digest(b"Valid1Password"));
+		let (prefix, suffix) = hash.split_at(5);
+		std::fs::write(
+			dir.join(format!("{}.txt", prefix)),
+			format!("0005AD76BD555C1D6D771DE417A4B87E4B4:0\r\n{}:12\r\n", suffix.to_lowercase()),
+		).unwrap();
+		let padded = hex::encode_upper(Sha1::digest(b"Other1Password"));
+		std::fs::write(dir.join(format!("{}.txt", &padded[..5])), format!("{}:0\n", &padded[5..])).unwrap();
+
+		let policy = PasswordPolicy { breached_dir: Some(dir.clone()), ..PasswordPolicy::default() };
+		assert_eq!(codes(&policy, "Valid1Password").await, vec![ErrorCode::PasswordBreached]);
+		assert_eq!(codes(&policy, "Other1Password").await, vec![], "padding lines don't count");
+		assert_eq!(codes(&policy, "Valid1Password!").await, vec![], "no range file, no match");
+
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
 	#[test]
-	fn test_validate_password() {
-		assert!(validate_password("Valid1").is_err());
-		assert!(validate_password("validpassword").is_err());
-		assert!(validate_password("VALIDPASSWORD").is_err());
-		assert!(validate_password("ValidPassword").is_err());
-		assert!(validate_password("Valid1Password").is_ok());
-		assert!(validate_password("Valid1Password!").is_ok());
+	fn test_normalize_email() {
+		let normalize = |email: &str| normalize_email("email", email).ok();
+
+		assert_eq!(normalize("  Bob@Example.COM "), Some("Bob@example.com".into()));
+		assert_eq!(normalize("first.last+tag@sub.example.co.uk"), Some("first.last+tag@sub.example.co.uk".into()));
+		assert_eq!(normalize("ola@Blåbær.NO"), Some("ola@xn--blbr-roah.no".into()));
+		assert_eq!(normalize("jørgen@example.no"), Some("jørgen@example.no".into()));
+
+		for email in [
+			"", "bob", "@example.com", "bob@", "bob@localhost", "bob@example.123", "bob@@example.com",
+			".bob@example.com", "bob.@example.com", "bo..b@example.com", "bob smith@example.com",
+			"\"bob\"@example.com", "bob@-example.com", "bob@exa_mple.com", "bob@[127.0.0.1]",
+		] {
+			assert_eq!(normalize(email), None, "{}", email);
+		}
+		assert_eq!(normalize(&format!("{}@example.com", "a".repeat(MAX_LOCAL_PART_LEN + 1))), None);
 	}
 }
@@ -0,0 +1,702 @@
+use std::{
+	error::Error,
+	fmt,
+	net::{IpAddr, SocketAddr},
+	sync::Arc,
+	time::Duration,
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::{dns::{Addrs, Name, Resolve, Resolving}, Url};
+use serde_json::json;
+use sha2::Sha256;
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
+use tokio::task::JoinSet;
+use tracing::info;
+use uuid::Uuid;
+use crate::{
+	config::Config,
+	models::webhook::{
+		CreateWebhookPayload, CreatedWebhook, DeliveryFilter, UpdateWebhookPayload, Webhook,
+		WebhookDelivery, WebhookEventType, WebhookFilter, WebhookScope,
+	},
+	util::{
+		error::{AppError, AppResult, ErrorCode, FieldError},
+		pagination::{ListQuery, Page},
+		tenant,
+		validation::validated,
+	},
+};
+
+const WEBHOOK_COLUMNS: &str = "x.id, x.workspace_id, x.url, x.events, x.description, x.consecutive_failures, x.disabled_at, x.created_at";
+const DELIVERY_COLUMNS: &str = "x.id, x.webhook_id, x.event_type, x.data, x.status, x.attempts, x.next_attempt_at, \
+	x.response_status, x.error, x.created_at, x.delivered_at";
+const MAX_URL_LEN: usize = 2048;
+/// Attempts per delivery before it is marked as failed, about a day's worth with the backoff below.
+pub const MAX_ATTEMPTS: i32 = 12;
+/// Failed attempts in a row, across deliveries, after which the webhook is disabled.
+pub const DISABLE_AFTER_FAILURES: i32 = 30;
+const FIRST_RETRY: Duration = Duration::from_secs(30);
+const MAX_RETRY: Duration = Duration::from_secs(6 * 60 * 60);
+/// How long a claimed delivery is left alone by other replicas; must outlast `webhook_timeout`.
+const LEASE: Duration = Duration::from_secs(5 * 60);
+/// Deliveries one replica claims per poll.
+const BATCH_SIZE: i64 = 20;
+
+pub static SIGNATURE_HEADER: &str = "Kvitter-Signature";
+pub static EVENT_HEADER: &str = "Kvitter-Event";
+pub static DELIVERY_HEADER: &str = "Kvitter-Delivery";
+
+/// A claimed delivery together with where and how to send it.
+#[derive(sqlx::FromRow)]
+struct Due {
+	id: Uuid,
+	webhook_id: Uuid,
+	event_type: WebhookEventType,
+	data: serde_json::Value,
+	created_at: chrono::DateTime<Utc>,
+	url: String,
+	secret: String,
+}
+
+/// The HTTP client deliveries go out with.
+#[derive(Clone)]
+pub struct WebhookClient {
+	http: reqwest::Client,
+	allow_private: bool,
+}
+
+/// How one attempt went: the receiver's status code, if it answered, and what went wrong, if anything.
+struct Attempt {
+	status: Option<u16>,
+	error: Option<String>,
+}
+
+/// Refusal to connect to an address that isn't public.
+#[derive(Debug)]
+struct PrivateTarget;
+
+impl fmt::Display for PrivateTarget {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("Webhook URLs must point to a public address")
+	}
+}
+
+impl Error for PrivateTarget {}
+
+/// Whether `ip` is reachable from the internet at large. Loopback, private, link-local (where the cloud
+/// metadata endpoints live), shared and reserved ranges are not, and webhooks must not reach into them.
+fn is_public(ip: IpAddr) -> bool {
+	match ip {
+		IpAddr::V4(ip) => {
+			let [a, b, c, _] = ip.octets();
+			!(ip.is_unspecified()
+				|| ip.is_loopback()
+				|| ip.is_private()
+				|| ip.is_link_local()
+				|| ip.is_broadcast()
+				|| ip.is_documentation()
+				|| ip.is_multicast()
+				|| a == 0
+				|| a >= 240
+				// Shared address space behind carrier-grade NAT, 100.64.0.0/10.
+				|| a == 100 && (b & 0xc0) == 64
+				// Protocol assignments, 192.0.0.0/24, and benchmarking, 198.18.0.0/15.
+				|| a == 192 && b == 0 && c == 0
+				|| a == 198 && (b & 0xfe) == 18)
+		}
+		IpAddr::V6(ip) => {
+			if let Some(ip) = ip.to_ipv4() {
+				return is_public(IpAddr::V4(ip));
+			}
+			let [first, second, ..] = ip.segments();
+			!(ip.is_unspecified()
+				|| ip.is_multicast()
+				// Unique local, fc00::/7, and link-local, fe80::/10.
+				|| (first & 0xfe00) == 0xfc00
+				|| (first & 0xffc0) == 0xfe80
+				// NAT64, 64:ff9b::/96, which embeds IPv4 addresses, and documentation, 2001:db8::/32.
+				|| first == 0x64 && second == 0xff9b
+				|| first == 0x2001 && second == 0xdb8)
+		}
+	}
+}
+
+/// Resolves webhook hosts for the HTTP client and fails for names with any address that isn't public.
+/// It runs for every connection, so a name can't pass at registration and point inwards later.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+	fn resolve(&self, name: Name) -> Resolving {
+		Box::pin(async move {
+			let addrs = tokio::net::lookup_host((name.as_str(), 0)).await?.collect::<Vec<SocketAddr>>();
+			match addrs.iter().all(|addr| is_public(addr.ip())) {
+				true => Ok(Box::new(addrs.into_iter()) as Addrs),
+				false => Err(Box::new(PrivateTarget) as Box<dyn Error + Send + Sync>),
+			}
+		})
+	}
+}
+
+/// The host of `url` if it is an address rather than a name.
+fn literal_ip(url: &Url) -> Option<IpAddr> {
+	url.host_str()?.trim_start_matches('[').trim_end_matches(']').parse().ok()
+}
+
+fn validate_url(url: &str) -> Option<FieldError> {
+	let valid = url.len() <= MAX_URL_LEN
+		&& Url::parse(url)
+			.is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.host().is_some());
+
+	match valid {
+		true => None,
+		false => Some(FieldError::new(
+			"url",
+			ErrorCode::ValidationFailed,
+			format!("Must be an http or https URL of at most {} characters", MAX_URL_LEN),
+		)),
+	}
+}
+
+/// `validate_url`, and unless private targets are allowed, whether the host resolves to public addresses
+/// only. Names that don't resolve are let through: every delivery resolves them again.
+async fn validate_target(url: &str, allow_private: bool) -> Option<FieldError> {
+	if let Some(error) = validate_url(url) {
+		return Some(error);
+	}
+	if allow_private {
+		return None;
+	}
+
+	let url = Url::parse(url).ok()?;
+	let public = match literal_ip(&url) {
+		Some(ip) => is_public(ip),
+		None => tokio::net::lookup_host((url.host_str()?, 0)).await
+			.map_or(true, |mut addrs| addrs.all(|addr| is_public(addr.ip()))),
+	};
+
+	match public {
+		true => None,
+		false => Some(FieldError::new("url", ErrorCode::ValidationFailed, PrivateTarget.to_string())),
+	}
+}
+
+fn validate_events(events: &[WebhookEventType], scope: WebhookScope) -> Option<FieldError> {
+	if events.is_empty() || events.contains(&WebhookEventType::Ping) {
+		return Some(FieldError::new(
+			"events",
+			ErrorCode::ValidationFailed,
+			"Subscribe to at least one event; ping is only sent by the test endpoint",
+		));
+	}
+	match scope == WebhookScope::Workspace && events.contains(&WebhookEventType::UserSignedUp) {
+		true => Some(FieldError::new("events", ErrorCode::ValidationFailed, "A workspace's webhook only hears of its projects")),
+		false => None,
+	}
+}
+
+/// Narrows `builder` to the webhooks the user may manage: their own, and the active workspace's if they
+/// are an owner or admin of it.
+fn push_manageable(builder: &mut QueryBuilder<Postgres>, user_id: &Uuid, workspace_id: &Uuid) {
+	builder.push("((x.workspace_id IS NULL AND x.user_id = ")
+		.push_bind(*user_id)
+		.push(") OR (x.workspace_id = ")
+		.push_bind(*workspace_id)
+		.push(" AND EXISTS (SELECT 1 FROM workspace_members m WHERE m.workspace_id = x.workspace_id AND m.user_id = ")
+		.push_bind(*user_id)
+		.push(" AND m.role <> 'MEMBER')))");
+}
+
+fn not_found() -> AppError {
+	AppError::NotFound(ErrorCode::NotFound, "Webhook not found".into())
+}
+
+/// `t=<unix seconds>,v1=<hex HMAC-SHA256 of "<t>.<body>">`. Receivers should recompute it with their secret
+/// and reject old timestamps to stop replays.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+	let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+	mac.update(timestamp.to_string().as_bytes());
+	mac.update(b".");
+	mac.update(body);
+	format!("t={},v1={}", timestamp, hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Doubles from `FIRST_RETRY` with every failed attempt, up to `MAX_RETRY`.
+fn backoff(attempts: i32) -> Duration {
+	let exponent = attempts.saturating_sub(1).clamp(0, 20) as u32;
+	FIRST_RETRY.saturating_mul(2u32.pow(exponent)).min(MAX_RETRY)
+}
+
+pub fn http_client(config: &Config) -> AppResult<WebhookClient> {
+	let allow_private = config.webhook_allow_private_targets;
+	let mut builder = reqwest::Client::builder()
+		.timeout(config.webhook_timeout)
+		// A redirect could point the signed payload anywhere.
+		.redirect(reqwest::redirect::Policy::none())
+		// A proxy would resolve the host itself, past `PublicResolver`.
+		.no_proxy()
+		.user_agent("Kvitter-Webhooks/1");
+	if !allow_private {
+		builder = builder.dns_resolver(Arc::new(PublicResolver));
+	}
+
+	let http = builder.build()
+		.map_err(|err| AppError::internal_from("Failed to build webhook client", err))?;
+	Ok(WebhookClient { http, allow_private })
+}
+
+/// The user's own webhooks and those of the active workspace, if they manage it.
+pub async fn list_webhooks(
+	pool: &PgPool,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	query: &ListQuery<WebhookFilter>,
+) -> AppResult<Page<Webhook>> {
+	let mut tx = tenant::begin(pool, user_id, workspace_id).await?;
+	let mut builder = QueryBuilder::new(format!("SELECT {} FROM webhooks x WHERE ", WEBHOOK_COLUMNS));
+	push_manageable(&mut builder, user_id, workspace_id);
+	query.push_page(&mut builder, "x.id");
+
+	let rows = builder.build_query_as::<Webhook>()
+		.fetch_all(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to list webhooks", err))?;
+
+	Ok(query.paginate(rows))
+}
+
+pub async fn create_webhook(
+	pool: &PgPool,
+	config: &Config,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	payload: &CreateWebhookPayload,
+) -> AppResult<CreatedWebhook> {
+	let scope = payload.scope.unwrap_or_default();
+	validated([
+		validate_target(&payload.url, config.webhook_allow_private_targets).await,
+		validate_events(&payload.events, scope),
+	])?;
+
+	let mut tx = tenant::begin(pool, user_id, workspace_id).await?;
+	let owner = match scope {
+		WebhookScope::User => None,
+		WebhookScope::Workspace => {
+			let manages = sqlx::query_scalar::<_, bool>(
+				"SELECT EXISTS (SELECT 1 FROM workspace_members WHERE user_id = $1 AND workspace_id = $2 AND role <> 'MEMBER')"
+			)
+				.bind(user_id)
+				.bind(workspace_id)
+				.fetch_one(&mut *tx)
+				.await
+				.map_err(|err| AppError::database("Failed to check workspace role", err))?;
+			if !manages {
+				return Err(AppError::Forbidden(ErrorCode::Forbidden, "Only owners and admins can add a workspace's webhooks".into()));
+			}
+			Some(*workspace_id)
+		}
+	};
+
+	let secret = format!("whsec_{}", hex::encode(rand::random::<[u8; 32]>()));
+	let webhook = sqlx::query_as::<_, Webhook>(&format!(
+		"INSERT INTO webhooks AS x (user_id, workspace_id, url, secret, events, description) \
+		VALUES ($1, $2, $3, $4, $5, $6) RETURNING {}",
+		WEBHOOK_COLUMNS,
+	))
+		.bind(user_id)
+		.bind(owner)
+		.bind(&payload.url)
+		.bind(&secret)
+		.bind(&payload.events)
+		.bind(&payload.description)
+		.fetch_one(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to create webhook", err))?;
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to create webhook", err))?;
+
+	Ok(CreatedWebhook { webhook, secret })
+}
+
+pub async fn update_webhook(
+	pool: &PgPool,
+	config: &Config,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	webhook_id: &Uuid,
+	payload: &UpdateWebhookPayload,
+) -> AppResult<Webhook> {
+	let url_error = match &payload.url {
+		Some(url) => validate_target(url, config.webhook_allow_private_targets).await,
+		None => None,
+	};
+	validated([
+		url_error,
+		payload.events.as_deref().and_then(|events| validate_events(events, WebhookScope::User)),
+	])?;
+
+	let mut tx = tenant::begin(pool, user_id, workspace_id).await?;
+	let scope = match manageable_workspace(&mut tx, user_id, workspace_id, webhook_id).await? {
+		Some(_) => WebhookScope::Workspace,
+		None => WebhookScope::User,
+	};
+	validated([payload.events.as_deref().and_then(|events| validate_events(events, scope))])?;
+
+	let mut builder = QueryBuilder::<Postgres>::new("UPDATE webhooks x SET id = x.id");
+	if let Some(url) = &payload.url {
+		builder.push(", url = ").push_bind(url);
+	}
+	if let Some(events) = &payload.events {
+		builder.push(", events = ").push_bind(events);
+	}
+	if let Some(description) = &payload.description {
+		builder.push(", description = ").push_bind(description);
+	}
+	match payload.enabled {
+		Some(true) => builder.push(", disabled_at = NULL, consecutive_failures = 0"),
+		Some(false) => builder.push(", disabled_at = COALESCE(x.disabled_at, NOW())"),
+		None => &mut builder,
+	};
+	builder.push(" WHERE x.id = ")
+		.push_bind(*webhook_id)
+		.push(format_args!(" RETURNING {}", WEBHOOK_COLUMNS));
+
+	let webhook = builder.build_query_as::<Webhook>()
+		.fetch_optional(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to update webhook", err))?
+		.ok_or_else(not_found)?;
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to update webhook", err))?;
+
+	Ok(webhook)
+}
+
+pub async fn delete_webhook(pool: &PgPool, user_id: &Uuid, workspace_id: &Uuid, webhook_id: &Uuid) -> AppResult<()> {
+	let mut tx = tenant::begin(pool, user_id, workspace_id).await?;
+	let mut builder = QueryBuilder::<Postgres>::new("DELETE FROM webhooks x WHERE x.id = ");
+	builder.push_bind(*webhook_id).push(" AND ");
+	push_manageable(&mut builder, user_id, workspace_id);
+
+	let deleted = builder.build()
+		.execute(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to delete webhook", err))?;
+	if deleted.rows_affected() == 0 {
+		return Err(not_found());
+	}
+
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to delete webhook", err))
+}
+
+/// The workspace of a webhook the user may manage, `None` for one of their own; 404 for any other.
+async fn manageable_workspace(
+	conn: &mut PgConnection,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	webhook_id: &Uuid,
+) -> AppResult<Option<Uuid>> {
+	let mut builder = QueryBuilder::<Postgres>::new("SELECT x.workspace_id FROM webhooks x WHERE x.id = ");
+	builder.push_bind(*webhook_id).push(" AND ");
+	push_manageable(&mut builder, user_id, workspace_id);
+
+	builder.build_query_scalar::<Option<Uuid>>()
+		.fetch_optional(conn)
+		.await
+		.map_err(|err| AppError::database("Failed to fetch webhook", err))?
+		.ok_or_else(not_found)
+}
+
+pub async fn list_deliveries(
+	pool: &PgPool,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	webhook_id: &Uuid,
+	query: &ListQuery<DeliveryFilter>,
+) -> AppResult<Page<WebhookDelivery>> {
+	let mut tx = tenant::begin(pool, user_id, workspace_id).await?;
+	manageable_workspace(&mut tx, user_id, workspace_id, webhook_id).await?;
+
+	let mut builder = QueryBuilder::new(format!("SELECT {} FROM webhook_deliveries x WHERE x.webhook_id = ", DELIVERY_COLUMNS));
+	builder.push_bind(*webhook_id);
+	if let Some(status) = query.filter.status {
+		builder.push(" AND x.status = ").push_bind(status);
+	}
+	query.push_page(&mut builder, "x.id");
+
+	let rows = builder.build_query_as::<WebhookDelivery>()
+		.fetch_all(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to list webhook deliveries", err))?;
+
+	Ok(query.paginate(rows))
+}
+
+/// Queues a `ping` and sends it right away, even to a disabled webhook, so the receiver can be checked
+/// before turning it back on. Returns the delivery as it stands after the attempt.
+pub async fn send_test_event(
+	pool: &PgPool,
+	client: &WebhookClient,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	webhook_id: &Uuid,
+) -> AppResult<WebhookDelivery> {
+	let mut tx = tenant::begin(pool, user_id, workspace_id).await?;
+	manageable_workspace(&mut tx, user_id, workspace_id, webhook_id).await?;
+
+	let due = sqlx::query_as::<_, Due>(
+		"WITH queued AS ( \
+			INSERT INTO webhook_deliveries (webhook_id, event_type, data, next_attempt_at) \
+			VALUES ($1, 'ping', jsonb_build_object('webhook_id', $1), NOW() + make_interval(secs => $2)) \
+			RETURNING * \
+		) \
+		SELECT q.id, q.webhook_id, q.event_type, q.data, q.created_at, w.url, w.secret \
+		FROM queued q JOIN webhooks w ON w.id = q.webhook_id"
+	)
+		.bind(webhook_id)
+		.bind(LEASE.as_secs_f64())
+		.fetch_one(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to queue test event", err))?;
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to queue test event", err))?;
+
+	let id = due.id;
+	let attempt = deliver(client, &due).await;
+	record(pool, &due, attempt).await?;
+
+	sqlx::query_as::<_, WebhookDelivery>(&format!("SELECT {} FROM webhook_deliveries x WHERE x.id = $1", DELIVERY_COLUMNS))
+		.bind(id)
+		.fetch_one(pool)
+		.await
+		.map_err(|err| AppError::database("Failed to fetch webhook delivery", err))
+}
+
+/// Whether `url` names a host `client` may connect to. Only addresses written into the URL are checked
+/// here; the client resolves names with `PublicResolver`.
+fn may_reach(client: &WebhookClient, url: &str) -> bool {
+	client.allow_private || Url::parse(url).ok().and_then(|url| literal_ip(&url)).is_none_or(is_public)
+}
+
+/// What went wrong sending a delivery, without the URL, which the webhook's owner knows anyway.
+fn describe(err: reqwest::Error) -> String {
+	let mut source = err.source();
+	while let Some(cause) = source {
+		if cause.is::<PrivateTarget>() {
+			return cause.to_string();
+		}
+		source = cause.source();
+	}
+	err.without_url().to_string()
+}
+
+async fn deliver(client: &WebhookClient, due: &Due) -> Attempt {
+	if !may_reach(client, &due.url) {
+		return Attempt { status: None, error: Some(PrivateTarget.to_string()) };
+	}
+
+	let body = json!({
+		"id": due.id,
+		"type": due.event_type,
+		"created_at": due.created_at,
+		"data": due.data,
+	}).to_string();
+	let signature = sign(&due.secret, Utc::now().timestamp(), body.as_bytes());
+	let event_type = serde_json::to_value(due.event_type).ok()
+		.and_then(|value| value.as_str().map(str::to_owned))
+		.unwrap_or_default();
+
+	let response = client.http.post(&due.url)
+		.header(reqwest::header::CONTENT_TYPE, "application/json")
+		.header(SIGNATURE_HEADER, signature)
+		.header(EVENT_HEADER, event_type)
+		.header(DELIVERY_HEADER, due.id.to_string())
+		.body(body)
+		.send()
+		.await;
+
+	// The body of the answer is never read: it would show the webhook's owner whatever the receiver said.
+	match response {
+		Ok(response) if response.status().is_success() => Attempt { status: Some(response.status().as_u16()), error: None },
+		Ok(response) => Attempt {
+			status: Some(response.status().as_u16()),
+			error: Some(format!("Receiver answered {}", response.status())),
+		},
+		Err(err) => Attempt { status: None, error: Some(describe(err)) },
+	}
+}
+
+/// Stores the outcome of an attempt and schedules the next one. Failures count against the webhook,
+/// which is disabled once `DISABLE_AFTER_FAILURES` attempts in a row failed; a success clears the count.
+async fn record(pool: &PgPool, due: &Due, attempt: Attempt) -> AppResult<()> {
+	// The webhook may belong to any workspace.
+	let mut tx = tenant::bypass(pool).await?;
+
+	let attempts = sqlx::query_scalar::<_, i32>(
+		"UPDATE webhook_deliveries SET attempts = attempts + 1, response_status = $2, error = $3, \
+			status = CASE WHEN $3 IS NULL THEN 'succeeded' WHEN attempts + 1 >= $4 THEN 'failed' ELSE 'pending' END, \
+			delivered_at = CASE WHEN $3 IS NULL THEN NOW() END \
+		WHERE id = $1 RETURNING attempts"
+	)
+		.bind(due.id)
+		.bind(attempt.status.map(|status| status as i16))
+		.bind(&attempt.error)
+		.bind(MAX_ATTEMPTS)
+		.fetch_one(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to record webhook delivery", err))?;
+
+	match attempt.error {
+		None => {
+			sqlx::query("UPDATE webhooks SET consecutive_failures = 0 WHERE id = $1")
+				.bind(due.webhook_id)
+				.execute(&mut *tx)
+				.await
+				.map_err(|err| AppError::database("Failed to record webhook delivery", err))?;
+		}
+		Some(_) => {
+			sqlx::query("UPDATE webhook_deliveries SET next_attempt_at = NOW() + make_interval(secs => $2) WHERE id = $1")
+				.bind(due.id)
+				.bind(backoff(attempts).as_secs_f64())
+				.execute(&mut *tx)
+				.await
+				.map_err(|err| AppError::database("Failed to schedule webhook retry", err))?;
+			sqlx::query(
+				"UPDATE webhooks SET consecutive_failures = consecutive_failures + 1, \
+					disabled_at = CASE WHEN consecutive_failures + 1 >= $2 THEN COALESCE(disabled_at, NOW()) ELSE disabled_at END \
+				WHERE id = $1"
+			)
+				.bind(due.webhook_id)
+				.bind(DISABLE_AFTER_FAILURES)
+				.execute(&mut *tx)
+				.await
+				.map_err(|err| AppError::database("Failed to record webhook failure", err))?;
+		}
+	}
+
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to record webhook delivery", err))
+}
+
+/// Claims up to `BATCH_SIZE` due deliveries of enabled webhooks and sends them concurrently.
+/// Claiming pushes `next_attempt_at` past the attempt, so replicas polling at the same time skip them,
+/// and a replica that dies mid-attempt only delays the delivery. Returns how many were attempted.
+pub async fn dispatch_due(pool: &PgPool, client: &WebhookClient) -> AppResult<usize> {
+	let mut tx = tenant::bypass(pool).await?;
+	let claimed = sqlx::query_as::<_, Due>(
+		"WITH claimed AS ( \
+			UPDATE webhook_deliveries d SET next_attempt_at = NOW() + make_interval(secs => $2) \
+			WHERE d.id IN ( \
+				SELECT d.id FROM webhook_deliveries d JOIN webhooks w ON w.id = d.webhook_id \
+				WHERE d.status = 'pending' AND d.next_attempt_at <= NOW() AND w.disabled_at IS NULL \
+				ORDER BY d.next_attempt_at LIMIT $1 FOR UPDATE OF d SKIP LOCKED \
+			) RETURNING d.* \
+		) \
+		SELECT c.id, c.webhook_id, c.event_type, c.data, c.created_at, w.url, w.secret \
+		FROM claimed c JOIN webhooks w ON w.id = c.webhook_id"
+	)
+		.bind(BATCH_SIZE)
+		.bind(LEASE.as_secs_f64())
+		.fetch_all(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to claim webhook deliveries", err))?;
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to claim webhook deliveries", err))?;
+
+	let count = claimed.len();
+	let mut attempts = JoinSet::new();
+	for due in claimed {
+		let (pool, client) = (pool.clone(), client.clone());
+		attempts.spawn(async move {
+			let attempt = deliver(&client, &due).await;
+			record(&pool, &due, attempt).await
+		});
+	}
+	while let Some(done) = attempts.join_next().await {
+		match done {
+			Ok(Ok(())) => {}
+			Ok(Err(err)) => err.log(),
+			Err(err) => AppError::internal_from("Webhook delivery task failed", err).log(),
+		}
+	}
+
+	Ok(count)
+}
+
+/// Sends due deliveries every `webhook_poll_interval` for the lifetime of the process.
+pub fn spawn_dispatcher(pool: PgPool, config: Arc<Config>) -> AppResult<()> {
+	let client = http_client(&config)?;
+
+	tokio::spawn(async move {
+		let mut interval = tokio::time::interval(config.webhook_poll_interval);
+		loop {
+			interval.tick().await;
+
+			match dispatch_due(&pool, &client).await {
+				Ok(0) => {}
+				Ok(count) => info!(count, "Attempted webhook deliveries"),
+				Err(err) => err.log(),
+			}
+		}
+	});
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_signature_matches_known_vector() {
+		// echo -n '1700000000.{"a":1}' | openssl dgst -sha256 -hmac whsec_test
+		assert_eq!(
+			sign("whsec_test", 1_700_000_000, br#"{"a":1}"#),
+			"t=1700000000,v1=38877139021993b830af32feea6e18a8da83eb2f6e49ee50bd9e4cf4ca4d3789",
+		);
+	}
+
+	#[test]
+	fn test_backoff_doubles_up_to_the_cap() {
+		assert_eq!(backoff(1), Duration::from_secs(30));
+		assert_eq!(backoff(2), Duration::from_secs(60));
+		assert_eq!(backoff(5), Duration::from_secs(480));
+		assert_eq!(backoff(MAX_ATTEMPTS), MAX_RETRY);
+	}
+
+	#[test]
+	fn test_validate_url() {
+		assert!(validate_url("https://hooks.example.com/kvitter").is_none());
+		assert!(validate_url("http://localhost:8080/").is_none(), "where it points is checked separately");
+		assert!(validate_url("ftp://example.com/").is_some());
+		assert!(validate_url("not a url").is_some());
+		assert!(validate_url(&format!("https://example.com/{}", "a".repeat(MAX_URL_LEN))).is_some());
+	}
+
+	#[test]
+	fn test_is_public() {
+		for ip in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946", "100.128.0.1"] {
+			assert!(is_public(ip.parse().unwrap()), "{}", ip);
+		}
+		let private = [
+			"127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1", "0.0.0.0",
+			"255.255.255.255", "::1", "::", "fe80::1", "fd00:ec2::254", "::ffff:127.0.0.1", "::ffff:169.254.169.254",
+			"64:ff9b::a9fe:a9fe",
+		];
+		for ip in private {
+			assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+		}
+	}
+
+	#[tokio::test]
+	async fn test_validate_target() {
+		assert!(validate_target("http://127.0.0.1:8080/", false).await.is_some());
+		assert!(validate_target("http://[::1]/", false).await.is_some());
+		assert!(validate_target("http://localhost:8080/", false).await.is_some());
+		assert!(validate_target("http://169.254.169.254/latest/meta-data/", false).await.is_some());
+		assert!(validate_target("http://93.184.216.34/", false).await.is_none());
+		assert!(validate_target("http://localhost:8080/", true).await.is_none());
+		assert!(validate_target("ftp://localhost/", true).await.is_some());
+	}
+}
@@ -0,0 +1,478 @@
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+use crate::{
+	config::Config,
+	models::{
+		audit::{AuditAction, EntityType},
+		workspace::{
+			CreateInvitationPayload, CreateWorkspacePayload, Invitation, Workspace, WorkspaceMember, WorkspaceRole,
+		},
+	},
+	util::{
+		audit::{self, AuditContext},
+		error::{AppError, AppResult, ErrorCode, FieldError},
+		mailer::{self, SendEmail},
+		validation::normalize_email,
+	},
+};
+
+/// How long an invitation can be accepted.
+pub const INVITATION_TTL: Duration = Duration::days(7);
+const MAX_NAME_LEN: usize = 200;
+
+const WORKSPACE_COLUMNS: &str = "w.id, w.name, w.personal, m.role, w.created_at";
+const MEMBER_COLUMNS: &str = "m.user_id, u.email, m.role, m.joined_at";
+const INVITATION_COLUMNS: &str = "i.id, i.workspace_id, i.email, i.role, i.invited_by, i.created_at, i.expires_at";
+
+/// Body of the `me` CTE the visibility rules of other services read: the acting member's `id`,
+/// `workspace_id` and `role`. Empty when the user isn't a member, so every rule built on it matches nothing.
+pub fn member_cte(user_param: &str, workspace_param: &str) -> String {
+	format!(
+		"SELECT user_id AS id, workspace_id, role FROM workspace_members WHERE user_id = {} AND workspace_id = {}",
+		user_param,
+		workspace_param,
+	)
+}
+
+/// The project `p` belongs to the acting member's workspace. Expects a `me` CTE.
+pub const IN_WORKSPACE: &str = "p.workspace_id = me.workspace_id";
+
+/// The acting member may change project `p` and what belongs to it: they created it, or they are an
+/// admin or owner of its workspace. Expects a `me` CTE.
+pub const MANAGES_PROJECT: &str = "p.workspace_id = me.workspace_id AND (p.created_by = me.id OR me.role <> 'MEMBER')";
+
+fn not_found() -> AppError {
+	AppError::NotFound(ErrorCode::NotFound, "Workspace not found".into())
+}
+
+fn invitation_invalid() -> AppError {
+	AppError::NotFound(ErrorCode::InvitationInvalid, "Invitation is invalid, expired or already used".into())
+}
+
+fn hash_token(token: &str) -> String {
+	hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn validate_name(name: &str) -> AppResult<()> {
+	match name.trim().is_empty() || name.chars().count() > MAX_NAME_LEN {
+		true => Err(AppError::Validation(vec![FieldError::new(
+			"name",
+			ErrorCode::ValidationFailed,
+			format!("Must be 1 to {} characters", MAX_NAME_LEN),
+		)])),
+		false => Ok(()),
+	}
+}
+
+/// The workspace a request acts in: `requested` if given, else the personal one.
+/// `None` when the user isn't a member of it.
+pub async fn active_membership(
+	pool: &PgPool,
+	user_id: &Uuid,
+	requested: Option<Uuid>,
+) -> AppResult<Option<Uuid>> {
+	sqlx::query_scalar::<_, Uuid>(
+		"SELECT m.workspace_id FROM workspace_members m JOIN workspaces w ON w.id = m.workspace_id \
+		WHERE m.user_id = $1 AND CASE WHEN $2::uuid IS NULL THEN w.personal AND w.created_by = $1 \
+			ELSE m.workspace_id = $2 END"
+	)
+		.bind(user_id)
+		.bind(requested)
+		.fetch_optional(pool)
+		.await
+		.map_err(|err| AppError::database("Failed to check workspace membership", err))
+}
+
+/// Every member of the workspace with their role. With `lock`, the memberships stay locked until the
+/// transaction ends, so concurrent role changes can't both remove "the other" owner.
+async fn member_roles(conn: &mut PgConnection, workspace_id: &Uuid, lock: bool) -> AppResult<Vec<(Uuid, WorkspaceRole)>> {
+	let sql = match lock {
+		true => "SELECT user_id, role FROM workspace_members WHERE workspace_id = $1 FOR UPDATE",
+		false => "SELECT user_id, role FROM workspace_members WHERE workspace_id = $1",
+	};
+
+	sqlx::query_as::<_, (Uuid, WorkspaceRole)>(sql)
+		.bind(workspace_id)
+		.fetch_all(conn)
+		.await
+		.map_err(|err| AppError::database("Failed to fetch workspace members", err))
+}
+
+/// 404 unless the user belongs to the workspace, 403 unless their role is at least `role`.
+fn require_role(members: &[(Uuid, WorkspaceRole)], user_id: &Uuid, role: WorkspaceRole) -> AppResult<WorkspaceRole> {
+	match members.iter().find(|(member, _)| member == user_id) {
+		None => Err(not_found()),
+		Some((_, actual)) if *actual < role =>
+			Err(AppError::Forbidden(ErrorCode::Forbidden, "Your role in this workspace doesn't allow this".into())),
+		Some((_, actual)) => Ok(*actual),
+	}
+}
+
+/// Records a membership change as an update of the workspace.
+async fn record_member_change(
+	conn: &mut PgConnection,
+	context: &AuditContext,
+	workspace_id: &Uuid,
+	member_id: &Uuid,
+	before: Option<WorkspaceRole>,
+	after: Option<WorkspaceRole>,
+) -> AppResult<()> {
+	let key = format!("members.{}", member_id);
+	let changes = audit::diff(&json!({ &key: before }), &json!({ &key: after }));
+	audit::record(conn, context, EntityType::Workspace, *workspace_id, AuditAction::Updated, changes).await
+}
+
+/// Every workspace the user belongs to, personal first.
+pub async fn list_workspaces(pool: &PgPool, user_id: &Uuid) -> AppResult<Vec<Workspace>> {
+	sqlx::query_as::<_, Workspace>(&format!(
+		"SELECT {} FROM workspaces w JOIN workspace_members m ON m.workspace_id = w.id \
+		WHERE m.user_id = $1 ORDER BY w.personal DESC, w.name, w.id",
+		WORKSPACE_COLUMNS,
+	))
+		.bind(user_id)
+		.fetch_all(pool)
+		.await
+		.map_err(|err| AppError::database("Failed to list workspaces", err))
+}
+
+pub async fn fetch_workspace(pool: &PgPool, user_id: &Uuid, workspace_id: &Uuid) -> AppResult<Workspace> {
+	sqlx::query_as::<_, Workspace>(&format!(
+		"SELECT {} FROM workspaces w JOIN workspace_members m ON m.workspace_id = w.id \
+		WHERE w.id = $1 AND m.user_id = $2",
+		WORKSPACE_COLUMNS,
+	))
+		.bind(workspace_id)
+		.bind(user_id)
+		.fetch_optional(pool)
+		.await
+		.map_err(|err| AppError::database("Failed to fetch workspace", err))?
+		.ok_or_else(not_found)
+}
+
+/// A shared workspace with the user as its owner.
+pub async fn create_workspace(
+	pool: &PgPool,
+	user_id: &Uuid,
+	payload: &CreateWorkspacePayload,
+	context: &AuditContext,
+) -> AppResult<Workspace> {
+	validate_name(&payload.name)?;
+
+	let mut tx = pool.begin().await
+		.map_err(|err| AppError::database("Failed to start transaction", err))?;
+	let workspace = sqlx::query_as::<_, Workspace>(
+		"WITH w AS (INSERT INTO workspaces (name, created_by) VALUES ($1, $2) RETURNING *), \
+		m AS (INSERT INTO workspace_members (workspace_id, user_id, role) SELECT w.id, $2, 'OWNER' FROM w RETURNING *) \
+		SELECT w.id, w.name, w.personal, m.role, w.created_at FROM w, m"
+	)
+		.bind(payload.name.trim())
+		.bind(user_id)
+		.fetch_one(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to create workspace", err))?;
+
+	let after = json!({ "name": workspace.name, format!("members.{}", user_id): WorkspaceRole::Owner });
+	audit::record(&mut tx, context, EntityType::Workspace, workspace.id, AuditAction::Created, audit::diff(&Value::Null, &after)).await?;
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to create workspace", err))?;
+
+	Ok(workspace)
+}
+
+pub async fn list_members(pool: &PgPool, user_id: &Uuid, workspace_id: &Uuid) -> AppResult<Vec<WorkspaceMember>> {
+	let members = sqlx::query_as::<_, WorkspaceMember>(&format!(
+		"SELECT {} FROM workspace_members m JOIN users u ON u.id = m.user_id \
+		WHERE m.workspace_id = $1 AND EXISTS (SELECT 1 FROM workspace_members me WHERE me.workspace_id = $1 AND me.user_id = $2) \
+		ORDER BY m.joined_at, m.user_id",
+		MEMBER_COLUMNS,
+	))
+		.bind(workspace_id)
+		.bind(user_id)
+		.fetch_all(pool)
+		.await
+		.map_err(|err| AppError::database("Failed to list workspace members", err))?;
+
+	// A workspace always has its owner, so nothing at all means the user isn't in it.
+	match members.is_empty() {
+		true => Err(not_found()),
+		false => Ok(members),
+	}
+}
+
+/// Admins manage members and admins; only owners make or unmake owners, and the last owner stays.
+pub async fn update_member_role(
+	pool: &PgPool,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	member_id: &Uuid,
+	role: WorkspaceRole,
+	context: &AuditContext,
+) -> AppResult<WorkspaceMember> {
+	let mut tx = pool.begin().await
+		.map_err(|err| AppError::database("Failed to start transaction", err))?;
+	let members = member_roles(&mut tx, workspace_id, true).await?;
+	let actor_role = require_role(&members, user_id, WorkspaceRole::Admin)?;
+	let current = members.iter()
+		.find(|(member, _)| member == member_id)
+		.map(|(_, role)| *role)
+		.ok_or_else(|| AppError::NotFound(ErrorCode::UserNotFound, "Not a member of this workspace".into()))?;
+
+	if (current == WorkspaceRole::Owner || role == WorkspaceRole::Owner) && actor_role != WorkspaceRole::Owner {
+		return Err(AppError::Forbidden(ErrorCode::Forbidden, "Only owners can grant or revoke ownership".into()));
+	}
+	let owners = members.iter().filter(|(_, role)| *role == WorkspaceRole::Owner).count();
+	if current == WorkspaceRole::Owner && role != WorkspaceRole::Owner && owners == 1 {
+		return Err(AppError::Conflict(ErrorCode::LastOwner, "A workspace needs at least one owner".into()));
+	}
+
+	let member = sqlx::query_as::<_, WorkspaceMember>(&format!(
+		"WITH m AS (UPDATE workspace_members SET role = $3 WHERE workspace_id = $1 AND user_id = $2 RETURNING *) \
+		SELECT {} FROM m JOIN users u ON u.id = m.user_id",
+		MEMBER_COLUMNS,
+	))
+		.bind(workspace_id)
+		.bind(member_id)
+		.bind(role)
+		.fetch_one(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to update member", err))?;
+
+	if current != role {
+		record_member_change(&mut tx, context, workspace_id, member_id, Some(current), Some(role)).await?;
+	}
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to update member", err))?;
+
+	Ok(member)
+}
+
+/// Admins remove members and admins, owners anyone; everyone may leave. The last owner can't go,
+/// and neither can the creator of a personal workspace. Time entries the member logged stay.
+pub async fn remove_member(
+	pool: &PgPool,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	member_id: &Uuid,
+	context: &AuditContext,
+) -> AppResult<()> {
+	let mut tx = pool.begin().await
+		.map_err(|err| AppError::database("Failed to start transaction", err))?;
+	let members = member_roles(&mut tx, workspace_id, true).await?;
+	let required = match user_id == member_id {
+		true => WorkspaceRole::Member,
+		false => WorkspaceRole::Admin,
+	};
+	let actor_role = require_role(&members, user_id, required)?;
+	let current = members.iter()
+		.find(|(member, _)| member == member_id)
+		.map(|(_, role)| *role)
+		.ok_or_else(|| AppError::NotFound(ErrorCode::UserNotFound, "Not a member of this workspace".into()))?;
+
+	let personal = sqlx::query_scalar::<_, bool>("SELECT personal AND created_by = $2 FROM workspaces WHERE id = $1")
+		.bind(workspace_id)
+		.bind(member_id)
+		.fetch_one(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to fetch workspace", err))?;
+	if personal {
+		return Err(AppError::Forbidden(ErrorCode::Forbidden, "Nobody can leave their personal workspace".into()));
+	}
+	if current == WorkspaceRole::Owner && actor_role != WorkspaceRole::Owner {
+		return Err(AppError::Forbidden(ErrorCode::Forbidden, "Only owners can remove owners".into()));
+	}
+	let owners = members.iter().filter(|(_, role)| *role == WorkspaceRole::Owner).count();
+	if current == WorkspaceRole::Owner && owners == 1 {
+		return Err(AppError::Conflict(ErrorCode::LastOwner, "A workspace needs at least one owner".into()));
+	}
+
+	sqlx::query("DELETE FROM workspace_members WHERE workspace_id = $1 AND user_id = $2")
+		.bind(workspace_id)
+		.bind(member_id)
+		.execute(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to remove member", err))?;
+	record_member_change(&mut tx, context, workspace_id, member_id, Some(current), None).await?;
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to remove member", err))?;
+
+	Ok(())
+}
+
+/// Invites `email` to the workspace by mailing them a link with the token. Only its hash is stored,
+/// and the inviter never sees it, so only the invited address can accept.
+pub async fn create_invitation(
+	pool: &PgPool,
+	config: &Config,
+	user_id: &Uuid,
+	workspace_id: &Uuid,
+	payload: &CreateInvitationPayload,
+) -> AppResult<Invitation> {
+	let role = payload.role.unwrap_or(WorkspaceRole::Member);
+	let email = normalize_email("email", &payload.email)?;
+
+	let mut tx = pool.begin().await
+		.map_err(|err| AppError::database("Failed to start transaction", err))?;
+	let members = member_roles(&mut tx, workspace_id, true).await?;
+	let required = match role {
+		WorkspaceRole::Owner => WorkspaceRole::Owner,
+		_ => WorkspaceRole::Admin,
+	};
+	require_role(&members, user_id, required)?;
+
+	let already_member = sqlx::query_scalar::<_, bool>(
+		"SELECT EXISTS (SELECT 1 FROM workspace_members m JOIN users u ON u.id = m.user_id \
+		WHERE m.workspace_id = $1 AND lower(u.email) = lower($2))"
+	)
+		.bind(workspace_id)
+		.bind(&email)
+		.fetch_one(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to check workspace members", err))?;
+	if already_member {
+		return Err(AppError::Conflict(ErrorCode::AlreadyMember, "This address already belongs to a member".into()));
+	}
+
+	let token = format!("inv_{}", hex::encode(rand::random::<[u8; 32]>()));
+	let invitation = sqlx::query_as::<_, Invitation>(&format!(
+		"INSERT INTO workspace_invitations AS i (workspace_id, email, role, token_hash, invited_by, expires_at) \
+		VALUES ($1, $2, $3, $4, $5, $6) RETURNING {}",
+		INVITATION_COLUMNS,
+	))
+		.bind(workspace_id)
+		.bind(&email)
+		.bind(role)
+		.bind(hash_token(&token))
+		.bind(user_id)
+		.bind(Utc::now() + INVITATION_TTL)
+		.fetch_one(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to create invitation", err))?;
+
+	let (workspace, inviter) = sqlx::query_as::<_, (String, String)>(
+		"SELECT w.name, u.email FROM workspaces w, users u WHERE w.id = $1 AND u.id = $2"
+	)
+		.bind(workspace_id)
+		.bind(user_id)
+		.fetch_one(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to fetch workspace", err))?;
+	mailer::send(&mut *tx, &SendEmail {
+		to: email.clone(),
+		subject: format!("You're invited to {} on Kvitter", workspace),
+		body: format!(
+			"{} invited you to the workspace {} on Kvitter. Follow this link within {} days to join:\n\n\
+			{}/invitations/accept?token={}\n\n\
+			If you don't have an account yet, sign up with this address first.",
+			inviter, workspace, INVITATION_TTL.num_days(), config.app_url, token,
+		),
+	}).await?;
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to create invitation", err))?;
+
+	Ok(invitation)
+}
+
+/// Invitations that can still be accepted, newest first. For admins and owners.
+pub async fn list_invitations(pool: &PgPool, user_id: &Uuid, workspace_id: &Uuid) -> AppResult<Vec<Invitation>> {
+	let mut conn = pool.acquire().await
+		.map_err(|err| AppError::database("Failed to acquire connection", err))?;
+	let members = member_roles(&mut conn, workspace_id, false).await?;
+	require_role(&members, user_id, WorkspaceRole::Admin)?;
+
+	sqlx::query_as::<_, Invitation>(&format!(
+		"SELECT {} FROM workspace_invitations i \
+		WHERE i.workspace_id = $1 AND i.accepted_at IS NULL AND i.expires_at > NOW() \
+		ORDER BY i.created_at DESC, i.id",
+		INVITATION_COLUMNS,
+	))
+		.bind(workspace_id)
+		.fetch_all(&mut *conn)
+		.await
+		.map_err(|err| AppError::database("Failed to list invitations", err))
+}
+
+pub async fn revoke_invitation(pool: &PgPool, user_id: &Uuid, workspace_id: &Uuid, invitation_id: &Uuid) -> AppResult<()> {
+	let mut conn = pool.acquire().await
+		.map_err(|err| AppError::database("Failed to acquire connection", err))?;
+	let members = member_roles(&mut conn, workspace_id, false).await?;
+	require_role(&members, user_id, WorkspaceRole::Admin)?;
+
+	let deleted = sqlx::query(
+		"DELETE FROM workspace_invitations WHERE id = $1 AND workspace_id = $2 AND accepted_at IS NULL"
+	)
+		.bind(invitation_id)
+		.bind(workspace_id)
+		.execute(&mut *conn)
+		.await
+		.map_err(|err| AppError::database("Failed to revoke invitation", err))?;
+
+	match deleted.rows_affected() {
+		0 => Err(invitation_invalid()),
+		_ => Ok(()),
+	}
+}
+
+/// Joins the workspace of the invitation behind `token`, which must be addressed to the user's email.
+/// A user who is already a member keeps their role.
+pub async fn accept_invitation(
+	pool: &PgPool,
+	user_id: &Uuid,
+	token: &str,
+	context: &AuditContext,
+) -> AppResult<Workspace> {
+	let mut tx = pool.begin().await
+		.map_err(|err| AppError::database("Failed to start transaction", err))?;
+	let accepted = sqlx::query_as::<_, (Uuid, WorkspaceRole)>(
+		"UPDATE workspace_invitations i SET accepted_at = NOW() FROM users u \
+		WHERE i.token_hash = $1 AND i.accepted_at IS NULL AND i.expires_at > NOW() \
+			AND u.id = $2 AND u.deleted_at IS NULL AND lower(u.email) = lower(i.email) \
+		RETURNING i.workspace_id, i.role"
+	)
+		.bind(hash_token(token))
+		.bind(user_id)
+		.fetch_optional(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to accept invitation", err))?;
+	let (workspace_id, role) = accepted.ok_or_else(invitation_invalid)?;
+
+	let joined = sqlx::query(
+		"INSERT INTO workspace_members (workspace_id, user_id, role) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING"
+	)
+		.bind(workspace_id)
+		.bind(user_id)
+		.bind(role)
+		.execute(&mut *tx)
+		.await
+		.map_err(|err| AppError::database("Failed to join workspace", err))?;
+	if joined.rows_affected() > 0 {
+		record_member_change(&mut tx, context, &workspace_id, user_id, None, Some(role)).await?;
+	}
+	tx.commit().await
+		.map_err(|err| AppError::database("Failed to accept invitation", err))?;
+
+	fetch_workspace(pool, user_id, &workspace_id).await
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_roles_are_ordered_by_permissions() {
+		assert!(WorkspaceRole::Owner > WorkspaceRole::Admin);
+		assert!(WorkspaceRole::Admin > WorkspaceRole::Member);
+	}
+
+	#[test]
+	fn test_require_role() {
+		let (owner, member) = (Uuid::new_v4(), Uuid::new_v4());
+		let members = [(owner, WorkspaceRole::Owner), (member, WorkspaceRole::Member)];
+
+		assert_eq!(require_role(&members, &owner, WorkspaceRole::Admin).unwrap(), WorkspaceRole::Owner);
+		assert!(matches!(require_role(&members, &member, WorkspaceRole::Admin), Err(AppError::Forbidden(..))));
+		assert!(matches!(require_role(&members, &Uuid::new_v4(), WorkspaceRole::Member), Err(AppError::NotFound(..))));
+	}
+}
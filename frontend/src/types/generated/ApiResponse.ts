@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ErrorCode } from "./ErrorCode";
+import type { FieldError } from "./FieldError";
+
+/**
+ * # ApiResponse
+ * Envelope around every JSON response.
+ * `data` is set on success; `error`, `code` and `details` are set on failure.
+ * List endpoints set `next_cursor` while more pages follow.
+ */
+export type ApiResponse<T> = { status: number, data: T | null, next_cursor?: string, error: string | null, code?: ErrorCode, details?: Array<FieldError>, error_id?: string, request_id?: string, };
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AuditAction } from "./AuditAction";
+import type { EntityType } from "./EntityType";
+
+/**
+ * # AuditEvent
+ * One change to one entity. `actor_id` is empty for changes made by the system.
+ * `changes` maps each touched field to its `before` and `after` value; secrets are redacted.
+ */
+export type AuditEvent = { id: string, occurred_at: string, actor_id: string | null, entity_type: EntityType, entity_id: string, action: AuditAction, changes: Record<string, { before: unknown, after: unknown }>, request_id: string | null, ip: string | null, };
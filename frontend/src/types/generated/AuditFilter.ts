@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AuditAction } from "./AuditAction";
+import type { AuditSort } from "./AuditSort";
+import type { EntityType } from "./EntityType";
+
+/**
+ * # AuditFilter
+ * Query parameters of the admin audit log. `from` and `to` are inclusive UTC dates.
+ */
+export type AuditFilter = { sort?: AuditSort, actor_id?: string, entity_type?: EntityType, entity_id?: string, action?: AuditAction, request_id?: string, from?: string, to?: string, };
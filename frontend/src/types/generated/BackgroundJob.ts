@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { JobStatus } from "./JobStatus";
+
+/**
+ * # BackgroundJob
+ * One unit of work in the queue. `last_error` is the failure of the most recent attempt.
+ */
+export type BackgroundJob = { id: string, kind: string, 
+/**
+ * `null` for jobs whose payload holds secrets, like emails.
+ */
+payload: Record<string, unknown> | null, status: JobStatus, attempts: number, max_attempts: number, run_at: string, last_error: string | null, created_at: string, finished_at: string | null, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * # ChangeEmailPayload
+ * Asks to move the account to `new_email`. The current password guards against a hijacked session.
+ */
+export type ChangeEmailPayload = { new_email: string, password: string, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { HealthStatus } from "./HealthStatus";
+
+export type ComponentHealth = { status: HealthStatus, latency_ms?: number, detail?: string, };
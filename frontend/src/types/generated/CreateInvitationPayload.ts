@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { WorkspaceRole } from "./WorkspaceRole";
+
+export type CreateInvitationPayload = { email: string, 
+/**
+ * Defaults to `MEMBER`. Only owners may invite owners.
+ */
+role?: WorkspaceRole, };
@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { WebhookEventType } from "./WebhookEventType";
+import type { WebhookScope } from "./WebhookScope";
+
+export type CreateWebhookPayload = { 
+/**
+ * `http` or `https` URL the events are posted to. It must lead to a public address.
+ */
+url: string, events: Array<WebhookEventType>, description?: string, scope?: WebhookScope, };
@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { WebhookEventType } from "./WebhookEventType";
+
+/**
+ * # CreatedWebhook
+ * The new webhook with the secret its deliveries are signed with. The secret is only ever shown here.
+ */
+export type CreatedWebhook = { secret: string, id: string, 
+/**
+ * The workspace the webhook belongs to; none for the user's own.
+ */
+workspace_id: string | null, url: string, events: Array<WebhookEventType>, description: string | null, consecutive_failures: number, disabled_at: string | null, created_at: string, };
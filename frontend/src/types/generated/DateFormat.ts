@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * How dates are shown, e.g. on invoices.
+ */
+export type DateFormat = "year_month_day" | "day_month_year" | "day_month_year_slash" | "month_day_year";
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DeliveryStatus } from "./DeliveryStatus";
+import type { WebhookSort } from "./WebhookSort";
+
+/**
+ * Query parameters of a webhook's delivery log.
+ */
+export type DeliveryFilter = { status?: DeliveryStatus, sort?: WebhookSort, };
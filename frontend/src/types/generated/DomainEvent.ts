@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AuditAction } from "./AuditAction";
+import type { EntityType } from "./EntityType";
+
+/**
+ * # DomainEvent
+ * A committed change to project data, as pushed to `/events` subscribers.
+ * Only identifies the change; fetch the entity for its current state.
+ */
+export type DomainEvent = { entity_type: EntityType, entity_id: string, action: AuditAction, 
+/**
+ * The project the entity belongs to.
+ */
+project_id: string, 
+/**
+ * The workspace of the project; subscribers only hear about their active workspace.
+ */
+workspace_id: string, 
+/**
+ * Version of the row after the change; the last version for purged rows.
+ */
+version: number, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * # EmailChange
+ * A requested email change, waiting for the link sent to `new_email` to be followed before `expires_at`.
+ */
+export type EmailChange = { id: string, new_email: string, created_at: string, expires_at: string, };
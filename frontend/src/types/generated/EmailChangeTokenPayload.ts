@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * # EmailChangeTokenPayload
+ * The token from the confirmation link sent to the new address, or the undo link sent to the old one.
+ */
+export type EmailChangeTokenPayload = { token: string, };
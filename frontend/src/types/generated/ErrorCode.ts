@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * # ErrorCode
+ * Stable, machine-readable identifier sent with every error.
+ * Clients switch on these instead of the English message, so existing codes must never be renamed.
+ */
+export type ErrorCode = "auth.missing_token" | "auth.invalid_token" | "auth.invalid_credentials" | "auth.incorrect_password" | "auth.token_generation_failed" | "auth.forbidden" | "user.not_found" | "user.email_taken" | "user.email_change_invalid" | "workspace.not_member" | "workspace.last_owner" | "workspace.already_member" | "workspace.invitation_invalid" | "validation.failed" | "email.invalid" | "password.empty" | "password.too_short" | "password.too_long" | "password.missing_uppercase" | "password.missing_lowercase" | "password.missing_digit" | "password.missing_symbol" | "password.too_many_repeats" | "password.too_weak" | "password.breached" | "request.bad_request" | "request.invalid_query" | "request.invalid_cursor" | "request.precondition_required" | "request.idempotency_key_reused" | "request.idempotency_key_in_progress" | "resource.not_found" | "resource.version_mismatch" | "database.error" | "internal.error" | "internal.misconfigured";
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ErrorCode } from "./ErrorCode";
+
+/**
+ * # FieldError
+ * One failed rule on one input field, carried by `AppError::Validation`.
+ */
+export type FieldError = { field: string, code: ErrorCode, message: string, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AuditSort } from "./AuditSort";
+
+/**
+ * Query parameters of an entity's history.
+ */
+export type HistoryFilter = { sort?: AuditSort, };
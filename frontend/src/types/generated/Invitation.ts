@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { WorkspaceRole } from "./WorkspaceRole";
+
+/**
+ * # Invitation
+ * An open invitation to join a workspace, for the account with `email`.
+ */
+export type Invitation = { id: string, workspace_id: string, email: string, role: WorkspaceRole, invited_by: string | null, created_at: string, expires_at: string, };
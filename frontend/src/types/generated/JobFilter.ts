@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { JobSort } from "./JobSort";
+import type { JobStatus } from "./JobStatus";
+
+/**
+ * Query parameters of the admin job list.
+ */
+export type JobFilter = { sort?: JobSort, status?: JobStatus, kind?: string, };
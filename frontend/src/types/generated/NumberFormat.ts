@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * How amounts are shown: the digit group separator, then the decimal separator.
+ */
+export type NumberFormat = "comma_period" | "period_comma" | "space_comma";
@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ErrorCode } from "./ErrorCode";
+import type { FieldError } from "./FieldError";
+
+/**
+ * # ProblemDetails
+ * RFC 7807 body, extended with the stable error `code`, field `errors` and the `request_id`.
+ */
+export type ProblemDetails = { type: string, title: string, status: number, detail: string, code: ErrorCode, errors?: Array<FieldError>, error_id?: string, request_id?: string, };
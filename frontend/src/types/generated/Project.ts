@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ProjectStatus } from "./ProjectStatus";
+
+export type Project = { id: string, name: string, description: string | null, client_id: string, total_budget: string | null, default_hourly_rate: string | null, is_fixed_price: boolean, start_date: string | null, end_date: string | null, status: ProjectStatus, created_at: string, 
+/**
+ * `null` once the account that created the project is gone.
+ */
+created_by: string | null, workspace_id: string, 
+/**
+ * Also sent as the `ETag` of `GET /projects/{id}`; changes must send it back in `If-Match`.
+ */
+version: number, };
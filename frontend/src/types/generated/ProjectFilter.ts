@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ProjectSort } from "./ProjectSort";
+import type { ProjectStatus } from "./ProjectStatus";
+
+/**
+ * # ProjectFilter
+ * Query parameters of `GET /projects`.
+ * `from` and `to` bound the start date and are inclusive; projects without one are left out when either is set.
+ */
+export type ProjectFilter = { sort?: ProjectSort, status?: ProjectStatus, client_id?: string, from?: string, to?: string, };
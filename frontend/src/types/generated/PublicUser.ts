@@ -0,0 +1,13 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { UserProfile } from "./UserProfile";
+
+/**
+ * # PublicUser
+ * A public representation of a user, excluding sensitive information like password hash.
+ * This is used for responses that do not require sensitive data.
+ */
+export type PublicUser = { id: string, email: string, created_at: string, 
+/**
+ * Also sent as the `ETag`; changes to the user must send it back in `If-Match`.
+ */
+version: number, profile: UserProfile, };
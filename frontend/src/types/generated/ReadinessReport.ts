@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ComponentHealth } from "./ComponentHealth";
+import type { HealthStatus } from "./HealthStatus";
+
+/**
+ * # ReadinessReport
+ * Per-component result of the readiness probe.
+ * The overall `status` is `error` as soon as one component reports `error`.
+ */
+export type ReadinessReport = { status: HealthStatus, components: { [key in string]?: ComponentHealth }, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RunTrigger } from "./RunTrigger";
+import type { ScheduleSort } from "./ScheduleSort";
+
+/**
+ * Query parameters of a task's run history.
+ */
+export type RunFilter = { trigger?: RunTrigger, sort?: ScheduleSort, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ScheduleSort } from "./ScheduleSort";
+
+/**
+ * Query parameters of the scheduled task list.
+ */
+export type ScheduleFilter = { sort?: ScheduleSort, };
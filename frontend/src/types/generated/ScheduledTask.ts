@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * # ScheduledTask
+ * Recurring work: a background job of `job_kind` is queued with `payload` whenever `schedule` fires.
+ */
+export type ScheduledTask = { id: string, name: string, description: string | null, 
+/**
+ * Cron expression in UTC, with or without a leading seconds field.
+ */
+schedule: string, job_kind: string, payload: Record<string, unknown>, enabled: boolean, next_run_at: string, last_run_at: string | null, created_at: string, };
@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { JobStatus } from "./JobStatus";
+import type { RunTrigger } from "./RunTrigger";
+
+/**
+ * # ScheduledTaskRun
+ * One time a task fired. The outcome is that of the queued job, as long as the job is kept.
+ */
+export type ScheduledTaskRun = { id: string, task_id: string, job_id: string | null, trigger: RunTrigger, triggered_by: string | null, created_at: string, job_status: JobStatus | null, job_error: string | null, finished_at: string | null, };
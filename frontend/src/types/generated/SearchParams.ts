@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SearchKind } from "./SearchKind";
+
+/**
+ * # SearchParams
+ * `q` uses web search syntax: `"quoted phrases"`, `or` and `-excluded` words.
+ */
+export type SearchParams = { q: string, 
+/**
+ * Only return results of this kind.
+ */
+type?: SearchKind, 
+/**
+ * 1 to 50. Defaults to 20.
+ */
+limit?: number, };
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SearchKind } from "./SearchKind";
+
+/**
+ * # SearchResult
+ * One match, ranked against all other kinds.
+ * `highlight` is HTML-escaped text with the matched words wrapped in `<mark>` tags.
+ * `project_id` points at the enclosing project for jobs and time entries.
+ */
+export type SearchResult = { kind: SearchKind, id: string, project_id: string | null, title: string, highlight: string, rank: number, };
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SyncKind } from "./SyncKind";
+import type { SyncOperation } from "./SyncOperation";
+
+/**
+ * # SyncChange
+ * One change made offline. `base_version` is the version the change was made against and is required
+ * for rows that exist on the server; new rows leave it out.
+ */
+export type SyncChange = { kind: SyncKind, id: string, op: SyncOperation, base_version?: number, data: Record<string, unknown>, };
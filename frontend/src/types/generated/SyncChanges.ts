@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SyncRecord } from "./SyncRecord";
+
+/**
+ * # SyncChanges
+ * Everything that changed since the cursor. `created` and `updated` are both upserts for the client:
+ * a row counts as created while it is still at its first version. Rows may show up again on the next sync.
+ * With `reset` set the client must drop its copy first, because this is a full copy rather than a delta.
+ */
+export type SyncChanges = { created: Array<SyncRecord>, updated: Array<SyncRecord>, deleted: Array<SyncRecord>, 
+/**
+ * Send back as `since` on the next sync.
+ */
+cursor: string, reset: boolean, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The tables an offline client keeps a copy of.
+ */
+export type SyncKind = "client" | "project" | "job" | "time_entry" | "milestone";
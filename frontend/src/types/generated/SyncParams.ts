@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Query parameters of `GET /sync`.
+ */
+export type SyncParams = { 
+/**
+ * `cursor` of the previous sync. Leave out for a full copy.
+ */
+since?: string, };
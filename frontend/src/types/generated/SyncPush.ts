@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SyncChange } from "./SyncChange";
+
+/**
+ * # SyncPush
+ * Body of `POST /sync`. Changes are applied in order, each on its own.
+ */
+export type SyncPush = { changes: Array<SyncChange>, };
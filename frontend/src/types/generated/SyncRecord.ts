@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SyncKind } from "./SyncKind";
+
+/**
+ * # SyncRecord
+ * A row as the server has it. `data` holds the same fields as the REST representation of the kind;
+ * `deleted_at` is set for rows in the trash.
+ */
+export type SyncRecord = { kind: SyncKind, id: string, version: number, deleted_at: string | null, data: Record<string, unknown>, };
@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SyncKind } from "./SyncKind";
+import type { SyncOutcome } from "./SyncOutcome";
+import type { SyncRecord } from "./SyncRecord";
+
+/**
+ * # SyncResult
+ * What became of one `SyncChange`.
+ */
+export type SyncResult = { kind: SyncKind, id: string, outcome: SyncOutcome, 
+/**
+ * Version of the row after the change.
+ */
+version?: number, server?: SyncRecord, error?: string, };
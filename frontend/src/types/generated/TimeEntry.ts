@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * # TimeEntry
+ * `time_spent` is stored as an `INTERVAL` and exposed in whole seconds.
+ */
+export type TimeEntry = { id: string, job_id: string, user_id: string, time_spent_seconds: number, description: string | null, entry_date: string, created_at: string, 
+/**
+ * Also sent as the `ETag` of `GET /time-entries/{id}`; changes must send it back in `If-Match`.
+ */
+version: number, };
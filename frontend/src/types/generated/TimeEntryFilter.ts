@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TimeEntrySort } from "./TimeEntrySort";
+
+/**
+ * # TimeEntryFilter
+ * Query parameters of `GET /time-entries`. `from` and `to` bound the entry date and are inclusive.
+ */
+export type TimeEntryFilter = { sort?: TimeEntrySort, from?: string, to?: string, job_id?: string, project_id?: string, client_id?: string, };
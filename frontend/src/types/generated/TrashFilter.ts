@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TrashKind } from "./TrashKind";
+import type { TrashSort } from "./TrashSort";
+
+export type TrashFilter = { sort?: TrashSort, 
+/**
+ * Only list deleted rows of this kind.
+ */
+type?: TrashKind, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TrashKind } from "./TrashKind";
+
+/**
+ * # TrashItem
+ * A soft-deleted row the user may restore until `purge_at`.
+ */
+export type TrashItem = { kind: TrashKind, id: string, title: string, deleted_at: string, purge_at: string, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * # UpdateScheduledTaskPayload
+ * Fields left out stay as they are. A new schedule takes effect from now on.
+ */
+export type UpdateScheduledTaskPayload = { schedule?: string, enabled?: boolean, };
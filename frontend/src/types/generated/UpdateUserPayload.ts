@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DateFormat } from "./DateFormat";
+import type { NumberFormat } from "./NumberFormat";
+
+/**
+ * # UpdateUserPayload
+ * Profile fields to change with `PATCH /me`; fields left out stay as they are.
+ * Text is trimmed, and an empty string clears a field that may be empty.
+ * The email address and password have their own endpoints, which ask for the current password.
+ */
+export type UpdateUserPayload = { display_name?: string, avatar_url?: string, locale?: string, timezone?: string, currency?: string, date_format?: DateFormat, number_format?: NumberFormat, org_number?: string, vat_number?: string, bank_account?: string, address_line1?: string, address_line2?: string, postal_code?: string, city?: string, country?: string, };
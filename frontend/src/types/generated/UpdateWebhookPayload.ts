@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { WebhookEventType } from "./WebhookEventType";
+
+/**
+ * # UpdateWebhookPayload
+ * Fields left out stay as they are. `enabled: true` also resets the failure count.
+ */
+export type UpdateWebhookPayload = { url?: string, events?: Array<WebhookEventType>, description?: string, enabled?: boolean, };
@@ -0,0 +1,29 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DateFormat } from "./DateFormat";
+import type { NumberFormat } from "./NumberFormat";
+
+/**
+ * # UserProfile
+ * Preferences that shape what the user sees, and the business details printed on their invoices.
+ */
+export type UserProfile = { display_name: string | null, avatar_url: string | null, 
+/**
+ * BCP 47 language tag, e.g. `nb-NO`.
+ */
+locale: string, 
+/**
+ * IANA time zone, e.g. `Europe/Oslo`.
+ */
+timezone: string, 
+/**
+ * ISO 4217 code used for new amounts.
+ */
+currency: string, date_format: DateFormat, number_format: NumberFormat, org_number: string | null, vat_number: string | null, 
+/**
+ * An IBAN, or a domestic account number.
+ */
+bank_account: string | null, address_line1: string | null, address_line2: string | null, postal_code: string | null, city: string | null, 
+/**
+ * ISO 3166-1 alpha-2 code, e.g. `NO`.
+ */
+country: string | null, };
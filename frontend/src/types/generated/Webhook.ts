@@ -0,0 +1,13 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { WebhookEventType } from "./WebhookEventType";
+
+/**
+ * # Webhook
+ * A subscription of the user's, or of a workspace they manage. `disabled_at` is set after too many failed
+ * deliveries in a row; re-enabling it resumes the deliveries that were still pending.
+ */
+export type Webhook = { id: string, 
+/**
+ * The workspace the webhook belongs to; none for the user's own.
+ */
+workspace_id: string | null, url: string, events: Array<WebhookEventType>, description: string | null, consecutive_failures: number, disabled_at: string | null, created_at: string, };
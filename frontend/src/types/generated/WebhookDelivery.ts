@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DeliveryStatus } from "./DeliveryStatus";
+import type { WebhookEventType } from "./WebhookEventType";
+
+/**
+ * # WebhookDelivery
+ * One event sent, or still to be sent, to one webhook. `response_status` and `error` describe the last attempt.
+ */
+export type WebhookDelivery = { id: string, webhook_id: string, event_type: WebhookEventType, data: Record<string, unknown>, status: DeliveryStatus, attempts: number, next_attempt_at: string, response_status: number | null, error: string | null, created_at: string, delivered_at: string | null, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Events a webhook can subscribe to. Project events reach the webhooks of the project's workspace and
+ * of its members, `user.signed_up` reaches administrators' own.
+ */
+export type WebhookEventType = "time_entry.created" | "milestone.completed" | "user.signed_up" | "ping";
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Whom a new webhook belongs to.
+ */
+export type WebhookScope = "user" | "workspace";
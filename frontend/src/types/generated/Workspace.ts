@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { WorkspaceRole } from "./WorkspaceRole";
+
+/**
+ * # Workspace
+ * A workspace the user belongs to, with their role in it.
+ */
+export type Workspace = { id: string, name: string, 
+/**
+ * Created with the account and active when a request names no other workspace.
+ */
+personal: boolean, role: WorkspaceRole, created_at: string, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { WorkspaceRole } from "./WorkspaceRole";
+
+export type WorkspaceMember = { user_id: string, email: string, role: WorkspaceRole, joined_at: string, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Mirrors the `workspace_role` Postgres enum. Variants are ordered by what they may do,
+ * so `role >= WorkspaceRole::Admin` reads as "at least an admin".
+ */
+export type WorkspaceRole = "MEMBER" | "ADMIN" | "OWNER";
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Workspace } from "./Workspace";
+
+/**
+ * # WorkspaceToken
+ * A token that makes `workspace` the active workspace of the requests it authorizes.
+ */
+export type WorkspaceToken = { token: string, workspace: Workspace, };